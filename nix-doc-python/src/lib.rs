@@ -0,0 +1,208 @@
+//! Python bindings to nix-doc, searching nix source on disk from Python without going through the
+//! command and its output
+use nix_doc::hover::hover;
+use nix_doc::{is_searchable, search_results, Error, SearchOptions, SearchResult, SortKey};
+
+use pyo3::create_exception;
+use pyo3::exceptions::{
+    PyException, PyFileNotFoundError, PyOSError, PyPermissionError, PyValueError,
+};
+use pyo3::prelude::*;
+use regex::Regex;
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+create_exception!(
+    nix_doc,
+    NixDocError,
+    PyException,
+    "Nix source that couldn't be searched, like a file that isn't valid nix"
+);
+
+/// A documented definition, the Python side of a `SearchResult`. Lines and columns are 1-based,
+/// and columns count characters.
+#[pyclass(name = "Result", module = "nix_doc", frozen, get_all)]
+#[derive(Clone, Debug)]
+struct Found {
+    identifier: String,
+    /// The cleaned up doc comment, without any `@tag` lines
+    doc: String,
+    /// The first line of `doc`
+    synopsis: Option<String>,
+    /// `function`, `option`, `package` or `value`
+    kind: String,
+    /// `attr`, `rec`, `let` or `file`
+    def_kind: String,
+    attr_path: Vec<String>,
+    /// The parameters of a function, as written
+    param_block: String,
+    file: Option<PathBuf>,
+    line: usize,
+    column: usize,
+    end_line: usize,
+    end_column: usize,
+    /// The warning given, if it's a deprecated shim around `warn` or `throw`
+    deprecated: Option<String>,
+    /// How many curried arguments it takes, if it's visibly a function
+    arity: Option<usize>,
+}
+
+#[pymethods]
+impl Found {
+    fn __repr__(&self) -> String {
+        let file = match &self.file {
+            Some(file) => file.display().to_string(),
+            None => "<source>".to_string(),
+        };
+        format!("<Result {} at {}:{}>", self.identifier, file, self.line)
+    }
+}
+
+impl From<SearchResult> for Found {
+    fn from(res: SearchResult) -> Self {
+        Found {
+            kind: res.kind.to_string(),
+            def_kind: res.def_kind.to_string(),
+            line: res.start.line,
+            column: res.start.col_chars,
+            end_line: res.end.line,
+            end_column: res.end.col_chars,
+            identifier: res.identifier,
+            doc: res.doc,
+            synopsis: res.synopsis,
+            attr_path: res.attr_path,
+            param_block: res.param_block,
+            file: res.file,
+            deprecated: res.deprecated,
+            arity: res.arity,
+        }
+    }
+}
+
+/// `err` reading `path`, as the `OSError` subclass Python has for it
+fn io_error(path: &Path, err: io::Error) -> PyErr {
+    let message = format!("{}: {}", path.display(), err);
+    match err.kind() {
+        io::ErrorKind::NotFound => PyFileNotFoundError::new_err(message),
+        io::ErrorKind::PermissionDenied => PyPermissionError::new_err(message),
+        _ => PyOSError::new_err(message),
+    }
+}
+
+/// `err` as a Python exception: `ValueError` for a bad pattern or options that don't go together,
+/// `OSError` for a file that couldn't be read, and `NixDocError` for anything else
+fn to_py_err(err: Error) -> PyErr {
+    match err {
+        Error::Io {
+            path: Some(path),
+            source,
+        } => io_error(&path, source),
+        Error::Io { .. } => PyOSError::new_err(err.to_string()),
+        Error::InvalidRegex(_) | Error::InvalidConfig(_) => PyValueError::new_err(err.to_string()),
+        _ => NixDocError::new_err(err.to_string()),
+    }
+}
+
+/// Searches the `.nix` files under `path`, or the file `path`, for documented functions whose
+/// names match the regex `pattern`, like `nix-doc search`.
+///
+/// The keyword arguments are its flags: `kinds` a list of `function`, `option`, `package` and
+/// `value`, `sort_by` one of `name`, `file`, `line`, `doc-length` and `relevance`, and
+/// `path_prefix` a dotted attribute path like `lib.lists`. Files that couldn't be searched are
+/// left out, unless `strict` is set, which raises `NixDocError` for them instead.
+#[pyfunction]
+#[pyo3(signature = (
+    path,
+    pattern,
+    *,
+    kinds = None,
+    let_bindings = false,
+    include_reexports = false,
+    all_fields = false,
+    ignore_case = false,
+    path_prefix = None,
+    max_matches_per_file = None,
+    sort_by = None,
+    jobs = None,
+    strict = false,
+))]
+#[allow(clippy::too_many_arguments)]
+fn search(
+    py: Python<'_>,
+    path: PathBuf,
+    pattern: &str,
+    kinds: Option<Vec<String>>,
+    let_bindings: bool,
+    include_reexports: bool,
+    all_fields: bool,
+    ignore_case: bool,
+    path_prefix: Option<&str>,
+    max_matches_per_file: Option<usize>,
+    sort_by: Option<&str>,
+    jobs: Option<usize>,
+    strict: bool,
+) -> PyResult<Vec<Found>> {
+    fs::metadata(&path).map_err(|err| io_error(&path, err))?;
+    let source = match ignore_case {
+        true => format!("(?i){}", pattern),
+        false => pattern.to_string(),
+    };
+    let matching = Regex::new(&source).map_err(|err| to_py_err(err.into()))?;
+
+    let mut builder = SearchOptions::builder()
+        .ordered(true)
+        .let_bindings(let_bindings)
+        .include_reexports(include_reexports)
+        .all_fields(all_fields)
+        .path_prefix(match path_prefix {
+            Some(prefix) => prefix.split('.').map(str::to_string).collect(),
+            None => Vec::new(),
+        })
+        .max_matches_per_file(max_matches_per_file)
+        .jobs(jobs);
+    for kind in kinds.unwrap_or_default() {
+        builder = builder.kind(kind.parse().map_err(PyValueError::new_err)?);
+    }
+    if let Some(key) = sort_by {
+        builder = builder.sort_by(key.parse::<SortKey>().map_err(PyValueError::new_err)?);
+    }
+    let opts = builder.build().map_err(to_py_err)?;
+
+    // the search runs on threads of its own, which needn't wait on the GIL
+    let (results, errors) =
+        py.allow_threads(|| search_results(&path, &matching, &opts, is_searchable));
+    if strict && !errors.is_empty() {
+        let messages = errors.iter().map(|err| err.message.as_str());
+        return Err(NixDocError::new_err(
+            messages.collect::<Vec<_>>().join("\n"),
+        ));
+    }
+    Ok(results.into_iter().map(Found::from).collect())
+}
+
+/// The documented definition at the 1-based `line` and byte `col` of the file `path`, like
+/// `nix-doc hover`: the one an identifier there names, or the innermost one around it. `None` if
+/// there's nothing documented there.
+#[pyfunction]
+fn doc_at(py: Python<'_>, path: PathBuf, line: usize, col: usize) -> PyResult<Option<Found>> {
+    let content = fs::read_to_string(&path).map_err(|err| io_error(&path, err))?;
+    let found = py
+        .allow_threads(|| hover(&content, line, col))
+        .map_err(|err| to_py_err(err.in_file(&path)))?;
+    Ok(found.map(|res| Found {
+        file: Some(path),
+        ..Found::from(res)
+    }))
+}
+
+#[pymodule]
+#[pyo3(name = "nix_doc")]
+fn module(py: Python<'_>, m: &PyModule) -> PyResult<()> {
+    m.add_class::<Found>()?;
+    m.add("NixDocError", py.get_type::<NixDocError>())?;
+    m.add_function(wrap_pyfunction!(search, m)?)?;
+    m.add_function(wrap_pyfunction!(doc_at, m)?)?;
+    Ok(())
+}
@@ -0,0 +1,73 @@
+"""Searching the fixtures nix-doc's own tests use, through the Python module.
+
+Build the module into the current virtualenv with `maturin develop` first.
+"""
+from pathlib import Path
+
+import pytest
+
+import nix_doc
+
+FIXTURES = Path(__file__).parents[2] / "nix-doc" / "testdata"
+PIPELINE = FIXTURES / "pipeline"
+
+
+def names(results):
+    return [res.identifier for res in results]
+
+
+def test_search():
+    results = nix_doc.search(PIPELINE, "^concat")
+    assert names(results) == ["concatMap", "concatMapStrings", "concatStringsSep"]
+    concat_map = results[0]
+    assert isinstance(concat_map, nix_doc.Result)
+    assert concat_map.file == str(PIPELINE / "lib" / "lists.nix")
+    assert concat_map.line == 10
+    assert concat_map.synopsis == "Maps, then concatenates the lists that come out"
+    assert concat_map.kind == "function"
+    assert concat_map.def_kind == "rec"
+    assert concat_map.attr_path == ["concatMap"]
+    assert concat_map.arity == 2
+    assert "concatMap" in repr(concat_map)
+
+
+def test_search_options():
+    assert names(nix_doc.search(PIPELINE, "^MAP$", ignore_case=True)) == ["map"]
+    assert names(nix_doc.search(PIPELINE, "", max_matches_per_file=1, jobs=1)) == [
+        "map",
+        "concatMapStrings",
+        "mapGreeting",
+    ]
+    by_name = nix_doc.search(PIPELINE, "^concat", sort_by="name")
+    assert names(by_name) == sorted(names(by_name))
+
+
+def test_bad_arguments():
+    with pytest.raises(ValueError, match="regex"):
+        nix_doc.search(PIPELINE, "(")
+    with pytest.raises(ValueError, match="unknown kind"):
+        nix_doc.search(PIPELINE, "", kinds=["thing"])
+    with pytest.raises(ValueError, match="at least one job"):
+        nix_doc.search(PIPELINE, "", jobs=0)
+    with pytest.raises(FileNotFoundError):
+        nix_doc.search(FIXTURES / "nowhere", "")
+    with pytest.raises(TypeError):
+        nix_doc.search(PIPELINE, "", colour=True)
+
+
+def test_strict(tmp_path):
+    (tmp_path / "broken.nix").write_text("{ f = ; }")
+    (tmp_path / "fine.nix").write_text("{\n  # Does nothing\n  f = x: x;\n}\n")
+    assert names(nix_doc.search(tmp_path, "")) == ["f"]
+    with pytest.raises(nix_doc.NixDocError, match="broken.nix"):
+        nix_doc.search(tmp_path, "", strict=True)
+
+
+def test_doc_at():
+    lists = PIPELINE / "lib" / "lists.nix"
+    found = nix_doc.doc_at(lists, 4, 3)
+    assert found.identifier == "map"
+    assert found.file == str(lists)
+    assert nix_doc.doc_at(lists, 12, 3) is None
+    with pytest.raises(FileNotFoundError):
+        nix_doc.doc_at(PIPELINE / "missing.nix", 1, 1)
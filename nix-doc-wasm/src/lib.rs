@@ -0,0 +1,54 @@
+//! JavaScript bindings to nix-doc, searching nix source handed over as a string, since there's no
+//! filesystem to walk in the browser
+use nix_doc::doc::{doc_sections, split_tags, DocSection, DocTag};
+use nix_doc::{cleanup_comments, search_ast, SearchOptions, SearchResult};
+
+use regex::Regex;
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+/// A doc comment taken apart by [`parse_doc_comment`]
+#[derive(Serialize)]
+struct ParsedDoc {
+    /// The cleaned up comment, without its tags
+    description: String,
+    tags: Vec<DocTag>,
+    sections: Vec<DocSection>,
+}
+
+fn to_js<T: Serialize>(value: &T) -> Result<JsValue, JsValue> {
+    serde_wasm_bindgen::to_value(value).map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+/// Searches the nix source `source` for definitions matching the regex `pattern`, returning an
+/// array of results shaped like the ones from `nix-doc search --json`. Throws if the pattern or
+/// the source doesn't parse.
+#[wasm_bindgen(js_name = searchSource)]
+pub fn search_source(source: &str, pattern: &str) -> Result<JsValue, JsValue> {
+    let matching = Regex::new(pattern).map_err(|e| JsValue::from_str(&e.to_string()))?;
+    let ast = rnix::Root::parse(source)
+        .ok()
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    let results: Vec<SearchResult> = search_ast(&matching, &ast, &SearchOptions::default());
+    to_js(&results)
+}
+
+/// Cleans up one doc comment as written in nix source, either a `/* ... */` block or `#` lines,
+/// returning `{ description, tags, sections }`
+#[wasm_bindgen(js_name = parseDocComment)]
+pub fn parse_doc_comment(comment: &str) -> Result<JsValue, JsValue> {
+    let comment = comment.trim();
+    // the cleanup takes the comments nearest to the definition first
+    let doc = if comment.starts_with("/*") {
+        cleanup_comments(&mut std::iter::once(comment))
+    } else {
+        cleanup_comments(&mut comment.lines().rev())
+    };
+    let (description, tags) = split_tags(&doc);
+    let sections = doc_sections(&description);
+    to_js(&ParsedDoc {
+        description,
+        tags,
+        sections,
+    })
+}
@@ -0,0 +1,62 @@
+//! Times a search that prints a lot of results, to see what formatting and writing them costs.
+//! Results go to stdout like they do for people running nix-doc, so run it with stdout thrown
+//! away; the timings are printed to stderr:
+//!
+//! ```text
+//! cargo bench --bench format > /dev/null
+//! ```
+use std::env;
+use std::fmt::Write;
+use std::fs;
+use std::time::{Duration, Instant};
+
+use regex::Regex;
+
+use nix_doc::{is_searchable, SearchOptions};
+
+const FILES: usize = 50;
+const FUNCTIONS_PER_FILE: usize = 400;
+const RUNS: usize = 5;
+
+/// A file of documented functions, with enough lines of docs that indenting them counts
+fn generated_file(n: usize) -> String {
+    let mut src = String::from("{\n");
+    for f in 0..FUNCTIONS_PER_FILE {
+        writeln!(
+            src,
+            "  /* Does thing {f} of file {n}.\n\n     More about it, over\n     a few lines.\n\n     Example:\n       fn{f} 1 2\n  */\n  fn{f} = a: {{ b ? 1, c }}: a;\n",
+            f = f,
+            n = n
+        )
+        .unwrap();
+    }
+    src.push_str("}\n");
+    src
+}
+
+fn main() {
+    let dir = env::temp_dir().join(format!("nix-doc-format-bench-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    for n in 0..FILES {
+        fs::write(dir.join(format!("f{}.nix", n)), generated_file(n)).unwrap();
+    }
+
+    let all = Regex::new("").unwrap();
+    let mut times = Vec::new();
+    for _ in 0..RUNS {
+        let start = Instant::now();
+        nix_doc::search(&dir, all.clone(), SearchOptions::default(), is_searchable).unwrap();
+        times.push(start.elapsed());
+    }
+    fs::remove_dir_all(&dir).unwrap();
+
+    times.sort();
+    let mean = times.iter().sum::<Duration>() / RUNS as u32;
+    eprintln!(
+        "{} results, {} runs: fastest {:.1?}, mean {:.1?}",
+        FILES * FUNCTIONS_PER_FILE,
+        RUNS,
+        times[0],
+        mean
+    );
+}
@@ -2,9 +2,10 @@ use std::env;
 use std::fs;
 use std::str;
 
-use rnix::types::{Lambda, TypedNode};
-use rnix::NodeOrToken;
+use rnix::ast::Lambda;
 use rnix::SyntaxKind::*;
+use rnix::{NodeOrToken, Root};
+use rowan::ast::AstNode;
 
 use nix_doc::pprint;
 
@@ -15,15 +16,15 @@ fn main() {
     let file = args.next().expect("missing file argument");
     let file = fs::read(file).unwrap();
 
-    let parsed = rnix::parse(str::from_utf8(&file).unwrap());
+    let parsed = Root::parse(str::from_utf8(&file).unwrap());
 
     match op.as_str() {
         "dump" => {
             println!("==========           ast           ==========");
-            println!("{}", parsed.root().dump());
+            println!("{:#?}", parsed.syntax());
 
             println!("========== descendants_with_tokens ==========");
-            for node in parsed.node().descendants_with_tokens() {
+            for node in parsed.syntax().descendants_with_tokens() {
                 match node {
                     NodeOrToken::Node(n) => {
                         println!("N {:?} {}", n.kind(), n);
@@ -37,16 +38,13 @@ fn main() {
 
         "pprint" => {
             // tests pprint on all the functions it finds
-            for node in parsed.node().descendants() {
-                match node.kind() {
-                    NODE_LAMBDA => {
-                        println!("lambda!! {}", node);
-                        let lambda = Lambda::cast(node).unwrap();
-                        println!("pprint_args: {}", pprint::pprint_args(&lambda));
-                        let arg = lambda.arg().unwrap();
-                        println!("args!! {:?} {}", arg.kind(), arg);
-                    }
-                    _ => {}
+            for node in parsed.syntax().descendants() {
+                if node.kind() == NODE_LAMBDA {
+                    println!("lambda!! {}", node);
+                    let lambda = Lambda::cast(node).unwrap();
+                    println!("pprint_args: {}", pprint::pprint_args(&lambda));
+                    let arg = lambda.param().unwrap();
+                    println!("args!! {:?} {}", arg.syntax().kind(), arg);
                 }
             }
         }
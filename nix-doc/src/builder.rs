@@ -0,0 +1,384 @@
+//! Setting up [`SearchOptions`] a knob at a time, checking that they go together
+use crate::cache::FileCache;
+use crate::changes::ChangedLines;
+use crate::format::JSON_FIELDS;
+use crate::index::Index;
+use crate::output::Glyphs;
+use crate::profile::Profile;
+use crate::source::FileSource;
+use crate::{
+    ArityRange, Backend, CancellationToken, DefKind, DirFilter, Error, OutputFormat, Result,
+    ResultKind, SearchOptions, SortKey,
+};
+
+use regex::Regex;
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// Builds [`SearchOptions`], starting from the defaults, which search like `nix-doc search` with
+/// no flags does
+///
+/// ```
+/// use nix_doc::{OutputFormat, ResultKind, SearchOptions};
+///
+/// let opts = SearchOptions::builder()
+///     .jobs(8)
+///     .kind(ResultKind::Function)
+///     .format(OutputFormat::Csv)
+///     .build()
+///     .unwrap();
+/// assert_eq!(opts.jobs, Some(8));
+///
+/// // fuzzy matching can't be ranked against the docs
+/// assert!(SearchOptions::builder()
+///     .fuzzy("cMapStr".to_string())
+///     .all_fields(true)
+///     .build()
+///     .is_err());
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct SearchOptionsBuilder {
+    opts: SearchOptions,
+    /// Has a definition kind been switched on or off? Switching them all off leaves `def_kinds`
+    /// empty, which would otherwise mean the defaults.
+    def_kinds_switched: bool,
+}
+
+/// Setters for fields of [`SearchOptions`] that are set as they are, and ones that are optional,
+/// which take the value or an `Option` of it
+macro_rules! setters {
+    ($($field:ident: $ty:ty),* $(,)?; $($opt_field:ident: $opt_ty:ty),* $(,)?) => {
+        $(
+            #[doc = concat!("Sets [`SearchOptions::", stringify!($field), "`]")]
+            pub fn $field(mut self, $field: $ty) -> Self {
+                self.opts.$field = $field;
+                self
+            }
+        )*
+        $(
+            #[doc = concat!("Sets [`SearchOptions::", stringify!($opt_field), "`]")]
+            pub fn $opt_field(mut self, $opt_field: impl Into<Option<$opt_ty>>) -> Self {
+                self.opts.$opt_field = $opt_field.into();
+                self
+            }
+        )*
+    };
+}
+
+impl SearchOptionsBuilder {
+    setters! {
+        packages: bool,
+        options: bool,
+        any_key: bool,
+        kinds: Vec<ResultKind>,
+        any_value: bool,
+        ordered: bool,
+        reverse: bool,
+        explain_skips: bool,
+        no_deprecated: bool,
+        include_reexports: bool,
+        format: OutputFormat,
+        raw_docs: bool,
+        highlight: bool,
+        no_filename: bool,
+        glyphs: Glyphs,
+        def_kinds: Vec<DefKind>,
+        file_functions: bool,
+        path_prefix: Vec<String>,
+        path_exact: bool,
+        dirs: DirFilter,
+        follow_imports: bool,
+        json_compat_array: bool,
+        json_fields: Vec<String>,
+        backend: Backend,
+        all_fields: bool,
+        aliases: bool;
+
+        sort_by: SortKey,
+        arity: ArityRange,
+        min_arity: usize,
+        max_matches_per_file: usize,
+        doc_prefix: String,
+        match_synopsis: Regex,
+        takes_arg: Regex,
+        arg_default: Regex,
+        arg_required: Regex,
+        example: bool,
+        typed: bool,
+        file_regex: Regex,
+        changed: Arc<ChangedLines>,
+        fuzzy: String,
+        jobs: usize,
+        output: PathBuf,
+        width: usize,
+        relative_to: PathBuf,
+        profile: Arc<Profile>,
+        cancel: CancellationToken,
+        index: Arc<Index>,
+        cache: FileCache,
+    }
+
+    /// Sets [`SearchOptions::file_source`], to walk and read the files searched from `source`
+    pub fn file_source(mut self, source: impl FileSource + 'static) -> Self {
+        self.opts.file_source = Some(Arc::new(source));
+        self
+    }
+
+    /// Also reports results of `kind`, on top of the ones in [`SearchOptions::kinds`] already
+    pub fn kind(mut self, kind: ResultKind) -> Self {
+        self.opts.kinds.push(kind);
+        self
+    }
+
+    /// Also searches definitions made with `def_kind`, on top of the ones in
+    /// [`SearchOptions::def_kinds`] already
+    pub fn def_kind(mut self, def_kind: DefKind) -> Self {
+        self.opts.def_kinds.push(def_kind);
+        self
+    }
+
+    /// Searches plain attrsets, `{ f = ...; }`, or doesn't, which it does by default (see
+    /// [`DefKind::Attr`])
+    pub fn plain_sets(self, on: bool) -> Self {
+        self.switch_def_kind(DefKind::Attr, on)
+    }
+
+    /// Searches recursive attrsets, `rec { f = ...; }`, or doesn't, which it does by default
+    /// (see [`DefKind::RecAttr`])
+    pub fn rec_sets(self, on: bool) -> Self {
+        self.switch_def_kind(DefKind::RecAttr, on)
+    }
+
+    /// Searches `let f = ...; in ...` bindings, or doesn't, which it doesn't by default (see
+    /// [`DefKind::LetIn`])
+    pub fn let_bindings(self, on: bool) -> Self {
+        self.switch_def_kind(DefKind::LetIn, on)
+    }
+
+    /// Reports a file that's a function as a whole, named after the file, or doesn't, which it
+    /// doesn't by default (see [`DefKind::File`])
+    pub fn top_level_lambda(self, on: bool) -> Self {
+        self.switch_def_kind(DefKind::File, on)
+    }
+
+    /// Adds `kind` to the definition kinds searched, or takes it out, starting from the default
+    /// ones if none were picked
+    fn switch_def_kind(mut self, kind: DefKind, on: bool) -> Self {
+        let kinds = &mut self.opts.def_kinds;
+        if kinds.is_empty() && !self.def_kinds_switched {
+            kinds.extend_from_slice(DefKind::DEFAULT);
+        }
+        kinds.retain(|&searched| searched != kind);
+        if on {
+            kinds.push(kind);
+        }
+        if kind == DefKind::File {
+            self.opts.file_functions = on;
+        }
+        self.def_kinds_switched = true;
+        self
+    }
+
+    /// The options, unless some of them don't go together, which is an
+    /// [`Error::InvalidConfig`] saying which
+    pub fn build(self) -> Result<SearchOptions> {
+        let opts = self.opts;
+        let invalid = |message: &str| Err(Error::InvalidConfig(message.to_string()));
+        if self.def_kinds_switched && opts.def_kinds.is_empty() {
+            return invalid(
+                "every kind of definition is switched off, so there's nothing to search",
+            );
+        }
+        if opts.jobs == Some(0) {
+            return invalid("a search needs at least one job");
+        }
+        if opts.max_matches_per_file == Some(0) {
+            return invalid("at most 0 matches per file would never match anything");
+        }
+        if opts.fuzzy.is_some() && opts.all_fields {
+            return invalid("fuzzy matching only matches names, so it can't match all fields");
+        }
+        #[cfg(feature = "serde")]
+        if opts.json_compat_array && opts.format != OutputFormat::Json {
+            return invalid("the bare JSON array is only for JSON output");
+        }
+        if let Some(field) = opts
+            .json_fields
+            .iter()
+            .find(|field| !JSON_FIELDS.contains(&field.as_str()))
+        {
+            return invalid(&format!(
+                "unknown JSON field `{}`, expected some of: {}",
+                field,
+                JSON_FIELDS.join(", ")
+            ));
+        }
+        if opts.no_filename && opts.format != OutputFormat::Human {
+            return invalid("leaving out file names is only for human-readable output");
+        }
+        // there's no JSON to pick them from without the `serde` feature
+        if !opts.json_fields.is_empty() && opts.format.name() != "json" {
+            return invalid("picking JSON fields is only for JSON output");
+        }
+        if opts.follow_imports && !(opts.dirs.include.is_empty() && opts.dirs.exclude.is_empty()) {
+            return invalid("following imports doesn't walk directories to include or exclude");
+        }
+        if opts.path_exact && opts.path_prefix.is_empty() {
+            return invalid("an exact attribute path needs the path to match");
+        }
+        if opts
+            .relative_to
+            .as_ref()
+            .is_some_and(|dir| dir.is_relative())
+        {
+            return invalid("paths can only be printed relative to an absolute directory");
+        }
+        Ok(opts)
+    }
+}
+
+impl SearchOptions {
+    /// Starts building options, from the defaults
+    pub fn builder() -> SearchOptionsBuilder {
+        SearchOptionsBuilder::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_defaults() {
+        let built = SearchOptions::builder().build().unwrap();
+        // Debug is the only way to compare them all, since there's no PartialEq
+        assert_eq!(
+            format!("{:?}", built),
+            format!("{:?}", SearchOptions::default())
+        );
+    }
+
+    #[test]
+    fn test_setters() {
+        let opts = SearchOptions::builder()
+            .kind(ResultKind::Option)
+            .kind(ResultKind::Value)
+            .def_kind(DefKind::LetIn)
+            .sort_by(SortKey::Name)
+            .width(None)
+            .min_arity(Some(2))
+            .doc_prefix("##".to_string())
+            .build()
+            .unwrap();
+        assert_eq!(opts.kinds, [ResultKind::Option, ResultKind::Value]);
+        assert_eq!(opts.def_kinds, [DefKind::LetIn]);
+        assert_eq!(opts.sort_by, Some(SortKey::Name));
+        assert_eq!(opts.width, None);
+        assert_eq!(opts.min_arity, Some(2));
+        assert_eq!(opts.doc_prefix.as_deref(), Some("##"));
+    }
+
+    #[test]
+    fn test_invalid() {
+        let invalid = |builder: SearchOptionsBuilder| match builder.build() {
+            Err(Error::InvalidConfig(message)) => message,
+            other => panic!("expected an invalid config, got {:?}", other),
+        };
+        invalid(SearchOptions::builder().jobs(0));
+        invalid(SearchOptions::builder().max_matches_per_file(0));
+        invalid(
+            SearchOptions::builder()
+                .fuzzy("cMap".to_string())
+                .all_fields(true),
+        );
+        #[cfg(feature = "serde")]
+        invalid(SearchOptions::builder().json_compat_array(true));
+        invalid(SearchOptions::builder().json_fields(vec!["line".to_string()]));
+        invalid(
+            SearchOptions::builder()
+                .no_filename(true)
+                .format(OutputFormat::Csv),
+        );
+        let message = invalid(
+            SearchOptions::builder()
+                .json_fields(vec!["identifier".to_string(), "name".to_string()]),
+        );
+        assert!(
+            message.starts_with("unknown JSON field `name`"),
+            "{}",
+            message
+        );
+        invalid(
+            SearchOptions::builder()
+                .follow_imports(true)
+                .dirs(DirFilter {
+                    include: vec!["lib".to_string()],
+                    exclude: Vec::new(),
+                }),
+        );
+        invalid(SearchOptions::builder().path_exact(true));
+        invalid(SearchOptions::builder().relative_to(PathBuf::from("lib")));
+        invalid(SearchOptions::builder().plain_sets(false).rec_sets(false));
+    }
+
+    #[test]
+    fn test_def_kind_switches() {
+        let searched = |builder: SearchOptionsBuilder| {
+            let opts = builder.build().unwrap();
+            [
+                DefKind::Attr,
+                DefKind::RecAttr,
+                DefKind::LetIn,
+                DefKind::File,
+            ]
+            .iter()
+            .copied()
+            .filter(|&kind| opts.wants_def_kind(kind))
+            .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            searched(SearchOptions::builder()),
+            [DefKind::Attr, DefKind::RecAttr]
+        );
+        assert_eq!(
+            searched(SearchOptions::builder().let_bindings(true)),
+            [DefKind::Attr, DefKind::RecAttr, DefKind::LetIn]
+        );
+        assert_eq!(
+            searched(
+                SearchOptions::builder()
+                    .let_bindings(true)
+                    .plain_sets(false)
+                    .rec_sets(false)
+            ),
+            [DefKind::LetIn]
+        );
+        assert_eq!(
+            searched(SearchOptions::builder().top_level_lambda(true)),
+            [DefKind::Attr, DefKind::RecAttr, DefKind::File]
+        );
+        // switching one on twice doesn't search it twice
+        let opts = SearchOptions::builder()
+            .rec_sets(true)
+            .rec_sets(true)
+            .build()
+            .unwrap();
+        assert_eq!(opts.def_kinds, [DefKind::Attr, DefKind::RecAttr]);
+
+        let source =
+            "let\n  # In a let\n  inLet = x: x;\nin rec {\n  # In a rec\n  inRec = x: x;\n}";
+        let ast = rnix::Root::parse(source).ok().unwrap();
+        let found = |builder: SearchOptionsBuilder| {
+            crate::search_ast(&Regex::new("").unwrap(), &ast, &builder.build().unwrap())
+                .into_iter()
+                .map(|res| res.identifier)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(found(SearchOptions::builder()), ["inRec"]);
+        assert_eq!(
+            found(SearchOptions::builder().let_bindings(true).rec_sets(false)),
+            ["inLet"]
+        );
+    }
+}
@@ -0,0 +1,261 @@
+//! A cache of what's documented in each file searched, kept between runs in the user's cache
+//! directory, so searching files that haven't changed only takes matching the pattern against
+//! what was found in them last time, rather than parsing them again.
+//!
+//! Each file's entry is a file of its own, named by a hash of the file's path, and written the
+//! way the lines of an index are (see [`crate::index`]):
+//!
+//! ```text
+//! nix-doc cache 1
+//! file    lib/lists.nix
+//! stamp   1700000000  0  1234  9c5aa0b2c3d4e5f6
+//! def     ...
+//! ```
+//!
+//! The `stamp` is the modification time and size of the file, with a hash of its content, which
+//! all have to be the same for the entry to be used.
+use crate::index::{
+    escape, extracting_everything, fnv1a, matching_entries, read_entry_line, unescape,
+    write_atomically, write_entry, Entry, Stamp,
+};
+use crate::log;
+use crate::search::search_source;
+use crate::{Result, SearchOptions, SearchResult};
+
+use regex::Regex;
+
+use std::env;
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The version of the format of cache entries, for ones from another version to be made again
+pub const CACHE_VERSION: u32 = 1;
+
+const HEADER: &str = "nix-doc cache";
+
+/// Where the documented definitions of each file searched are kept between runs (see the
+/// [module docs](self))
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FileCache {
+    dir: PathBuf,
+    refresh: bool,
+    /// What relative paths of files searched are relative to, for naming their entries
+    cwd: Option<PathBuf>,
+}
+
+impl FileCache {
+    /// The cache in the user's cache directory, `$XDG_CACHE_HOME/nix-doc/files` or
+    /// `~/.cache/nix-doc/files`, if there's a home directory for it to be in
+    pub fn new() -> Option<Self> {
+        Some(FileCache::at(crate::index::cache_dir()?.join("files")))
+    }
+
+    /// The cache in `dir`, which is made when the first entry is written. Files searched by
+    /// relative paths are cached by where they are from the current directory as it is now.
+    pub fn at(dir: impl Into<PathBuf>) -> Self {
+        FileCache {
+            dir: dir.into(),
+            refresh: false,
+            cwd: env::current_dir().ok(),
+        }
+    }
+
+    /// With `refresh`, entries are never read, only written, so every file is parsed again and
+    /// what's in the cache replaced
+    pub fn refresh(self, refresh: bool) -> Self {
+        FileCache { refresh, ..self }
+    }
+
+    /// Searches `content`, which was read from `file`, for definitions matching `matching`, the
+    /// way [`search_source`] would, taking what's documented in it from the cache if it hasn't
+    /// changed since, and caching it otherwise. As with an index, the search has to be one the
+    /// cache can answer (see [`crate::index::Index::answers`]).
+    pub(crate) fn search(
+        &self,
+        file: &Path,
+        content: &str,
+        matching: &Regex,
+        opts: &SearchOptions,
+    ) -> Result<Vec<SearchResult>> {
+        let stamp = match Stamp::of(file) {
+            Ok(stamp) => stamp,
+            Err(_) => return search_source(file, content, matching, opts),
+        };
+        let hash = fnv1a(content.as_bytes());
+        let path = self.entry_path(file);
+        if !self.refresh {
+            if let Some(entries) = read_entries(&path, file, stamp, hash) {
+                return Ok(matching_entries(&entries, file, matching, opts));
+            }
+        }
+
+        let everything = search_source(
+            file,
+            content,
+            &Regex::new("")?,
+            &extracting_everything(opts),
+        )?;
+        let entries = everything.iter().map(Entry::new).collect::<Vec<_>>();
+        if let Err(err) = write_atomically(&path, &write_entries(file, stamp, hash, &entries)) {
+            log::info!("not caching {}: {}", file.display(), err);
+        }
+        Ok(matching_entries(&entries, file, matching, opts))
+    }
+
+    /// Where the entry for `file` goes, by its absolute path, so that the same file searched from
+    /// different directories is only cached once
+    fn entry_path(&self, file: &Path) -> PathBuf {
+        let absolute = match &self.cwd {
+            Some(cwd) => cwd.join(file),
+            None => file.to_path_buf(),
+        };
+        let hash = fnv1a(absolute.to_string_lossy().as_bytes());
+        self.dir.join(format!("{:016x}.entries", hash))
+    }
+}
+
+/// The contents of the cache entry for `file`, with `stamp` and the content hash `hash`
+fn write_entries(file: &Path, stamp: Stamp, hash: u64, entries: &[Entry]) -> String {
+    let mut out = format!("{} {}\n", HEADER, CACHE_VERSION);
+    let _ = writeln!(out, "file\t{}", escape(&file.to_string_lossy()));
+    let _ = writeln!(
+        out,
+        "stamp\t{}\t{}\t{}\t{:016x}",
+        stamp.secs, stamp.nanos, stamp.size, hash
+    );
+    for entry in entries {
+        write_entry(&mut out, entry);
+    }
+    out
+}
+
+/// The definitions in the cache entry at `path`, if it's there and is for `file` as it is now,
+/// with `stamp` and the content hash `hash`. One that can't be read is as good as not being
+/// there, since it's made again.
+fn read_entries(path: &Path, file: &Path, stamp: Stamp, hash: u64) -> Option<Vec<Entry>> {
+    let content = fs::read_to_string(path).ok()?;
+    let mut lines = content
+        .lines()
+        .map(|line| line.split('\t').map(unescape).collect::<Vec<_>>());
+
+    if lines.next()? != [format!("{} {}", HEADER, CACHE_VERSION)] {
+        return None;
+    }
+    // the file is needed as it was given, since the names of files that are functions are
+    // made from it, and another path can hash the same
+    if lines.next()? != ["file".to_string(), file.to_string_lossy().into_owned()] {
+        return None;
+    }
+    let fresh = vec![
+        "stamp".to_string(),
+        stamp.secs.to_string(),
+        stamp.nanos.to_string(),
+        stamp.size.to_string(),
+        format!("{:016x}", hash),
+    ];
+    if lines.next()? != fresh {
+        log::info!("{} changed since it was cached", file.display());
+        return None;
+    }
+
+    let mut entries = Vec::new();
+    for fields in lines {
+        let fields = fields.iter().map(String::as_str).collect::<Vec<_>>();
+        match read_entry_line(&mut entries, &fields) {
+            Some(Ok(())) => (),
+            Some(Err(why)) => {
+                log::info!("{}: {}", path.display(), why);
+                return None;
+            }
+            None => {
+                log::info!("{}: unknown line", path.display());
+                return None;
+            }
+        }
+    }
+    Some(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::search::search_file;
+
+    #[test]
+    fn test_cache() {
+        let dir = env::temp_dir().join(format!("nix-doc-cache-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("lists.nix");
+        fs::write(
+            &file,
+            "{\n  # Maps\n  #\n  # @since 21.05\n  map = f: xs: xs;\n  # Sums\n  sum = xs: 0;\n}\n",
+        )
+        .unwrap();
+
+        let cache = FileCache::at(dir.join("cache"));
+        let cached = SearchOptions {
+            cache: Some(cache.clone()),
+            ..SearchOptions::default()
+        };
+        let search = |matching: &str, opts: &SearchOptions| {
+            let results = search_file(&file, &Regex::new(matching).unwrap(), opts).unwrap();
+            results
+                .iter()
+                .map(|res| {
+                    let tags = res.tags.iter().map(|tag| tag.text.as_str());
+                    let tags = tags.collect::<Vec<_>>().join(",");
+                    format!("{}:{}:{}:{}", res.identifier, res.start.line, res.doc, tags)
+                })
+                .collect::<Vec<_>>()
+        };
+        // the first search caches everything, not just what it matched
+        assert_eq!(search("^map$", &cached), ["map:5:Maps:21.05"]);
+        let path = cache.entry_path(&file);
+        // a file is cached once, however it's named
+        let cwd = env::current_dir().unwrap();
+        assert_eq!(
+            cache.entry_path(Path::new("lists.nix")),
+            cache.entry_path(&cwd.join("lists.nix"))
+        );
+        let stamp = Stamp::of(&file).unwrap();
+        let hash = fnv1a(fs::read(&file).unwrap().as_slice());
+        assert_eq!(read_entries(&path, &file, stamp, hash).unwrap().len(), 2);
+        assert_eq!(search("", &cached), search("", &SearchOptions::default()));
+
+        // what's cached is what's used while the file is the same...
+        let entry = fs::read_to_string(&path).unwrap();
+        fs::write(&path, entry.replace("Sums", "Adds")).unwrap();
+        assert_eq!(search("sum", &cached), ["sum:7:Adds:"]);
+        // ...unless it's refreshed, which caches it again
+        let refreshed = SearchOptions {
+            cache: Some(cache.clone().refresh(true)),
+            ..SearchOptions::default()
+        };
+        assert_eq!(search("sum", &refreshed), ["sum:7:Sums:"]);
+        assert_eq!(fs::read_to_string(&path).unwrap(), entry);
+
+        // changed files are parsed again
+        fs::write(&file, "{\n  # Sums up\n  sum = xs: 0;\n}\n").unwrap();
+        assert_eq!(search("sum", &cached), ["sum:3:Sums up:"]);
+        // and so is a file that's cached under another path, or with a broken entry
+        assert_eq!(
+            read_entries(&path, Path::new("lists.nix"), stamp, hash),
+            None
+        );
+        let entry = fs::read_to_string(&path).unwrap();
+        fs::write(&path, entry.replace("def\t", "bad\t")).unwrap();
+        assert_eq!(search("sum", &cached), ["sum:3:Sums up:"]);
+        // searches an index couldn't answer don't use it
+        fs::write(&path, "").unwrap();
+        let fuzzy = SearchOptions {
+            fuzzy: Some("sm".to_string()),
+            ..cached
+        };
+        assert_eq!(search("", &fuzzy), ["sum:3:Sums up:"]);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,154 @@
+//! The lines of nix files changed since a git ref, for only searching what a change touched
+use crate::{Error, Result};
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Which lines of which files are different from a git ref, as ranges of 1-based line numbers
+/// in the files as they are now
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ChangedLines {
+    /// By canonical path. Files that were only deleted from aren't here, having nothing left
+    /// where they were changed.
+    files: BTreeMap<PathBuf, Vec<Range<usize>>>,
+}
+
+impl ChangedLines {
+    /// The `.nix` files changed in the work tree since `gitref`, going by `git diff` in the
+    /// repository `dir` is in
+    pub fn since(gitref: &str, dir: &Path) -> Result<Self> {
+        let root = git(dir, &["rev-parse", "--show-toplevel"])?;
+        let diff = git(
+            dir,
+            &[
+                "diff",
+                "--unified=0",
+                "--no-color",
+                "--no-ext-diff",
+                "--no-renames",
+                gitref,
+                "--",
+                "*.nix",
+            ],
+        )?;
+        Ok(Self::from_diff(&diff, Path::new(root.trim())))
+    }
+
+    /// Reads the hunks out of a `git diff --unified=0` whose paths are relative to `root`
+    pub fn from_diff(diff: &str, root: &Path) -> Self {
+        let mut files = BTreeMap::<PathBuf, Vec<Range<usize>>>::new();
+        let mut file = None;
+        for line in diff.lines() {
+            if let Some(path) = line.strip_prefix("+++ ") {
+                file = path
+                    .strip_prefix("b/")
+                    .map(|path| canonical(&root.join(path)));
+            } else if let (Some(file), Some(hunk)) = (&file, line.strip_prefix("@@ ")) {
+                if let Some(lines) = new_lines(hunk).filter(|lines| !lines.is_empty()) {
+                    files.entry(file.clone()).or_default().push(lines);
+                }
+            }
+        }
+        ChangedLines { files }
+    }
+
+    /// The changed lines of `file`, none if it wasn't changed
+    pub fn lines(&self, file: &Path) -> &[Range<usize>] {
+        self.files
+            .get(&canonical(file))
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// Was `line` of `file` changed?
+    pub fn contains(&self, file: &Path, line: usize) -> bool {
+        self.lines(file).iter().any(|lines| lines.contains(&line))
+    }
+}
+
+/// The lines a hunk header, after the `@@ `, says it covers in the new file, like `12,3` for
+/// `-10,2 +12,3 @@`. A count of 0 is a hunk that only deletes lines.
+fn new_lines(hunk: &str) -> Option<Range<usize>> {
+    let new = hunk.split(' ').find_map(|part| part.strip_prefix('+'))?;
+    let (start, count) = match new.split_once(',') {
+        Some((start, count)) => (start.parse::<usize>().ok()?, count.parse::<usize>().ok()?),
+        None => (new.parse().ok()?, 1),
+    };
+    Some(start..start + count)
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Runs git in `dir`, returning what it printed
+pub(crate) fn git(dir: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .map_err(|err| Error::io(Path::new("git"), err))?;
+    // most likely a ref that doesn't exist, or a directory that isn't in a repository
+    if !output.status.success() {
+        return Err(Error::InvalidConfig(format!(
+            "git {} failed: {}",
+            args[0],
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_diff() {
+        let diff = "\
+diff --git a/lib/lists.nix b/lib/lists.nix
+index 1111111..2222222 100644
+--- a/lib/lists.nix
++++ b/lib/lists.nix
+@@ -10,0 +11,3 @@ rec {
++  # Adds one
++  inc = x: x + 1;
++
+@@ -40,2 +43 @@ rec {
+-  old = 1;
+-  older = 2;
++  new = 1;
+@@ -60 +62,0 @@ rec {
+-  gone = 3;
+diff --git a/old.nix b/old.nix
+deleted file mode 100644
+--- a/old.nix
++++ /dev/null
+@@ -1,2 +0,0 @@
+-{
+-}
+";
+        let root = Path::new("/nonexistent/repo");
+        let changed = ChangedLines::from_diff(diff, root);
+        let lists = root.join("lib/lists.nix");
+        assert_eq!(changed.lines(&lists), [11..14, 43..44]);
+        assert!(changed.contains(&lists, 12));
+        assert!(changed.contains(&lists, 43));
+        assert!(!changed.contains(&lists, 14));
+        // only deleted from
+        assert!(!changed.contains(&lists, 62));
+        assert!(changed.lines(&root.join("old.nix")).is_empty());
+    }
+
+    #[test]
+    fn test_new_lines() {
+        assert_eq!(new_lines("-10,2 +12,3 @@ rec {"), Some(12..15));
+        assert_eq!(new_lines("-1 +1 @@"), Some(1..2));
+        assert_eq!(new_lines("-5,1 +4,0 @@"), Some(4..4));
+        assert_eq!(new_lines("nonsense"), None);
+    }
+}
@@ -0,0 +1,58 @@
+//! The colors of human-readable output, from `colorful` with the `color` feature, or the same
+//! methods doing nothing without it
+#[cfg(feature = "color")]
+pub(crate) use colorful::{Color, Colorful};
+
+#[cfg(not(feature = "color"))]
+pub(crate) use plain::{Color, Colorful};
+
+#[cfg(not(feature = "color"))]
+mod plain {
+    /// The colors that get named outside of the [`Colorful`] methods
+    #[derive(Clone, Copy, Debug)]
+    pub(crate) enum Color {
+        Grey27,
+    }
+
+    /// The `colorful::Colorful` methods nix-doc uses, leaving the text as it is
+    pub(crate) trait Colorful: Sized {
+        fn plain(self) -> String;
+
+        fn color(self, _color: Color) -> String {
+            self.plain()
+        }
+        fn bold(self) -> String {
+            self.plain()
+        }
+        fn white(self) -> String {
+            self.plain()
+        }
+        fn red(self) -> String {
+            self.plain()
+        }
+        fn yellow(self) -> String {
+            self.plain()
+        }
+        fn blue(self) -> String {
+            self.plain()
+        }
+        fn green(self) -> String {
+            self.plain()
+        }
+        fn dark_gray(self) -> String {
+            self.plain()
+        }
+    }
+
+    impl Colorful for &str {
+        fn plain(self) -> String {
+            self.to_string()
+        }
+    }
+
+    impl Colorful for String {
+        fn plain(self) -> String {
+            self
+        }
+    }
+}
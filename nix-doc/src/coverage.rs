@@ -0,0 +1,286 @@
+//! Documentation coverage reports: how many of the functions in a tree have doc comments
+use crate::filter::FileFilter;
+use crate::output::Output;
+use crate::parse::{is_blank_source, parse_nix};
+use crate::search::{search_ast_with_skips, SkipReason};
+#[cfg(feature = "fs")]
+use crate::walk::walk_files;
+use crate::walk::{file_attr_path, is_too_large};
+use crate::{OutputFormat, Result, SearchOptions};
+
+use regex::Regex;
+#[cfg(feature = "serde")]
+use serde::Serialize;
+
+use std::collections::BTreeMap;
+use std::fmt::{self, Display};
+use std::fs;
+use std::io::Write;
+use std::path::{Component, Path};
+use std::str;
+use std::sync::mpsc::channel;
+
+/// How a coverage report is broken down
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum CoverageBy {
+    /// A line per file
+    #[default]
+    File,
+    /// A line per directory directly under the one searched, with files at the top together
+    /// under `.`
+    Dir,
+}
+
+impl str::FromStr for CoverageBy {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "file" => Ok(CoverageBy::File),
+            "dir" => Ok(CoverageBy::Dir),
+            _ => Err(format!(
+                "unknown coverage breakdown `{}`, expected one of: file, dir",
+                s
+            )),
+        }
+    }
+}
+
+/// How many functions were found, and how many of them are documented
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct Coverage {
+    pub documented: usize,
+    pub total: usize,
+}
+
+impl Coverage {
+    fn add(&mut self, other: Coverage) {
+        self.documented += other.documented;
+        self.total += other.total;
+    }
+}
+
+impl Display for Coverage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{} documented", self.documented, self.total)?;
+        if let Some(percent) = (self.documented * 100).checked_div(self.total) {
+            write!(f, " ({}%)", percent)?;
+        }
+        Ok(())
+    }
+}
+
+/// Counts the documented and undocumented functions matching `matching` in the nix source
+/// `content`. Only the pattern, fuzzy or not, [`SearchOptions::def_kinds`] and what counts as a
+/// doc comment apply: the other filters only ever look at documented functions, so counting with
+/// them would skew the numbers.
+pub fn source_coverage(content: &str, matching: &Regex, opts: &SearchOptions) -> Result<Coverage> {
+    if is_blank_source(content) {
+        return Ok(Coverage::default());
+    }
+    let ast = parse_nix(content)?;
+    let opts = SearchOptions {
+        explain_skips: true,
+        def_kinds: opts.def_kinds.clone(),
+        any_key: opts.any_key,
+        fuzzy: opts.fuzzy.clone(),
+        doc_prefix: opts.doc_prefix.clone(),
+        ..SearchOptions::default()
+    };
+    let (results, skips) = search_ast_with_skips(matching, &ast, &opts);
+    let undocumented = skips
+        .iter()
+        .filter(|skip| matches!(skip.reason, SkipReason::NoComment))
+        .count();
+    Ok(Coverage {
+        documented: results.len(),
+        total: results.len() + undocumented,
+    })
+}
+
+/// Which line of the report `file` counts towards
+#[cfg(feature = "fs")]
+fn group_of(root: &Path, file: &Path, by: CoverageBy) -> String {
+    let relative = file.strip_prefix(root).unwrap_or(file);
+    match by {
+        CoverageBy::File => file.display().to_string(),
+        CoverageBy::Dir => {
+            let mut components = relative.components();
+            match (components.next(), components.next()) {
+                (Some(Component::Normal(dir)), Some(_)) => root.join(dir).display().to_string(),
+                _ => ".".to_string(),
+            }
+        }
+    }
+}
+
+#[cfg(all(feature = "serde", feature = "fs"))]
+#[derive(Serialize)]
+struct Report<'a> {
+    groups: Vec<Group<'a>>,
+    #[serde(flatten)]
+    total: Coverage,
+}
+
+#[cfg(all(feature = "serde", feature = "fs"))]
+#[derive(Serialize)]
+struct Group<'a> {
+    name: &'a str,
+    #[serde(flatten)]
+    coverage: Coverage,
+}
+
+/// Prints a report of how many of the functions in `dir` matching `matching` are documented,
+/// broken down `by` file or directory, then in total. Files without any functions are left out.
+#[cfg(feature = "fs")]
+pub fn coverage<F>(
+    dir: &Path,
+    matching: Regex,
+    opts: SearchOptions,
+    by: CoverageBy,
+    should_search: F,
+) -> Result<()>
+where
+    F: FileFilter,
+{
+    // open it first, so a bad path fails before any counting is done
+    let mut out = Output::open(opts.output.as_deref())?;
+    let pool = opts.thread_pool();
+    let (tx, rx) = channel();
+
+    let files = walk_files(dir, false, &opts.dirs, &should_search)
+        .filter(|entry| opts.path_could_match(&file_attr_path(dir, entry.path())))
+        .take_while(|_| !opts.is_cancelled());
+    for direntry in files {
+        let my_tx = tx.clone();
+        let matching = matching.clone();
+        let opts = opts.clone();
+        pool.push(move || {
+            let path = direntry.path();
+            let counted = match is_too_large(path) {
+                _ if opts.is_cancelled() => Ok(Coverage::default()),
+                Ok(true) => Ok(Coverage::default()),
+                Ok(false) => fs::read_to_string(path)
+                    .map_err(Into::into)
+                    .and_then(|content| source_coverage(&content, &matching, &opts)),
+                Err(err) => Err(err),
+            };
+            match counted {
+                Ok(counted) => my_tx
+                    .send((direntry.into_path(), counted))
+                    .expect("failed to send coverage to display"),
+                Err(err) => err.in_file(path).warn(opts.format),
+            }
+        });
+    }
+    drop(tx);
+    pool.done();
+
+    let mut groups = BTreeMap::<String, Coverage>::new();
+    let mut total = Coverage::default();
+    for (file, counted) in rx {
+        if counted.total == 0 || !opts.file_matches(&file) {
+            continue;
+        }
+        groups
+            .entry(group_of(dir, &file, by))
+            .or_default()
+            .add(counted);
+        total.add(counted);
+    }
+
+    match opts.format {
+        OutputFormat::Human | OutputFormat::Table => {
+            for (name, coverage) in &groups {
+                writeln!(out, "{}: {}", name, coverage)?;
+            }
+            writeln!(out, "total: {}", total)?;
+        }
+        #[cfg(feature = "serde")]
+        OutputFormat::Json => {
+            let report = Report {
+                groups: groups
+                    .iter()
+                    .map(|(name, &coverage)| Group { name, coverage })
+                    .collect(),
+                total,
+            };
+            writeln!(out, "{}", serde_json::to_string(&report)?)?;
+        }
+        OutputFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(&mut out);
+            writer.write_record(["name", "documented", "total"])?;
+            for (name, coverage) in &groups {
+                writer.write_record([
+                    name.as_str(),
+                    &coverage.documented.to_string(),
+                    &coverage.total.to_string(),
+                ])?;
+            }
+            writer.flush()?;
+        }
+    }
+    Ok(out.flush()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_source_coverage() {
+        let src = r#"{
+          # documented
+          a = x: x;
+          b = x: x;
+          c = { y }: y;
+          # not a function
+          d = 1;
+          # filtered out of normal searches, but still counted
+          e = a: b: c: a;
+          inherit (lib) f;
+        }"#;
+        let opts = SearchOptions {
+            arity: Some(crate::ArityRange::exactly(1)),
+            ..SearchOptions::default()
+        };
+        let all = Regex::new("").unwrap();
+        assert_eq!(
+            source_coverage(src, &all, &opts).unwrap(),
+            Coverage {
+                documented: 2,
+                total: 4
+            }
+        );
+        assert_eq!(
+            source_coverage(src, &Regex::new("^[ab]$").unwrap(), &opts).unwrap(),
+            Coverage {
+                documented: 1,
+                total: 2
+            }
+        );
+        assert_eq!(
+            Coverage {
+                documented: 2,
+                total: 4
+            }
+            .to_string(),
+            "2/4 documented (50%)"
+        );
+        assert_eq!(Coverage::default().to_string(), "0/0 documented");
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn test_group_of() {
+        let root = Path::new("/n");
+        let file = Path::new("/n/lib/strings.nix");
+        assert_eq!(group_of(root, file, CoverageBy::File), "/n/lib/strings.nix");
+        assert_eq!(group_of(root, file, CoverageBy::Dir), "/n/lib");
+        assert_eq!(
+            group_of(root, Path::new("/n/default.nix"), CoverageBy::Dir),
+            "."
+        );
+    }
+}
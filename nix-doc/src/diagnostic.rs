@@ -0,0 +1,150 @@
+//! Parse errors shown the way compilers show them: the lines leading up to the error, with a
+//! caret under where it is, rather than just a line saying what it was
+use crate::color::Colorful;
+use crate::lines::Position;
+use crate::output::strip_colors;
+
+#[cfg(feature = "serde")]
+use serde::Serialize;
+
+use std::fmt::Write;
+use std::iter;
+use std::path::Path;
+
+/// How many lines before the one with the error are shown with it
+const CONTEXT_LINES: usize = 2;
+
+/// The lines of a file leading up to an error, kept with it for showing them once the file is
+/// gone. In JSON output, the errors have these as they are, for tools to show them their own
+/// way.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct Snippet {
+    /// The number of the first line, 1-based
+    pub first_line: usize,
+    /// The lines, without their line endings, the last being the one with the error
+    pub lines: Vec<String>,
+}
+
+impl Snippet {
+    /// The lines of `content` up to the one `position` is on
+    pub fn new(content: &str, position: &Position) -> Self {
+        let first_line = position.line.saturating_sub(CONTEXT_LINES).max(1);
+        // the end of a file ending in a newline is on a line of its own, with nothing on it
+        let lines = content
+            .lines()
+            .chain(iter::repeat(""))
+            .skip(first_line - 1)
+            .take(position.line + 1 - first_line)
+            .map(str::to_string)
+            .collect();
+        Snippet { first_line, lines }
+    }
+
+    /// Shows `message`, about `position` in `path`, over the lines with a caret under the
+    /// position, colored if `color` is set:
+    ///
+    /// ```text
+    /// error: can't parse: unexpected TOKEN_SEMICOLON at 16..17
+    ///  --> lib/x.nix:3:7
+    ///   |
+    /// 1 | {
+    /// 2 |   a = 1;
+    /// 3 |   b = ;
+    ///   |       ^
+    /// ```
+    pub fn render(
+        &self,
+        message: &str,
+        path: Option<&Path>,
+        position: &Position,
+        color: bool,
+    ) -> String {
+        let last_line = self.first_line + self.lines.len().saturating_sub(1);
+        let width = last_line.to_string().len();
+        let gutter = |number: &str| format!("{:>width$} |", number, width = width).blue();
+
+        let mut out = String::new();
+        let _ = writeln!(out, "{}: {}", "error".red().bold(), message);
+        let location = match path {
+            Some(path) => format!("{}:", path.display()),
+            None => String::new(),
+        };
+        let _ = writeln!(
+            out,
+            "{}{} {}{}:{}",
+            " ".repeat(width),
+            "-->".blue(),
+            location,
+            position.line,
+            position.col_chars
+        );
+        let _ = writeln!(out, "{}", gutter(""));
+        for (number, line) in (self.first_line..).zip(&self.lines) {
+            let _ = writeln!(out, "{} {}", gutter(&number.to_string()), line);
+        }
+        // tabs before the caret are kept, so that it lines up however wide they're shown
+        let before = self.lines.last().map_or("", String::as_str);
+        let padding = before
+            .chars()
+            .take(position.col_chars.saturating_sub(1))
+            .map(|ch| if ch == '\t' { '\t' } else { ' ' })
+            .collect::<String>();
+        let _ = write!(out, "{} {}{}", gutter(""), padding, "^".red().bold());
+        match color {
+            true => out,
+            false => strip_colors(&out),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::lines::LineIndex;
+
+    fn position(content: &str, offset: usize) -> Position {
+        LineIndex::new(content).position(content, offset)
+    }
+
+    #[test]
+    fn test_snippet() {
+        let content = "{\n  a = 1;\n  b = 2;\n\tc = ;\n}\n";
+        let at = position(content, content.find("\tc = ;").unwrap() + 5);
+        assert_eq!((at.line, at.col_chars), (4, 6));
+        let snippet = Snippet::new(content, &at);
+        assert_eq!(snippet.first_line, 2);
+        assert_eq!(snippet.lines, ["  a = 1;", "  b = 2;", "\tc = ;"]);
+        assert_eq!(
+            snippet.render("can't parse: it", Some(Path::new("x.nix")), &at, false),
+            "\
+error: can't parse: it
+ --> x.nix:4:6
+  |
+2 |   a = 1;
+3 |   b = 2;
+4 | \tc = ;
+  | \t    ^"
+        );
+        let colored = snippet.render("can't parse: it", None, &at, true);
+        assert!(strip_colors(&colored).contains(" --> 4:6\n"));
+        #[cfg(feature = "color")]
+        assert_ne!(strip_colors(&colored), colored);
+    }
+
+    #[test]
+    fn test_snippet_edges() {
+        let at = position("{", 1);
+        assert_eq!(Snippet::new("{", &at).lines, ["{"]);
+        // the end of a file ending in a newline
+        let content = "{\n  a = 1;\n";
+        let at = position(content, content.len());
+        assert_eq!(at.line, 3);
+        let snippet = Snippet::new(content, &at);
+        assert_eq!(snippet.lines, ["{", "  a = 1;", ""]);
+        assert!(snippet
+            .render("m", None, &at, false)
+            .ends_with("3 | \n  | ^"));
+    }
+}
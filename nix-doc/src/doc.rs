@@ -0,0 +1,405 @@
+//! Structure found inside of cleaned up doc comments
+#[cfg(feature = "serde")]
+use serde::Serialize;
+
+use std::fmt::Write;
+
+/// One `@tag text` line (plus its continuation lines) of a doc comment, such as
+/// `@param f The function to apply`
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct DocTag {
+    /// Name of the tag, without the `@`
+    pub tag: String,
+
+    /// Everything after the tag name, with continuation lines joined by spaces
+    pub text: String,
+}
+
+/// Splits the `@tag` lines out of a cleaned doc comment, returning the remaining description and
+/// the tags in the order they were written. Tags that nix-doc doesn't know about are kept as-is.
+///
+/// A tag runs from its `@name` line until a blank line or the next tag.
+pub fn split_tags(doc: &str) -> (String, Vec<DocTag>) {
+    let mut description = Vec::new();
+    let mut tags: Vec<DocTag> = Vec::new();
+    let mut in_tag = false;
+
+    for line in doc.lines() {
+        let trimmed = line.trim();
+        if let Some(tag) = parse_tag_line(trimmed) {
+            tags.push(tag);
+            in_tag = true;
+        } else if trimmed.is_empty() {
+            in_tag = false;
+            description.push(line);
+        } else if in_tag {
+            let tag = tags.last_mut().unwrap();
+            if !tag.text.is_empty() {
+                tag.text.push(' ');
+            }
+            tag.text.push_str(trimmed);
+        } else {
+            description.push(line);
+        }
+    }
+
+    if tags.is_empty() {
+        return (doc.to_string(), tags);
+    }
+
+    let description = description.join("\n");
+    (description.trim_end().to_string(), tags)
+}
+
+fn parse_tag_line(line: &str) -> Option<DocTag> {
+    let rest = line.strip_prefix('@')?;
+    let name_len = rest
+        .find(|ch: char| !(ch.is_alphanumeric() || ch == '_' || ch == '-'))
+        .unwrap_or(rest.len());
+    if name_len == 0 {
+        return None;
+    }
+    Some(DocTag {
+        tag: rest[..name_len].to_string(),
+        text: rest[name_len..].trim().to_string(),
+    })
+}
+
+/// Renders tags as a list with the text column aligned, e.g.
+///
+/// ```text
+/// @param  f The function to apply
+/// @return the concatenated string
+/// ```
+pub fn format_tags(tags: &[DocTag]) -> String {
+    let width = tags.iter().map(|t| t.tag.len()).max().unwrap_or(0);
+    let mut out = String::new();
+    for (i, tag) in tags.iter().enumerate() {
+        if i != 0 {
+            out.push('\n');
+        }
+        write!(out, "@{:width$} {}", tag.tag, tag.text, width = width).unwrap();
+    }
+    out
+}
+
+/// Does a cleaned doc comment, with its `tags`, have an example in it? Both the older nixpkgs
+/// convention of an `Example:` line and the RFC 145 ones of an `# Example(s)` heading or a
+/// `:::{.example}` block count, as does an `@example` tag. Lines inside code fences don't, since
+/// they're code.
+pub fn has_example(doc: &str, tags: &[DocTag]) -> bool {
+    if tags.iter().any(|tag| tag.tag == "example") {
+        return true;
+    }
+    let mut in_fence = false;
+    doc.lines().map(str::trim).any(|line| {
+        if line.starts_with("```") {
+            in_fence = !in_fence;
+            return false;
+        }
+        // cleaning up a block comment takes the `#` off its `# Examples` heading, so a line of
+        // just the title counts as a heading too
+        let title = line.trim_start_matches('#').trim();
+        let heading = title == "Example" || title == "Examples";
+        !in_fence
+            && (line.starts_with("Example:")
+                || line.starts_with("Examples:")
+                || heading
+                || line.starts_with(":::") && line.contains(".example"))
+    })
+}
+
+/// Does a cleaned doc comment give the function's type, the nixpkgs convention being a `Type:`
+/// line or a `# Type` section with the signature in it? A section with nothing in it before the
+/// next one starts doesn't count.
+pub fn has_type(doc: &str) -> bool {
+    let mut lines = doc.lines().map(str::trim);
+    while let Some(line) = lines.next() {
+        let signature = match line.strip_prefix("Type:") {
+            Some(rest) => rest.trim(),
+            // as with examples, the `#` may have been cleaned off of the heading
+            None if line.trim_start_matches('#').trim() == "Type" => "",
+            None => continue,
+        };
+        if !signature.is_empty() {
+            return true;
+        }
+        let mut section = lines.clone().take_while(|line| !is_section_start(line));
+        if section.any(|line| !line.is_empty() && !line.starts_with("```")) {
+            return true;
+        }
+    }
+    false
+}
+
+/// The parameters a cleaned doc comment talks about
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DocParams {
+    /// Names documented as parameters, by `@param name` tags or entries of an RFC 145 `# Inputs`
+    /// (or `# Arguments`) section, like `` `sep` `` on a line of its own. In the order written.
+    pub listed: Vec<String>,
+
+    /// Every word in backticks, like `` `list` ``, wherever it is. These often name other
+    /// functions, so they can only show that a parameter is mentioned, not that one is missing.
+    pub mentioned: Vec<String>,
+}
+
+/// Finds the parameters that a cleaned doc comment, with its `tags`, documents or mentions
+pub fn doc_params(doc: &str, tags: &[DocTag]) -> DocParams {
+    let mut params = DocParams::default();
+    for tag in tags.iter().filter(|tag| tag.tag == "param") {
+        // skip a JSDoc style type, as in `@param {string} sep`
+        let name = tag
+            .text
+            .split_whitespace()
+            .find(|word| !word.starts_with('{') && !word.ends_with('}'));
+        if let Some(name) = name.filter(|name| is_param_name(name)) {
+            push_new(&mut params.listed, name);
+        }
+    }
+
+    let mut in_inputs = false;
+    let mut in_fence = false;
+    for line in doc.lines().map(str::trim) {
+        if line.starts_with("```") {
+            in_fence = !in_fence;
+            continue;
+        }
+        if in_fence {
+            continue;
+        }
+        let title = line.trim_start_matches('#').trim().trim_end_matches(':');
+        if title == "Inputs" || title == "Arguments" {
+            in_inputs = true;
+            continue;
+        }
+        let entry = line.trim_start_matches(['-', '*']).trim_start();
+        if in_inputs {
+            match backticked(entry).next() {
+                Some((name, rest))
+                    if entry.starts_with('`')
+                        && (rest.is_empty() || rest.starts_with([':', '-'])) =>
+                {
+                    push_new(&mut params.listed, name);
+                }
+                // the rest of a definition, `: The separator`, or a description of one
+                _ if line.starts_with(':') || line.is_empty() || !is_section_start(line) => (),
+                _ => in_inputs = false,
+            }
+        }
+        for (name, _) in backticked(line) {
+            push_new(&mut params.mentioned, name);
+        }
+    }
+    params
+}
+
+/// The names in backticks in `line` that could be parameters, each with the text after it
+fn backticked(line: &str) -> impl Iterator<Item = (&str, &str)> {
+    let mut rest = line;
+    std::iter::from_fn(move || loop {
+        let start = rest.find('`')? + 1;
+        let len = rest[start..].find('`')?;
+        let name = &rest[start..start + len];
+        rest = &rest[start + len + 1..];
+        if is_param_name(name) {
+            return Some((name, rest.trim()));
+        }
+    })
+}
+
+/// Could `name` be the name of a parameter?
+fn is_param_name(name: &str) -> bool {
+    name.starts_with(|ch: char| ch.is_alphabetic() || ch == '_')
+        && name
+            .chars()
+            .all(|ch| ch.is_alphanumeric() || matches!(ch, '_' | '\'' | '-'))
+}
+
+fn push_new(names: &mut Vec<String>, name: &str) {
+    if !names.iter().any(|known| known == name) {
+        names.push(name.to_string());
+    }
+}
+
+/// A headed part of a cleaned doc comment, like the `# Type` or `Example:` sections of nixpkgs
+/// docs
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct DocSection {
+    /// The heading, without any `#`s or trailing `:`
+    pub title: String,
+
+    /// Everything after the heading until the next one, without blank lines at either end. A
+    /// `Type: f :: a` line's signature is the first line.
+    pub body: String,
+}
+
+/// Splits the sections out of a cleaned doc comment, in the order written. The description before
+/// the first heading isn't a section, and neither is anything that looks like a heading inside a
+/// code fence.
+pub fn doc_sections(doc: &str) -> Vec<DocSection> {
+    let mut sections = Vec::<DocSection>::new();
+    let mut in_fence = false;
+    for line in doc.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with("```") {
+            in_fence = !in_fence;
+        }
+        match section_heading(trimmed).filter(|_| !in_fence) {
+            Some((title, rest)) => sections.push(DocSection {
+                title: title.to_string(),
+                body: rest.to_string(),
+            }),
+            None => {
+                if let Some(section) = sections.last_mut() {
+                    section.body.push('\n');
+                    section.body.push_str(line);
+                }
+            }
+        }
+    }
+    for section in &mut sections {
+        section.body = section.body.trim_matches('\n').trim_end().to_string();
+    }
+    sections
+}
+
+/// The title of the section the (trimmed) doc line is the heading of, and what follows the
+/// heading on the same line, if it is one
+fn section_heading(line: &str) -> Option<(&str, &str)> {
+    if line.starts_with('#') {
+        let title = line.trim_start_matches('#').trim();
+        return Some((title, "")).filter(|_| !title.is_empty());
+    }
+    let (title, rest) = line.split_once(':')?;
+    let rest = rest.trim();
+    let is_title = !title.is_empty() && !title.contains(|ch: char| ch.is_whitespace() || ch == '`');
+    Some((title, rest)).filter(|_| is_title && (rest.is_empty() || title == "Type"))
+}
+
+/// Does the (trimmed) doc line start a new section, with a heading or a `Name:` line?
+fn is_section_start(line: &str) -> bool {
+    const SECTIONS: &[&str] = &["Type", "Inputs", "Arguments", "Example", "Examples"];
+    let title = line.trim_start_matches('#').trim().trim_end_matches(':');
+    line.starts_with('#')
+        || SECTIONS.contains(&title)
+        || line.ends_with(':') && !title.is_empty() && !title.contains(char::is_whitespace)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tag(tag: &str, text: &str) -> DocTag {
+        DocTag {
+            tag: tag.to_string(),
+            text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_split_tags() {
+        let doc = "Applies f to each element.\n\n@param f The function\n  to apply\n@param list The list\n@return the new list\n@since 23.05";
+        let (desc, tags) = split_tags(doc);
+        assert_eq!(desc, "Applies f to each element.");
+        assert_eq!(
+            tags,
+            [
+                tag("param", "f The function to apply"),
+                tag("param", "list The list"),
+                tag("return", "the new list"),
+                tag("since", "23.05"),
+            ]
+        );
+        assert_eq!(
+            format_tags(&tags),
+            "@param  f The function to apply\n@param  list The list\n@return the new list\n@since  23.05"
+        );
+    }
+
+    #[test]
+    fn test_has_example() {
+        assert!(has_example("Maps.\n\nExample:\n  map f [ 1 ]", &[]));
+        assert!(has_example(
+            "Maps.\n\n# Examples\n\n```nix\nmap f [ 1 ]\n```",
+            &[]
+        ));
+        assert!(has_example(
+            "Maps.\n\n:::{.example}\n## `map` usage\n:::",
+            &[]
+        ));
+        assert!(has_example("Maps.", &[tag("example", "map f [ 1 ]")]));
+        assert!(!has_example("Maps.\n\n# Type\n\n```\n# Example\n```", &[]));
+        assert!(!has_example("An example of mapping", &[]));
+    }
+
+    #[test]
+    fn test_has_type() {
+        assert!(has_type(
+            "Joins.\n\nType: concatStringsSep :: string -> [string] -> string"
+        ));
+        assert!(has_type("Does things\n\n# Type\n\n```\nf :: a\n```"));
+        assert!(has_type("Does things\n\nType\n\n    f :: a"));
+        assert!(has_type("Does things\n\nType:\n  f :: a"));
+        assert!(!has_type("Types are checked"));
+        assert!(!has_type("Does things\n\nType:   \n\nExample:\n  f 1"));
+        assert!(!has_type(
+            "Does things\n\n# Type\n\n```\n```\n\n# Examples\n\n    f 1"
+        ));
+        assert!(!has_type("Does things\n\nType\n\nExamples\n\n    f 1"));
+    }
+
+    #[test]
+    fn test_doc_params() {
+        let rfc145 = "Concatenates `list` with `sep` between, like `lib.join` would.\n\n# Inputs\n\n`sep`\n: The separator\n\n`list`\n: The strings, see `toString`\n\n# Examples\n\n```nix\n`x`\n```";
+        assert_eq!(
+            doc_params(rfc145, &[]),
+            DocParams {
+                listed: vec!["sep".to_string(), "list".to_string()],
+                mentioned: ["list", "sep", "toString"]
+                    .iter()
+                    .map(|s| s.to_string())
+                    .collect(),
+            }
+        );
+
+        let (doc, tags) =
+            split_tags("Maps.\n\n@param {function} f The function\n@param list\n@return a list");
+        assert_eq!(doc_params(&doc, &tags).listed, ["f", "list"]);
+
+        let listed = "Inputs:\n  - `f`: the function\n  - `xs` - the list\n\nExample:\n  `y`";
+        assert_eq!(doc_params(listed, &[]).listed, ["f", "xs"]);
+        assert!(doc_params("Maps `f` over a list", &[]).listed.is_empty());
+    }
+
+    #[test]
+    fn test_doc_sections() {
+        let section = |title: &str, body: &str| DocSection {
+            title: title.to_string(),
+            body: body.to_string(),
+        };
+        assert_eq!(
+            doc_sections("Joins. See: `lib.concat`\n\nType: join :: string -> string\n\nExample:\n  join \"a\"\n\n"),
+            [
+                section("Type", "join :: string -> string"),
+                section("Example", "  join \"a\""),
+            ]
+        );
+        assert_eq!(
+            doc_sections("Maps.\n\n# Inputs\n\n`f`\n: The function\n\n# Examples\n\n```nix\n# not a heading\n```"),
+            [
+                section("Inputs", "`f`\n: The function"),
+                section("Examples", "```nix\n# not a heading\n```"),
+            ]
+        );
+        assert!(doc_sections("Just a description").is_empty());
+    }
+
+    #[test]
+    fn test_no_tags() {
+        let doc = "Sends mail to user@example.com\n\n  @ not a tag";
+        assert_eq!(split_tags(doc), (doc.to_string(), vec![]));
+    }
+}
@@ -0,0 +1,333 @@
+//! What can go wrong searching nix files
+use crate::diagnostic::Snippet;
+use crate::lines::{LineIndex, Position};
+use crate::OutputFormat;
+
+use rnix::parser::ParseError;
+#[cfg(feature = "serde")]
+use serde::Serialize;
+
+use std::fmt::{self, Display};
+use std::io::{self, IsTerminal};
+use std::path::{Path, PathBuf};
+
+/// An error from nix-doc
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// Reading or writing something failed
+    Io {
+        /// The file, if it's known
+        path: Option<PathBuf>,
+        source: io::Error,
+    },
+    /// Nix source that doesn't parse
+    Parse {
+        /// The file the source is from, if it's known
+        path: Option<PathBuf>,
+        message: String,
+        /// Where the first error is, if it's known
+        position: Option<Position>,
+        /// The lines leading up to `position`, for showing it in
+        snippet: Option<Snippet>,
+    },
+    /// A pattern that isn't a regex
+    InvalidRegex(regex::Error),
+    /// Options that don't go together, or a value for one that doesn't work, like a lookup path
+    /// that isn't in `NIX_PATH` or a git ref that doesn't exist
+    InvalidConfig(String),
+    /// The search was cancelled (see [`crate::CancellationToken`]) before it got to this
+    Cancelled,
+}
+
+impl Error {
+    /// An error reading or writing `path`
+    pub fn io(path: &Path, source: io::Error) -> Self {
+        Error::Io {
+            path: Some(path.to_path_buf()),
+            source,
+        }
+    }
+
+    /// The error rnix found parsing `content`, which came from `path` if it's given
+    pub fn parse(err: &ParseError, content: &str, path: Option<&Path>) -> Self {
+        let offset = match err {
+            ParseError::Unexpected(range)
+            | ParseError::UnexpectedExtra(range)
+            | ParseError::UnexpectedWanted(_, range, _)
+            | ParseError::UnexpectedDoubleBind(range)
+            | ParseError::DuplicatedArgs(range, _) => Some(usize::from(range.start())),
+            ParseError::UnexpectedEOF | ParseError::UnexpectedEOFWanted(_) => Some(content.len()),
+            // nesting too deep to parse has no one place to point at, and neither may errors
+            // rnix adds later
+            _ => None,
+        };
+        let position = offset.map(|offset| LineIndex::new(content).position(content, offset));
+        Error::Parse {
+            path: path.map(Path::to_path_buf),
+            message: err.to_string(),
+            snippet: position
+                .as_ref()
+                .map(|position| Snippet::new(content, position)),
+            position,
+        }
+    }
+
+    /// The name of the variant, like `Io` or `Parse`, for telling errors apart in machine
+    /// readable output
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Error::Io { .. } => "Io",
+            Error::Parse { .. } => "Parse",
+            Error::InvalidRegex(_) => "InvalidRegex",
+            Error::InvalidConfig(_) => "InvalidConfig",
+            Error::Cancelled => "Cancelled",
+        }
+    }
+
+    /// This error, about `path` if it didn't say what it was about already
+    pub fn in_file(self, file: &Path) -> Self {
+        match self {
+            Error::Io { path: None, source } => Error::io(file, source),
+            Error::Parse {
+                path: None,
+                message,
+                position,
+                snippet,
+            } => Error::Parse {
+                path: Some(file.to_path_buf()),
+                message,
+                position,
+                snippet,
+            },
+            err => err,
+        }
+    }
+
+    /// A parse error drawn over the source around it, colored if stderr is a terminal, for
+    /// showing there alongside results in `format`. `None` for other errors, and for the machine
+    /// readable formats, whose errors have the snippet as fields of their own instead.
+    pub fn rendered(&self, format: OutputFormat) -> Option<String> {
+        match self {
+            Error::Parse {
+                path,
+                message,
+                position: Some(position),
+                snippet: Some(snippet),
+            } if format == OutputFormat::Human => {
+                let color = cfg!(feature = "color") && io::stderr().is_terminal();
+                let message = format!("can't parse: {}", message);
+                Some(snippet.render(&message, path.as_deref(), position, color))
+            }
+            _ => None,
+        }
+    }
+
+    /// Reports this on stderr as the reason a file was left out of a search printing results in
+    /// `format`, which goes on without it. Parse errors show the source around them in
+    /// human-readable output (see [`Error::rendered`]).
+    pub(crate) fn warn(&self, format: OutputFormat) {
+        match self.rendered(format) {
+            Some(rendered) => eprintln!("{}", rendered),
+            None => eprintln!("Failure handling {} ({})", self, self.kind()),
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io {
+                path: Some(path),
+                source,
+            } => write!(f, "{}: {}", path.display(), source),
+            Error::Io { path: None, source } => write!(f, "{}", source),
+            Error::Parse {
+                path,
+                message,
+                position,
+                ..
+            } => {
+                if let Some(path) = path {
+                    write!(f, "{}:", path.display())?;
+                }
+                if let Some(position) = position {
+                    write!(f, "{}:{}:", position.line, position.col_chars)?;
+                }
+                if path.is_some() || position.is_some() {
+                    write!(f, " ")?;
+                }
+                write!(f, "can't parse: {}", message)
+            }
+            Error::InvalidRegex(err) => write!(f, "{}", err),
+            Error::InvalidConfig(message) => write!(f, "{}", message),
+            Error::Cancelled => write!(f, "cancelled"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io { source, .. } => Some(source),
+            Error::InvalidRegex(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(source: io::Error) -> Self {
+        Error::Io { path: None, source }
+    }
+}
+
+impl From<regex::Error> for Error {
+    fn from(err: regex::Error) -> Self {
+        Error::InvalidRegex(err)
+    }
+}
+
+/// A file that couldn't be searched, and why, for reporting alongside the results of a search
+/// that went on without it
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct FileError {
+    pub file: PathBuf,
+    /// What went wrong, starting with the file
+    pub message: String,
+    /// The [`Error`] variant it was, like `Io` for a file that couldn't be read or `Parse` for
+    /// one that isn't valid nix (see [`Error::kind`])
+    pub kind: &'static str,
+    /// Where in the file a parse error is, if it's known
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub position: Option<Position>,
+    /// The lines leading up to `position`
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub snippet: Option<Snippet>,
+}
+
+impl FileError {
+    pub(crate) fn new(file: &Path, err: &Error) -> Self {
+        let (position, snippet) = match err {
+            Error::Parse {
+                position, snippet, ..
+            } => (*position, snippet.clone()),
+            _ => (None, None),
+        };
+        FileError {
+            file: file.to_path_buf(),
+            message: err.to_string(),
+            kind: err.kind(),
+            position,
+            snippet,
+        }
+    }
+}
+
+impl Display for FileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for FileError {}
+
+/// Writing CSV fails when writing the output does
+impl From<csv::Error> for Error {
+    fn from(err: csv::Error) -> Self {
+        io::Error::from(err).into()
+    }
+}
+
+/// Writing JSON fails when writing the output does
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Self {
+        io::Error::from(err).into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_error() {
+        let content = "{\n  a = 1;\n  b = ;\n}";
+        let err = rnix::Root::parse(content).ok().err().unwrap();
+        let err = Error::parse(&err, content, Some(Path::new("x.nix")));
+        assert_eq!(err.kind(), "Parse");
+        match &err {
+            Error::Parse {
+                position: Some(position),
+                ..
+            } => assert_eq!((position.line, position.col_chars), (3, 7)),
+            other => panic!("expected a parse error with a position, got {:?}", other),
+        }
+        assert!(err.to_string().starts_with("x.nix:3:7: can't parse: "));
+        let file_err = FileError::new(Path::new("x.nix"), &err);
+        assert_eq!(file_err.position.map(|at| at.line), Some(3));
+        assert_eq!(
+            file_err.snippet.unwrap().lines,
+            ["{", "  a = 1;", "  b = ;"]
+        );
+
+        let err = Error::parse(&err_eof(), "{", None);
+        assert!(err.to_string().starts_with("1:2: can't parse: "));
+
+        // nesting too deep for rnix to parse isn't anywhere in particular
+        let deep = format!("{}1{}", "(".repeat(600), ")".repeat(600));
+        let err = rnix::Root::parse(&deep).ok().err().unwrap();
+        let err = Error::parse(&err, &deep, None);
+        assert!(
+            matches!(
+                err,
+                Error::Parse {
+                    position: None,
+                    snippet: None,
+                    ..
+                }
+            ),
+            "{:?}",
+            err
+        );
+        assert_eq!(err.to_string(), "can't parse: recursion limit exceeded");
+    }
+
+    #[test]
+    fn test_rendered() {
+        let content = "{\n  b = ;\n}";
+        let err = rnix::Root::parse(content).ok().err().unwrap();
+        let err = Error::parse(&err, content, Some(Path::new("x.nix")));
+        // colored if the tests' stderr happens to be a terminal
+        let rendered = crate::output::strip_colors(&err.rendered(OutputFormat::Human).unwrap());
+        assert!(rendered.contains("x.nix:2:7"), "{}", rendered);
+        assert!(rendered.ends_with('^'), "{}", rendered);
+        // machine readable output has the snippet as fields of its errors instead
+        assert_eq!(err.rendered(OutputFormat::Csv), None);
+        assert_eq!(err.rendered(OutputFormat::Table), None);
+        let err = Error::from(io::Error::from(io::ErrorKind::NotFound));
+        assert_eq!(err.rendered(OutputFormat::Human), None);
+    }
+
+    fn err_eof() -> ParseError {
+        rnix::Root::parse("{").ok().err().unwrap()
+    }
+
+    #[test]
+    fn test_in_file() {
+        let err = Error::from(io::Error::from(io::ErrorKind::NotFound)).in_file(Path::new("a.nix"));
+        assert_eq!(err.kind(), "Io");
+        assert!(err.to_string().starts_with("a.nix: "));
+        // already about a file
+        let err = Error::io(Path::new("b.nix"), io::ErrorKind::NotFound.into());
+        assert!(err
+            .in_file(Path::new("a.nix"))
+            .to_string()
+            .starts_with("b.nix: "));
+        assert_eq!(
+            Error::Cancelled.in_file(Path::new("a.nix")).kind(),
+            "Cancelled"
+        );
+    }
+}
@@ -0,0 +1,257 @@
+//! Which of the files a walk comes across get searched
+use crate::changes::git;
+use crate::glob;
+use crate::{is_searchable, Error, Result};
+
+use regex::Regex;
+
+use std::collections::HashSet;
+use std::fmt::{self, Display};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Decides which files get searched. This is asked about every entry a walk comes across,
+/// directories included, before anything is read from them, so it has to be cheap.
+///
+/// Any `Fn(&Path) -> bool` is one, like [`is_searchable`], and [`NixFiles`] makes one out of the
+/// usual checks.
+pub trait FileFilter {
+    /// Should `path` be searched?
+    fn should_search(&self, path: &Path) -> bool;
+}
+
+impl<F: Fn(&Path) -> bool> FileFilter for F {
+    fn should_search(&self, path: &Path) -> bool {
+        self(path)
+    }
+}
+
+/// The files `nix-doc search` looks at: ones ending in `.nix`, or another of the
+/// [`NixFiles::extension`]s, then as many of the other checks as are set up, cheapest first
+///
+/// ```
+/// use nix_doc::filter::{FileFilter, NixFiles};
+/// use std::path::Path;
+///
+/// let filter = NixFiles::new().glob("*-packages.nix").unwrap();
+/// assert!(filter.should_search(Path::new("pkgs/top-level/all-packages.nix")));
+/// assert!(!filter.should_search(Path::new("lib/lists.nix")));
+/// assert!(!filter.should_search(Path::new("pkgs/README.md")));
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct NixFiles {
+    /// Without the `.`, like `nix`
+    extensions: Vec<String>,
+    /// The globs as given, with their regexes, which file names have to match one of, if there
+    /// are any
+    globs: Vec<(String, Regex)>,
+    /// Largest file searched, in bytes
+    max_size: Option<u64>,
+    /// What git ignores, if that's left out
+    ignored: Option<GitIgnored>,
+}
+
+impl NixFiles {
+    /// Every `.nix` file, like [`is_searchable`]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Searches files ending in `.{ext}` instead of `.nix`. Given more than once, files with any
+    /// of them are searched.
+    pub fn extension(mut self, ext: &str) -> Self {
+        self.extensions
+            .push(ext.trim_start_matches('.').to_string());
+        self
+    }
+
+    /// Only searches files whose names match the shell-style `glob`, like `*-packages.nix`
+    /// (see [`glob::to_regex`]), or one of the others given
+    pub fn glob(mut self, glob: &str) -> Result<Self> {
+        let re = glob::to_regex(glob).map_err(Error::InvalidConfig)?;
+        self.globs.push((glob.to_string(), Regex::new(&re)?));
+        Ok(self)
+    }
+
+    /// Leaves out files bigger than `bytes`. Files over 200 kB are never searched anyway, being
+    /// too big to be anything but generated.
+    pub fn max_size(mut self, bytes: impl Into<Option<u64>>) -> Self {
+        self.max_size = bytes.into();
+        self
+    }
+
+    /// Leaves out the files git ignores in `dir`, which has to be in a git repository. Walks
+    /// have to start from `dir` for the files to be recognized.
+    pub fn gitignore(mut self, dir: &Path) -> Result<Self> {
+        self.ignored = Some(GitIgnored::in_dir(dir)?);
+        Ok(self)
+    }
+
+    fn has_extension(&self, path: &Path) -> bool {
+        match self.extensions.is_empty() {
+            true => is_searchable(path),
+            false => path
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| self.extensions.iter().any(|wanted| wanted == ext)),
+        }
+    }
+
+    fn matches_globs(&self, path: &Path) -> bool {
+        self.globs.is_empty()
+            || path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| self.globs.iter().any(|(_, re)| re.is_match(name)))
+    }
+
+    fn small_enough(&self, path: &Path) -> bool {
+        match self.max_size {
+            // one that can't be looked at is left for the search to report
+            Some(max) => fs::metadata(path).map_or(true, |meta| meta.len() <= max),
+            None => true,
+        }
+    }
+}
+
+impl FileFilter for NixFiles {
+    fn should_search(&self, path: &Path) -> bool {
+        self.has_extension(path)
+            && self.matches_globs(path)
+            && !self
+                .ignored
+                .as_ref()
+                .is_some_and(|ignored| ignored.contains(path))
+            && self.small_enough(path)
+    }
+}
+
+/// Which files get searched, like `.nix files named like *-packages.nix, not ignored by git`
+impl Display for NixFiles {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let extensions = match self.extensions.is_empty() {
+            true => ".nix".to_string(),
+            false => self
+                .extensions
+                .iter()
+                .map(|ext| format!(".{}", ext))
+                .collect::<Vec<_>>()
+                .join(" or "),
+        };
+        write!(f, "{} files", extensions)?;
+        if !self.globs.is_empty() {
+            let globs = self.globs.iter().map(|(glob, _)| glob.as_str());
+            write!(f, " named like {}", globs.collect::<Vec<_>>().join(" or "))?;
+        }
+        if let Some(max) = self.max_size {
+            write!(f, " of at most {} bytes", max)?;
+        }
+        if self.ignored.is_some() {
+            write!(f, ", not ignored by git")?;
+        }
+        Ok(())
+    }
+}
+
+/// The files and directories git ignores under a directory, asked for once up front, so that
+/// checking a path doesn't mean running git again
+#[derive(Clone, Debug)]
+struct GitIgnored {
+    dir: PathBuf,
+    /// Joined onto `dir`, without the `/` git puts after directories
+    paths: HashSet<PathBuf>,
+}
+
+impl GitIgnored {
+    fn in_dir(dir: &Path) -> Result<Self> {
+        let listed = git(
+            dir,
+            &[
+                "ls-files",
+                "--others",
+                "--ignored",
+                "--exclude-standard",
+                "--directory",
+                "-z",
+            ],
+        )?;
+        Ok(Self::from_listing(dir, &listed))
+    }
+
+    /// From what `git ls-files -z --directory` printed in `dir`, paths relative to it
+    fn from_listing(dir: &Path, listed: &str) -> Self {
+        let paths = listed
+            .split('\0')
+            .filter(|path| !path.is_empty())
+            .map(|path| dir.join(path.trim_end_matches('/')))
+            .collect();
+        GitIgnored {
+            dir: dir.to_path_buf(),
+            paths,
+        }
+    }
+
+    /// Is `path`, or a directory it's in, ignored?
+    fn contains(&self, path: &Path) -> bool {
+        !self.paths.is_empty()
+            && path
+                .ancestors()
+                .take_while(|&dir| dir != self.dir)
+                .any(|dir| self.paths.contains(dir))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nix_files() {
+        let filter = NixFiles::new();
+        assert!(filter.should_search(Path::new("lib/lists.nix")));
+        assert!(!filter.should_search(Path::new("lib/lists.nix.bak")));
+
+        let filter = NixFiles::new().extension("nix").extension(".nixpkgs");
+        assert!(filter.should_search(Path::new("a.nixpkgs")));
+        assert!(filter.should_search(Path::new("a.nix")));
+        assert!(!filter.should_search(Path::new("a.md")));
+
+        let filter = NixFiles::new()
+            .glob("l*.nix")
+            .unwrap()
+            .glob("default.nix")
+            .unwrap();
+        assert!(filter.should_search(Path::new("lib/lists.nix")));
+        assert!(filter.should_search(Path::new("lib/default.nix")));
+        assert!(!filter.should_search(Path::new("lib/strings.nix")));
+        assert!(NixFiles::new().glob("lib/*.nix").is_err());
+        assert_eq!(
+            NixFiles::new()
+                .extension("nix")
+                .extension("nixpkgs")
+                .glob("l*")
+                .unwrap()
+                .max_size(100)
+                .to_string(),
+            ".nix or .nixpkgs files named like l* of at most 100 bytes"
+        );
+
+        let fixture = Path::new(env!("CARGO_MANIFEST_DIR")).join("testdata/test.nix");
+        let size = fs::metadata(&fixture).unwrap().len();
+        assert!(NixFiles::new().max_size(size).should_search(&fixture));
+        assert!(!NixFiles::new().max_size(size - 1).should_search(&fixture));
+    }
+
+    #[test]
+    fn test_git_ignored() {
+        let root = Path::new("/repo");
+        let ignored = GitIgnored::from_listing(root, "result/\0lib/generated.nix\0");
+        let filter = NixFiles {
+            ignored: Some(ignored),
+            ..NixFiles::new()
+        };
+        assert!(!filter.should_search(Path::new("/repo/lib/generated.nix")));
+        assert!(!filter.should_search(Path::new("/repo/result/lib/lists.nix")));
+        assert!(filter.should_search(Path::new("/repo/lib/lists.nix")));
+    }
+}
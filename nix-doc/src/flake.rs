@@ -0,0 +1,113 @@
+//! Resolution of flake references to their source in the store, by asking `nix`
+use std::io;
+use std::path::PathBuf;
+use std::process::Command;
+
+/// The flake reference in a search root like `nixpkgs#`, if it is one
+pub fn flake_ref(spec: &str) -> Option<&str> {
+    spec.strip_suffix('#').filter(|r| !r.is_empty())
+}
+
+/// Finds the store path of the source of the flake `reference`, fetching it if need be
+pub fn resolve(reference: &str) -> Result<PathBuf, String> {
+    let what = format!("flake `{}`", reference);
+    let json = run_nix(&what, &["flake", "metadata", "--json", reference])?;
+    metadata_path(&json).ok_or_else(|| format!("`nix flake metadata` gave no path for {}", what))
+}
+
+/// Finds the store path of the input `name` of the flake in the current directory
+pub fn resolve_input(name: &str) -> Result<PathBuf, String> {
+    let what = format!("flake input `{}`", name);
+    let expr = format!(
+        "(builtins.getFlake (toString ./.)).inputs.{}.outPath",
+        quote_attr(name)
+    );
+    let path = run_nix(&what, &["eval", "--raw", "--impure", "--expr", &expr])?;
+    Ok(PathBuf::from(path.trim()))
+}
+
+/// Runs `nix` with flakes enabled, returning its stdout
+fn run_nix(what: &str, args: &[&str]) -> Result<String, String> {
+    let output = Command::new("nix")
+        .args(["--extra-experimental-features", "nix-command flakes"])
+        .args(args)
+        .output()
+        .map_err(|err| match err.kind() {
+            io::ErrorKind::NotFound => format!(
+                "could not resolve {}: the `nix` command was not found, is Nix installed and on \
+                 the PATH?",
+                what
+            ),
+            _ => format!("could not resolve {}: failed to run `nix`: {}", what, err),
+        })?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let reason = stderr.trim().lines().last().unwrap_or("no error message");
+        return Err(format!(
+            "could not resolve {}, nix said: {} (if you are offline, try a flake that is already \
+             in the store)",
+            what, reason
+        ));
+    }
+    String::from_utf8(output.stdout)
+        .map_err(|_| format!("`nix` gave non-UTF-8 output for {}", what))
+}
+
+/// Pulls the source path out of the output of `nix flake metadata --json`
+fn metadata_path(json: &str) -> Option<PathBuf> {
+    let metadata: serde_json::Value = serde_json::from_str(json).ok()?;
+    metadata.get("path")?.as_str().map(PathBuf::from)
+}
+
+/// Quotes an attribute name for use in a nix expression, unless it's a plain identifier
+fn quote_attr(name: &str) -> String {
+    let is_ident = name
+        .chars()
+        .next()
+        .is_some_and(|ch| ch.is_ascii_alphabetic() || ch == '_')
+        && name
+            .chars()
+            .all(|ch| ch.is_ascii_alphanumeric() || "_-'".contains(ch));
+    if is_ident {
+        name.to_string()
+    } else {
+        serde_json::to_string(name).unwrap().replace("${", "\\${")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flake_ref() {
+        assert_eq!(flake_ref("nixpkgs#"), Some("nixpkgs"));
+        assert_eq!(
+            flake_ref("github:NixOS/nixpkgs#"),
+            Some("github:NixOS/nixpkgs")
+        );
+        assert_eq!(flake_ref("#"), None);
+        assert_eq!(flake_ref("./lib"), None);
+    }
+
+    #[test]
+    fn test_metadata_path() {
+        let json = r#"{"description":"A collection of packages","lastModified":1700000000,
+            "path":"/nix/store/abc-source","locked":{"type":"github"}}"#;
+        assert_eq!(
+            metadata_path(json),
+            Some(PathBuf::from("/nix/store/abc-source"))
+        );
+        assert_eq!(metadata_path("{}"), None);
+        assert_eq!(metadata_path("not json"), None);
+    }
+
+    #[test]
+    fn test_quote_attr() {
+        assert_eq!(quote_attr("nixpkgs"), "nixpkgs");
+        assert_eq!(quote_attr("home-manager"), "home-manager");
+        assert_eq!(quote_attr("has.dot"), "\"has.dot\"");
+        assert_eq!(quote_attr("${x}"), "\"\\${x}\"");
+    }
+}
@@ -0,0 +1,1008 @@
+//! Turning search results into text, for people, JSON, CSV or tables
+use crate::color::{Color, Colorful};
+use crate::doc::format_tags;
+use crate::output::{strip_colors, Glyphs, Output};
+use crate::profile::{Phase, Profile};
+#[cfg(feature = "fs")]
+use crate::search::{FileFound, FileResults};
+use crate::search::{MatchedField, RelevanceQuery, SearchResult};
+#[cfg(feature = "fs")]
+use crate::stream::SearchStats;
+use crate::table::{layout, TableLine, TableRow};
+use crate::{FileError, OutputFormat, ResultKind, SearchOptions, SortKey};
+
+use regex::Regex;
+use rnix::tokenizer::Tokenizer;
+use rnix::SyntaxKind::*;
+#[cfg(feature = "serde")]
+use serde::Serialize;
+
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::fmt::{self, Display};
+use std::io::{self, IsTerminal, Write};
+use std::ops::Range;
+use std::path::Path;
+#[cfg(feature = "fs")]
+use std::path::PathBuf;
+use std::str;
+use std::sync::Arc;
+
+pub(crate) const DOC_INDENT: usize = 3;
+
+/// The `schema_version` of JSON search output, bumped whenever the fields in it change
+pub const JSON_SCHEMA_VERSION: u32 = 3;
+
+/// The fields of a result in JSON output, for picking some of them with
+/// [`SearchOptions::json_fields`]. The optional ones are only there when they have a value.
+pub const JSON_FIELDS: &[&str] = &[
+    "file",
+    "line",
+    "identifier",
+    "doc",
+    "raw_doc",
+    "synopsis",
+    "tags",
+    "sections",
+    "doc_position",
+    "kind",
+    "def_kind",
+    "attr_path",
+    "signature",
+    "start",
+    "end",
+    "identifier_start",
+    "deprecated",
+    "arity",
+    "arity_may_be_higher",
+    "score",
+    "matched_field",
+    "aliases",
+];
+
+impl SearchResult {
+    pub(crate) fn format<P: Display>(&self, filename: P, line: usize) -> String {
+        let mut out = String::new();
+        self.write_human(&mut out, Some(filename), line, false)
+            .unwrap();
+        out
+    }
+
+    /// Writes the result as [`SearchResult::format`] formats it to `out`, a piece at a time
+    /// rather than building up strings to join together, since there can be a lot of results.
+    /// With `highlight_signature`, the signature is colored like source code (see [`highlight_nix`]).
+    /// Without a `filename`, the `# file:line` line is left out.
+    pub(crate) fn write_human<W: fmt::Write, P: Display>(
+        &self,
+        out: &mut W,
+        filename: Option<P>,
+        line: usize,
+        highlight_signature: bool,
+    ) -> fmt::Result {
+        match &self.field_range {
+            Some(range) if self.matched_field == Some(MatchedField::Doc) => {
+                write_indented(out, &highlight(&self.doc, range), DOC_INDENT)?
+            }
+            _ => write_indented(out, &self.doc, DOC_INDENT)?,
+        }
+        if !self.tags.is_empty() {
+            if !self.doc.is_empty() {
+                // the blank line between the docs and the tags is indented too
+                write!(out, "\n{:indent$}\n", "", indent = DOC_INDENT)?;
+            }
+            write_indented(out, &format_tags(&self.tags), DOC_INDENT)?;
+        }
+
+        out.write_char('\n')?;
+        self.write_highlighted_identifier(out)?;
+        match highlight_signature {
+            true => write!(out, " = {}", highlight_nix(&self.param_block))?,
+            false => write!(out, " = {}", self.param_block)?,
+        }
+        // functions are what's usually searched for, so only other kinds get labelled
+        if self.kind != ResultKind::Function {
+            write!(out, " {}", format!("[{}]", self.kind).as_str().blue())?;
+        }
+        match (self.matched_field, &self.field_range) {
+            (Some(MatchedField::Path), Some(range)) => {
+                let path = highlight(&self.attr_path.join("."), range);
+                write!(out, " {} {}", "matched path".dark_gray(), path)?;
+            }
+            (Some(MatchedField::Doc), _) => write!(out, " {}", "matched docs".dark_gray())?,
+            _ => (),
+        }
+
+        if let Some(msg) = &self.deprecated {
+            write!(out, "\n{} {}", "DEPRECATED:".red().bold(), msg)?;
+        }
+        if let Some((first, rest)) = self.aliases.split_first() {
+            write!(out, "\n{} {}", "aliases:".dark_gray(), first)?;
+            for alias in rest {
+                write!(out, ", {}", alias)?;
+            }
+        }
+        match filename {
+            Some(filename) => write!(out, "\n# {}:{}", filename, line),
+            None => Ok(()),
+        }
+    }
+
+    /// Writes the identifier in bold, with the characters a fuzzy pattern matched picked out
+    fn write_highlighted_identifier<W: fmt::Write>(&self, out: &mut W) -> fmt::Result {
+        if self.matched.is_empty() {
+            return write!(out, "{}", self.identifier.as_str().white().bold());
+        }
+        let mut buf = [0; 4];
+        for (idx, ch) in self.identifier.chars().enumerate() {
+            let ch: &str = ch.encode_utf8(&mut buf);
+            if self.matched.contains(&idx) {
+                write!(out, "{}", ch.yellow().bold())?;
+            } else {
+                write!(out, "{}", ch.white().bold())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Formats the result as a JSON object
+    #[cfg(feature = "serde")]
+    pub(crate) fn format_json<P: Display>(&self, filename: P) -> String {
+        self.format_json_fields(filename, &[])
+    }
+
+    /// Formats the result as a JSON object with just `fields`, in that order, or all of them if
+    /// there are none
+    #[cfg(feature = "serde")]
+    pub(crate) fn format_json_fields<P: Display>(&self, filename: P, fields: &[String]) -> String {
+        #[derive(Serialize)]
+        struct Located<'a> {
+            file: String,
+            line: usize,
+            #[serde(flatten)]
+            result: &'a SearchResult,
+        }
+
+        let located = Located {
+            file: filename.to_string(),
+            line: self.start.line,
+            result: self,
+        };
+        if fields.is_empty() {
+            return serde_json::to_string(&located).expect("failed to serialize result");
+        }
+        let value = serde_json::to_value(&located).expect("failed to serialize result");
+        let picked = fields
+            .iter()
+            .filter_map(|field| {
+                let name = serde_json::to_string(field).ok()?;
+                Some(format!("{}:{}", name, value.get(field)?))
+            })
+            .collect::<Vec<_>>();
+        format!("{{{}}}", picked.join(","))
+    }
+
+    /// Formats the result as a CSV row with the columns in [`CSV_HEADER`]
+    pub(crate) fn format_csv<P: Display>(&self, filename: P, line: usize) -> String {
+        let mut writer = csv::WriterBuilder::new()
+            .has_headers(false)
+            .terminator(csv::Terminator::Any(b'\n'))
+            .from_writer(Vec::new());
+        writer
+            .write_record([
+                self.identifier.as_str(),
+                &filename.to_string(),
+                &line.to_string(),
+                self.kind.name(),
+                &self.has_doc().to_string(),
+                &self.doc,
+            ])
+            .expect("failed to serialize result");
+        let mut row = String::from_utf8(writer.into_inner().unwrap()).unwrap();
+        row.pop();
+        row
+    }
+}
+
+/// Colors the nix source `source` by what its tokens are: keywords, names, literals like strings
+/// and paths, and the punctuation and operators between them
+pub(crate) fn highlight_nix(source: &str) -> String {
+    let mut out = String::with_capacity(source.len());
+    for (kind, text) in Tokenizer::new(source) {
+        let colored = match kind {
+            TOKEN_WHITESPACE | TOKEN_ERROR => text.to_string(),
+            TOKEN_ASSERT | TOKEN_ELSE | TOKEN_IF | TOKEN_IN | TOKEN_INHERIT | TOKEN_LET
+            | TOKEN_REC | TOKEN_THEN | TOKEN_WITH => text.blue().to_string(),
+            TOKEN_IDENT => text.white().to_string(),
+            TOKEN_FLOAT | TOKEN_INTEGER | TOKEN_PATH | TOKEN_URI | TOKEN_STRING_START
+            | TOKEN_STRING_CONTENT | TOKEN_STRING_END => text.green().to_string(),
+            _ => text.dark_gray().to_string(),
+        };
+        out.push_str(&colored);
+    }
+    out
+}
+
+/// Picks out the byte range `range` of `s`, the way matched characters of identifiers are
+pub(crate) fn highlight(s: &str, range: &Range<usize>) -> String {
+    format!(
+        "{}{}{}",
+        &s[..range.start],
+        s[range.clone()].yellow().bold(),
+        &s[range.end..]
+    )
+}
+
+/// Columns of CSV output
+pub(crate) const CSV_HEADER: &str = "identifier,file,line,kind,has_doc,doc";
+
+/// Columns of CSV output from [`search_patterns`]: the pattern each result matched, then the
+/// usual ones
+#[cfg(feature = "fs")]
+pub(crate) const PATTERN_CSV_HEADER: &str = "pattern,identifier,file,line,kind,has_doc,doc";
+
+/// What a [`Formatter`] has to go on besides the results themselves
+#[cfg(feature = "fs")]
+#[derive(Clone, Copy, Debug)]
+pub struct FormatContext<'a> {
+    /// The pattern the results matched, unless [`SearchOptions::fuzzy`] was matched instead
+    pub matching: &'a Regex,
+    /// The options the search was run with, including the ones about printing, like
+    /// [`SearchOptions::format`] and [`SearchOptions::raw_docs`]
+    pub opts: &'a SearchOptions,
+    /// Can the output have colors: is it going to stdout, on a terminal?
+    pub color: bool,
+}
+
+/// Lays out the results of a search, for [`search_formatted`](crate::search_formatted) to hand
+/// them to as they come in. The formats `--format` picks from are [`builtin_formatter`]s; other
+/// tools can write their own, to print results however they like.
+///
+/// Results come in the order [`search`] prints them in, so with [`SearchOptions::sort_by`] they
+/// only start once every file has been searched.
+#[cfg(feature = "fs")]
+pub trait Formatter {
+    /// The next results are from `file`, as [`SearchOptions::relative_to`] shows it. Sorted
+    /// results can go back and forth between files, and this is called each time they do.
+    fn file_header(&mut self, _file: &Path, _cx: &FormatContext<'_>) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Lays out `result`, found in `file`, shown the same way as for [`Formatter::file_header`]
+    fn result(
+        &mut self,
+        result: &SearchResult,
+        file: &Path,
+        cx: &FormatContext<'_>,
+    ) -> io::Result<()>;
+
+    /// `err` is why a file couldn't be searched, which has already been said on stderr
+    fn file_error(&mut self, _err: &FileError, _cx: &FormatContext<'_>) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Called once every result has been handed over, with how many there were
+    fn finish(&mut self, stats: &SearchStats, cx: &FormatContext<'_>) -> io::Result<()>;
+}
+
+/// The [`Formatter`] for `opts.format`, writing to `opts.output`, or stdout, like `nix-doc search`
+/// does
+#[cfg(feature = "fs")]
+pub fn builtin_formatter(opts: &SearchOptions) -> crate::Result<Box<dyn Formatter>> {
+    let out = Output::open(opts.output.as_deref())?;
+    Ok(Box::new(ResultPrinter::start(opts, out)))
+}
+
+#[cfg(feature = "fs")]
+impl Formatter for ResultPrinter {
+    fn result(
+        &mut self,
+        result: &SearchResult,
+        file: &Path,
+        cx: &FormatContext<'_>,
+    ) -> io::Result<()> {
+        self.print_result(result, file.display(), cx.opts);
+        Ok(())
+    }
+
+    fn file_error(&mut self, err: &FileError, _cx: &FormatContext<'_>) -> io::Result<()> {
+        ResultPrinter::file_error(self, err.clone());
+        Ok(())
+    }
+
+    fn finish(&mut self, _stats: &SearchStats, _cx: &FormatContext<'_>) -> io::Result<()> {
+        self.finish_output()
+    }
+}
+
+/// Prints the results of [`search`] to `out` as they come in, from the channel the searchers are
+/// sending them down or from a search that's over, with the format `opts` asks for
+#[cfg(feature = "fs")]
+pub(crate) fn print_search_results(
+    found: impl IntoIterator<Item = FileResults>,
+    matching: &Regex,
+    opts: &SearchOptions,
+    out: Output,
+) -> io::Result<()> {
+    let cx = FormatContext {
+        matching,
+        opts,
+        color: out.color && io::stdout().is_terminal(),
+    };
+    let mut printer = ResultPrinter::start(opts, out);
+    format_search_results(found, &cx, &mut printer).map(|_| ())
+}
+
+/// Hands the results of a search to `formatter` as they come in, or once they're all in if they
+/// need sorting, then finishes it. Every result is received even if formatting fails, so the
+/// searchers can always finish, but nothing more is handed over after the first failure, which is
+/// what's returned.
+#[cfg(feature = "fs")]
+pub(crate) fn format_search_results(
+    files: impl IntoIterator<Item = FileResults>,
+    cx: &FormatContext<'_>,
+    formatter: &mut dyn Formatter,
+) -> io::Result<SearchStats> {
+    let opts = cx.opts;
+    let mut handover = Handover {
+        formatter,
+        cx,
+        stats: SearchStats::default(),
+        last_file: None,
+        failed: None,
+    };
+
+    if let Some(key) = opts.sort_key() {
+        let query = RelevanceQuery::new(cx.matching, opts);
+        let mut all = Vec::new();
+        for (_, file, results) in files {
+            for mut result in handover.found(results) {
+                if key == SortKey::Relevance {
+                    result.relevance = result.relevance(&query, &file);
+                }
+                all.push((file.clone(), result));
+            }
+        }
+
+        all.sort_by(|(_, a), (_, b)| compare_results(key, a, b));
+        if opts.reverse {
+            all.reverse();
+        }
+        for (file, result) in all {
+            handover.result(&file, &result);
+        }
+    } else if opts.ordered {
+        let mut pending = BTreeMap::new();
+        let mut next_seq = 0;
+        for (seq, file, results) in files {
+            pending.insert(seq, (file, handover.found(results)));
+            while let Some((file, results)) = pending.remove(&next_seq) {
+                for result in &results {
+                    handover.result(&file, result);
+                }
+                next_seq += 1;
+            }
+        }
+    } else {
+        for (_, file, results) in files {
+            for result in &handover.found(results) {
+                handover.result(&file, result);
+            }
+        }
+    }
+
+    let Handover {
+        formatter,
+        mut stats,
+        failed,
+        ..
+    } = handover;
+    if let Some(err) = failed {
+        return Err(err);
+    }
+    stats.cancelled = opts.is_cancelled();
+    formatter.finish(&stats, cx)?;
+    Ok(stats)
+}
+
+/// Hands results over to a [`Formatter`] for [`format_search_results`], keeping count
+#[cfg(feature = "fs")]
+struct Handover<'f, 'c> {
+    formatter: &'f mut dyn Formatter,
+    cx: &'c FormatContext<'c>,
+    stats: SearchStats,
+    /// The file the last result was from, and how it's shown
+    last_file: Option<(PathBuf, PathBuf)>,
+    /// The first time the formatter failed, after which it's left alone
+    failed: Option<io::Error>,
+}
+
+#[cfg(feature = "fs")]
+impl Handover<'_, '_> {
+    /// The results of a file that was searched. One that couldn't be has nothing to print, but
+    /// the formatter may want to say so.
+    fn found(&mut self, results: Result<Vec<SearchResult>, FileError>) -> Vec<SearchResult> {
+        self.stats.files += 1;
+        match results {
+            Ok(results) => {
+                self.stats.results += results.len();
+                results
+            }
+            Err(err) => {
+                self.stats.errors += 1;
+                if self.failed.is_none() {
+                    self.failed = self.formatter.file_error(&err, self.cx).err();
+                }
+                Vec::new()
+            }
+        }
+    }
+
+    /// Hands over `result`, from `file`, after saying which file it's from if that's changed
+    fn result(&mut self, file: &Path, result: &SearchResult) {
+        let Handover {
+            formatter,
+            cx,
+            last_file,
+            failed,
+            ..
+        } = self;
+        if failed.is_some() {
+            return;
+        }
+        let mut written = Ok(());
+        let shown = match last_file {
+            Some((last, shown)) if last == file => shown,
+            _ => {
+                let shown = cx.opts.display_path(file).into_owned();
+                written = formatter.file_header(&shown, cx);
+                &last_file.insert((file.to_path_buf(), shown)).1
+            }
+        };
+        *failed = written
+            .and_then(|_| formatter.result(result, shown, cx))
+            .err();
+    }
+}
+
+/// Prints what [`search_files`](crate::search::search_files) found to `opts.output`, or stdout,
+/// the same as [`search`](crate::search) with `opts.ordered` would have
+#[cfg(feature = "fs")]
+pub fn print_results(
+    found: Vec<FileFound>,
+    matching: &Regex,
+    opts: &SearchOptions,
+) -> crate::Result<()> {
+    let out = Output::open(opts.output.as_deref())?;
+    let found = found
+        .into_iter()
+        .enumerate()
+        .map(|(seq, (file, results))| (seq, file, results));
+    Ok(print_search_results(found, matching, opts, out)?)
+}
+
+/// How [`format_result`] lays a result out, on top of the [`OutputFormat`] it's in
+#[derive(Clone, Debug, Default)]
+pub struct FormatOptions {
+    /// What to call the file the result was found in, instead of the path in
+    /// [`SearchResult::file`], like one relative to somewhere else
+    pub file_name: Option<String>,
+    /// Keeps the colors of human-readable and table output, which are left out otherwise
+    pub color: bool,
+    /// How many columns a table row can take up, its synopsis being cut short to fit
+    pub width: Option<usize>,
+    /// Colors the signatures in human-readable output like source code, if colors are kept
+    pub highlight: bool,
+    /// What table cells cut short end in
+    pub glyphs: Glyphs,
+    /// Which fields JSON output has (see [`SearchOptions::json_fields`]), all of them if empty
+    pub json_fields: Vec<String>,
+    /// Leaves the `# file:line` line out of human-readable output
+    pub no_filename: bool,
+}
+
+/// Formats `result` as one result of `nix-doc search --format` with `format` would be, without
+/// a newline after it
+///
+/// ```
+/// use nix_doc::format::{format_result, FormatOptions};
+/// use nix_doc::OutputFormat;
+/// use regex::Regex;
+/// use std::path::Path;
+///
+/// let source = "{\n  # Doubles it\n  double = x: x * 2;\n}";
+/// let opts = nix_doc::SearchOptions::default();
+/// let found = nix_doc::search_str_named(Path::new("a.nix"), source, &Regex::new("").unwrap(), &opts)
+///     .unwrap();
+/// assert_eq!(
+///     format_result(&found[0], OutputFormat::Csv, &FormatOptions::default()),
+///     "double,a.nix,3,function,true,Doubles it"
+/// );
+/// ```
+pub fn format_result(result: &SearchResult, format: OutputFormat, opts: &FormatOptions) -> String {
+    let mut out = String::new();
+    match &opts.file_name {
+        Some(name) => write_formatted(&mut out, result, name, format, opts),
+        None => {
+            let name = result.file.as_deref().unwrap_or(Path::new(""));
+            write_formatted(&mut out, result, name.display(), format, opts)
+        }
+    }
+    if opts.color {
+        out
+    } else {
+        strip_colors(&out)
+    }
+}
+
+/// Writes `result` to `out` as [`format_result`] formats it, then a newline
+pub fn write_result<W: Write>(
+    out: &mut W,
+    result: &SearchResult,
+    format: OutputFormat,
+    opts: &FormatOptions,
+) -> io::Result<()> {
+    writeln!(out, "{}", format_result(result, format, opts))
+}
+
+/// Formats `result`, found in the file called `name`, the way `opts` asks
+pub(crate) fn format_with_opts<P: Display>(
+    result: &SearchResult,
+    name: P,
+    opts: &SearchOptions,
+) -> String {
+    let mut out = String::new();
+    write_with_opts(&mut out, result, name, opts);
+    out
+}
+
+/// Adds `result`, formatted as [`format_with_opts`] does, to the end of `out`
+pub(crate) fn write_with_opts<P: Display>(
+    out: &mut String,
+    result: &SearchResult,
+    name: P,
+    opts: &SearchOptions,
+) {
+    let layout_opts = FormatOptions {
+        highlight: opts.highlight,
+        glyphs: opts.glyphs,
+        json_fields: opts.json_fields.clone(),
+        no_filename: opts.no_filename,
+        ..FormatOptions::default()
+    };
+    opts.timed(Phase::Format, || {
+        write_formatted(out, result, name, opts.format, &layout_opts)
+    })
+}
+
+/// Adds `result`, found in the file called `name`, to the end of `out` in `format`, laid out as
+/// `layout_opts` asks, other than the file name and whether colors are kept
+fn write_formatted<P: Display>(
+    out: &mut String,
+    result: &SearchResult,
+    name: P,
+    format: OutputFormat,
+    layout_opts: &FormatOptions,
+) {
+    let line = result.start.line;
+    match format {
+        OutputFormat::Human => {
+            let name = Some(name).filter(|_| !layout_opts.no_filename);
+            result
+                .write_human(out, name, line, layout_opts.highlight)
+                .unwrap()
+        }
+        #[cfg(feature = "serde")]
+        OutputFormat::Json => {
+            out.push_str(&result.format_json_fields(name, &layout_opts.json_fields))
+        }
+        OutputFormat::Csv => out.push_str(&result.format_csv(name, line)),
+        OutputFormat::Table => {
+            let row = TableLine::Row(TableRow::new(result, name));
+            out.push_str(&layout(&[row], layout_opts.width, &layout_opts.glyphs)[0])
+        }
+    }
+}
+
+/// Orders results found in files for [`SortKey`] `key`. Ties are broken by location, so the order
+/// is always the same from run to run.
+pub(crate) fn compare_results(key: SortKey, a: &SearchResult, b: &SearchResult) -> Ordering {
+    let (a_file, b_file) = (&a.file, &b.file);
+    let by_location = || a_file.cmp(b_file).then(a.start.byte.cmp(&b.start.byte));
+    match key {
+        SortKey::Name => a.identifier.cmp(&b.identifier).then_with(by_location),
+        SortKey::File => by_location(),
+        SortKey::Line => a
+            .start
+            .line
+            .cmp(&b.start.line)
+            .then_with(|| a_file.cmp(b_file)),
+        SortKey::DocLength => b.doc.len().cmp(&a.doc.len()).then_with(by_location),
+        SortKey::Score => b
+            .score
+            .cmp(&a.score)
+            .then(a.identifier.len().cmp(&b.identifier.len()))
+            .then_with(by_location),
+        SortKey::Relevance => b
+            .relevance
+            .cmp(&a.relevance)
+            .then_with(|| a.identifier.cmp(&b.identifier))
+            .then_with(by_location),
+    }
+}
+
+/// Prints formatted results to an [`Output`], with whatever the output format wants around and
+/// between them: separator lines for humans, brackets and commas for JSON, and a header row for
+/// CSV
+pub(crate) struct ResultPrinter {
+    out: Output,
+    separator: Option<String>,
+    /// Written after the last result, like the `]` closing a JSON array
+    footer: Option<&'static str>,
+    /// The files that couldn't be searched, for JSON output to list after the results
+    errors: Option<Vec<FileError>>,
+    is_first: bool,
+    /// Has a [`ResultPrinter::group`] been started?
+    grouped: bool,
+    /// The first write that failed, after which nothing more is written
+    error: Option<io::Error>,
+    profile: Option<Arc<Profile>>,
+    /// Where [`ResultPrinter::print_result`] formats results, kept between them so it only
+    /// needs to grow to fit the longest
+    buf: String,
+    /// For table output, everything to print, which has to wait for the widths of the columns
+    table: Option<Vec<TableLine>>,
+    /// How wide table output can be
+    width: Option<usize>,
+    glyphs: Glyphs,
+}
+
+impl ResultPrinter {
+    pub(crate) fn start(opts: &SearchOptions, out: Output) -> Self {
+        Self::with_csv_header(opts, out, CSV_HEADER)
+    }
+
+    pub(crate) fn with_csv_header(opts: &SearchOptions, out: Output, csv_header: &str) -> Self {
+        let mut printer = ResultPrinter {
+            out,
+            separator: None,
+            footer: None,
+            errors: None,
+            is_first: true,
+            grouped: false,
+            error: None,
+            profile: opts.profile.clone(),
+            buf: String::new(),
+            table: None,
+            width: None,
+            glyphs: opts.glyphs,
+        };
+        match opts.format {
+            OutputFormat::Human => {
+                printer.separator =
+                    Some(opts.glyphs.rule.repeat(45).color(Color::Grey27).to_string())
+            }
+            #[cfg(feature = "serde")]
+            OutputFormat::Json if opts.json_compat_array => {
+                printer.line("[");
+                printer.separator = Some(",".to_string());
+                printer.footer = Some("]");
+            }
+            #[cfg(feature = "serde")]
+            OutputFormat::Json => {
+                printer.line(&format!(
+                    "{{\"schema_version\":{},\"results\":[",
+                    JSON_SCHEMA_VERSION
+                ));
+                printer.separator = Some(",".to_string());
+                printer.errors = Some(Vec::new());
+            }
+            OutputFormat::Csv => printer.line(csv_header),
+            OutputFormat::Table => {
+                printer.table = Some(Vec::new());
+                printer.width = opts.width.or_else(|| printer.out.terminal_width());
+            }
+        }
+        printer
+    }
+
+    /// Starts a group of results under `heading`, a blank line after the last group
+    pub(crate) fn group(&mut self, heading: &str) {
+        if let Some(table) = &mut self.table {
+            if self.grouped {
+                table.push(TableLine::Heading(String::new()));
+            }
+            table.push(TableLine::Heading(heading.to_string()));
+            self.grouped = true;
+            return;
+        }
+        if self.grouped {
+            self.line("");
+        }
+        self.line(heading);
+        self.grouped = true;
+        self.is_first = true;
+    }
+
+    pub(crate) fn line(&mut self, line: &str) {
+        if self.error.is_some() {
+            return;
+        }
+        let out = &mut self.out;
+        let written = match &self.profile {
+            Some(profile) => profile.time(Phase::Output, || out.line(line)),
+            None => out.line(line),
+        };
+        self.error = written.err();
+    }
+
+    pub(crate) fn print(&mut self, results: Vec<String>) {
+        for result in results {
+            self.result(&result);
+        }
+    }
+
+    /// Formats `result`, found in the file called `name`, and prints it
+    pub(crate) fn print_result<P: Display>(
+        &mut self,
+        result: &SearchResult,
+        name: P,
+        opts: &SearchOptions,
+    ) {
+        if let Some(table) = &mut self.table {
+            table.push(TableLine::Row(TableRow::new(result, name)));
+            return;
+        }
+        let mut buf = std::mem::take(&mut self.buf);
+        buf.clear();
+        write_with_opts(&mut buf, result, name, opts);
+        self.result(&buf);
+        self.buf = buf;
+    }
+
+    /// Prints one formatted result, after a separator if it isn't the first
+    fn result(&mut self, result: &str) {
+        if self.is_first {
+            self.is_first = false;
+        } else if let Some(separator) = self.separator.take() {
+            self.line(&separator);
+            self.separator = Some(separator);
+        }
+        self.line(result);
+    }
+
+    /// Notes that a file couldn't be searched, which was already reported on stderr
+    pub(crate) fn file_error(&mut self, err: FileError) {
+        if let Some(errors) = &mut self.errors {
+            errors.push(err);
+        }
+    }
+
+    pub(crate) fn finish(mut self) -> io::Result<()> {
+        self.finish_output()
+    }
+
+    /// Writes whatever goes after the results, returning the first write that failed
+    fn finish_output(&mut self) -> io::Result<()> {
+        if let Some(table) = self.table.take() {
+            for line in layout(&table, self.width, &self.glyphs) {
+                self.line(&line);
+            }
+        }
+        if let Some(footer) = self.footer.take() {
+            self.line(footer);
+        }
+        #[cfg(feature = "serde")]
+        if let Some(errors) = self.errors.take() {
+            let errors = serde_json::to_string(&errors).expect("failed to serialize errors");
+            self.line(&format!("],\"errors\":{}}}", errors));
+        }
+        match self.error.take() {
+            Some(err) => Err(err),
+            None => self.out.flush(),
+        }
+    }
+}
+
+/// Writes a string `s` to `out`, with each of its lines indented by `indent` spaces
+pub(crate) fn write_indented<W: fmt::Write>(out: &mut W, s: &str, indent: usize) -> fmt::Result {
+    for (i, line) in s.split('\n').enumerate() {
+        if i != 0 {
+            out.write_char('\n')?;
+        }
+        write!(out, "{:indent$}{}", "", line, indent = indent)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lines::Position;
+
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_sort_keys() {
+        let found = |file: &str, name: &str, doc: &str, line: usize| {
+            let start = Position {
+                byte: line * 10,
+                line,
+                col_chars: 1,
+                col_utf16: 1,
+            };
+            SearchResult {
+                file: Some(PathBuf::from(file)),
+                start,
+                ..SearchResult::new(
+                    name.into(),
+                    doc.to_string().into(),
+                    String::new(),
+                    start.byte,
+                )
+            }
+        };
+        let mut all = vec![
+            found("b.nix", "short", "a", 1),
+            found("a.nix", "long", "a longer doc", 5),
+            found("a.nix", "mid", "medium", 2),
+        ];
+        let sorted = |all: &mut Vec<_>, key| {
+            all.sort_by(|a, b| compare_results(key, a, b));
+            all.iter()
+                .map(|r: &SearchResult| r.identifier.clone())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(sorted(&mut all, SortKey::Name), ["long", "mid", "short"]);
+        assert_eq!(sorted(&mut all, SortKey::File), ["mid", "long", "short"]);
+        assert_eq!(sorted(&mut all, SortKey::Line), ["short", "mid", "long"]);
+        assert_eq!(
+            sorted(&mut all, SortKey::DocLength),
+            ["long", "mid", "short"]
+        );
+        assert_eq!("doc-length".parse::<SortKey>(), Ok(SortKey::DocLength));
+    }
+
+    #[test]
+    fn test_format_csv() {
+        let res = SearchResult::new(
+            "f".into(),
+            "Says \"hi\", then\nleaves".to_string().into(),
+            "x: ...".into(),
+            0,
+        );
+        assert_eq!(
+            res.format_csv("a,b.nix", 3),
+            "f,\"a,b.nix\",3,function,true,\"Says \"\"hi\"\", then\nleaves\""
+        );
+    }
+
+    #[test]
+    fn test_format_result() {
+        let res = SearchResult {
+            file: Some(PathBuf::from("lib/a.nix")),
+            start: Position {
+                byte: 0,
+                line: 1,
+                col_chars: 1,
+                col_utf16: 1,
+            },
+            ..SearchResult::new("f".into(), "Does f".to_string().into(), "x: ...".into(), 0)
+        };
+        let plain = FormatOptions::default();
+        let human = format_result(&res, OutputFormat::Human, &plain);
+        assert!(human.ends_with("# lib/a.nix:1"), "{}", human);
+        assert!(!human.contains('\x1b'));
+        assert_eq!(
+            format_result(
+                &res,
+                OutputFormat::Human,
+                &FormatOptions {
+                    color: true,
+                    ..plain.clone()
+                }
+            ),
+            format_with_opts(&res, "lib/a.nix", &SearchOptions::default())
+        );
+        let nameless = FormatOptions {
+            no_filename: true,
+            ..plain.clone()
+        };
+        assert_eq!(
+            format_result(&res, OutputFormat::Human, &nameless),
+            "   Does f\nf = x: ..."
+        );
+        let deprecated = SearchResult {
+            deprecated: Some("use g".to_string()),
+            ..res.clone()
+        };
+        assert_eq!(
+            format_result(&deprecated, OutputFormat::Human, &nameless),
+            "   Does f\nf = x: ...\nDEPRECATED: use g"
+        );
+
+        let renamed = FormatOptions {
+            file_name: Some("b.nix".to_string()),
+            ..plain.clone()
+        };
+        assert_eq!(
+            format_result(&res, OutputFormat::Csv, &renamed),
+            "f,b.nix,1,function,true,Does f"
+        );
+        let mut written = Vec::new();
+        write_result(&mut written, &res, OutputFormat::Csv, &renamed).unwrap();
+        assert_eq!(written, b"f,b.nix,1,function,true,Does f\n");
+
+        let table = format_result(&res, OutputFormat::Table, &plain);
+        assert!(
+            table.starts_with('f') && table.contains("lib/a.nix:1"),
+            "{}",
+            table
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_json_fields() {
+        let res = SearchResult {
+            score: Some(3),
+            matched_field: Some(crate::search::MatchedField::Name),
+            aliases: vec!["g".to_string()],
+            deprecated: Some("use g".to_string()),
+            ..SearchResult::new(
+                "f".into(),
+                "Does f\n\nExample:\n  f 1".to_string().into(),
+                "x: ...".into(),
+                0,
+            )
+        };
+        let all = serde_json::from_str::<serde_json::Value>(&res.format_json("a.nix")).unwrap();
+        let mut written = all.as_object().unwrap().keys().cloned().collect::<Vec<_>>();
+        let mut fields = JSON_FIELDS
+            .iter()
+            .map(|f| f.to_string())
+            .collect::<Vec<_>>();
+        written.sort();
+        fields.sort();
+        assert_eq!(written, fields);
+
+        let picked = ["line", "identifier", "file", "score"].map(String::from);
+        assert_eq!(
+            res.format_json_fields("a.nix", &picked),
+            r#"{"line":0,"identifier":"f","file":"a.nix","score":3}"#
+        );
+        // optional fields without a value are left out, as they are with all of them
+        let res = SearchResult::new("f".into(), String::new().into(), "x: ...".into(), 0);
+        assert_eq!(
+            res.format_json_fields("a.nix", &picked),
+            r#"{"line":0,"identifier":"f","file":"a.nix"}"#
+        );
+    }
+
+    #[test]
+    fn test_highlight_nix() {
+        let source = "{ a, b ? \"x\" }: let c = 1; in ...";
+        let highlighted = highlight_nix(source);
+        assert_eq!(strip_colors(&highlighted), source);
+        #[cfg(feature = "color")]
+        {
+            assert!(highlighted.contains(&"let".blue().to_string()));
+            assert!(highlighted.contains(&"b".white().to_string()));
+            assert!(highlighted.contains(&"1".green().to_string()));
+            assert!(highlighted.contains(&"...".dark_gray().to_string()));
+        }
+
+        let res = SearchResult::new("f".into(), String::new().into(), "x: ...".into(), 0);
+        let highlighted = FormatOptions {
+            color: true,
+            highlight: true,
+            ..FormatOptions::default()
+        };
+        let human = format_result(&res, OutputFormat::Human, &highlighted);
+        assert!(human.contains(&highlight_nix("x: ...")));
+        assert_eq!(
+            strip_colors(&human),
+            format_result(&res, OutputFormat::Human, &FormatOptions::default())
+        );
+    }
+}
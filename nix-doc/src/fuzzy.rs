@@ -0,0 +1,139 @@
+//! Fuzzy matching of identifiers, where the pattern's characters only have to appear in order, so
+//! `cMapStr` finds `concatMapStrings`
+//!
+//! Matches are scored the way fzf scores them, if more simply: every matched character scores,
+//! with bonuses for starting a word (the start of the identifier, a camelCase hump, or after a
+//! `-`, `_` or `'`) and for following on from the previous match, and a penalty for each gap
+//! between matches. The best-scoring way of placing the pattern in the identifier is the one
+//! reported.
+
+/// Score for each matched character
+const MATCH: i64 = 16;
+/// Extra score for a character matched at the very start of the identifier
+const START_BONUS: i64 = 10;
+/// Extra score for a character matched at the start of a word
+const BOUNDARY_BONUS: i64 = 8;
+/// Extra score for a character matched straight after the previous one
+const CONSECUTIVE_BONUS: i64 = 8;
+/// Extra score for a character matched with the same case as in the pattern
+const CASE_BONUS: i64 = 1;
+/// Penalty for starting a gap between matched characters, and for each character it goes on for
+const GAP_START: i64 = 3;
+const GAP_EXTENSION: i64 = 1;
+
+/// Matches scoring less than this per pattern character are too scattered to be worth showing
+pub const MIN_SCORE_PER_CHAR: i64 = 10;
+
+/// How well an identifier matched a fuzzy pattern
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FuzzyMatch {
+    pub score: i64,
+    /// Indices, in characters, of where each character of the pattern matched
+    pub positions: Vec<usize>,
+}
+
+/// Matches `pattern` against `candidate`, ignoring case, returning `None` if the characters of
+/// the pattern don't all appear in order, or do but too scattered to be a good match (see
+/// [`MIN_SCORE_PER_CHAR`])
+pub fn fuzzy_match(pattern: &str, candidate: &str) -> Option<FuzzyMatch> {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let candidate: Vec<char> = candidate.chars().collect();
+    if pattern.is_empty() {
+        return Some(FuzzyMatch {
+            score: 0,
+            positions: Vec::new(),
+        });
+    }
+
+    let char_score = |i: usize, j: usize| -> Option<i64> {
+        let (p, c) = (pattern[i], candidate[j]);
+        if !p.to_lowercase().eq(c.to_lowercase()) {
+            return None;
+        }
+        let bonus = match j.checked_sub(1).map(|prev| candidate[prev]) {
+            None => START_BONUS,
+            Some(prev) if c.is_uppercase() && !prev.is_uppercase() => BOUNDARY_BONUS,
+            Some(prev) if "-_'.".contains(prev) => BOUNDARY_BONUS,
+            Some(_) => 0,
+        };
+        Some(MATCH + bonus + if p == c { CASE_BONUS } else { 0 })
+    };
+
+    // best[i][j]: the best score for matching pattern[..=i] with pattern[i] at candidate[j], and
+    // where pattern[i - 1] went to get it
+    let mut best: Vec<Vec<Option<(i64, usize)>>> = vec![vec![None; candidate.len()]; pattern.len()];
+    for i in 0..pattern.len() {
+        for j in i..candidate.len() {
+            let here = match char_score(i, j) {
+                Some(score) => score,
+                None => continue,
+            };
+            best[i][j] = if i == 0 {
+                Some((here, 0))
+            } else {
+                (i - 1..j)
+                    .filter_map(|k| best[i - 1][k].map(|(score, _)| (score, k)))
+                    .map(|(score, k)| {
+                        let gap = (j - k - 1) as i64;
+                        let transition = if gap == 0 {
+                            CONSECUTIVE_BONUS
+                        } else {
+                            -(GAP_START + (gap - 1) * GAP_EXTENSION)
+                        };
+                        (score + transition + here, k)
+                    })
+                    .max_by_key(|&(score, _)| score)
+            };
+        }
+    }
+
+    let last = pattern.len() - 1;
+    let (end, score) = best[last]
+        .iter()
+        .enumerate()
+        .filter_map(|(j, cell)| cell.map(|(score, _)| (j, score)))
+        .max_by_key(|&(_, score)| score)?;
+    if score < MIN_SCORE_PER_CHAR * pattern.len() as i64 {
+        return None;
+    }
+
+    let mut positions = vec![end];
+    for i in (1..=last).rev() {
+        let (_, prev) = best[i][positions[0]]?;
+        positions.insert(0, prev);
+    }
+    Some(FuzzyMatch { score, positions })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn positions(pattern: &str, candidate: &str) -> Option<Vec<usize>> {
+        fuzzy_match(pattern, candidate).map(|m| m.positions)
+    }
+
+    #[test]
+    fn test_matches() {
+        assert_eq!(
+            positions("cMapStr", "concatMapStrings"),
+            Some(vec![0, 6, 7, 8, 9, 10, 11])
+        );
+        assert_eq!(
+            positions("cmapstr", "concatMapStrings").map(|p| p.len()),
+            Some(7)
+        );
+        assert_eq!(positions("map", "concatMapStrings"), Some(vec![6, 7, 8]));
+        assert_eq!(positions("xyz", "concatMapStrings"), None);
+        assert_eq!(positions("sm", "mapStrings"), None);
+        // in order, but too spread out to be what was meant
+        assert_eq!(positions("cat", "cxxxxxxxxxxxxxxaxxxxxxxxxxxxxxt"), None);
+    }
+
+    #[test]
+    fn test_ranking() {
+        let score = |candidate| fuzzy_match("fold", candidate).unwrap().score;
+        assert!(score("foldAttrs'") > score("mapFold"));
+        assert!(score("mapFold") > score("fooOld"));
+    }
+}
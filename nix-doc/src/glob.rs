@@ -0,0 +1,85 @@
+//! Shell-style wildcard patterns for identifiers, like `concat*Sep`, translated to regexes
+use regex::escape;
+
+/// Translates the glob `glob` into a regex matching whole identifiers. `*` matches any run of
+/// characters, `?` any one character, and `[...]` any character in the set, with `[!...]` or
+/// `[^...]` for its complement.
+pub fn to_regex(glob: &str) -> Result<String, String> {
+    if glob.contains('/') {
+        return Err(format!(
+            "glob `{}` contains `/`, but globs here match identifiers, not paths (to pick which \
+             files are searched, pass a directory or use --include/--exclude)",
+            glob
+        ));
+    }
+
+    let mut out = String::from("^(?:");
+    let mut chars = glob.chars().peekable();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '*' => out.push_str(".*"),
+            '?' => out.push('.'),
+            '[' => {
+                out.push('[');
+                if let Some('!') | Some('^') = chars.peek() {
+                    chars.next();
+                    out.push('^');
+                }
+                // a `]` straight after the opening bracket is part of the set
+                if chars.peek() == Some(&']') {
+                    chars.next();
+                    out.push_str("\\]");
+                }
+                loop {
+                    match chars.next() {
+                        Some(']') => break,
+                        Some(ch @ '\\') | Some(ch @ '[') | Some(ch @ '&') | Some(ch @ '~') => {
+                            out.push('\\');
+                            out.push(ch);
+                        }
+                        Some(ch) => out.push(ch),
+                        None => return Err(format!("unclosed `[` in glob `{}`", glob)),
+                    }
+                }
+                out.push(']');
+            }
+            ch => out.push_str(&escape(ch.encode_utf8(&mut [0; 4]))),
+        }
+    }
+    out.push_str(")$");
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use regex::Regex;
+
+    fn matches(glob: &str, name: &str) -> bool {
+        Regex::new(&to_regex(glob).unwrap()).unwrap().is_match(name)
+    }
+
+    #[test]
+    fn test_wildcards() {
+        assert!(matches("concat*Sep", "concatStringsSep"));
+        assert!(matches("concat*Sep", "concatSep"));
+        assert!(!matches("concat*Sep", "concatStringsSeparated"));
+        assert!(!matches("concat", "concatMap"));
+        assert!(matches("map?", "mapA"));
+        assert!(!matches("map?", "map"));
+        assert!(matches("fold[lr]", "foldl"));
+        assert!(!matches("fold[!lr]", "foldl"));
+        assert!(matches("fold[!lr]", "folds"));
+        assert!(matches("x[]]", "x]"));
+        assert!(matches("a.b", "a.b"));
+        assert!(!matches("a.b", "axb"));
+        assert!(matches("to+", "to+"));
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(to_regex("lib/*").unwrap_err().contains("not paths"));
+        assert!(to_regex("fold[lr").is_err());
+    }
+}
@@ -0,0 +1,263 @@
+//! Looking up the documentation for a position in a file, for editors to show on hover
+use crate::format::ResultPrinter;
+use crate::lines::LineIndex;
+use crate::output::Output;
+use crate::parse::{is_blank_source, parse_nix};
+use crate::search::{search_ast_with_skips, SearchResult, SkipReason};
+use crate::{DefKind, Error, Result, ResultKind, SearchOptions};
+
+use regex::Regex;
+use rnix::ast::AttrpathValue;
+use rnix::SyntaxKind::*;
+use rnix::{Root, SyntaxNode, SyntaxToken, TextSize};
+use rowan::ast::AstNode;
+
+use std::fs;
+use std::path::Path;
+
+/// Finds the documented definition at the 1-based `line` and byte `col` of the nix source
+/// `content`. An identifier there naming a definition in the same file, like `map` in `map f xs`
+/// or `lib.map`, means that definition; anywhere else, it's the innermost documented definition
+/// around the position. `None` if there's nothing documented there, or the position is outside
+/// the file.
+pub fn hover(content: &str, line: usize, col: usize) -> Result<Option<SearchResult>> {
+    if is_blank_source(content) {
+        return Ok(None);
+    }
+    let pos = match LineIndex::new(content).offset(line, col) {
+        Some(pos) => pos,
+        None => return Ok(None),
+    };
+    let ast = parse_nix(content)?;
+    let opts = SearchOptions {
+        kinds: ResultKind::ALL.to_vec(),
+        ..SearchOptions::default()
+    };
+    let (results, _) = search_ast_with_skips(&Regex::new("")?, &ast, &opts);
+    let contains = |res: &&SearchResult| res.defined_at_start <= pos && pos < res.defined_at_end;
+    let innermost = |res: &&SearchResult| res.defined_at_end - res.defined_at_start;
+
+    // a key is the name of what it defines, which the definition around it covers
+    let referenced = ast
+        .syntax()
+        .token_at_offset(TextSize::from(pos as u32))
+        .find(|token| token.kind() == TOKEN_IDENT && !is_key(token));
+    if let Some(token) = referenced {
+        let named = results
+            .iter()
+            .filter(|res| res.identifier == token.text())
+            .collect::<Vec<_>>();
+        // the definition in scope is likelier the one around the cursor, if there is one
+        let found = named
+            .iter()
+            .copied()
+            .filter(contains)
+            .min_by_key(innermost)
+            .or_else(|| named.first().copied());
+        if let Some(found) = found {
+            return Ok(Some(found.clone()));
+        }
+    }
+    Ok(results
+        .iter()
+        .filter(contains)
+        .min_by_key(innermost)
+        .cloned())
+}
+
+/// Is the identifier `token` part of the key of a binding, like `b` in `a.b = 1;`? It's part of
+/// the same sort of attribute path in a select, like `lib.map`, but that one refers to something.
+fn is_key(token: &SyntaxToken) -> bool {
+    token
+        .parent_ancestors()
+        .nth(1)
+        .filter(|path| path.kind() == NODE_ATTRPATH)
+        .and_then(|path| path.parent())
+        .is_some_and(|binding| binding.kind() == NODE_ATTRPATH_VALUE)
+}
+
+/// What [`definition_at`] found at a position
+#[derive(Clone, Debug)]
+pub enum Definition {
+    /// A documented definition
+    Documented(Box<SearchResult>),
+    /// A binding without any documentation, by name
+    Undocumented(String),
+}
+
+/// Finds the innermost attrset entry or `let` binding around the 1-based `line` and byte `col` of
+/// the nix source `content`. In a doc comment, that's the definition it documents. `None` if
+/// there's no binding there, or the position is outside the file.
+pub fn definition_at(content: &str, line: usize, col: usize) -> Result<Option<Definition>> {
+    if is_blank_source(content) {
+        return Ok(None);
+    }
+    let pos = match LineIndex::new(content).offset(line, col) {
+        Some(pos) => pos,
+        None => return Ok(None),
+    };
+    let ast = parse_nix(content)?;
+    let binding = match binding_at(&ast, pos) {
+        Some(binding) => binding,
+        None => return Ok(None),
+    };
+    let key = match binding.attrpath() {
+        Some(key) => key,
+        None => return Ok(None),
+    };
+    let opts = SearchOptions {
+        kinds: ResultKind::ALL.to_vec(),
+        def_kinds: vec![DefKind::Attr, DefKind::RecAttr, DefKind::LetIn],
+        explain_skips: true,
+        ..SearchOptions::default()
+    };
+    let (results, skips) = search_ast_with_skips(&Regex::new("")?, &ast, &opts);
+    // results and skips both start at the whole of their key
+    let key_start = usize::from(key.syntax().text_range().start());
+    if let Some(res) = results
+        .into_iter()
+        .find(|res| res.defined_at_start == key_start)
+    {
+        return Ok(Some(Definition::Documented(Box::new(res))));
+    }
+    Ok(skips
+        .into_iter()
+        .find(|skip| {
+            skip.defined_at_start == key_start && matches!(skip.reason, SkipReason::NoComment)
+        })
+        .map(|skip| Definition::Undocumented(skip.identifier)))
+}
+
+/// The innermost binding around byte `pos` of `ast`, or the one after it if `pos` is in a
+/// comment
+fn binding_at(ast: &Root, pos: usize) -> Option<AttrpathValue> {
+    let mut token = ast
+        .syntax()
+        .token_at_offset(TextSize::from(pos as u32))
+        .right_biased()?;
+    if token.kind() == TOKEN_COMMENT {
+        while token.kind().is_trivia() {
+            token = token.next_token()?;
+        }
+    }
+    token
+        .parent_ancestors()
+        .find(|node: &SyntaxNode| node.kind() == NODE_ATTRPATH_VALUE)
+        .and_then(AttrpathValue::cast)
+}
+
+/// Prints the definition at `line` and `col` of `file` (see [`definition_at`]) to stdout the way
+/// `nix-doc search` shows results, if it's documented. Returns what was there.
+pub fn print_definition_at(file: &Path, line: usize, col: usize) -> Result<Option<Definition>> {
+    let content = fs::read_to_string(file).map_err(|err| Error::io(file, err))?;
+    let found = definition_at(&content, line, col)?;
+    if let Some(Definition::Documented(res)) = &found {
+        let opts = SearchOptions::default();
+        let mut printer = ResultPrinter::start(&opts, Output::open(None)?);
+        printer.print_result(res, file.display(), &opts);
+        printer.finish()?;
+    }
+    Ok(found)
+}
+
+/// The documented definition at `line` and `col` of `file` (see [`hover`]) as JSON, like a
+/// result of `nix-doc search --json`, or `{}` if there isn't one
+#[cfg(feature = "serde")]
+pub fn hover_json(file: &Path, line: usize, col: usize) -> Result<String> {
+    let content = fs::read_to_string(file).map_err(|err| Error::io(file, err))?;
+    Ok(match hover(&content, line, col)? {
+        Some(res) => res.format_json(file.display()),
+        None => "{}".to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hover() {
+        let src = r#"{
+  # Applies `f` to each element
+  map = f: list: [];
+
+  # Maps over the strings
+  mapStrings = f: strings:
+    map f strings;
+
+  # Shadowed inside
+  strings = {
+    # The inner map
+    map = g: xs: lib.map g xs;
+  };
+
+  undocumented = x: map x;
+}"#;
+        let at = |line, col| hover(src, line, col).unwrap().map(|res| res.doc);
+        // on a definition's name, or anywhere in it
+        assert_eq!(at(3, 4).as_deref(), Some("Applies `f` to each element"));
+        assert_eq!(at(6, 18).as_deref(), Some("Maps over the strings"));
+        // on a reference, which is looked up by name
+        assert_eq!(at(7, 5).as_deref(), Some("Applies `f` to each element"));
+        assert_eq!(at(15, 21).as_deref(), Some("Applies `f` to each element"));
+        // the one whose definition the cursor is in wins, as the one in scope
+        assert_eq!(at(12, 23).as_deref(), Some("The inner map"));
+        // the innermost definition around the cursor
+        assert_eq!(at(12, 14).as_deref(), Some("The inner map"));
+        assert_eq!(at(10, 5).as_deref(), Some("Shadowed inside"));
+        assert_eq!(at(15, 3), None);
+        assert_eq!(at(40, 1), None);
+
+        let res = hover(src, 3, 4).unwrap().unwrap();
+        assert_eq!(res.identifier, "map");
+        assert_eq!(res.param_block, "f: list: ...");
+    }
+
+    #[test]
+    fn test_definition_at() {
+        let src = r#"{
+  # Applies `f` to each element
+  map = f: list: [];
+
+  strings = {
+    /* The inner one,
+       over two lines */
+    inner = g: xs: lib.map g xs;
+    plain = 1;
+  };
+
+  lets = let
+    # A helper
+    helper = x: x;
+    other = 2;
+  in helper;
+}"#;
+        let at = |line, col| match definition_at(src, line, col).unwrap() {
+            Some(Definition::Documented(res)) => Some(res.identifier),
+            Some(Definition::Undocumented(name)) => Some(format!("undocumented {}", name)),
+            None => None,
+        };
+        assert_eq!(at(3, 3).as_deref(), Some("map"));
+        // anywhere in the binding, not just its name
+        assert_eq!(at(3, 18).as_deref(), Some("map"));
+        // the comment is for the definition after it
+        assert_eq!(at(2, 10).as_deref(), Some("map"));
+        assert_eq!(at(7, 8).as_deref(), Some("inner"));
+        // the innermost binding, even if it isn't documented
+        assert_eq!(at(9, 5).as_deref(), Some("undocumented plain"));
+        assert_eq!(at(5, 3).as_deref(), Some("undocumented strings"));
+        assert_eq!(at(14, 7).as_deref(), Some("helper"));
+        assert_eq!(at(15, 5).as_deref(), Some("undocumented other"));
+        // outside of any binding
+        assert_eq!(at(1, 1), None);
+        assert_eq!(at(4, 1), None);
+        assert_eq!(at(40, 1), None);
+
+        let res = match definition_at(src, 8, 5).unwrap() {
+            Some(Definition::Documented(res)) => res,
+            other => panic!("expected a documented definition, got {:?}", other),
+        };
+        assert_eq!(res.doc, "The inner one,\nover two lines");
+        assert_eq!(res.param_block, "g: xs: ...");
+    }
+}
@@ -0,0 +1,119 @@
+//! Following `import ./file.nix` from an entry file, for searching the files its docs are in
+//! rather than a whole directory
+use crate::parse::parse_nix;
+
+use rnix::ast::{Apply, Expr};
+use rnix::SyntaxKind::*;
+use rnix::{Root, SyntaxNode};
+use rowan::ast::AstNode;
+
+use std::collections::{HashSet, VecDeque};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The files `import`ed by the one `ast` was parsed from, which is in `dir`. Only literal paths
+/// are followed, like `import ./lists.nix` or `builtins.import ../lib { }`, since computed ones
+/// can't be known without evaluating the code. Lookup paths like `<nixpkgs>` aren't followed
+/// either. An imported directory means its `default.nix`, as in nix.
+pub fn static_imports(ast: &Root, dir: &Path) -> Vec<PathBuf> {
+    ast.syntax()
+        .descendants()
+        .filter_map(Apply::cast)
+        .filter(|apply| {
+            apply.lambda().is_some_and(|f| {
+                matches!(f, Expr::Ident(_) | Expr::Select(_))
+                    && matches!(f.to_string().as_str(), "import" | "builtins.import")
+            })
+        })
+        .filter_map(|apply| path_literal(apply.argument()?.syntax()))
+        .map(|path| {
+            // without the `.` in `lib/./lists.nix`
+            let path = dir.join(path).components().collect::<PathBuf>();
+            match path.is_dir() {
+                true => path.join("default.nix"),
+                false => path,
+            }
+        })
+        .collect()
+}
+
+/// The path a literal like `./lists.nix` is, unless it's a lookup path or from the home
+/// directory
+fn path_literal(node: &SyntaxNode) -> Option<String> {
+    // one with `${...}` in it is computed
+    if node.kind() != NODE_PATH || node.first_child().is_some() {
+        return None;
+    }
+    Some(node.to_string()).filter(|path| !path.starts_with(['<', '~']))
+}
+
+/// `entry` and every file it imports, directly or through the files it imports (see
+/// [`static_imports`]), in the order they're found. An `entry` that's a directory means its
+/// `default.nix`. Each file is there once, however many times it's imported, so import cycles are
+/// fine. Files that can't be read or parsed are still there, for the search to report, but their
+/// imports can't be followed.
+pub fn reachable_files(entry: &Path) -> Vec<PathBuf> {
+    let entry = match entry.is_dir() {
+        true => entry.join("default.nix"),
+        false => entry.to_path_buf(),
+    };
+    let mut seen = HashSet::new();
+    let mut files = Vec::new();
+    let mut queue = VecDeque::from([entry]);
+    while let Some(file) = queue.pop_front() {
+        let canonical = fs::canonicalize(&file).unwrap_or_else(|_| file.clone());
+        if !seen.insert(canonical) {
+            continue;
+        }
+        let imports = fs::read_to_string(&file)
+            .ok()
+            .and_then(|content| parse_nix(&content).ok())
+            .map(|ast| static_imports(&ast, file.parent().unwrap_or_else(|| Path::new("."))));
+        queue.extend(imports.into_iter().flatten());
+        files.push(file);
+    }
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_static_imports() {
+        let ast = rnix::Root::parse(
+            r#"
+            let
+              lists = import ./lists.nix;
+              strings = builtins.import ../strings.nix { inherit lib; };
+              nixpkgs = import <nixpkgs> { };
+              computed = import (./. + "/computed.nix");
+              named = import name;
+            in { imported = import ./lists.nix; }
+            "#,
+        )
+        .ok()
+        .unwrap();
+        assert_eq!(
+            static_imports(&ast, Path::new("lib")),
+            [
+                Path::new("lib/lists.nix"),
+                Path::new("lib/../strings.nix"),
+                Path::new("lib/lists.nix")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_reachable_files() {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("testdata/imports");
+        let files = reachable_files(&root)
+            .iter()
+            .map(|file| fs::canonicalize(file).unwrap())
+            .map(|file| file.strip_prefix(&root).unwrap().display().to_string())
+            .collect::<Vec<_>>();
+        // the cycle back to `lists.nix` from `nested/default.nix` is only followed once, and
+        // `unused.nix` isn't imported by anything
+        assert_eq!(files, ["default.nix", "lists.nix", "nested/default.nix"]);
+    }
+}
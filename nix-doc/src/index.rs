@@ -0,0 +1,708 @@
+//! A persistent index of the documented definitions under a directory, made by
+//! `nix-doc index build`, so that searching a tree as big as nixpkgs doesn't parse every file in
+//! it each time. Files that changed since the index was made are parsed as usual.
+//!
+//! The index is a text file, a line for each file and definition, with tabs between the fields
+//! (shown as spaces here):
+//!
+//! ```text
+//! nix-doc index 1
+//! root /home/me/nixpkgs
+//! file 1700000000 0 1234 lib/strings.nix
+//! def function attr 200 12 3 3 2 0 - concatStrings list: ... Concatenates concatStrings
+//! tag since 21.05
+//! alias concat
+//! ```
+//!
+//! A `def` line is the kind, the definition kind, where it starts (a byte offset, a line and the
+//! columns), the arity (or `-`), whether it may be higher, the deprecation message (`-` for none,
+//! or `+` then the message), the identifier, the signature, the docs, then the attribute path,
+//! one field for each part. The `tag` and `alias` lines after it are its `@tag`s and the other
+//! names it's bound to. Tabs, newlines and backslashes in fields are escaped with backslashes.
+use crate::doc::{doc_sections, DocTag};
+use crate::format::ResultPrinter;
+use crate::lines::Position;
+use crate::log;
+use crate::output::Output;
+use crate::search::{filtered_out, synopsis, SearchResult};
+use crate::{DefKind, Error, OutputFormat, Result, ResultKind, SearchOptions};
+
+use regex::Regex;
+
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::UNIX_EPOCH;
+
+/// The version of the index format, on its first line. An index with another version is rebuilt
+/// rather than read.
+pub const INDEX_VERSION: u32 = 1;
+
+/// What the first line of an index starts with, before the version
+const HEADER: &str = "nix-doc index";
+
+/// When a file last changed, as far as the index can tell: its modification time and size
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct Stamp {
+    pub(crate) secs: u64,
+    pub(crate) nanos: u32,
+    pub(crate) size: u64,
+}
+
+impl Stamp {
+    pub(crate) fn of(file: &Path) -> io::Result<Stamp> {
+        let metadata = fs::metadata(file)?;
+        let modified = metadata
+            .modified()?
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        Ok(Stamp {
+            secs: modified.as_secs(),
+            nanos: modified.subsec_nanos(),
+            size: metadata.len(),
+        })
+    }
+}
+
+/// A documented definition, with what's needed to print it and filter it like a search does
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Entry {
+    identifier: String,
+    attr_path: Vec<String>,
+    doc: String,
+    tags: Vec<DocTag>,
+    signature: String,
+    kind: ResultKind,
+    def_kind: DefKind,
+    start: Position,
+    deprecated: Option<String>,
+    arity: Option<usize>,
+    arity_may_be_higher: bool,
+    aliases: Vec<String>,
+}
+
+impl Entry {
+    pub(crate) fn new(res: &SearchResult) -> Self {
+        Entry {
+            identifier: res.identifier.clone(),
+            attr_path: res.attr_path.clone(),
+            doc: res.doc.clone(),
+            tags: res.tags.clone(),
+            signature: res.param_block.clone(),
+            kind: res.kind,
+            def_kind: res.def_kind,
+            start: res.start,
+            deprecated: res.deprecated.clone(),
+            arity: res.arity,
+            arity_may_be_higher: res.arity_may_be_higher,
+            aliases: res.aliases.clone(),
+        }
+    }
+
+    /// The search result for this, in `file`, for a search with `opts`. It has none of what the
+    /// index leaves out, like where the definition ends or the comments as written.
+    fn result(&self, file: PathBuf, opts: &SearchOptions) -> SearchResult {
+        let res = SearchResult::new(
+            self.identifier.clone(),
+            self.doc.clone().into(),
+            self.signature.clone(),
+            self.start.byte,
+        );
+        SearchResult {
+            synopsis: synopsis(&self.doc),
+            sections: doc_sections(&self.doc),
+            tags: self.tags.clone(),
+            kind: self.kind,
+            def_kind: self.def_kind,
+            attr_path: self.attr_path.clone(),
+            start: self.start,
+            end: self.start,
+            identifier_start: self.start,
+            file: Some(file),
+            deprecated: self.deprecated.clone(),
+            arity: self.arity,
+            arity_may_be_higher: self.arity_may_be_higher,
+            aliases: match opts.aliases {
+                true => self.aliases.clone(),
+                false => Vec::new(),
+            },
+            ..res
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct IndexedFile {
+    stamp: Stamp,
+    entries: Vec<Entry>,
+}
+
+/// The documented definitions in each `.nix` file under a directory, as they were when the index
+/// was made, for a search to use instead of parsing the files that haven't changed since (see
+/// [`SearchOptions::index`])
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Index {
+    /// The directory, absolute
+    root: PathBuf,
+    /// By path relative to `root`
+    files: BTreeMap<PathBuf, IndexedFile>,
+    /// The working directory when the index was loaded, which relative paths searched are from
+    cwd: PathBuf,
+}
+
+impl Index {
+    /// Where the index of `root` is kept: `$XDG_CACHE_HOME/nix-doc` or `~/.cache/nix-doc`, in a
+    /// file named after a hash of the absolute path of `root`. `None` if there's no home
+    /// directory to put it in.
+    pub fn path_for(root: &Path) -> Option<PathBuf> {
+        let root = fs::canonicalize(root).unwrap_or_else(|_| root.to_path_buf());
+        let name = format!("{:016x}.idx", fnv1a(root.to_string_lossy().as_bytes()));
+        Some(cache_dir()?.join(name))
+    }
+
+    /// Indexes everything documented under `root`, the way `nix-doc index build` does, along
+    /// with the files that couldn't be, which are left out. Only the threads, backend and
+    /// cancellation of `opts` are used: the index has every kind of result and definition, for
+    /// a search to pick from. A cancelled build has only some of the files, so shouldn't be
+    /// saved.
+    #[cfg(feature = "fs")]
+    pub fn build(root: &Path, opts: &SearchOptions) -> Result<(Index, Vec<crate::FileError>)> {
+        use crate::search::{files_to_search, search_files};
+        use crate::walk::is_searchable;
+
+        let root = fs::canonicalize(root).map_err(|err| Error::io(root, err))?;
+        let opts = SearchOptions {
+            ordered: true,
+            ..extracting_everything(opts)
+        };
+        // stamped before they're read, so that a file changing while it's indexed is taken to
+        // have changed since, rather than the other way around
+        let stamps = files_to_search(&root, &opts, &is_searchable)
+            .filter_map(|(file, _)| Some((file.clone(), Stamp::of(&file).ok()?)))
+            .collect::<BTreeMap<_, _>>();
+        let (found, _) = search_files(&root, &Regex::new("")?, &opts, is_searchable);
+
+        let mut index = Index {
+            cwd: env::current_dir().unwrap_or_else(|_| root.clone()),
+            root: root.clone(),
+            files: BTreeMap::new(),
+        };
+        let mut errors = Vec::new();
+        for (file, results) in found {
+            let results = match results {
+                Ok(results) => results,
+                Err(err) => {
+                    errors.push(err);
+                    continue;
+                }
+            };
+            let (stamp, relative) = match (stamps.get(&file), file.strip_prefix(&root)) {
+                (Some(stamp), Ok(relative)) => (*stamp, relative),
+                _ => continue,
+            };
+            let entries = results.iter().map(Entry::new).collect();
+            index
+                .files
+                .insert(relative.to_path_buf(), IndexedFile { stamp, entries });
+        }
+        Ok((index, errors))
+    }
+
+    /// Reads the index at `path`. `None` if there isn't one, or it was written by a version of
+    /// nix-doc using another format, and so needs to be rebuilt.
+    pub fn load(path: &Path) -> Result<Option<Index>> {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(Error::io(path, err)),
+        };
+        let invalid = |line: usize, why: &str| {
+            let message = format!("line {} of the index: {}", line, why);
+            Error::io(path, io::Error::new(io::ErrorKind::InvalidData, message))
+        };
+        let mut lines = content.lines().enumerate().map(|(number, line)| {
+            let fields = line.split('\t').map(unescape).collect::<Vec<_>>();
+            (number + 1, fields)
+        });
+
+        let version = match lines.next() {
+            Some((_, fields)) if fields.len() == 1 => fields[0]
+                .strip_prefix(HEADER)
+                .and_then(|version| version.trim().parse::<u32>().ok()),
+            _ => None,
+        };
+        match version {
+            Some(INDEX_VERSION) => (),
+            Some(version) => {
+                log::info!("{} is an index of version {}", path.display(), version);
+                return Ok(None);
+            }
+            None => return Err(invalid(1, "not a nix-doc index")),
+        }
+        let root = match lines.next() {
+            Some((_, fields)) if fields.len() == 2 && fields[0] == "root" => {
+                PathBuf::from(&fields[1])
+            }
+            _ => return Err(invalid(2, "expected the root")),
+        };
+
+        let mut files = BTreeMap::new();
+        let mut file: Option<(PathBuf, IndexedFile)> = None;
+        for (number, fields) in lines {
+            let fields = fields.iter().map(String::as_str).collect::<Vec<_>>();
+            if let Some((_, indexed)) = &mut file {
+                match read_entry_line(&mut indexed.entries, &fields) {
+                    Some(Ok(())) => continue,
+                    Some(Err(why)) => return Err(invalid(number, why)),
+                    None => (),
+                }
+            }
+            match fields.as_slice() {
+                ["file", secs, nanos, size, path] => {
+                    let stamp = (|| {
+                        Some(Stamp {
+                            secs: secs.parse().ok()?,
+                            nanos: nanos.parse().ok()?,
+                            size: size.parse().ok()?,
+                        })
+                    })()
+                    .ok_or_else(|| invalid(number, "bad modification time or size"))?;
+                    let indexed = IndexedFile {
+                        stamp,
+                        entries: Vec::new(),
+                    };
+                    if let Some((path, indexed)) = file.replace((PathBuf::from(path), indexed)) {
+                        files.insert(path, indexed);
+                    }
+                }
+                _ if file.is_none() => return Err(invalid(number, "expected a file")),
+                _ => return Err(invalid(number, "unknown line")),
+            }
+        }
+        if let Some((path, indexed)) = file {
+            files.insert(path, indexed);
+        }
+        let cwd = env::current_dir().unwrap_or_else(|_| root.clone());
+        Ok(Some(Index { root, files, cwd }))
+    }
+
+    /// Writes the index to `path`, making the directory it goes in if need be. It's written
+    /// next to it first, then renamed over it, so that a search never reads half of one.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let mut out = String::new();
+        let _ = writeln!(out, "{} {}", HEADER, INDEX_VERSION);
+        let _ = writeln!(out, "root\t{}", escape(&self.root.to_string_lossy()));
+        for (file, indexed) in &self.files {
+            // a path that isn't unicode can't be written down, so the file is parsed each time
+            let file = match file.to_str() {
+                Some(file) => file,
+                None => continue,
+            };
+            let Stamp { secs, nanos, size } = indexed.stamp;
+            let _ = writeln!(out, "file\t{}\t{}\t{}\t{}", secs, nanos, size, escape(file));
+            for entry in &indexed.entries {
+                write_entry(&mut out, entry);
+            }
+        }
+
+        write_atomically(path, &out)
+    }
+
+    /// How many files are indexed
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    /// Are there no files indexed?
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// Can a search with `opts` be answered from an index? Not if it needs something the index
+    /// leaves out, like the arguments of functions, or the full positions JSON and the other
+    /// machine readable formats print, or if its results would be made differently, like with
+    /// another doc comment style. Those searches parse every file.
+    pub fn answers(opts: &SearchOptions) -> bool {
+        // a definition of a kind that isn't wanted is tried as the kinds after it, which the
+        // index doesn't have it as
+        let kinds = [ResultKind::Option, ResultKind::Package, ResultKind::Value]
+            .map(|kind| opts.wants_kind(kind));
+        opts.format == OutputFormat::Human
+            && opts.file_source.is_none()
+            && kinds.windows(2).all(|pair| pair[0] || !pair[1])
+            && !opts.any_key
+            && !opts.include_reexports
+            && !opts.raw_docs
+            && opts.doc_prefix.is_none()
+            && opts.fuzzy.is_none()
+            && !opts.all_fields
+            && !opts.explain_skips
+            && opts.takes_arg.is_none()
+            && opts.arg_default.is_none()
+            && opts.arg_required.is_none()
+    }
+
+    /// The results of searching `file` for `matching` with `opts`, from the index, if it can
+    /// answer the search (see [`Index::answers`]) and `file` hasn't changed since it was indexed
+    pub fn results(
+        &self,
+        file: &Path,
+        matching: &Regex,
+        opts: &SearchOptions,
+    ) -> Option<Vec<SearchResult>> {
+        if !Index::answers(opts) {
+            return None;
+        }
+        let absolute = self.cwd.join(file);
+        let indexed = self.files.get(absolute.strip_prefix(&self.root).ok()?)?;
+        if Stamp::of(file).ok()? != indexed.stamp {
+            log::info!("{} changed since it was indexed", file.display());
+            return None;
+        }
+        Some(matching_entries(&indexed.entries, file, matching, opts))
+    }
+
+    /// Everything in the index that matches `matching` with `opts`, in the order of the files,
+    /// without looking at the files themselves, so some of it may be out of date
+    pub fn query(&self, matching: &Regex, opts: &SearchOptions) -> Vec<SearchResult> {
+        let mut results = Vec::new();
+        for (file, indexed) in &self.files {
+            let file = self.root.join(file);
+            results.extend(matching_entries(&indexed.entries, &file, matching, opts));
+        }
+        results
+    }
+
+    /// Prints what [`Index::query`] finds the way `nix-doc search` prints results, returning
+    /// how many there were
+    pub fn print_query(&self, matching: &Regex, opts: &SearchOptions) -> Result<usize> {
+        let results = self.query(matching, opts);
+        let mut printer = ResultPrinter::start(opts, Output::open(opts.output.as_deref())?);
+        for res in &results {
+            let file = res.file.as_deref().unwrap_or(&self.root);
+            printer.print_result(res, file.display(), opts);
+        }
+        printer.finish()?;
+        Ok(results.len())
+    }
+}
+
+/// nix-doc's directory in the user's cache directory, `$XDG_CACHE_HOME/nix-doc` or
+/// `~/.cache/nix-doc`, if there's a home directory for it to be in
+pub(crate) fn cache_dir() -> Option<PathBuf> {
+    let cache = env::var_os("XDG_CACHE_HOME").map(PathBuf::from);
+    let home = env::var_os("HOME").map(PathBuf::from);
+    cache_dir_in(cache.as_deref(), home.as_deref())
+}
+
+fn cache_dir_in(cache: Option<&Path>, home: Option<&Path>) -> Option<PathBuf> {
+    match (cache, home) {
+        (Some(cache), _) if cache.is_absolute() => Some(cache.join("nix-doc")),
+        (_, Some(home)) => Some(home.join(".cache/nix-doc")),
+        _ => None,
+    }
+}
+
+/// Options like `opts` for finding everything documented in a file, of every kind of result and
+/// definition, for an index or cache of it to answer any search from
+pub(crate) fn extracting_everything(opts: &SearchOptions) -> SearchOptions {
+    SearchOptions {
+        kinds: ResultKind::ALL.to_vec(),
+        def_kinds: DefKind::ALL.to_vec(),
+        file_functions: true,
+        aliases: true,
+        jobs: opts.jobs,
+        backend: opts.backend,
+        cancel: opts.cancel.clone(),
+        profile: opts.profile.clone(),
+        ..SearchOptions::default()
+    }
+}
+
+/// Writes `content` to `path`, making the directory it goes in if need be. It's written next to
+/// it first, then renamed over it, so that nothing reading it ever sees half of it, even with
+/// other threads or processes writing it at the same time.
+pub(crate) fn write_atomically(path: &Path, content: &str) -> Result<()> {
+    static WRITES: AtomicUsize = AtomicUsize::new(0);
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|err| Error::io(dir, err))?;
+    }
+    let write = WRITES.fetch_add(1, Ordering::Relaxed);
+    let mut partial = path.as_os_str().to_owned();
+    partial.push(format!(".{}-{}.tmp", std::process::id(), write));
+    let partial = PathBuf::from(partial);
+    fs::write(&partial, content).map_err(|err| Error::io(&partial, err))?;
+    fs::rename(&partial, path).map_err(|err| {
+        let _ = fs::remove_file(&partial);
+        Error::io(path, err)
+    })
+}
+
+/// The 64-bit FNV-1a hash of `bytes`, which unlike std's hashers is the same in every version
+pub(crate) fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// The `entries` of `file` that a search for `matching` with `opts` would find
+pub(crate) fn matching_entries(
+    entries: &[Entry],
+    file: &Path,
+    matching: &Regex,
+    opts: &SearchOptions,
+) -> Vec<SearchResult> {
+    entries
+        .iter()
+        .filter(|entry| opts.wants_def_kind(entry.def_kind) && matching.is_match(&entry.identifier))
+        .map(|entry| entry.result(file.to_path_buf(), opts))
+        .filter(|res| filtered_out(res, opts).is_none())
+        .collect()
+}
+
+/// Adds the `def` line for `entry` to `out`, and its `tag` and `alias` lines
+pub(crate) fn write_entry(out: &mut String, entry: &Entry) {
+    let arity = match entry.arity {
+        Some(arity) => arity.to_string(),
+        None => "-".to_string(),
+    };
+    let deprecated = match &entry.deprecated {
+        Some(message) => format!("+{}", message),
+        None => "-".to_string(),
+    };
+    let start = entry.start;
+    let _ = write!(
+        out,
+        "def\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+        entry.kind.name(),
+        entry.def_kind.name(),
+        start.byte,
+        start.line,
+        start.col_chars,
+        start.col_utf16,
+        arity,
+        entry.arity_may_be_higher as u8,
+        escape(&deprecated),
+        escape(&entry.identifier),
+        escape(&entry.signature),
+        escape(&entry.doc),
+    );
+    for part in &entry.attr_path {
+        let _ = write!(out, "\t{}", escape(part));
+    }
+    out.push('\n');
+    for tag in &entry.tags {
+        let _ = writeln!(out, "tag\t{}\t{}", escape(&tag.tag), escape(&tag.text));
+    }
+    for alias in &entry.aliases {
+        let _ = writeln!(out, "alias\t{}", escape(alias));
+    }
+}
+
+/// Reads a `def`, `tag` or `alias` line, split into its `fields`, into the `entries` it's
+/// one of, the last of them for a `tag` or `alias`. `None` if it's another kind of line.
+pub(crate) fn read_entry_line(
+    entries: &mut Vec<Entry>,
+    fields: &[&str],
+) -> Option<std::result::Result<(), &'static str>> {
+    let read = match fields {
+        ["def", fields @ ..] => parse_entry(fields)
+            .map(|entry| entries.push(entry))
+            .ok_or("bad definition"),
+        ["tag", tag, text] => match entries.last_mut() {
+            Some(entry) => {
+                entry.tags.push(DocTag {
+                    tag: tag.to_string(),
+                    text: text.to_string(),
+                });
+                Ok(())
+            }
+            None => Err("tag outside of a definition"),
+        },
+        ["alias", alias] => match entries.last_mut() {
+            Some(entry) => {
+                entry.aliases.push(alias.to_string());
+                Ok(())
+            }
+            None => Err("alias outside of a definition"),
+        },
+        _ => return None,
+    };
+    Some(read)
+}
+
+/// The fields of a `def` line after `def`
+fn parse_entry(fields: &[&str]) -> Option<Entry> {
+    let (head, attr_path) = (fields.get(..12)?, &fields[12..]);
+    let kind = head[0].parse().ok()?;
+    let def_kind = *DefKind::ALL.iter().find(|kind| kind.name() == head[1])?;
+    let start = Position {
+        byte: head[2].parse().ok()?,
+        line: head[3].parse().ok()?,
+        col_chars: head[4].parse().ok()?,
+        col_utf16: head[5].parse().ok()?,
+    };
+    let arity = match head[6] {
+        "-" => None,
+        arity => Some(arity.parse().ok()?),
+    };
+    let deprecated = match head[8] {
+        "-" => None,
+        message => Some(message.strip_prefix('+')?.to_string()),
+    };
+    Some(Entry {
+        identifier: head[9].to_string(),
+        attr_path: attr_path.iter().map(|part| part.to_string()).collect(),
+        doc: head[11].to_string(),
+        tags: Vec::new(),
+        signature: head[10].to_string(),
+        kind,
+        def_kind,
+        start,
+        deprecated,
+        arity,
+        arity_may_be_higher: head[7] == "1",
+        aliases: Vec::new(),
+    })
+}
+
+/// `field` with its tabs, newlines and backslashes escaped, to go on a line of the index
+pub(crate) fn escape(field: &str) -> String {
+    let mut out = String::with_capacity(field.len());
+    for ch in field.chars() {
+        match ch {
+            '\\' => out.push_str("\\\\"),
+            '\t' => out.push_str("\\t"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            ch => out.push(ch),
+        }
+    }
+    out
+}
+
+pub(crate) fn unescape(field: &str) -> String {
+    let mut out = String::with_capacity(field.len());
+    let mut chars = field.chars();
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            out.push(ch);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => out.push('\t'),
+            Some('n') => out.push('\n'),
+            Some('r') => out.push('\r'),
+            Some(other) => out.push(other),
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape() {
+        let field = "a\tb\nc\\d\re\\";
+        assert_eq!(escape(field), "a\\tb\\nc\\\\d\\re\\\\");
+        assert!(!escape(field).contains(['\t', '\n']));
+        assert_eq!(unescape(&escape(field)), field);
+    }
+
+    #[test]
+    fn test_cache_dir() {
+        // the same in every version and on every machine, so the index is found again
+        assert_eq!(fnv1a(b"/home/me/nixpkgs"), 0xccd0091860415c1b);
+        let home = Some(Path::new("/home/me"));
+        assert_eq!(
+            cache_dir_in(Some(Path::new("/cache")), home),
+            Some(PathBuf::from("/cache/nix-doc"))
+        );
+        // a relative `XDG_CACHE_HOME` is to be ignored
+        assert_eq!(
+            cache_dir_in(Some(Path::new("cache")), home),
+            Some(PathBuf::from("/home/me/.cache/nix-doc"))
+        );
+        assert_eq!(cache_dir_in(None, None), None);
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn test_index() {
+        use crate::search::search_results;
+        use crate::walk::is_searchable;
+        use std::sync::Arc;
+
+        let root = env::temp_dir().join(format!("nix-doc-index-{}", std::process::id()));
+        fs::create_dir_all(root.join("lib")).unwrap();
+        fs::write(
+            root.join("lib/lists.nix"),
+            "{\n  # Maps\n  #\n  # @since 21.05\n  map = f: xs: xs;\n  # Old\n  \
+             each = lib.warn \"use map\" map;\n  # Tab\tand \\ slash\n  \"a.b\" = x: x;\n}\n",
+        )
+        .unwrap();
+        fs::write(root.join("lib/broken.nix"), "{ a = ; }").unwrap();
+
+        let (index, errors) = Index::build(&root, &SearchOptions::default()).unwrap();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(index.len(), 1);
+        let path = root.join("index.idx");
+        index.save(&path).unwrap();
+        assert_eq!(Index::load(&path).unwrap().as_ref(), Some(&index));
+
+        let search = |opts: SearchOptions| {
+            let (results, _) =
+                search_results(&root, &Regex::new("").unwrap(), &opts, is_searchable);
+            results
+                .iter()
+                .map(|res| {
+                    let tags = res.tags.iter().map(|tag| tag.text.as_str());
+                    let tags = tags.collect::<Vec<_>>().join(",");
+                    format!("{}:{}:{}:{}", res.identifier, res.start.line, res.doc, tags)
+                })
+                .collect::<Vec<_>>()
+        };
+        let indexed = SearchOptions {
+            index: Some(Arc::new(index)),
+            ..SearchOptions::default()
+        };
+        let found = search(indexed.clone());
+        assert_eq!(
+            found,
+            [
+                "map:5:Maps:21.05",
+                "each:7:Old:",
+                "a.b:9:Tab\tand \\ slash:"
+            ]
+        );
+        assert_eq!(found, search(SearchOptions::default()));
+
+        // files changed since are parsed again
+        fs::write(
+            root.join("lib/lists.nix"),
+            "{\n  # Maps\n  map = f: x: x;\n}\n",
+        )
+        .unwrap();
+        assert_eq!(search(indexed.clone()), ["map:3:Maps:"]);
+        assert!(!Index::answers(&SearchOptions {
+            fuzzy: Some("mp".to_string()),
+            ..indexed
+        }));
+
+        // an index of another version is to be rebuilt, and anything else isn't an index
+        fs::write(&path, "nix-doc index 0\nroot\t/\n").unwrap();
+        assert_eq!(Index::load(&path).unwrap(), None);
+        fs::write(&path, "{}").unwrap();
+        assert!(Index::load(&path).is_err());
+        assert_eq!(Index::load(&root.join("missing.idx")).unwrap(), None);
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
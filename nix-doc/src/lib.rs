@@ -1,452 +1,806 @@
 //! library components of nix-doc
+// without the walker, some of what is shared with it goes unused
+#![cfg_attr(not(feature = "fs"), allow(dead_code, unused_imports))]
+pub mod builder;
+pub mod cache;
+pub mod changes;
+mod color;
+pub mod coverage;
+pub mod diagnostic;
+pub mod doc;
+pub mod error;
+pub mod filter;
+pub mod flake;
+pub mod format;
+pub mod fuzzy;
+pub mod glob;
+pub mod hover;
+#[cfg(feature = "fs")]
+pub mod imports;
+pub mod index;
+pub mod lines;
+pub mod lint;
+pub mod log;
+#[cfg(feature = "fs")]
+pub mod lookup;
+pub mod nixpath;
+pub mod options;
+pub mod output;
+pub mod parse;
+#[cfg(feature = "fs")]
+pub mod plan;
 pub mod pprint;
+pub mod profile;
+pub mod search;
+pub mod source;
+#[cfg(feature = "fs")]
+pub mod stream;
+pub mod table;
+#[cfg(feature = "fs")]
 pub mod tags;
+#[cfg(feature = "fs")]
 pub mod threadpool;
-
-use crate::pprint::pprint_args;
+pub mod walk;
+pub mod width;
+
+pub use crate::builder::SearchOptionsBuilder;
+pub use crate::parse::{
+    cleanup_comments, cleanup_single_line, comment_for, comment_for_ast, dedent_comment,
+    extract_doc, get_function_docs, Doc,
+};
+#[cfg(feature = "fs")]
+pub use crate::search::{search, search_files, search_formatted, search_patterns, search_results};
+pub use crate::search::{
+    search_ast, search_file, search_stdin, search_str, search_str_named, FileFound, SearchResult,
+};
+#[cfg(feature = "fs")]
+pub use crate::stream::{search_with, Search, SearchEvent, SearchIter, SearchStats};
+pub use crate::walk::{file_attr_path, is_searchable, DirFilter};
+#[cfg(feature = "fs")]
+pub use crate::walk::{is_ignored, list_files};
+
+use crate::cache::FileCache;
+use crate::changes::ChangedLines;
+use crate::doc::{has_example, has_type};
+use crate::index::Index;
+use crate::output::Glyphs;
+use crate::parse::DocStyle;
+use crate::pprint::ArgEntry;
+use crate::profile::{Phase, Profile};
+use crate::source::{FileSource, RealFs};
+#[cfg(feature = "fs")]
 use crate::threadpool::ThreadPool;
 
-use colorful::{Color, Colorful};
 use regex::Regex;
-use rnix::types::{AttrSet, EntryHolder, Ident, Lambda, TokenWrapper, TypedNode};
-use rnix::SyntaxKind::*;
-use rnix::{NodeOrToken, SyntaxNode, TextUnit, WalkEvent, AST};
-use walkdir::{DirEntry, WalkDir};
+#[cfg(feature = "serde")]
+use serde::Serialize;
 
-use std::fs;
-use std::iter;
-use std::path::Path;
-use std::sync::mpsc::channel;
-use std::{fmt::Display, str};
+use std::borrow::Cow;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering as AtomicOrdering};
+use std::sync::Arc;
+use std::{
+    fmt::{self, Display},
+    str,
+};
 
-pub type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+pub use crate::error::{Error, FileError};
 
-const DOC_INDENT: usize = 3;
+pub type Result<T> = std::result::Result<T, Error>;
 
-/// Max size of files we will consider searching. It takes a long time to parse 300k lines of nix
-/// in hackage-packages.nix and no files this big will have search results in them as they
-/// categorically do not contain functions. 200k bytes is ~7.5k lines
-const MAX_FILE_SIZE: u64 = 200_000;
+/// Knobs controlling how a search runs and what kinds of definitions it picks up
+#[derive(Clone, Debug, Default)]
+pub struct SearchOptions {
+    /// Also report package-like attrsets (e.g. `mkDerivation` calls) carrying a
+    /// `meta.description`, with the description standing in for the doc comment
+    pub packages: bool,
 
-struct SearchResult {
-    /// Name of the function
-    identifier: String,
+    /// Also report NixOS module option declarations, like `mkOption { ... }` and
+    /// `mkEnableOption "foo"`, with their description standing in for the doc comment
+    pub options: bool,
 
-    /// Dedented documentation comments
-    doc: String,
+    /// Also report definitions with computed keys like `${name}`, under the source of the key
+    pub any_key: bool,
 
-    /// Parameter block for the function
-    param_block: String,
+    /// Which kinds of results to report. Empty means just functions. The `packages`, `options`
+    /// and `any_value` switches each add their kind to these.
+    pub kinds: Vec<ResultKind>,
 
-    /// Start of the definition of the function
-    defined_at_start: usize,
-}
+    /// Also report documented attributes whose values aren't functions, with the kind of value in
+    /// place of a signature
+    pub any_value: bool,
+
+    /// Print results in a deterministic order (sorted by path) rather than as they come in
+    pub ordered: bool,
+
+    /// Collect all the results and print them sorted by this
+    pub sort_by: Option<SortKey>,
+
+    /// Flip the sort order. Without `sort_by`, this sorts by file, in reverse.
+    pub reverse: bool,
+
+    /// Print to stderr each binding that matched the pattern but was left out, and why
+    pub explain_skips: bool,
+
+    /// Leave out definitions that are deprecated shims around `warn`/`throw`
+    pub no_deprecated: bool,
+
+    /// Report names brought in with `inherit` that have a doc comment, like
+    /// `# Maps f over list` before `inherit (builtins) map;`, as well as definitions
+    pub include_reexports: bool,
+
+    /// Report at most this many results from any one file, the ones that come first in it, so
+    /// that one big generated file doesn't crowd the rest out. Applies after every other filter.
+    pub max_matches_per_file: Option<usize>,
+
+    /// How to print the results
+    pub format: OutputFormat,
+
+    /// Only report functions taking this many curried arguments (see [`pprint::arity`] for how
+    /// they're counted). Functions that might return another function are judged by the
+    /// arguments they visibly take.
+    pub arity: Option<ArityRange>,
+
+    /// Only report functions taking at least this many curried arguments
+    pub min_arity: Option<usize>,
+
+    /// Keep doc comments as written, only removing the comment delimiters
+    pub raw_docs: bool,
+
+    /// Color the signatures of human-readable results like nix source, when there are colors
+    pub highlight: bool,
+
+    /// Leave the `# file:line` line out of human-readable results, like `grep -h`
+    pub no_filename: bool,
+
+    /// Only take line comments starting with this, like `##`, as documentation, leaving plain `#`
+    /// comments for notes about the code. `None` takes every comment.
+    pub doc_prefix: Option<String>,
+
+    /// Which definition syntaxes to search. Empty means plain and `rec` attrsets, but not `let`
+    pub def_kinds: Vec<DefKind>,
+
+    /// Also report files that are a function as a whole, like most packages, under the name of
+    /// the file, as if [`DefKind::File`] were in `def_kinds`
+    pub file_functions: bool,
+
+    /// Only report definitions whose attribute path starts with these components. Each file's
+    /// attributes are taken to be under the path of the file relative to the search root (see
+    /// [`file_attr_path`]), so files elsewhere are not searched at all.
+    pub path_prefix: Vec<String>,
+
+    /// Only report the definition at exactly `path_prefix`, not the ones under it
+    pub path_exact: bool,
+
+    /// Only report definitions whose synopsis, the first line of their docs, matches this
+    pub match_synopsis: Option<Regex>,
+
+    /// Only report functions with a formal parameter, at any currying level, whose name matches
+    /// this. A pattern's `...` doesn't count as accepting anything.
+    pub takes_arg: Option<Regex>,
+
+    /// Only report functions with a pattern field matching this name that has a default, like
+    /// `stdenv` in `{ stdenv ? null }:`
+    pub arg_default: Option<Regex>,
+
+    /// Only report functions with a parameter matching this name that has to be given: a plain
+    /// argument, `@` binding, or pattern field without a default
+    pub arg_required: Option<Regex>,
+
+    /// Only report functions with an example in their docs (`Some(true)`), or only ones without
+    /// (`Some(false)`). See [`doc::has_example`] for what counts.
+    pub example: Option<bool>,
+
+    /// Only report functions whose docs give their type (`Some(true)`), or only ones that don't
+    /// (`Some(false)`). See [`doc::has_type`] for what counts.
+    pub typed: Option<bool>,
+
+    /// Which directories to walk into
+    pub dirs: DirFilter,
+
+    /// Only report results from files whose path, as it would be printed, matches this. Unlike
+    /// `dirs`, this doesn't change which files are read, only which results are kept.
+    pub file_regex: Option<Regex>,
+
+    /// Only report definitions starting on one of these lines, like the ones changed since a git
+    /// ref (see [`ChangedLines::since`]). Files without any aren't read at all.
+    pub changed: Option<Arc<ChangedLines>>,
+
+    /// Rather than walking the directory being searched, search the `default.nix` in it, or the
+    /// file given in its place, and the files that imports with `import ./file.nix`, and so on
+    /// (see [`imports::reachable_files`]). `dirs` doesn't apply then.
+    pub follow_imports: bool,
+
+    /// Where the files searched are walked and read from, if not the filesystem, like the
+    /// [`source::MemoryFs`] of an editor's buffers. Following imports always reads from the
+    /// filesystem.
+    pub file_source: Option<Arc<dyn FileSource>>,
+
+    /// An index of the directory searched, from `nix-doc index build`, to take what's in files
+    /// that haven't changed since it was made from, rather than parsing them. It's only used if
+    /// it can answer the search (see [`Index::answers`]).
+    pub index: Option<Arc<Index>>,
+
+    /// Where to keep what's documented in each file searched between runs, to take it from
+    /// there for files that haven't changed rather than parsing them again. As with
+    /// [`SearchOptions::index`], it's only used for searches an index could answer.
+    pub cache: Option<FileCache>,
+
+    /// Print `OutputFormat::Json` output as a bare array of results, the way it was before it
+    /// went in an object with a `schema_version` and the files that couldn't be searched
+    pub json_compat_array: bool,
 
-fn find_line(file: &str, pos: usize) -> usize {
-    file[..pos].lines().count()
+    /// Only write these fields of each result in `OutputFormat::Json` output, in this order, out
+    /// of the ones in [`format::JSON_FIELDS`]. Empty means all of them.
+    pub json_fields: Vec<String>,
+
+    /// Match identifiers against this fuzzy pattern (see [`fuzzy`]) rather than the regex, which
+    /// is then ignored. Results are sorted best match first unless `sort_by` says otherwise.
+    pub fuzzy: Option<String>,
+
+    /// What to run the search on
+    pub backend: Backend,
+
+    /// How many threads to search on, instead of one for each CPU
+    pub jobs: Option<usize>,
+
+    /// File to write results to instead of stdout, without colors
+    pub output: Option<PathBuf>,
+
+    /// How many characters wide `OutputFormat::Table` output can be, instead of the terminal's
+    /// width, or unlimited when not writing to one
+    pub width: Option<usize>,
+
+    /// The characters drawn between results and at the end of table cells that were cut short,
+    /// which are [`Glyphs::ASCII`] ones for terminals that can't show unicode
+    pub glyphs: Glyphs,
+
+    /// Print result paths relative to this directory, which should be absolute, instead of as
+    /// they were found. Files outside of it are printed with their absolute path.
+    pub relative_to: Option<PathBuf>,
+
+    /// Where to add up the time spent in each phase of the search, if anywhere
+    pub profile: Option<Arc<Profile>>,
+
+    /// Cancelled to stop the search early, like on Ctrl-C. Files not searched yet are skipped,
+    /// files being searched stop at the next attrset or `let`, and what was found up to then is
+    /// still printed or handed over.
+    pub cancel: Option<CancellationToken>,
+
+    /// Match the pattern against the attribute path and docs too, not just the name. Results
+    /// are sorted by relevance unless `sort_by` says otherwise, which puts name matches first.
+    pub all_fields: bool,
+
+    /// Also look for other bindings in the same file that are plain references to each result,
+    /// like `join = concatStringsSep;`, and list them under it
+    pub aliases: bool,
 }
 
-fn find_pos(file: &str, line: usize, col: usize) -> usize {
-    let mut lines = 1;
-    let mut line_start = 0;
-    for (count, ch) in file.chars().enumerate() {
-        if ch == '\n' {
-            lines += 1;
-            line_start = count;
+impl SearchOptions {
+    fn wants_kind(&self, kind: ResultKind) -> bool {
+        let switched_on = match kind {
+            ResultKind::Function => self.kinds.is_empty(),
+            ResultKind::Option => self.options,
+            ResultKind::Package => self.packages,
+            ResultKind::Value => self.any_value,
+        };
+        switched_on || self.kinds.contains(&kind)
+    }
+
+    /// The key to sort by, if results need to be collected and sorted before printing
+    fn sort_key(&self) -> Option<SortKey> {
+        match self.sort_by {
+            None if self.fuzzy.is_some() => Some(SortKey::Score),
+            None if self.all_fields => Some(SortKey::Relevance),
+            None if self.reverse => Some(SortKey::File),
+            key => key,
         }
-        if lines == line && count - line_start == col {
-            return count;
+    }
+
+    fn doc_style(&self) -> DocStyle<'_> {
+        DocStyle {
+            raw: self.raw_docs,
+            line_prefix: self.doc_prefix.as_deref(),
         }
     }
-    unreachable!();
-}
 
-impl SearchResult {
-    fn format<P: Display>(&self, filename: P, line: usize) -> String {
-        format!(
-            "{}\n{} = {}\n# {}",
-            indented(&self.doc, DOC_INDENT),
-            self.identifier.as_str().white().bold(),
-            self.param_block,
-            format!("{}:{}", filename, line).as_str(),
-        )
+    fn wants_def_kind(&self, kind: DefKind) -> bool {
+        if kind == DefKind::File && self.file_functions {
+            true
+        } else if self.def_kinds.is_empty() {
+            DefKind::DEFAULT.contains(&kind)
+        } else {
+            self.def_kinds.contains(&kind)
+        }
     }
-}
 
-/// Should the given path be searched?
-/// TODO: support globbing for files e.g. with lib in their name to improve perf significantly
-///       or avoid looking in absurdly large files like hackage.nix
-pub fn is_searchable(fname: &Path) -> bool {
-    fname.to_str().map(|s| s.ends_with(".nix")).unwrap_or(false)
-}
+    /// Could a file with attributes under `file_path` define anything under `path_prefix`?
+    fn path_could_match(&self, file_path: &[String]) -> bool {
+        let common = self.path_prefix.len().min(file_path.len());
+        self.path_prefix[..common] == file_path[..common]
+    }
 
-/// Runs a search for files matching the regex `matching`. Returns a list of such results with the
-/// associated file contents
-fn search_file(file: &Path, matching: &Regex) -> Result<Vec<(SearchResult, usize)>> {
-    // don't bother searching files that are so large they must be generated
-    let length = fs::metadata(file)?.len();
-    if length > MAX_FILE_SIZE {
-        return Ok(Vec::new());
-    }
-
-    let content = fs::read_to_string(file)?;
-    let ast = rnix::parse(&content).as_result()?;
-    let results = search_ast(&matching, &ast);
-
-    Ok(results
-        .into_iter()
-        .map(|res| {
-            let line = find_line(&content, res.defined_at_start);
-            (res, line)
-        })
-        .collect::<Vec<_>>())
-}
+    /// Is the definition at `attr_path` in a file with attributes under `file_path` under
+    /// `path_prefix` (or at it, with `path_exact`)?
+    fn path_matches(&self, file_path: &[String], attr_path: &[String]) -> bool {
+        let full_path = file_path.iter().chain(attr_path);
+        let len = full_path.clone().count();
+        (len == self.path_prefix.len() || !self.path_exact && len > self.path_prefix.len())
+            && self.path_prefix.iter().zip(full_path).all(|(a, b)| a == b)
+    }
 
-/// Is a file hidden or a unicode decode error?
-/// Let's not consider it.
-pub fn is_ignored(entry: &DirEntry) -> bool {
-    entry
-        .file_name()
-        .to_str()
-        .map(|s| s != "." && s.starts_with('.') || s == "target")
-        .unwrap_or(true)
-}
+    fn synopsis_matches(&self, synopsis: Option<&str>) -> bool {
+        match &self.match_synopsis {
+            Some(re) => synopsis.is_some_and(|s| re.is_match(s)),
+            None => true,
+        }
+    }
 
-/// Search the `dir` for files with function definitions matching `matching`
-pub fn search<F>(dir: &Path, matching: Regex, should_search: F)
-where
-    F: Fn(&Path) -> bool,
-{
-    let pool = ThreadPool::default();
-    let (tx, rx) = channel();
-
-    //println!("searching {}", dir.display());
-    for direntry in WalkDir::new(dir)
-        .into_iter()
-        .filter_entry(|e| !is_ignored(e))
-        .filter_map(|e| e.ok())
-        .filter(|e| should_search(e.path()) && e.path().is_file())
-    {
-        let my_tx = tx.clone();
-        let matching = matching.clone();
-        pool.push(move || {
-            //println!("{}", direntry.path().display());
-            let results = search_file(direntry.path(), &matching);
-            if let Err(err) = results {
-                eprintln!("Failure handling {}: {}", direntry.path().display(), err);
-                return;
-            }
-            let results = results.unwrap();
+    /// A pool with the threads `jobs` asks for
+    #[cfg(feature = "fs")]
+    fn thread_pool(&self) -> ThreadPool {
+        match self.jobs {
+            Some(jobs) => ThreadPool::with_threads(jobs),
+            None => ThreadPool::default(),
+        }
+    }
 
-            let formatted = results
-                .iter()
-                .map(|(result, line)| result.format(direntry.path().display(), *line))
-                .collect::<Vec<_>>();
-            if !formatted.is_empty() {
-                my_tx
-                    .send(formatted)
-                    .expect("failed to send messages to display");
-            }
-        });
+    /// Are the bindings left out of the search kept track of, to be explained or logged?
+    pub(crate) fn wants_skips(&self) -> bool {
+        self.explain_skips || log::enabled(log::Level::Info)
     }
 
-    drop(tx);
-    pool.done();
+    /// Has the search been asked to stop?
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel
+            .as_ref()
+            .is_some_and(CancellationToken::is_cancelled)
+    }
 
-    let line = iter::repeat("─")
-        .take(45)
-        .collect::<String>()
-        .color(Color::Grey27);
-    let mut is_first = true;
+    /// Runs `f`, timing it as `phase` if the search is being profiled
+    fn timed<T>(&self, phase: Phase, f: impl FnOnce() -> T) -> T {
+        match &self.profile {
+            Some(profile) => profile.time(phase, f),
+            None => f(),
+        }
+    }
 
-    while let Ok(results) = rx.recv() {
-        for result in results {
-            if !is_first {
-                println!("{}", &line);
-            } else {
-                is_first = false;
-            }
-            println!("{}", result);
+    /// How to print the path of `file`, a file that was searched (see `relative_to`)
+    fn display_path<'a>(&self, file: &'a Path) -> Cow<'a, Path> {
+        let base = match &self.relative_to {
+            Some(base) => base,
+            None => return Cow::Borrowed(file),
+        };
+        let absolute = self.source().absolute(file);
+        match absolute.strip_prefix(base) {
+            Ok(relative) => Cow::Owned(relative.to_path_buf()),
+            Err(_) => Cow::Owned(absolute),
         }
     }
-}
 
-/// Searches the given AST for functions called `identifier`
-fn search_ast(identifier: &Regex, ast: &AST) -> Vec<SearchResult> {
-    let mut results = Vec::new();
-    for ev in ast.node().preorder_with_tokens() {
-        match ev {
-            WalkEvent::Enter(enter) => {
-                //println!("enter {:?}", &enter);
-                if let Some(set) = enter.into_node().and_then(AttrSet::cast) {
-                    results.extend(visit_attrset(identifier, &set));
-                }
-            }
-            WalkEvent::Leave(_leave) => {
-                //println!("leave {:?}", &leave);
+    /// Where the files searched come from
+    fn source(&self) -> &dyn FileSource {
+        self.file_source.as_deref().unwrap_or(&RealFs)
+    }
+
+    /// Could `file` have anything in it inside of `changed`?
+    fn file_changed(&self, file: &Path) -> bool {
+        self.changed
+            .as_ref()
+            .is_none_or(|changed| !changed.lines(file).is_empty())
+    }
+
+    /// Drops the results from `file` that don't start inside of `changed`
+    fn retain_changed(&self, file: &Path, results: &mut Vec<SearchResult>) {
+        if let Some(changed) = &self.changed {
+            let lines = changed.lines(file);
+            results.retain(|res| lines.iter().any(|lines| lines.contains(&res.start.line)));
+        }
+    }
+
+    /// Drops the results past `max_matches_per_file` from one file's, keeping the ones that come
+    /// first in it
+    fn cap_per_file(&self, results: &mut Vec<SearchResult>) {
+        if let Some(max) = self.max_matches_per_file {
+            if results.len() > max {
+                results.sort_by_key(|res| res.defined_at_start);
+                results.truncate(max);
             }
         }
     }
-    results
-}
 
-/// Emits a string `s` indented by `indent` spaces
-fn indented(s: &str, indent: usize) -> String {
-    let indent_s = iter::repeat(' ').take(indent).collect::<String>();
-    s.split('\n')
-        .map(|line| indent_s.clone() + line)
-        .collect::<Vec<_>>()
-        .join("\n")
-}
+    fn file_matches(&self, file: &Path) -> bool {
+        match &self.file_regex {
+            Some(re) => re.is_match(&file.to_string_lossy()),
+            None => true,
+        }
+    }
 
-/// Cleans up a single line, erasing prefix single line comments but preserving indentation
-fn cleanup_single_line<'a>(s: &'a str) -> &'a str {
-    let mut cmt_new_start = 0;
-    for (idx, ch) in s.char_indices() {
-        // if we find a character, save the byte position after it as our new string start
-        if ch == '#' || ch == '*' {
-            cmt_new_start = idx + 1;
-            break;
+    fn takes_arg_matches(&self, args: &[ArgEntry]) -> bool {
+        match &self.takes_arg {
+            Some(re) => args.iter().any(|arg| re.is_match(&arg.name)),
+            None => true,
         }
-        // if, instead, we are on a line with no starting comment characters, leave it alone as it
-        // will be handled by dedent later
-        if !ch.is_whitespace() {
-            break;
+    }
+
+    /// Does the `arg_default` filter (or, if not `has_default`, the `arg_required` one) pass for
+    /// a function taking `args`?
+    fn arg_default_matches(&self, args: &[ArgEntry], has_default: bool) -> bool {
+        let filter = if has_default {
+            &self.arg_default
+        } else {
+            &self.arg_required
+        };
+        match filter {
+            Some(re) => args
+                .iter()
+                .any(|arg| arg.has_default == has_default && re.is_match(&arg.name)),
+            None => true,
         }
     }
-    &s[cmt_new_start..]
-}
 
-/// Erases indents in comments. This is *almost* a normal dedent function, but it starts by looking
-/// at the second line if it can.
-fn dedent_comment(s: &str) -> String {
-    let mut whitespaces = 0;
-    let mut lines = s.lines();
-    let first = lines.next();
-
-    // scan for whitespace
-    for line in lines.chain(first) {
-        let line_whitespace = line.chars().take_while(|ch| ch.is_whitespace()).count();
-
-        if line_whitespace != line.len() {
-            // a non-whitespace line, perfect for taking whitespace off of
-            whitespaces = line_whitespace;
-            break;
+    fn example_matches(&self, res: &SearchResult) -> bool {
+        self.example
+            .is_none_or(|wanted| has_example(&res.doc, &res.tags) == wanted)
+    }
+
+    fn typed_matches(&self, res: &SearchResult) -> bool {
+        self.typed.is_none_or(|wanted| has_type(&res.doc) == wanted)
+    }
+
+    /// Does a definition with the given arity (`None` if it isn't a visible function) pass the
+    /// arity filters?
+    fn arity_matches(&self, arity: Option<usize>) -> bool {
+        match (self.arity, self.min_arity, arity) {
+            (None, None, _) => true,
+            (_, _, None) => false,
+            (range, min, Some(n)) => {
+                range.is_none_or(|r| r.contains(n)) && min.is_none_or(|m| n >= m)
+            }
         }
     }
+}
 
-    // maybe the first considered line we found was indented further, so let's look for more lines
-    // that might have a shorter indent. In the case of one line, do nothing.
-    for line in s.lines().skip(1) {
-        let line_whitespace = line.chars().take_while(|ch| ch.is_whitespace()).count();
+/// What kind of thing a result is
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum ResultKind {
+    /// A function, or a deprecated shim standing in for one
+    Function,
+    /// A NixOS module option declaration
+    Option,
+    /// A package with a `meta.description`
+    Package,
+    /// Any other documented value
+    Value,
+}
 
-        if line_whitespace != line.len() {
-            whitespaces = line_whitespace.min(whitespaces);
+impl ResultKind {
+    pub const ALL: &'static [ResultKind] = &[
+        ResultKind::Function,
+        ResultKind::Option,
+        ResultKind::Package,
+        ResultKind::Value,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            ResultKind::Function => "function",
+            ResultKind::Option => "option",
+            ResultKind::Package => "package",
+            ResultKind::Value => "value",
         }
     }
+}
 
-    // delete up to `whitespaces` whitespace characters from each line and reconstitute the string
-    let mut out = String::new();
-    for line in s.lines() {
-        let content_begin = line.find(|ch: char| !ch.is_whitespace()).unwrap_or(0);
-        out.push_str(&line[content_begin.min(whitespaces)..]);
-        out.push('\n');
+impl str::FromStr for ResultKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        ResultKind::ALL
+            .iter()
+            .copied()
+            .find(|kind| kind.name() == s)
+            .ok_or_else(|| {
+                format!(
+                    "unknown kind `{}`, expected one of: function, option, package, value",
+                    s
+                )
+            })
     }
+}
 
-    out.truncate(out.trim_end_matches('\n').len());
-    out
+impl Display for ResultKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
 }
 
-/// Deletes whitespace and leading comment characters
-///
-/// Oversight we are choosing to ignore: if you put # characters at the beginning of lines in a
-/// multiline comment, they will be deleted.
-fn cleanup_comments<S: AsRef<str>, I: DoubleEndedIterator<Item = S>>(comment: &mut I) -> String {
-    dedent_comment(
-        &comment
-            .rev()
-            .map(|small_comment| {
-                small_comment
-                    .as_ref()
-                    // space before multiline start
-                    .trim_start()
-                    // multiline starts
-                    .trim_start_matches("/*")
-                    // trailing so we can grab multiline end
-                    .trim_end()
-                    // multiline ends
-                    .trim_end_matches("*/")
-                    // extra space that was in the multiline
-                    .trim()
-                    .split('\n')
-                    // erase single line comments and such
-                    .map(cleanup_single_line)
-                    .collect::<Vec<_>>()
-                    .join("\n")
-            })
-            .collect::<Vec<_>>()
-            .join("\n"),
-    )
+/// What syntax a definition was made with
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub enum DefKind {
+    /// An attribute in a plain attrset, `{ f = ...; }`
+    #[cfg_attr(feature = "serde", serde(rename = "attr"))]
+    Attr,
+    /// An attribute in a recursive attrset, `rec { f = ...; }`
+    #[cfg_attr(feature = "serde", serde(rename = "rec"))]
+    RecAttr,
+    /// A binding in a `let f = ...; in ...`
+    #[cfg_attr(feature = "serde", serde(rename = "let"))]
+    LetIn,
+    /// A whole file that is a function, `{ lib, ... }: ...`, named after the file
+    #[cfg_attr(feature = "serde", serde(rename = "file"))]
+    File,
 }
 
-/// Get the docs for a specific function
-pub fn get_function_docs(filename: &str, line: usize, col: usize) -> Option<String> {
-    let content = fs::read(filename).ok()?;
-    let decoded = str::from_utf8(&content).ok()?;
-    let pos = find_pos(&decoded, line, col);
-    let rowan_pos = TextUnit::from_usize(pos);
-    let tree = rnix::parse(decoded);
-
-    let mut lambda = None;
-    for node in tree.node().preorder() {
-        match node {
-            WalkEvent::Enter(n) => {
-                if n.text_range().start() >= rowan_pos && n.kind() == NODE_LAMBDA {
-                    lambda = Lambda::cast(n);
-                    break;
-                }
-            }
-            WalkEvent::Leave(_) => (),
+impl DefKind {
+    pub const ALL: &'static [DefKind] = &[
+        DefKind::Attr,
+        DefKind::RecAttr,
+        DefKind::LetIn,
+        DefKind::File,
+    ];
+
+    /// The kinds searched when none are asked for
+    const DEFAULT: &'static [DefKind] = &[DefKind::Attr, DefKind::RecAttr];
+
+    fn name(self) -> &'static str {
+        match self {
+            DefKind::Attr => "attr",
+            DefKind::RecAttr => "rec",
+            DefKind::LetIn => "let",
+            DefKind::File => "file",
         }
     }
-    let lambda = lambda?;
-    let res = visit_lambda("func".to_string(), pos, &lambda);
-    Some(res.format(filename, line))
 }
 
-fn visit_lambda(name: String, defined_at_start: usize, lambda: &Lambda) -> SearchResult {
-    // grab the arguments
-    let param_block = pprint_args(&lambda);
+impl Display for DefKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl str::FromStr for DefKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        DefKind::ALL
+            .iter()
+            .copied()
+            .find(|kind| kind.name() == s)
+            .ok_or_else(|| {
+                format!(
+                    "unknown definition kind `{}`, expected one of: attr, rec, let, file",
+                    s
+                )
+            })
+    }
+}
 
-    // find the doc comment
-    let comment = find_comment(lambda.node().clone()).unwrap_or_else(|| "".to_string());
+/// How search results are printed
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum OutputFormat {
+    /// Coloured, with the doc comment, signature and location of each result
+    #[default]
+    Human,
+    /// A JSON array of objects, with the `serde` feature
+    #[cfg(feature = "serde")]
+    Json,
+    /// CSV, with a header row
+    Csv,
+    /// A line per result, with its name, location and synopsis lined up in columns
+    Table,
+}
 
-    SearchResult {
-        identifier: name,
-        doc: comment,
-        param_block,
-        defined_at_start,
+impl OutputFormat {
+    fn name(self) -> &'static str {
+        match self {
+            OutputFormat::Human => "human",
+            #[cfg(feature = "serde")]
+            OutputFormat::Json => "json",
+            OutputFormat::Csv => "csv",
+            OutputFormat::Table => "table",
+        }
     }
 }
 
-fn visit_attrset(id_needle: &Regex, set: &AttrSet) -> Vec<SearchResult> {
-    let mut results = Vec::new();
-    for entry in set.entries() {
-        if let Some(lambda) = entry.value().and_then(Lambda::cast) {
-            if let Some(attr) = entry.key() {
-                let ident = attr.path().last().and_then(Ident::cast);
-                let defined_at_start = ident
-                    .as_ref()
-                    .map(|i| i.node().text_range().start().to_usize());
-
-                let ident_name = ident.as_ref().map(|id| id.as_str());
-
-                if ident_name.map(|id| id_needle.is_match(id)) != Some(true) {
-                    // rejected, not matching our pattern
-                    continue;
-                }
-                let ident_name = ident_name.unwrap();
-
-                let res = visit_lambda(ident_name.to_string(), defined_at_start.unwrap(), &lambda);
-                if !res.doc.is_empty() {
-                    results.push(res);
-                }
-            }
+impl str::FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "human" => Ok(OutputFormat::Human),
+            #[cfg(feature = "serde")]
+            "json" => Ok(OutputFormat::Json),
+            #[cfg(not(feature = "serde"))]
+            "json" => Err("JSON output needs nix-doc built with the `serde` feature".to_string()),
+            "csv" => Ok(OutputFormat::Csv),
+            "table" => Ok(OutputFormat::Table),
+            _ => Err(format!(
+                "unknown output format `{}`, expected one of: human, json, csv, table",
+                s
+            )),
         }
     }
-    results
 }
 
-fn find_comment(node: SyntaxNode) -> Option<String> {
-    let mut node = NodeOrToken::Node(node);
-    let mut comments = Vec::new();
-    loop {
-        loop {
-            if let Some(new) = node.prev_sibling_or_token() {
-                node = new;
-                break;
-            } else {
-                node = NodeOrToken::Node(node.parent()?);
-            }
+/// How [`search`] spreads the work of searching files across threads
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum Backend {
+    /// nix-doc's own [`ThreadPool`], fed each file as soon as the walk finds it
+    #[default]
+    Pool,
+    /// A rayon parallel iterator over all the files, which steals work between threads, so can
+    /// do better when a few huge files are mixed in with many small ones
+    #[cfg(feature = "rayon")]
+    Rayon,
+}
+
+impl Backend {
+    fn name(self) -> &'static str {
+        match self {
+            Backend::Pool => "pool",
+            #[cfg(feature = "rayon")]
+            Backend::Rayon => "rayon",
         }
+    }
+}
 
-        match node.kind() {
-            TOKEN_COMMENT => match &node {
-                NodeOrToken::Token(token) => comments.push(token.text().clone()),
-                NodeOrToken::Node(_) => unreachable!(),
-            },
-            // This stuff is found as part of `the-fn = f: ...`
-            // here:                           ^^^^^^^^
-            NODE_KEY | TOKEN_ASSIGN => (),
-            t if t.is_trivia() => (),
-            _ => break,
+impl str::FromStr for Backend {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "pool" => Ok(Backend::Pool),
+            #[cfg(feature = "rayon")]
+            "rayon" => Ok(Backend::Rayon),
+            #[cfg(not(feature = "rayon"))]
+            "rayon" => Err("this nix-doc was built without the `rayon` feature".to_string()),
+            _ => Err(format!(
+                "unknown backend `{}`, expected one of: pool, rayon",
+                s
+            )),
         }
     }
-    let doc = cleanup_comments(&mut comments.iter().map(|c| c.as_str()));
-    Some(doc).filter(|it| !it.is_empty())
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// What to sort results by with [`SearchOptions::sort_by`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SortKey {
+    /// Alphabetically by identifier
+    Name,
+    /// By file path, then position in the file
+    File,
+    /// By line number, then file path
+    Line,
+    /// Longest documentation first
+    DocLength,
+    /// Best [`SearchOptions::fuzzy`] match first, shortest identifier first among equals
+    Score,
+    /// Most likely to be what was searched for first, see [`SearchResult::relevance`]
+    Relevance,
+}
 
-    #[test]
-    fn test_bytepos() {
-        let fakefile = "abc\ndef\nghi";
-        assert_eq!(find_pos(fakefile, 2, 2), 5);
+impl SortKey {
+    fn name(self) -> &'static str {
+        match self {
+            SortKey::Name => "name",
+            SortKey::File => "file",
+            SortKey::Line => "line",
+            SortKey::DocLength => "doc-length",
+            SortKey::Score => "score",
+            SortKey::Relevance => "relevance",
+        }
     }
+}
 
-    #[test]
-    fn test_comment_stripping() {
-        let ex1 = ["/* blah blah blah\n      foooo baaar\n   blah */"];
-        assert_eq!(
-            cleanup_comments(&mut ex1.iter()),
-            "blah blah blah\n   foooo baaar\nblah"
-        );
+impl str::FromStr for SortKey {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "name" => Ok(SortKey::Name),
+            "file" => Ok(SortKey::File),
+            "line" => Ok(SortKey::Line),
+            "doc-length" => Ok(SortKey::DocLength),
+            "score" => Ok(SortKey::Score),
+            "relevance" => Ok(SortKey::Relevance),
+            _ => Err(format!(
+                "unknown sort key `{}`, expected one of: name, file, line, doc-length, score, \
+                 relevance",
+                s
+            )),
+        }
+    }
+}
+
+/// A way to stop a search from another thread, like when the query it's for has been replaced by
+/// a new one. Clones share the same flag, so one can be kept while another goes in
+/// [`SearchOptions::cancel`].
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
 
-        let ex2 = ["# a1", "#    a2", "# aa"];
-        assert_eq!(cleanup_comments(&mut ex2.iter()), "aa\n   a2\na1");
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
     }
 
-    #[test]
-    fn test_dedent() {
-        let ex1 = "a\n   b\n   c\n     d";
-        assert_eq!(dedent_comment(ex1), "a\nb\nc\n  d");
-        let ex2 = "a\nb\nc";
-        assert_eq!(dedent_comment(ex2), ex2);
-        let ex3 = "   a\n   b\n\n     c";
-        assert_eq!(dedent_comment(ex3), "a\nb\n\n  c");
+    /// Stops the searches using this token. There's no taking it back.
+    pub fn cancel(&self) {
+        self.0.store(true, AtomicOrdering::SeqCst);
     }
 
-    #[test]
-    fn test_single_line_comment_stripping() {
-        let ex1 = "    * a";
-        let ex2 = "    # a";
-        let ex3 = "   a";
-        assert_eq!(cleanup_single_line(ex1), " a");
-        assert_eq!(cleanup_single_line(ex2), " a");
-        assert_eq!(cleanup_single_line(ex3), ex3);
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(AtomicOrdering::Relaxed)
     }
+}
 
-    #[test]
-    fn test_regression_11() {
-        let out = r#"Create a fixed width string with additional prefix to match
-required width.
+/// A range of arities for [`SearchOptions::arity`], written `2`, `2+` or `1-3`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ArityRange {
+    pub min: usize,
+    /// Inclusive upper bound, or `None` for no limit
+    pub max: Option<usize>,
+}
 
-This function will fail if the input string is longer than the
-requested length.
+impl ArityRange {
+    pub fn exactly(n: usize) -> Self {
+        ArityRange {
+            min: n,
+            max: Some(n),
+        }
+    }
 
-Type: fixedWidthString :: int -> string -> string
+    pub fn contains(&self, n: usize) -> bool {
+        n >= self.min && self.max.is_none_or(|max| n <= max)
+    }
+}
 
-Example:
-  fixedWidthString 5 "0" (toString 15)
-  => "00015""#;
-        let ast = rnix::parse(include_str!("../testdata/regression-11.nix"))
-            .as_result()
-            .unwrap();
-        let results = search_ast(&regex::Regex::new("fixedWidthString").unwrap(), &ast);
-        assert_eq!(results.len(), 1);
+/// Written the way it's parsed, like `2`, `2+` or `1-3`
+impl Display for ArityRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.max {
+            Some(max) if max == self.min => write!(f, "{}", max),
+            Some(max) => write!(f, "{}-{}", self.min, max),
+            None => write!(f, "{}+", self.min),
+        }
+    }
+}
 
-        assert_eq!(results[0].doc, out);
+impl str::FromStr for ArityRange {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let number = |n: &str| {
+            n.trim().parse::<usize>().map_err(|_| {
+                format!(
+                    "invalid arity `{}`, expected a number like `2`, a minimum like `2+`, or a range \
+                     like `1-3`",
+                    s
+                )
+            })
+        };
+        if let Some(min) = s.strip_suffix('+') {
+            Ok(ArityRange {
+                min: number(min)?,
+                max: None,
+            })
+        } else if let Some((min, max)) = s.split_once('-') {
+            let (min, max) = (number(min)?, number(max)?);
+            if min > max {
+                return Err(format!(
+                    "invalid arity range `{}`, {} is more than {}",
+                    s, min, max
+                ));
+            }
+            Ok(ArityRange {
+                min,
+                max: Some(max),
+            })
+        } else {
+            number(s).map(ArityRange::exactly)
+        }
     }
 }
@@ -0,0 +1,164 @@
+//! Conversion between byte offsets and line/column positions in a file
+//!
+//! Lines and columns are both 1-based, and columns count bytes, matching the positions Nix
+//! reports. A `\r\n` line ending belongs to the line it ends, so CRLF files number their lines the
+//! same as LF ones.
+#[cfg(feature = "serde")]
+use serde::Serialize;
+
+/// A position in a file, in the units various consumers want: bytes for seeking, characters for
+/// editors, and UTF-16 code units for LSP. Lines and columns are 1-based.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct Position {
+    /// Byte offset from the start of the file
+    pub byte: usize,
+    pub line: usize,
+    /// Column counted in unicode scalar values
+    pub col_chars: usize,
+    /// Column counted in UTF-16 code units
+    pub col_utf16: usize,
+}
+
+/// Offsets of the start of each line in a file, built once so each lookup is a binary search
+/// rather than a scan from the start of the file.
+#[derive(Clone, Debug)]
+pub struct LineIndex {
+    /// Byte offset of the start of each line. Always starts with 0.
+    line_starts: Vec<usize>,
+    len: usize,
+}
+
+impl LineIndex {
+    pub fn new(text: &str) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(
+                text.bytes()
+                    .enumerate()
+                    .filter(|&(_, b)| b == b'\n')
+                    .map(|(i, _)| i + 1),
+            )
+            .collect();
+        LineIndex {
+            line_starts,
+            len: text.len(),
+        }
+    }
+
+    /// 1-based line containing the byte offset `pos`. Offsets past the end of the file count as
+    /// being on the last line.
+    pub fn line(&self, pos: usize) -> usize {
+        self.line_col(pos).0
+    }
+
+    /// 1-based line and byte column of the byte offset `pos`
+    pub fn line_col(&self, pos: usize) -> (usize, usize) {
+        let pos = pos.min(self.len);
+        let line = match self.line_starts.binary_search(&pos) {
+            Ok(idx) => idx,
+            Err(idx) => idx - 1,
+        };
+        (line + 1, pos - self.line_starts[line] + 1)
+    }
+
+    /// Full [`Position`] of the byte offset `pos` in `text`, which must be the text this index was
+    /// built from
+    pub fn position(&self, text: &str, pos: usize) -> Position {
+        let (line, col) = self.line_col(pos);
+        let line_start = self.line_starts[line - 1];
+        let before = &text[line_start..line_start + col - 1];
+        Position {
+            byte: line_start + col - 1,
+            line,
+            col_chars: before.chars().count() + 1,
+            col_utf16: before.encode_utf16().count() + 1,
+        }
+    }
+
+    /// Byte offset of the 1-based `line` and `col`, or `None` if the position is outside the file
+    pub fn offset(&self, line: usize, col: usize) -> Option<usize> {
+        let line_start = *self.line_starts.get(line.checked_sub(1)?)?;
+        let pos = line_start + col.checked_sub(1)?;
+        let line_end = self.line_starts.get(line).copied().unwrap_or(self.len);
+        if pos < line_end || pos == self.len {
+            Some(pos)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bytepos() {
+        let fakefile = "abc\ndef\nghi";
+        assert_eq!(LineIndex::new(fakefile).offset(2, 2), Some(5));
+    }
+
+    #[test]
+    fn test_start_and_end() {
+        let text = "abc\ndef\nghi";
+        let index = LineIndex::new(text);
+        assert_eq!(index.line_col(0), (1, 1));
+        assert_eq!(index.line_col(3), (1, 4));
+        assert_eq!(index.line_col(4), (2, 1));
+        assert_eq!(index.line_col(text.len() - 1), (3, 3));
+        assert_eq!(index.line_col(text.len()), (3, 4));
+
+        assert_eq!(index.offset(1, 1), Some(0));
+        assert_eq!(index.offset(2, 2), Some(5));
+        assert_eq!(index.offset(3, 4), Some(text.len()));
+        assert_eq!(index.offset(1, 5), None);
+        assert_eq!(index.offset(4, 1), None);
+        assert_eq!(index.offset(0, 1), None);
+    }
+
+    #[test]
+    fn test_trailing_newline() {
+        let index = LineIndex::new("abc\n");
+        assert_eq!(index.line_col(3), (1, 4));
+        assert_eq!(index.line_col(4), (2, 1));
+        assert_eq!(index.offset(2, 1), Some(4));
+    }
+
+    #[test]
+    fn test_crlf() {
+        let text = "abc\r\ndef\r\n\r\nghi";
+        let index = LineIndex::new(text);
+        assert_eq!(index.line_col(3), (1, 4));
+        assert_eq!(index.line_col(4), (1, 5));
+        assert_eq!(index.line_col(5), (2, 1));
+        assert_eq!(index.line(10), 3);
+        assert_eq!(index.line_col(text.find('g').unwrap()), (4, 1));
+        assert_eq!(index.offset(4, 1), text.find('g'));
+    }
+
+    #[test]
+    fn test_multibyte_position() {
+        // é is 2 bytes and 1 UTF-16 unit, 𝔣 is 4 bytes and 2 UTF-16 units
+        let text = "# é\n  é𝔣 = 1; f = 2;";
+        let index = LineIndex::new(text);
+        let pos = text.find("f =").unwrap();
+        assert_eq!(
+            index.position(text, pos),
+            Position {
+                byte: pos,
+                line: 2,
+                col_chars: 11,
+                col_utf16: 12,
+            }
+        );
+        assert_eq!(
+            index.position(text, 0),
+            Position {
+                byte: 0,
+                line: 1,
+                col_chars: 1,
+                col_utf16: 1,
+            }
+        );
+    }
+}
@@ -0,0 +1,249 @@
+//! Checks for documentation that has drifted out of step with the code it documents
+use crate::doc::doc_params;
+use crate::filter::FileFilter;
+use crate::lines::LineIndex;
+use crate::parse::{is_blank_source, parse_nix};
+use crate::search::search_ast_with_skips;
+#[cfg(feature = "fs")]
+use crate::threadpool::ThreadPool;
+#[cfg(feature = "fs")]
+use crate::walk::walk_files;
+use crate::walk::{is_too_large, DirFilter};
+use crate::{Error, OutputFormat, Result, SearchOptions};
+
+use regex::Regex;
+
+use std::collections::BTreeMap;
+use std::fmt::{self, Display};
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::channel;
+
+/// Mismatches to let slide, because the docs name things differently on purpose
+#[derive(Clone, Debug, Default)]
+pub struct Allowlist {
+    /// Each function, and the one name in its docs or parameters to allow, or `None` for all of
+    /// them
+    entries: Vec<(String, Option<String>)>,
+}
+
+impl Allowlist {
+    /// Allows all of the mismatches of a function, given as `function`, or just the ones about
+    /// one name, given as `function:name`
+    pub fn add(&mut self, entry: &str) {
+        let entry = match entry.split_once(':') {
+            Some((function, name)) => (function.to_string(), Some(name.to_string())),
+            None => (entry.to_string(), None),
+        };
+        self.entries.push(entry);
+    }
+
+    /// Adds the entries in a file, one per line in the form [`Allowlist::add`] takes. Blank
+    /// lines, and anything after a `#`, are ignored.
+    pub fn add_file(&mut self, path: &Path) -> Result<()> {
+        let content = fs::read_to_string(path).map_err(|err| Error::io(path, err))?;
+        for line in content.lines() {
+            let entry = line.split('#').next().unwrap_or_default().trim();
+            if !entry.is_empty() {
+                self.add(entry);
+            }
+        }
+        Ok(())
+    }
+
+    fn allows(&self, function: &str, name: &str) -> bool {
+        self.entries.iter().any(|(allowed, allowed_name)| {
+            allowed == function && allowed_name.as_deref().is_none_or(|n| n == name)
+        })
+    }
+}
+
+/// A way a function's docs disagree with its parameters
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum StaleArg {
+    /// The docs document a parameter by this name, but the function doesn't take one
+    NotAParameter(String),
+    /// The function takes this parameter, but the docs, which do document some, never mention it
+    Undocumented(String),
+}
+
+/// Something a lint found wrong with a function
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Finding {
+    pub line: usize,
+    pub identifier: String,
+    pub problem: StaleArg,
+}
+
+impl Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.problem {
+            StaleArg::NotAParameter(name) => write!(
+                f,
+                "`{}` documents `{}`, which isn't one of its parameters",
+                self.identifier, name
+            ),
+            StaleArg::Undocumented(name) => write!(
+                f,
+                "`{}` doesn't document its parameter `{}`",
+                self.identifier, name
+            ),
+        }
+    }
+}
+
+/// Compares the parameters the documented functions in the nix source `content` take with the
+/// ones their docs list (see [`crate::doc::doc_params`]). Functions whose docs don't list any
+/// are left alone, since there's nothing to be out of date.
+pub fn stale_args(content: &str, allow: &Allowlist) -> Result<Vec<Finding>> {
+    if is_blank_source(content) {
+        return Ok(Vec::new());
+    }
+    let ast = parse_nix(content)?;
+    let (results, _) = search_ast_with_skips(&Regex::new("")?, &ast, &SearchOptions::default());
+    let lines = LineIndex::new(content);
+
+    let mut findings = Vec::new();
+    for res in results {
+        let params = doc_params(&res.doc, &res.tags);
+        if params.listed.is_empty() {
+            continue;
+        }
+        let mut finding = |problem| {
+            findings.push(Finding {
+                line: lines.line(res.defined_at_start),
+                identifier: res.identifier.clone(),
+                problem,
+            })
+        };
+        for name in &params.listed {
+            if !res.args.iter().any(|arg| arg.name == *name) && !allow.allows(&res.identifier, name)
+            {
+                finding(StaleArg::NotAParameter(name.clone()));
+            }
+        }
+        for arg in &res.args {
+            let documented =
+                params.listed.contains(&arg.name) || params.mentioned.contains(&arg.name);
+            if !documented && !allow.allows(&res.identifier, &arg.name) {
+                finding(StaleArg::Undocumented(arg.name.clone()));
+            }
+        }
+    }
+    Ok(findings)
+}
+
+/// Prints the functions under `dir` whose docs are out of step with their parameters, as
+/// `file:line: problem`, sorted by file. Returns how many problems were found.
+#[cfg(feature = "fs")]
+pub fn lint<F>(dir: &Path, dirs: &DirFilter, allow: Allowlist, should_search: F) -> Result<usize>
+where
+    F: FileFilter,
+{
+    let pool = ThreadPool::default();
+    let (tx, rx) = channel();
+
+    for direntry in walk_files(dir, false, dirs, &should_search) {
+        let my_tx = tx.clone();
+        let allow = allow.clone();
+        pool.push(move || {
+            let path = direntry.path();
+            let found = match is_too_large(path) {
+                Ok(true) => Ok(Vec::new()),
+                Ok(false) => fs::read_to_string(path)
+                    .map_err(Into::into)
+                    .and_then(|content| stale_args(&content, &allow)),
+                Err(err) => Err(err),
+            };
+            match found {
+                Ok(found) => my_tx
+                    .send((direntry.into_path(), found))
+                    .expect("failed to send findings to display"),
+                Err(err) => err.in_file(path).warn(OutputFormat::Human),
+            }
+        });
+    }
+    drop(tx);
+    pool.done();
+
+    let by_file = rx
+        .into_iter()
+        .filter(|(_, found)| !found.is_empty())
+        .collect::<BTreeMap<PathBuf, Vec<Finding>>>();
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    let mut count = 0;
+    for (file, found) in &by_file {
+        for finding in found {
+            writeln!(out, "{}:{}: {}", file.display(), finding.line, finding)?;
+            count += 1;
+        }
+    }
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stale_args() {
+        let src = r#"{
+          /* Concatenates the strings in `list`, with `sep` between
+
+             # Inputs
+
+             `f`
+             : The function
+
+             `list`
+             : The strings
+          */
+          concatStringsSep = sep: list: builtins.concatStringsSep sep list;
+
+          # Maps `f` over `list`, which isn't documented as a parameter list
+          map = f: list: [];
+
+          # @param f The function
+          # @param list The list
+          imap = f: list: [];
+
+          # @param stdenv The stdenv
+          mkThing = { stdenv, lib }: {};
+        }"#;
+        let found = |allow: &Allowlist| {
+            stale_args(src, allow)
+                .unwrap()
+                .into_iter()
+                .map(|finding| (finding.line, finding.to_string()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            found(&Allowlist::default()),
+            [
+                (
+                    12,
+                    "`concatStringsSep` documents `f`, which isn't one of its parameters"
+                        .to_string()
+                ),
+                (
+                    22,
+                    "`mkThing` doesn't document its parameter `lib`".to_string()
+                ),
+            ]
+        );
+
+        let mut allow = Allowlist::default();
+        allow.add("concatStringsSep:f");
+        allow.add("mkThing");
+        assert!(found(&allow).is_empty());
+        let mut allow = Allowlist::default();
+        allow.add("concatStringsSep:sep");
+        assert_eq!(found(&allow).len(), 2);
+
+        assert!(stale_args("# nothing here\n", &Allowlist::default())
+            .unwrap()
+            .is_empty());
+    }
+}
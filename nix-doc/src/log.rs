@@ -0,0 +1,102 @@
+//! What a search is doing and why, on stderr for `-v` and `-vv`, so that finding out why a file
+//! or a binding was left out doesn't mean adding prints to the search
+use std::fmt;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::{Mutex, MutexGuard};
+
+/// How much gets logged, each level logging everything the ones before it do
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    /// Nothing, the default
+    Quiet,
+    /// The files searched, and why bindings that matched were left out (`-v`)
+    Info,
+    /// What was decided about every binding in every set searched (`-vv`)
+    Debug,
+}
+
+/// The [`Level`] logged at, as a number
+static LEVEL: AtomicU8 = AtomicU8::new(Level::Quiet as u8);
+
+/// Held while a line of log or a result is written, so neither ends up inside the other
+static STDERR: Mutex<()> = Mutex::new(());
+
+/// Logs at the level `verbosity` counts up to, 0 being quiet, as with the number of `-v`s
+pub fn set_verbosity(verbosity: u8) {
+    let level = match verbosity {
+        0 => Level::Quiet,
+        1 => Level::Info,
+        _ => Level::Debug,
+    };
+    LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+/// Is anything at `level` logged?
+#[inline]
+pub fn enabled(level: Level) -> bool {
+    level != Level::Quiet && LEVEL.load(Ordering::Relaxed) >= level as u8
+}
+
+/// Keeps results from being written at the same time as a line of log, if anything is logged
+pub(crate) fn lock() -> Option<MutexGuard<'static, ()>> {
+    match enabled(Level::Info) {
+        true => Some(
+            STDERR
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner()),
+        ),
+        false => None,
+    }
+}
+
+/// Writes `args` to stderr as a line of log at `level`. Use [`info!`] and [`debug!`] instead,
+/// which don't format anything unless the level is logged.
+pub(crate) fn write(level: Level, args: fmt::Arguments<'_>) {
+    let _guard = lock();
+    let prefix = match level {
+        Level::Debug => "debug",
+        _ => "info",
+    };
+    eprintln!("nix-doc: {}: {}", prefix, args);
+}
+
+/// Logs a line at [`Level::Info`], formatted like `format!`
+macro_rules! info {
+    ($($arg:tt)*) => {
+        if $crate::log::enabled($crate::log::Level::Info) {
+            $crate::log::write($crate::log::Level::Info, format_args!($($arg)*));
+        }
+    };
+}
+
+/// Logs a line at [`Level::Debug`], formatted like `format!`
+macro_rules! debug {
+    ($($arg:tt)*) => {
+        if $crate::log::enabled($crate::log::Level::Debug) {
+            $crate::log::write($crate::log::Level::Debug, format_args!($($arg)*));
+        }
+    };
+}
+
+pub(crate) use {debug, info};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_levels() {
+        // the level is global, so this is the only test that changes it
+        assert!(!enabled(Level::Info));
+        assert!(lock().is_none());
+        set_verbosity(1);
+        assert!(enabled(Level::Info));
+        assert!(!enabled(Level::Debug));
+        assert!(lock().is_some());
+        set_verbosity(3);
+        assert!(enabled(Level::Debug));
+        set_verbosity(0);
+        assert!(!enabled(Level::Info));
+        assert!(!enabled(Level::Quiet));
+    }
+}
@@ -0,0 +1,118 @@
+//! Looking definitions up by their attribute path, like `lib.strings.concatStrings`, the way
+//! people refer to them, rather than by a pattern for their names
+use crate::format::ResultPrinter;
+use crate::output::Output;
+use crate::search::search_results;
+use crate::{is_searchable, FileError, Result, ResultKind, SearchOptions, SearchResult};
+
+use regex::Regex;
+
+use std::path::Path;
+
+/// The documented definitions at exactly `attr_path` under `dir`, like
+/// `["lib", "strings", "concatStrings"]`, of any kind, with the files that couldn't be searched.
+/// As with [`SearchOptions::path_prefix`], each file's attributes are under its path relative to
+/// `dir`, and a file that's a function as a whole is at its own path. There's more than one when
+/// different files define the same path, like an overlay does, in the order of their files.
+///
+/// `opts` says how to search, its path and the kinds of result being set here.
+pub fn find_attr_path(
+    dir: &Path,
+    attr_path: &[String],
+    opts: &SearchOptions,
+) -> Result<(Vec<SearchResult>, Vec<FileError>)> {
+    let name = match attr_path.last() {
+        Some(name) => name,
+        None => return Ok((Vec::new(), Vec::new())),
+    };
+    let opts = SearchOptions {
+        kinds: ResultKind::ALL.to_vec(),
+        file_functions: true,
+        path_prefix: attr_path.to_vec(),
+        path_exact: true,
+        ordered: true,
+        ..opts.clone()
+    };
+    // only the last part of the path is the name, which leaves out most definitions cheaply
+    let matching = Regex::new(&format!("^{}$", regex::escape(name)))?;
+    Ok(search_results(dir, &matching, &opts, is_searchable))
+}
+
+/// Prints the definitions at `attr_path` under `dir` (see [`find_attr_path`]) to stdout the way
+/// `nix-doc search` shows results, saying on stderr when there's more than one. Returns how many
+/// there were.
+pub fn print_attr_path(dir: &Path, attr_path: &[String]) -> Result<usize> {
+    let opts = SearchOptions::default();
+    // the files that couldn't be searched have been reported on stderr already
+    let (results, _) = find_attr_path(dir, attr_path, &opts)?;
+    if results.len() > 1 {
+        eprintln!(
+            "{} is defined in {} places, each shown below:",
+            attr_path.join("."),
+            results.len()
+        );
+    }
+    let mut printer = ResultPrinter::start(&opts, Output::open(None)?);
+    for res in &results {
+        let file = res.file.as_deref().unwrap_or(dir);
+        printer.print_result(res, file.display(), &opts);
+    }
+    printer.finish()?;
+    Ok(results.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::source::MemoryFs;
+
+    use std::sync::Arc;
+
+    fn path(path: &str) -> Vec<String> {
+        path.split('.').map(str::to_string).collect()
+    }
+
+    #[test]
+    fn test_find_attr_path() {
+        let root = Path::new("/repo");
+        let files = MemoryFs::new()
+            .file(
+                "/repo/lib/lists.nix",
+                "{\n  # Maps\n  map = f: xs: xs;\n  # Sums\n  sum = xs: 0;\n}",
+            )
+            .file(
+                "/repo/lib/default.nix",
+                "{\n  lists = {\n    # Maps, overridden\n    map = f: xs: xs;\n  };\n}",
+            )
+            .file("/repo/pkgs/hello/default.nix", "# Says hello\n{ lib }: { }");
+        let opts = SearchOptions {
+            file_source: Some(Arc::new(files)),
+            ..SearchOptions::default()
+        };
+        let found = |attr_path: &str| {
+            let (results, errors) = find_attr_path(root, &path(attr_path), &opts).unwrap();
+            assert!(errors.is_empty(), "{:?}", errors);
+            results
+                .iter()
+                .map(|res| format!("{}:{}", res.file.as_ref().unwrap().display(), res.doc))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(found("lib.lists.sum"), ["/repo/lib/lists.nix:Sums"]);
+        assert_eq!(
+            found("lib.lists.map"),
+            [
+                "/repo/lib/default.nix:Maps, overridden",
+                "/repo/lib/lists.nix:Maps"
+            ]
+        );
+        assert_eq!(
+            found("pkgs.hello"),
+            ["/repo/pkgs/hello/default.nix:Says hello"]
+        );
+        // only the definition at the path, not ones under it or named like it elsewhere
+        assert!(found("lib.lists").is_empty());
+        assert!(found("lib.strings.map").is_empty());
+        assert!(find_attr_path(root, &[], &opts).unwrap().0.is_empty());
+    }
+}
@@ -1,25 +1,88 @@
 //! A nix documentation search program
 
-use nix_doc::{is_searchable, search, tags, Result};
+use nix_doc::cache::FileCache;
+use nix_doc::changes::ChangedLines;
+use nix_doc::coverage::{coverage, CoverageBy};
+use nix_doc::filter::{FileFilter, NixFiles};
+#[cfg(feature = "serde")]
+use nix_doc::hover::hover_json;
+use nix_doc::hover::{print_definition_at, Definition};
+use nix_doc::index::Index;
+use nix_doc::lint::{lint, Allowlist};
+use nix_doc::lookup::print_attr_path;
+use nix_doc::output::Glyphs;
+use nix_doc::plan::QueryPlan;
+use nix_doc::profile::Profile;
+use nix_doc::{
+    flake, glob, is_searchable, list_files, nixpath, search, search_patterns, search_stdin, tags,
+    ArityRange, Backend, CancellationToken, DefKind, DirFilter, Error, OutputFormat, Result,
+    ResultKind, SearchOptions, SearchOptionsBuilder, SortKey,
+};
 
 use regex::Regex;
 use structopt::StructOpt;
 
-use std::{fs, io::BufWriter, path::PathBuf};
+use std::{
+    cell::Cell,
+    ffi::{OsStr, OsString},
+    fmt, fs,
+    io::{self, BufWriter, IsTerminal},
+    path::{Path, PathBuf},
+    process,
+    str::FromStr,
+    sync::Arc,
+};
 
 #[derive(StructOpt, Debug)]
 #[structopt(about = "an AST based Nix documentation tool")]
+// only ever made once, parsing the command line
+#[allow(clippy::large_enum_variant)]
 enum Args {
     /// Search a directory of nix files for the given function
-    Search {
-        /// Regex to search with
-        re: String,
+    Search(SearchArgs),
+
+    /// Checks the docs in a directory of nix files against the code, printing what looks out of
+    /// date, and exiting with status 1 if anything does
+    Lint(LintArgs),
+
+    /// Prints the documentation of the definition at a position in a nix file as JSON, or `{}`
+    /// if nothing documented is there, for editors to show on hover
+    #[cfg(feature = "serde")]
+    Hover {
+        /// The file
+        #[structopt(parse(from_os_str))]
+        file: PathBuf,
+        /// 1-based line
+        line: usize,
+        /// 1-based column, in bytes
+        col: usize,
+    },
 
-        /// Directory to search
+    /// Prints the definition around a position in a nix file, the innermost attrset entry or
+    /// `let` binding there, with its documentation. Exits with status 1 if there's no binding
+    /// there or it isn't documented.
+    At {
+        /// `FILE:LINE:COL`, with a 1-based line and a 1-based column in bytes. In a doc comment,
+        /// it's the definition the comment is for.
+        position: FilePosition,
+    },
+
+    /// Prints the documented definition at an attribute path, like `lib.strings.concatStrings`,
+    /// with every file that defines it if there's more than one. Exits with status 1 if nothing
+    /// documented is there.
+    Path {
+        /// The dotted attribute path. Each file's attributes are under its path relative to the
+        /// directory, so `lib/strings.nix` holds `lib.strings`.
+        attr_path: String,
+        /// The directory to look in, or a lookup path like `<nixpkgs>`
         #[structopt(default_value = ".")]
-        dir: PathBuf,
+        dir: String,
     },
 
+    /// Builds or searches a persistent index of a directory of nix files, which searches of the
+    /// directory read instead of parsing the files that haven't changed since it was built
+    Index(IndexCommand),
+
     /// Generates a ctags compatible database for a directory of nix files
     Tags {
         /// The directory
@@ -28,13 +91,1139 @@ enum Args {
     },
 }
 
-fn main() -> Result<()> {
-    let args = Args::from_args();
+#[derive(StructOpt, Debug)]
+enum IndexCommand {
+    /// Parses every nix file in a directory, writing what's documented in them to an index in
+    /// `~/.cache/nix-doc`. Run it again to bring the index up to date.
+    Build {
+        /// The directory
+        #[structopt(default_value = ".", parse(from_os_str))]
+        dir: PathBuf,
+        /// How many threads to parse on. Defaults to one for each CPU.
+        #[structopt(short = "j", long)]
+        jobs: Option<usize>,
+    },
+
+    /// Searches the index of a directory for the given function, without reading the directory,
+    /// so files changed since the index was built are shown as they were then
+    Query {
+        /// Regex to search with
+        re: String,
+        /// The directory the index is of
+        #[structopt(default_value = ".", parse(from_os_str))]
+        dir: PathBuf,
+    },
+}
+
+#[derive(StructOpt, Debug)]
+struct LintArgs {
+    /// The directory to check
+    #[structopt(default_value = ".")]
+    dir: PathBuf,
+
+    /// Check that the parameters docs list, with `@param` tags or an `# Inputs` section, are the
+    /// ones the function takes. This is the only lint so far, and runs when none are given.
+    #[structopt(long)]
+    stale_args: bool,
+
+    /// Don't report mismatches for this function, given as `name`, or just the ones about one
+    /// of its parameters, given as `name:param`. Can be given more than once.
+    #[structopt(long, number_of_values = 1)]
+    allow: Vec<String>,
+
+    /// Read --allow entries from this file, one per line. `#` starts a comment.
+    #[structopt(long, parse(from_os_str))]
+    allow_file: Option<PathBuf>,
+
+    /// Don't walk into directories with this name. Can be given more than once.
+    #[structopt(long, number_of_values = 1)]
+    exclude: Vec<String>,
+}
+
+/// A check `nix-doc lint` can run, picked by the flag of the same name
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Lint {
+    StaleArgs,
+}
+
+impl Lint {
+    const ALL: &'static [Lint] = &[Lint::StaleArgs];
+}
+
+impl LintArgs {
+    /// The lints picked with their flags, or every one of them if none are
+    fn lints(&self) -> Vec<Lint> {
+        let picked = [(Lint::StaleArgs, self.stale_args)]
+            .iter()
+            .filter(|(_, picked)| *picked)
+            .map(|&(lint, _)| lint)
+            .collect::<Vec<_>>();
+        match picked.is_empty() {
+            true => Lint::ALL.to_vec(),
+            false => picked,
+        }
+    }
+}
+
+#[derive(StructOpt, Debug)]
+struct SearchArgs {
+    /// Regex to search with, or a glob with --glob
+    #[structopt(required_unless = "pattern-file")]
+    re: Option<String>,
+
+    /// Search for each of the patterns in this file, one per line, instead of the one on the
+    /// command line, printing the results grouped by pattern and which patterns matched nothing.
+    /// Blank lines and lines starting with `#` are skipped. Patterns are regexes, or globs with
+    /// --glob, so `^name$` or --glob makes each one match just that identifier.
+    #[structopt(
+        long,
+        parse(from_os_str),
+        conflicts_with_all = &["fuzzy", "all-fields", "coverage", "list-files", "stdin", "stdin-filename"]
+    )]
+    pattern_file: Option<PathBuf>,
+
+    /// With --pattern-file, exit with status 1 if any of the patterns has no documented match
+    #[structopt(long, requires = "pattern-file")]
+    require_all: bool,
+
+    /// Treat the pattern as a shell-style glob matching whole identifiers, like `concat*Sep`:
+    /// `*` matches anything, `?` one character, and `[...]` one of a set of characters
+    #[structopt(long)]
+    glob: bool,
+
+    /// Match identifiers fuzzily, like fzf: the characters of the pattern only have to appear
+    /// in order, so `cMapStr` finds `concatMapStrings`. Results are sorted best match first,
+    /// unless --sort-by is given.
+    #[structopt(long, conflicts_with = "glob")]
+    fuzzy: bool,
+
+    /// Match the pattern against the attribute path and the docs as well as the identifier.
+    /// Results are ranked name matches first, then path matches, then doc matches, unless
+    /// --sort-by is given.
+    #[structopt(long, conflicts_with = "fuzzy")]
+    all_fields: bool,
+
+    /// Match the pattern case insensitively
+    #[structopt(short = "i", long)]
+    ignore_case: bool,
+
+    /// Directory to search, a lookup path like `<nixpkgs>` to find it in NIX_PATH, a flake
+    /// reference ending in `#` like `nixpkgs#`, or `-` to read a nix expression from stdin.
+    /// Defaults to the current directory.
+    dir: Option<PathBuf>,
+
+    /// Make leaving out the directory an error, rather than searching the current directory
+    #[structopt(long)]
+    no_default_path: bool,
+
+    /// Search `<nixpkgs>`, wherever NIX_PATH says it is
+    #[structopt(long)]
+    nixpkgs: bool,
+
+    /// Search the source of this flake, fetching it with `nix` if needed
+    #[structopt(long)]
+    flake: Option<String>,
+
+    /// Search the source of this input of the flake in the current directory
+    #[structopt(long)]
+    flake_input: Option<String>,
+
+    /// Read a nix expression from stdin instead of searching a directory
+    #[structopt(long)]
+    stdin: bool,
+
+    /// Path to show for the nix expression read from stdin, such as the file an editor buffer
+    /// belongs to. Implies --stdin.
+    #[structopt(long)]
+    stdin_filename: Option<PathBuf>,
+
+    /// Also search package definitions, showing their `meta.description`
+    #[structopt(long)]
+    packages: bool,
+
+    /// Also search NixOS option declarations, using their descriptions as documentation.
+    /// Understands `mkOption`, `mkEnableOption` and `mkPackageOption`.
+    #[structopt(long)]
+    options: bool,
+
+    /// Also show definitions with computed keys, like `${name} = ...`, under the source of the key
+    #[structopt(long)]
+    any_key: bool,
+
+    /// Kinds of results to show, comma separated: `function`, `option`, `package`, `value`, or
+    /// `any` for all of them. Defaults to `function`.
+    #[structopt(long, use_delimiter = true)]
+    kind: Vec<KindArg>,
+
+    /// Also search documented attributes that aren't functions
+    #[structopt(long)]
+    any_value: bool,
+
+    /// Print results in a stable order, sorted by file path. Slightly slower to show the first
+    /// results, since each file waits for the ones before it.
+    #[structopt(long)]
+    ordered: bool,
+
+    /// Only show definitions under this attribute path, like `lib.strings`. Each file's
+    /// attributes count as being under its path relative to the directory searched, so
+    /// `lib/strings.nix` holds `lib.strings`, and `lib/default.nix` holds `lib`.
+    #[structopt(long)]
+    path: Option<String>,
+
+    /// Only show the definition at this attribute path, like `lib.strings.toUpper`, or with a
+    /// trailing `.` or `*`, like `lib.strings.`, everything under it. Paths are worked out the
+    /// same way as for --path.
+    #[structopt(long, conflicts_with = "path")]
+    path_prefix: Option<PathQuery>,
+
+    /// Only show definitions whose synopsis, the first line of their documentation, matches
+    /// this regex
+    #[structopt(long)]
+    match_synopsis: Option<Regex>,
+
+    /// Only show functions with a parameter matching this name or regex, at any currying level:
+    /// either a plain `x:` argument or a field of a `{ ... }:` pattern. The regex has to match
+    /// the whole parameter name. Use `.` as the search pattern to see every such function.
+    #[structopt(long, parse(try_from_str = whole_name_regex))]
+    takes_arg: Option<Regex>,
+
+    /// Only show functions with a `{ ... }:` pattern field matching this name or regex that has
+    /// a default, like `stdenv ? null`. The regex has to match the whole name.
+    #[structopt(long, parse(try_from_str = whole_name_regex))]
+    arg_default: Option<Regex>,
+
+    /// Only show functions with a parameter matching this name or regex that has to be given:
+    /// a plain argument, or a pattern field without a default
+    #[structopt(long, parse(try_from_str = whole_name_regex))]
+    arg_required: Option<Regex>,
+
+    /// Only show functions with an example in their docs: an `Example:` line, an `# Examples`
+    /// heading, a `:::{.example}` block, or an `@example` tag
+    #[structopt(long)]
+    with_example: bool,
+
+    /// Only show functions without an example in their docs, to find ones that need one
+    #[structopt(long, conflicts_with = "with-example")]
+    without_example: bool,
+
+    /// Only show functions whose docs give their type, with a `Type:` line or a `# Type` section
+    #[structopt(long)]
+    with_type: bool,
+
+    /// Only show functions whose docs don't give their type, to find ones that need it
+    #[structopt(long, conflicts_with = "with-type")]
+    without_type: bool,
+
+    /// Sort the results by `name`, `file` (then line), `line`, `doc-length` (longest first),
+    /// `score` (best --fuzzy match first), or `relevance` (most likely to be what was meant
+    /// first). Nothing is printed until the whole search is done. Defaults to `relevance` when
+    /// printing to a terminal.
+    #[structopt(long, alias = "sort")]
+    sort_by: Option<SortKey>,
+
+    /// Don't sort by relevance by default when printing to a terminal, but print results as
+    /// they're found
+    #[structopt(long)]
+    no_relevance: bool,
+
+    /// Reverse the order of the results. Without --sort-by, sorts by file in reverse.
+    #[structopt(long)]
+    reverse: bool,
+
+    /// Print to stderr each matching binding that was left out of the results, and why
+    #[structopt(long)]
+    explain_skips: bool,
+
+    /// Log to stderr what the search is doing: the files searched and why bindings were left
+    /// out, and with -vv, what was decided about every binding
+    #[structopt(short = "v", long, parse(from_occurrences))]
+    verbose: u8,
+
+    /// Leave out deprecated functions (ones that just `warn` or `throw`)
+    #[structopt(long)]
+    no_deprecated: bool,
+
+    /// Also show names brought in with `inherit` that have a doc comment of their own
+    #[structopt(long)]
+    include_reexports: bool,
+
+    /// Show at most N results from any one file, the first ones in it, so that one big file
+    /// doesn't crowd out the rest. Applies after every other filter.
+    #[structopt(long)]
+    max_matches_per_file: Option<usize>,
+
+    /// How to print results: `human`, `json` (an object with the results and the files that
+    /// couldn't be searched), `csv` or `table` (a line
+    /// per result, with its name, location and synopsis in columns)
+    #[structopt(long, default_value = "human")]
+    format: OutputFormat,
+
+    /// Print a line per result, in columns, the same as `--format table`
+    #[structopt(long)]
+    table: bool,
+
+    /// How many characters wide `--table` output can be. Defaults to the width of the
+    /// terminal, or no limit when not printing to one.
+    #[structopt(long)]
+    width: Option<usize>,
+
+    /// Only print ASCII: `...` at the end of table cells that were cut short, and `-` for the
+    /// lines between results, for terminals that can't show unicode
+    #[structopt(long)]
+    ascii: bool,
+
+    /// Print results as JSON, the same as `--format json`
+    #[cfg(feature = "serde")]
+    #[structopt(long)]
+    json: bool,
+
+    /// Only include these fields in each JSON result, in this order, like
+    /// `identifier,file,line`. Implies --json.
+    #[structopt(long, use_delimiter = true)]
+    json_fields: Vec<String>,
+
+    /// Print JSON output as a bare array of results, without the `schema_version` and `errors`
+    /// around it, the way nix-doc did before they were added
+    #[structopt(long)]
+    json_compat_array: bool,
+
+    /// Only show functions taking this many curried arguments: exactly `N`, at least `N+`, or
+    /// between `N-M`. A `{ ... }:` pattern counts as one argument, so `{ a, b }: c: ...` takes
+    /// two.
+    #[structopt(long)]
+    arity: Option<ArityRange>,
+
+    /// Only show functions taking at least this many curried arguments
+    #[structopt(long)]
+    min_arity: Option<usize>,
+
+    /// Show doc comments exactly as written, only removing the comment delimiters, instead of
+    /// trimming and dedenting them
+    #[structopt(long)]
+    raw_docs: bool,
+
+    /// Color the signatures of results like nix source, picking out keywords, names, literals
+    /// and punctuation. Only when there are colors at all, so not with `--output`.
+    #[structopt(long)]
+    highlight: bool,
+
+    /// Don't say which file and line each result is from, like `grep -h`. Each result is still
+    /// its docs and identifier.
+    #[structopt(long)]
+    no_filename: bool,
+
+    /// Only treat line comments starting with this as documentation, like `##` to tell doc
+    /// comments apart from notes about the code. Block comments are always documentation. The
+    /// default, `#`, takes every comment.
+    #[structopt(long, default_value = "#", parse(try_from_str = parse_doc_prefix))]
+    doc_prefix: String,
+
+    /// Which kinds of definitions to search, comma separated: `attr` for attrsets, `rec` for
+    /// recursive attrsets, `let` for let bindings, `file` for whole files that are a function
+    /// (see --file-functions). Defaults to `attr,rec`.
+    #[structopt(long, use_delimiter = true)]
+    def_kind: Vec<DefKind>,
+
+    /// Also show files that are a function as a whole, like `{ lib, stdenv }: ...` packages,
+    /// under the name of the file (or of its directory, for a `default.nix`), documented by the
+    /// comment at the top of the file. Included in `--kind any`.
+    #[structopt(long)]
+    file_functions: bool,
+
+    /// Only search files under a directory with this name, at any depth. Can be given more than
+    /// once.
+    #[structopt(long, number_of_values = 1)]
+    include: Vec<String>,
+
+    /// Don't walk into directories with this name, like `tests` or `.git`. Can be given more than
+    /// once, and wins over --include.
+    #[structopt(long, number_of_values = 1)]
+    exclude: Vec<String>,
+
+    /// Only show results from files whose path matches this regex, like `stdenv|build-support`.
+    /// Every file is still searched; this only picks which results are shown.
+    #[structopt(long)]
+    path_regex: Option<Regex>,
+
+    /// Search files ending in this extension instead of `.nix`. Can be given more than once.
+    #[structopt(long, number_of_values = 1)]
+    extension: Vec<String>,
+
+    /// Only search files whose names match this shell-style glob, like `*-packages.nix`. Can be
+    /// given more than once, to search files matching any of them.
+    #[structopt(long, number_of_values = 1)]
+    file_glob: Vec<String>,
+
+    /// Leave out files bigger than this many bytes. Files over 200 kB are never searched.
+    #[structopt(long)]
+    max_file_size: Option<u64>,
+
+    /// Leave out files git ignores, like a `result` symlink, going by the repository being
+    /// searched
+    #[structopt(long)]
+    gitignore: bool,
+
+    /// Only show definitions starting on lines changed since this git ref, like `origin/main`,
+    /// according to `git diff` in the repository being searched. For checking the docs a branch
+    /// touches.
+    #[structopt(long, conflicts_with_all = &["stdin", "stdin-filename", "coverage"])]
+    since: Option<String>,
+
+    /// Instead of walking the directory, search the file given in its place, or the `default.nix`
+    /// in it, and every file reachable from there through imports of literal paths like
+    /// `import ./lists.nix`. Shows the docs reachable from an entry point.
+    #[structopt(long, conflicts_with_all = &["stdin", "stdin-filename", "coverage", "list-files"])]
+    follow_imports: bool,
+
+    /// Instead of printing results, report how many of the matching functions have doc comments,
+    /// per file and in total. Pass `.` as the pattern to count every function.
+    #[structopt(long)]
+    coverage: bool,
+
+    /// Break the coverage report down by `file`, or by `dir` for each directory directly under
+    /// the one searched
+    #[structopt(long, default_value = "file")]
+    coverage_by: CoverageBy,
+
+    /// How to spread the search over threads: `pool`, nix-doc's own thread pool, or `rayon`, a
+    /// work-stealing one, if nix-doc was built with the `rayon` feature
+    #[structopt(long, default_value = "pool")]
+    backend: Backend,
+
+    /// How many threads to search on. Defaults to one for each CPU.
+    #[structopt(short = "j", long)]
+    jobs: Option<usize>,
+
+    /// Just print the files that would be searched, without searching them. The pattern is
+    /// not used.
+    #[structopt(long)]
+    list_files: bool,
+
+    /// Print what the search would do, with everything the flags ask for worked out: the regex
+    /// it compiles to, the kinds of results and definitions, which files and directories, the
+    /// filters, the order, the format and how many threads. Nothing is searched.
+    #[structopt(long, conflicts_with_all = &["stdin", "stdin-filename", "list-files"])]
+    explain_query: bool,
+
+    /// Write the results to this file instead of stdout. Files never get colors, whether or
+    /// not stdout is a terminal.
+    #[structopt(short = "o", long = "output", parse(from_os_str))]
+    output: Option<PathBuf>,
+
+    /// Print result paths relative to this directory, like the root of a repo, rather than as
+    /// they were found. Results from files outside of it get their absolute path.
+    #[structopt(long, parse(try_from_os_str = parse_relative_to))]
+    relative_to: Option<PathBuf>,
+
+    /// Print how long the search spent walking directories, reading, parsing, extracting docs,
+    /// formatting and writing results to stderr at the end
+    #[structopt(long, alias = "time")]
+    profile: bool,
+
+    /// List other names each result is bound to in the same file, like `join = concatStringsSep;`.
+    /// On by default for human-readable output; this turns it on for `--format json` too.
+    #[structopt(long)]
+    aliases: bool,
+
+    /// Don't look for aliases of the results
+    #[structopt(long, conflicts_with = "aliases")]
+    no_aliases: bool,
+
+    /// Parse every file, even with an index of the directory from `nix-doc index build`
+    #[structopt(long)]
+    no_index: bool,
+
+    /// Parse every file again rather than taking what's in the ones that haven't changed from the
+    /// cache of earlier searches, replacing what's cached for them
+    #[structopt(long)]
+    refresh: bool,
+
+    /// Don't read or write the cache of earlier searches in `$XDG_CACHE_HOME/nix-doc/files`
+    #[structopt(long, conflicts_with = "refresh")]
+    no_cache: bool,
+}
+
+/// An attribute path given to `--path-prefix`
+#[derive(Debug)]
+struct PathQuery {
+    components: Vec<String>,
+    /// Did it end in `.` or `*`, asking for everything under the path?
+    descendants: bool,
+}
+
+impl FromStr for PathQuery {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let (path, descendants) = match s.strip_suffix(".*").or_else(|| s.strip_suffix('.')) {
+            Some(path) => (path, true),
+            None => match s.strip_suffix('*') {
+                Some(path) => (path.trim_end_matches('.'), true),
+                None => (s, false),
+            },
+        };
+        let components: Vec<String> = path.split('.').map(str::to_string).collect();
+        if components.iter().any(|c| c.is_empty() || c.contains('*')) {
+            return Err(format!(
+                "invalid attribute path `{}`, expected something like `lib.strings.toUpper` or \
+                 `lib.strings.`",
+                s
+            ));
+        }
+        Ok(PathQuery {
+            components,
+            descendants,
+        })
+    }
+}
+
+/// A position in a file given to `at`, as `FILE:LINE:COL`
+#[derive(Debug)]
+struct FilePosition {
+    file: PathBuf,
+    line: usize,
+    col: usize,
+}
+
+impl FromStr for FilePosition {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let invalid = || format!("invalid position `{}`, expected FILE:LINE:COL", s);
+        // from the end, since the file name could have a `:` in it
+        let mut parts = s.rsplitn(3, ':');
+        let col = parts.next().and_then(|col| col.parse().ok());
+        let line = parts.next().and_then(|line| line.parse().ok());
+        match (parts.next(), line, col) {
+            (Some(file), Some(line), Some(col)) if !file.is_empty() => Ok(FilePosition {
+                file: PathBuf::from(file),
+                line,
+                col,
+            }),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl fmt::Display for FilePosition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.file.display(), self.line, self.col)
+    }
+}
+
+/// One of the `--kind`s, which might be all of them
+#[derive(Debug)]
+enum KindArg {
+    Kind(ResultKind),
+    Any,
+}
+
+impl FromStr for KindArg {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "any" => Ok(KindArg::Any),
+            _ => s.parse().map(KindArg::Kind),
+        }
+    }
+}
+
+impl SearchArgs {
+    /// The directory to search, after resolving lookup paths and flakes
+    fn root(&self) -> Result<PathBuf> {
+        let spec = self.dir.as_deref().and_then(Path::to_str);
+        let flake = self
+            .flake
+            .as_deref()
+            .or_else(|| spec.and_then(flake::flake_ref));
+        if let Some(reference) = flake {
+            let path = flake::resolve(reference).map_err(Error::InvalidConfig)?;
+            eprintln!("searching flake {} in {}", reference, path.display());
+            return Ok(path);
+        } else if let Some(input) = &self.flake_input {
+            let path = flake::resolve_input(input).map_err(Error::InvalidConfig)?;
+            eprintln!("searching flake input {} in {}", input, path.display());
+            return Ok(path);
+        }
+
+        let spec = if self.nixpkgs {
+            "<nixpkgs>"
+        } else {
+            match spec {
+                Some(spec) if nixpath::is_lookup_path(spec) => spec,
+                _ => return self.dir_or_default(),
+            }
+        };
+        nixpath::resolve(spec).map_err(Error::InvalidConfig)
+    }
+
+    /// Was no directory to search given in any of the ways there are to give one?
+    fn is_default_dir(&self) -> bool {
+        self.dir.is_none() && !self.nixpkgs && self.flake.is_none() && self.flake_input.is_none()
+    }
+
+    /// With --pattern-file there's no pattern on the command line, so what was taken for one is
+    /// the directory
+    fn shift_positionals(&mut self) {
+        if self.pattern_file.is_some() && self.dir.is_none() {
+            self.dir = self.re.take().map(PathBuf::from);
+        }
+    }
+
+    /// The regex to match identifiers with
+    fn pattern(&self) -> Result<Regex> {
+        let re = if self.fuzzy {
+            // not used, the pattern is matched fuzzily instead
+            String::new()
+        } else {
+            self.regex_source(self.re.as_deref().unwrap_or_default())?
+        };
+        self.build_regex(&re)
+    }
+
+    /// The patterns in the --pattern-file, each as written and as a regex, with a regex matching
+    /// whatever any of them do
+    fn file_patterns(&self) -> Result<(Regex, Vec<(String, Regex)>)> {
+        let path = self
+            .pattern_file
+            .as_deref()
+            .ok_or_else(|| Error::InvalidConfig("no --pattern-file given".to_string()))?;
+        let content = fs::read_to_string(path).map_err(|err| Error::io(path, err))?;
+        let mut sources = Vec::new();
+        let mut patterns = Vec::new();
+        for line in content.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let source = self.regex_source(line)?;
+            patterns.push((line.to_string(), self.build_regex(&source)?));
+            sources.push(format!("(?:{})", source));
+        }
+        if patterns.is_empty() {
+            return Err(Error::InvalidConfig(format!(
+                "no patterns in {}",
+                path.display()
+            )));
+        }
+        Ok((self.build_regex(&sources.join("|"))?, patterns))
+    }
+
+    /// The regex for a pattern given on the command line, which is one itself unless it's a glob
+    fn regex_source(&self, pattern: &str) -> Result<String> {
+        if self.glob {
+            glob::to_regex(pattern).map_err(Error::InvalidConfig)
+        } else {
+            Ok(pattern.to_string())
+        }
+    }
+
+    /// Compiles `source`, with the flag for --ignore-case in it, so that the regex shows it
+    fn build_regex(&self, source: &str) -> Result<Regex> {
+        match self.ignore_case {
+            true => Ok(Regex::new(&format!("(?i){}", source))?),
+            false => Ok(Regex::new(source)?),
+        }
+    }
+
+    /// `--format`, or JSON with `--json`, or a table with `--table`
+    fn output_format(&self) -> OutputFormat {
+        #[cfg(feature = "serde")]
+        if self.json || !self.json_fields.is_empty() {
+            return OutputFormat::Json;
+        }
+        if self.table {
+            return OutputFormat::Table;
+        }
+        self.format
+    }
+
+    /// Results for people on a terminal are sorted most relevant first, unless some other order was asked for
+    fn default_sort(&self) -> Option<SortKey> {
+        let other_order = self.fuzzy || self.ordered || self.reverse || self.no_relevance;
+        let for_people = matches!(
+            self.output_format(),
+            OutputFormat::Human | OutputFormat::Table
+        );
+        let to_terminal = self.output.is_none() && io::stdout().is_terminal();
+        (!other_order && for_people && to_terminal).then_some(SortKey::Relevance)
+    }
+
+    /// Which files under `root` to search, from the flags picking them
+    fn file_filter(&self, root: &Path) -> Result<NixFiles> {
+        let mut filter = NixFiles::new().max_size(self.max_file_size);
+        for ext in &self.extension {
+            filter = filter.extension(ext);
+        }
+        for glob in &self.file_glob {
+            filter = filter.glob(glob)?;
+        }
+        if self.gitignore {
+            filter = filter.gitignore(root)?;
+        }
+        Ok(filter)
+    }
+
+    fn dir_filter(&self) -> DirFilter {
+        DirFilter {
+            include: self.include.clone(),
+            exclude: self.exclude.clone(),
+        }
+    }
+
+    /// The lines changed since `--since`, in the repository `root` is in
+    fn changed_lines(&self, root: &Path) -> Result<Option<Arc<ChangedLines>>> {
+        // an entry file with --follow-imports
+        let root = match root.is_file() {
+            true => root.parent().unwrap_or(root),
+            false => root,
+        };
+        match &self.since {
+            Some(gitref) => Ok(Some(Arc::new(ChangedLines::since(gitref, root)?))),
+            None => Ok(None),
+        }
+    }
+
+    fn dir_or_default(&self) -> Result<PathBuf> {
+        match &self.dir {
+            Some(dir) => Ok(dir.clone()),
+            None if self.no_default_path => Err(Error::InvalidConfig(
+                "no directory to search was given, and --no-default-path is set".to_string(),
+            )),
+            None => Ok(PathBuf::from(".")),
+        }
+    }
+
+    /// The index of `root` to search with, unless there's none or --no-index turns it off. One
+    /// written by another version of nix-doc is rebuilt first.
+    fn index(&self, root: &Path) -> Option<Arc<Index>> {
+        let path = Index::path_for(root).filter(|_| !self.no_index)?;
+        match Index::load(&path) {
+            Ok(Some(index)) => Some(Arc::new(index)),
+            Ok(None) if path.exists() => {
+                eprintln!(
+                    "rebuilding the index of {}, which another version of nix-doc made",
+                    root.display()
+                );
+                match build_index(root, self.jobs, &path) {
+                    Ok(index) => Some(Arc::new(index)),
+                    Err(err) => {
+                        eprintln!("nix-doc: not using the index: {}", err);
+                        None
+                    }
+                }
+            }
+            Ok(None) => None,
+            Err(err) => {
+                eprintln!("nix-doc: not using the index: {}", err);
+                None
+            }
+        }
+    }
+
+    /// The options the flags ask for, all set up in one place, for each kind of search to add
+    /// what only it needs and then build
+    fn options(&self) -> SearchOptionsBuilder {
+        SearchOptions::builder()
+            .packages(self.packages)
+            .options(self.options)
+            .any_key(self.any_key)
+            .kinds(
+                self.kind
+                    .iter()
+                    .flat_map(|kind| match kind {
+                        KindArg::Kind(kind) => vec![*kind],
+                        KindArg::Any => ResultKind::ALL.to_vec(),
+                    })
+                    .collect(),
+            )
+            .any_value(self.any_value)
+            .ordered(self.ordered)
+            .sort_by(self.sort_by.or_else(|| self.default_sort()))
+            .reverse(self.reverse)
+            .explain_skips(self.explain_skips)
+            .no_deprecated(self.no_deprecated)
+            .include_reexports(self.include_reexports)
+            .max_matches_per_file(self.max_matches_per_file)
+            .format(self.output_format())
+            .arity(self.arity)
+            .min_arity(self.min_arity)
+            .raw_docs(self.raw_docs)
+            .highlight(self.highlight)
+            .no_filename(self.no_filename)
+            .doc_prefix(Some(self.doc_prefix.clone()).filter(|prefix| prefix != "#"))
+            .def_kinds(self.def_kind.clone())
+            .file_functions(
+                self.file_functions || self.kind.iter().any(|kind| matches!(kind, KindArg::Any)),
+            )
+            .match_synopsis(self.match_synopsis.clone())
+            .takes_arg(self.takes_arg.clone())
+            .arg_default(self.arg_default.clone())
+            .arg_required(self.arg_required.clone())
+            .example(wanted(self.with_example, self.without_example))
+            .typed(wanted(self.with_type, self.without_type))
+            .dirs(self.dir_filter())
+            .file_regex(self.path_regex.clone())
+            // `changed` needs the directory to search, see `changed_lines`, and `cancel` is only
+            // set up by the searches that can be stopped early, see `interrupt_flag`
+            .follow_imports(self.follow_imports)
+            .json_compat_array(self.json_compat_array)
+            .json_fields(self.json_fields.clone())
+            .fuzzy(self.fuzzy.then(|| self.re.clone().unwrap_or_default()))
+            .backend(self.backend)
+            .jobs(self.jobs)
+            .output(self.output.clone())
+            .width(self.width)
+            .glyphs(match self.ascii {
+                true => Glyphs::ASCII,
+                false => Glyphs::UNICODE,
+            })
+            .relative_to(self.relative_to.clone())
+            .cache(match self.no_cache {
+                true => None,
+                false => FileCache::new().map(|cache| cache.refresh(self.refresh)),
+            })
+            .profile(self.profile.then(Default::default))
+            .all_fields(self.all_fields)
+            .aliases(
+                self.aliases || !self.no_aliases && self.output_format() == OutputFormat::Human,
+            )
+            .path_prefix(match (&self.path, &self.path_prefix) {
+                (Some(path), _) => path.split('.').map(str::to_string).collect(),
+                (None, Some(query)) => query.components.clone(),
+                (None, None) => Vec::new(),
+            })
+            .path_exact(
+                self.path_prefix
+                    .as_ref()
+                    .is_some_and(|query| !query.descendants),
+            )
+    }
+}
+
+/// Number of files searched in the current directory, when no directory was given, after which
+/// we warn that the search may have been run somewhere it wasn't meant to be
+const LARGE_DEFAULT_TREE: usize = 5_000;
+
+/// Checks a `--doc-prefix` is the start of a line comment
+fn parse_doc_prefix(s: &str) -> std::result::Result<String, String> {
+    if s.starts_with('#') {
+        Ok(s.to_string())
+    } else {
+        Err(format!(
+            "doc prefix `{}` doesn't start with `#`, so it can't start a line comment",
+            s
+        ))
+    }
+}
+
+/// Makes a `--relative-to` directory absolute, the way result paths are made to compare with it
+fn parse_relative_to(s: &OsStr) -> std::result::Result<PathBuf, OsString> {
+    match fs::canonicalize(s) {
+        Ok(dir) if dir.is_dir() => Ok(dir),
+        Ok(_) => Err(format!("{} isn't a directory", Path::new(s).display()).into()),
+        Err(err) => Err(format!("can't use {}: {}", Path::new(s).display(), err).into()),
+    }
+}
+
+/// What a `--with-x`/`--without-x` pair of flags asks for: things with x, things without x, or
+/// either
+fn wanted(with: bool, without: bool) -> Option<bool> {
+    match (with, without) {
+        (true, _) => Some(true),
+        (_, true) => Some(false),
+        _ => None,
+    }
+}
+
+/// Parses a regex that has to match a whole name, so that a plain name only matches itself
+fn whole_name_regex(s: &str) -> std::result::Result<Regex, regex::Error> {
+    Regex::new(&format!("^(?:{})$", s))
+}
+
+/// Wraps `should_search` to warn once it has let through [`LARGE_DEFAULT_TREE`] files
+fn warn_if_large(should_search: impl FileFilter) -> impl FileFilter {
+    let count = Cell::new(0);
+    move |path: &Path| {
+        let searched = should_search.should_search(path);
+        if searched {
+            count.set(count.get() + 1);
+            if count.get() == LARGE_DEFAULT_TREE {
+                eprintln!(
+                    "warning: searched {} files under the current directory so far. Pass a \
+                     directory to search a smaller tree. --no-default-path makes leaving it out \
+                     an error.",
+                    LARGE_DEFAULT_TREE
+                );
+            }
+        }
+        searched
+    }
+}
+
+/// Indexes `dir` on `jobs` threads, saving the index to `path`
+fn build_index(dir: &Path, jobs: Option<usize>, path: &Path) -> Result<Index> {
+    let opts = SearchOptions::builder().jobs(jobs).build()?;
+    let (index, errors) = Index::build(dir, &opts)?;
+    index.save(path)?;
+    eprintln!(
+        "indexed {} files in {} to {}",
+        index.len(),
+        dir.display(),
+        path.display()
+    );
+    if !errors.is_empty() {
+        eprintln!(
+            "{} files couldn't be indexed, and are parsed each search",
+            errors.len()
+        );
+    }
+    Ok(index)
+}
+
+/// Prints the `--profile` breakdown, if there is one
+fn print_profile(profile: Option<Arc<Profile>>) {
+    if let Some(profile) = profile {
+        eprintln!("{}", profile);
+    }
+}
+
+/// The error for when there's nowhere to keep an index, without a home or cache directory
+fn no_cache_dir() -> Error {
+    Error::InvalidConfig("there's no home directory to keep the index in".to_string())
+}
+
+/// Exit status after a search was stopped with Ctrl-C, the usual one for dying of SIGINT
+const INTERRUPTED_STATUS: i32 = 130;
+
+/// Makes Ctrl-C cancel the returned token, stopping the search early so that what it found so
+/// far still gets printed, rather than killing nix-doc outright. A second Ctrl-C still does that.
+fn interrupt_flag() -> CancellationToken {
+    let token = CancellationToken::new();
+    let handler_token = token.clone();
+    let installed = ctrlc::set_handler(move || {
+        if handler_token.is_cancelled() {
+            process::exit(INTERRUPTED_STATUS);
+        }
+        handler_token.cancel();
+    });
+    if let Err(err) = installed {
+        eprintln!("warning: Ctrl-C will not print partial results: {}", err);
+    }
+    token
+}
+
+/// Exits with [`INTERRUPTED_STATUS`], once the partial results have been printed, if the search
+/// was interrupted
+fn exit_if_interrupted(interrupted: &CancellationToken) {
+    if interrupted.is_cancelled() {
+        eprintln!("(interrupted)");
+        process::exit(INTERRUPTED_STATUS);
+    }
+}
+
+/// Exit status for an error, going by the ones in BSD's `sysexits.h`
+fn exit_status(err: &Error) -> i32 {
+    match err {
+        // EX_IOERR
+        Error::Io { .. } => 74,
+        // EX_DATAERR
+        Error::Parse { .. } => 65,
+        // EX_USAGE
+        Error::InvalidRegex(_) | Error::InvalidConfig(_) => 64,
+        Error::Cancelled => INTERRUPTED_STATUS,
+        _ => 1,
+    }
+}
+
+fn main() {
+    if let Err(err) = run() {
+        eprintln!("nix-doc: {}", err);
+        process::exit(exit_status(&err));
+    }
+}
+
+fn run() -> Result<()> {
+    let mut args = Args::from_args();
+    if let Args::Search(args) = &mut args {
+        args.shift_positionals();
+        nix_doc::log::set_verbosity(args.verbose);
+    }
 
     match args {
-        Args::Search { re, dir } => {
-            let re_match = Regex::new(&re)?;
-            search(&dir, re_match, is_searchable);
+        Args::Search(args) if args.explain_query => {
+            let re_match = match args.pattern_file.is_some() {
+                true => args.file_patterns()?.0,
+                false => args.pattern()?,
+            };
+            let root = args.root()?;
+            let opts = args.options().changed(args.changed_lines(&root)?).build()?;
+            let filter = args.file_filter(&root)?;
+            println!("{}", QueryPlan::new(&root, &re_match, &opts).files(&filter));
+        }
+
+        Args::Search(args) if args.list_files => {
+            let root = args.root()?;
+            list_files(
+                &root,
+                &args.dir_filter(),
+                args.output.as_deref(),
+                args.file_filter(&root)?,
+            )?;
+        }
+
+        Args::Search(args)
+            if args.stdin
+                || args.stdin_filename.is_some()
+                || args.dir.as_deref() == Some(Path::new("-")) =>
+        {
+            let re_match = args.pattern()?;
+            let opts = args.options().build()?;
+            let profile = opts.profile.clone();
+            let format = opts.format;
+            // the source is right there to show a parse error in, rather than on one line
+            if let Err(err) = search_stdin(args.stdin_filename.as_deref(), re_match, opts) {
+                match err.rendered(format) {
+                    Some(rendered) => {
+                        eprintln!("{}", rendered);
+                        process::exit(exit_status(&err));
+                    }
+                    None => return Err(err),
+                }
+            }
+            print_profile(profile);
+        }
+
+        Args::Search(args) if args.pattern_file.is_some() => {
+            let (re_match, patterns) = args.file_patterns()?;
+            let root = args.root()?;
+            let interrupted = interrupt_flag();
+            let opts = args
+                .options()
+                .cancel(interrupted.clone())
+                .changed(args.changed_lines(&root)?)
+                .index(args.index(&root))
+                .build()?;
+            let profile = opts.profile.clone();
+            let filter = args.file_filter(&root)?;
+            let unmatched = search_patterns(&root, re_match, &patterns, opts, filter)?;
+            print_profile(profile);
+            exit_if_interrupted(&interrupted);
+            if !unmatched.is_empty() {
+                eprintln!(
+                    "{} of {} patterns had no documented matches: {}",
+                    unmatched.len(),
+                    patterns.len(),
+                    unmatched.join(", ")
+                );
+                if args.require_all {
+                    process::exit(1);
+                }
+            }
+        }
+
+        Args::Search(args) if args.coverage => {
+            let re_match = args.pattern()?;
+            let interrupted = interrupt_flag();
+            let opts = args.options().cancel(interrupted.clone()).build()?;
+            let root = args.root()?;
+            let filter = args.file_filter(&root)?;
+            coverage(&root, re_match, opts, args.coverage_by, filter)?;
+            exit_if_interrupted(&interrupted);
+        }
+
+        Args::Search(args) => {
+            let re_match = args.pattern()?;
+            let root = args.root()?;
+            let interrupted = interrupt_flag();
+            let opts = args
+                .options()
+                .cancel(interrupted.clone())
+                .changed(args.changed_lines(&root)?)
+                .index(args.index(&root))
+                .build()?;
+            let profile = opts.profile.clone();
+            let filter = args.file_filter(&root)?;
+            if args.is_default_dir() {
+                search(&root, re_match, opts, warn_if_large(filter))?;
+            } else {
+                search(&root, re_match, opts, filter)?;
+            }
+            print_profile(profile);
+            exit_if_interrupted(&interrupted);
+        }
+
+        Args::Lint(args) => {
+            let mut allow = Allowlist::default();
+            if let Some(path) = &args.allow_file {
+                allow.add_file(path)?;
+            }
+            for entry in &args.allow {
+                allow.add(entry);
+            }
+            let dirs = DirFilter {
+                include: Vec::new(),
+                exclude: args.exclude.clone(),
+            };
+            let mut found = 0;
+            for picked in args.lints() {
+                found += match picked {
+                    Lint::StaleArgs => lint(&args.dir, &dirs, allow.clone(), is_searchable)?,
+                };
+            }
+            if found > 0 {
+                eprintln!("{} problems found", found);
+                process::exit(1);
+            }
+        }
+
+        #[cfg(feature = "serde")]
+        Args::Hover { file, line, col } => {
+            println!("{}", hover_json(&file, line, col)?);
+        }
+
+        Args::At { position } => {
+            match print_definition_at(&position.file, position.line, position.col)? {
+                Some(Definition::Documented(_)) => (),
+                Some(Definition::Undocumented(name)) => {
+                    eprintln!("`{}` at {} isn't documented", name, position);
+                    process::exit(1);
+                }
+                None => {
+                    eprintln!("no attrset entry or let binding at {}", position);
+                    process::exit(1);
+                }
+            }
+        }
+
+        Args::Path { attr_path, dir } => {
+            let dir = match nixpath::is_lookup_path(&dir) {
+                true => nixpath::resolve(&dir).map_err(Error::InvalidConfig)?,
+                false => PathBuf::from(dir),
+            };
+            let path = attr_path.split('.').map(str::to_string).collect::<Vec<_>>();
+            if print_attr_path(&dir, &path)? == 0 {
+                eprintln!("nothing documented at {} in {}", attr_path, dir.display());
+                process::exit(1);
+            }
+        }
+
+        Args::Index(IndexCommand::Build { dir, jobs }) => {
+            let path = Index::path_for(&dir).ok_or_else(no_cache_dir)?;
+            build_index(&dir, jobs, &path)?;
+        }
+
+        Args::Index(IndexCommand::Query { re, dir }) => {
+            let path = Index::path_for(&dir).ok_or_else(no_cache_dir)?;
+            let index = Index::load(&path)?.ok_or_else(|| {
+                Error::InvalidConfig(format!(
+                    "there's no index of {0} from this version of nix-doc, build one with \
+                     `nix-doc index build {0}`",
+                    dir.display()
+                ))
+            })?;
+            let opts = SearchOptions::builder().aliases(true).build()?;
+            index.print_query(&Regex::new(&re)?, &opts)?;
         }
 
         Args::Tags { dir } => {
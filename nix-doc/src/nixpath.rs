@@ -0,0 +1,161 @@
+//! Resolution of `<nixpkgs>`-style lookup paths, the way Nix does it
+use std::env;
+use std::path::{Path, PathBuf};
+
+/// Is `spec` an angle-bracket lookup path like `<nixpkgs>` or `<nixpkgs/lib>`?
+pub fn is_lookup_path(spec: &str) -> bool {
+    spec.len() > 2 && spec.starts_with('<') && spec.ends_with('>')
+}
+
+/// Resolves a lookup path like `<nixpkgs/lib>` against `$NIX_PATH`, falling back to the channels
+/// in `~/.nix-defexpr/channels`. The error lists every place that was tried.
+pub fn resolve(spec: &str) -> Result<PathBuf, String> {
+    let home = env::var_os("HOME").map(PathBuf::from);
+    resolve_in(spec, env::var("NIX_PATH").ok().as_deref(), home.as_deref())
+}
+
+fn resolve_in(spec: &str, nix_path: Option<&str>, home: Option<&Path>) -> Result<PathBuf, String> {
+    let name = spec
+        .strip_prefix('<')
+        .and_then(|s| s.strip_suffix('>'))
+        .filter(|s| !s.is_empty())
+        .ok_or_else(|| format!("`{}` is not a lookup path like `<nixpkgs>`", spec))?;
+
+    let mut tried = Vec::new();
+    let entries = nix_path.map(split_nix_path).unwrap_or_default();
+    let channels = home.map(|home| home.join(".nix-defexpr/channels"));
+    let fallback = channels
+        .as_deref()
+        .and_then(Path::to_str)
+        .map(|dir| (None, dir));
+
+    for (prefix, path) in entries.into_iter().chain(fallback) {
+        let rest = match prefix {
+            Some(prefix) if name == prefix => "",
+            Some(prefix) => match name.strip_prefix(prefix).and_then(|r| r.strip_prefix('/')) {
+                Some(rest) => rest,
+                None => continue,
+            },
+            None => name,
+        };
+
+        if path.contains("://") {
+            tried.push(format!("{} (URLs are not supported)", path));
+            continue;
+        }
+
+        let candidate = if rest.is_empty() {
+            PathBuf::from(path)
+        } else {
+            Path::new(path).join(rest)
+        };
+        if candidate.exists() {
+            return Ok(candidate);
+        }
+        tried.push(candidate.display().to_string());
+    }
+
+    if tried.is_empty() {
+        Err(format!(
+            "could not find {}: NIX_PATH is not set and there is no home directory",
+            spec
+        ))
+    } else {
+        Err(format!(
+            "could not find {}, tried: {}",
+            spec,
+            tried.join(", ")
+        ))
+    }
+}
+
+/// Splits `NIX_PATH` into `(prefix, path)` entries. Entries are separated by `:`, which URL
+/// entries like `nixpkgs=https://...` also contain, so a piece starting with `//` is glued back
+/// onto the one before it.
+fn split_nix_path(nix_path: &str) -> Vec<(Option<&str>, &str)> {
+    let mut pieces: Vec<&str> = Vec::new();
+    let mut start = 0;
+    for (idx, _) in nix_path.match_indices(':') {
+        if nix_path[idx + 1..].starts_with("//") {
+            continue;
+        }
+        pieces.push(&nix_path[start..idx]);
+        start = idx + 1;
+    }
+    pieces.push(&nix_path[start..]);
+
+    pieces
+        .into_iter()
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| match entry.split_once('=') {
+            Some((prefix, path)) => (Some(prefix), path),
+            None => (None, entry),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn testdata() -> String {
+        concat!(env!("CARGO_MANIFEST_DIR"), "/testdata").to_string()
+    }
+
+    #[test]
+    fn test_split_nix_path() {
+        assert_eq!(
+            split_nix_path("nixpkgs=/a/b:/c::x=https://example.com/x.tar.gz:/d"),
+            [
+                (Some("nixpkgs"), "/a/b"),
+                (None, "/c"),
+                (Some("x"), "https://example.com/x.tar.gz"),
+                (None, "/d"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_resolve() {
+        let data = testdata();
+        let nix_path = format!("nixpkgs={}:{}", data, env!("CARGO_MANIFEST_DIR"));
+
+        let resolve = |spec| resolve_in(spec, Some(&nix_path), None);
+        assert_eq!(resolve("<nixpkgs>"), Ok(PathBuf::from(&data)));
+        assert_eq!(
+            resolve("<nixpkgs/merge.nix>"),
+            Ok(Path::new(&data).join("merge.nix"))
+        );
+        // a plain directory entry is looked in for the whole name
+        assert_eq!(resolve("<testdata>"), Ok(PathBuf::from(&data)));
+        // `nixpkgs-foo` does not match the `nixpkgs` prefix
+        assert!(resolve("<nixpkgs-foo>").is_err());
+        assert!(resolve("nixpkgs").is_err());
+    }
+
+    #[test]
+    fn test_resolve_channels_and_errors() {
+        let home = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let err = resolve_in(
+            "<nixpkgs>",
+            Some("nixpkgs=https://example.com/x"),
+            Some(&home),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            format!(
+                "could not find <nixpkgs>, tried: https://example.com/x (URLs are not supported), {}",
+                home.join(".nix-defexpr/channels/nixpkgs").display()
+            )
+        );
+        assert!(resolve_in("<nixpkgs>", None, None).is_err());
+    }
+
+    #[test]
+    fn test_is_lookup_path() {
+        assert!(is_lookup_path("<nixpkgs>"));
+        assert!(!is_lookup_path("<>"));
+        assert!(!is_lookup_path("nixpkgs"));
+    }
+}
@@ -0,0 +1,233 @@
+//! Recognition of NixOS module option declarations, both `mkOption { ... }` and the helpers that
+//! build one, like `mkEnableOption "the foo service"`
+use crate::parse::{apply_parts, key_idents, string_value};
+
+use rnix::ast::{AttrSet, HasEntry};
+use rnix::SyntaxNode;
+use rowan::ast::AstNode;
+
+/// What an option declaration says about the option
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct OptionDecl {
+    /// The `description`, or what the helper would have generated
+    pub description: Option<String>,
+    /// The `type`, without the `types.` prefix
+    pub type_name: Option<String>,
+    /// Source text of the `default`
+    pub default: Option<String>,
+}
+
+impl OptionDecl {
+    /// Renders the declaration as the `mkOption` it amounts to, to stand in for a signature
+    pub fn signature(&self) -> String {
+        let mut out = "mkOption {".to_string();
+        if let Some(type_name) = &self.type_name {
+            out.push_str(&format!(" type = {};", type_name));
+        }
+        if let Some(default) = &self.default {
+            out.push_str(&format!(" default = {};", default));
+        }
+        out.push_str(" ... }");
+        out
+    }
+}
+
+/// A function that declares an option, and how to work out what it declares from its arguments
+struct OptionHelper {
+    name: &'static str,
+    declare: fn(&[SyntaxNode]) -> Option<OptionDecl>,
+}
+
+/// Every function recognized as declaring an option. Add new helpers here.
+const OPTION_HELPERS: &[OptionHelper] = &[
+    OptionHelper {
+        name: "mkOption",
+        declare: mk_option,
+    },
+    OptionHelper {
+        name: "mkEnableOption",
+        declare: mk_enable_option,
+    },
+    OptionHelper {
+        name: "mkPackageOption",
+        declare: mk_package_option,
+    },
+    OptionHelper {
+        name: "mkPackageOptionMD",
+        declare: mk_package_option,
+    },
+];
+
+/// Recognizes `value` as an option declaration, if it is a call to one of [`OPTION_HELPERS`]
+pub fn option_declaration(value: &SyntaxNode) -> Option<OptionDecl> {
+    let (head, args) = apply_parts(value)?;
+    let name = head.rsplit('.').next()?;
+    let helper = OPTION_HELPERS.iter().find(|h| h.name == name)?;
+    (helper.declare)(&args)
+}
+
+/// `mkOption { type = ...; default = ...; description = ...; }`
+fn mk_option(args: &[SyntaxNode]) -> Option<OptionDecl> {
+    let attrs = AttrSet::cast(args.first()?.clone())?;
+    let mut decl = OptionDecl::default();
+    for entry in attrs.attrpath_values() {
+        let (key, value) = match (entry.attrpath(), entry.value()) {
+            (Some(k), Some(v)) => (k, v.syntax().clone()),
+            _ => continue,
+        };
+        match key_idents(&key).as_slice() {
+            [name] if name == "description" => decl.description = doc_string(&value),
+            [name] if name == "type" => decl.type_name = Some(type_name(&value)),
+            [name] if name == "default" => decl.default = Some(short_expr(&value)),
+            _ => (),
+        }
+    }
+    Some(decl)
+}
+
+/// `mkEnableOption "the foo service"`
+fn mk_enable_option(args: &[SyntaxNode]) -> Option<OptionDecl> {
+    let name = doc_string(args.first()?)?;
+    Some(OptionDecl {
+        description: Some(format!("Whether to enable {}.", name)),
+        type_name: Some("bool".to_string()),
+        default: Some("false".to_string()),
+    })
+}
+
+/// `mkPackageOption pkgs "foo" { default = [ "foo" "bar" ]; }`, where the name and default can
+/// each be a string or a list of strings making up an attribute path
+fn mk_package_option(args: &[SyntaxNode]) -> Option<OptionDecl> {
+    let pkgs = args.first()?.to_string();
+    let name = package_path(args.get(1)?)?;
+
+    let mut default = Some(format!("{}.{}", pkgs, name));
+    if let Some(attrs) = args.get(2).cloned().and_then(AttrSet::cast) {
+        for entry in attrs.attrpath_values() {
+            if entry.attrpath().map(|k| key_idents(&k)) == Some(vec!["default".to_string()]) {
+                default = entry
+                    .value()
+                    .and_then(|v| package_path(v.syntax()))
+                    .map(|path| format!("{}.{}", pkgs, path));
+            }
+        }
+    }
+
+    Some(OptionDecl {
+        description: Some(format!("The {} package to use.", name)),
+        type_name: Some("package".to_string()),
+        default,
+    })
+}
+
+/// Reads a package attribute path given either as `"foo"` or `[ "foo" "bar" ]`
+fn package_path(value: &SyntaxNode) -> Option<String> {
+    if let Some(s) = string_value(value) {
+        return Some(s);
+    }
+    value
+        .children()
+        .map(|item| string_value(&item))
+        .collect::<Option<Vec<_>>>()
+        .filter(|parts| !parts.is_empty())
+        .map(|parts| parts.join("."))
+}
+
+/// The text of a string, looking through markup wrappers like `lib.mdDoc "..."`
+fn doc_string(value: &SyntaxNode) -> Option<String> {
+    string_value(value).or_else(|| {
+        let (_, args) = apply_parts(value)?;
+        string_value(args.last()?)
+    })
+}
+
+/// Renders an option type without the noise of where the types came from, e.g. `listOf str` for
+/// `lib.types.listOf lib.types.str`
+fn type_name(value: &SyntaxNode) -> String {
+    short_expr(value)
+        .replace("lib.types.", "")
+        .replace("types.", "")
+}
+
+/// The source of a short expression, or `...` for one that spans lines
+fn short_expr(value: &SyntaxNode) -> String {
+    let text = value.to_string();
+    if text.contains('\n') {
+        "...".to_string()
+    } else {
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn declared(src: &str) -> Option<OptionDecl> {
+        let ast = rnix::Root::parse(src).ok().unwrap();
+        option_declaration(ast.expr()?.syntax())
+    }
+
+    #[test]
+    fn test_mk_option() {
+        assert_eq!(
+            declared(
+                r#"lib.mkOption {
+                  type = lib.types.listOf types.str;
+                  default = [ ];
+                  description = lib.mdDoc "Extra flags.";
+                }"#
+            ),
+            Some(OptionDecl {
+                description: Some("Extra flags.".to_string()),
+                type_name: Some("listOf str".to_string()),
+                default: Some("[ ]".to_string()),
+            })
+        );
+        // quotes in an indented string are part of it
+        let decl = declared("mkOption {\n  description = ''\n    x\n    (p + \"/q\")\n  '';\n}");
+        assert_eq!(
+            decl.unwrap().description.as_deref(),
+            Some("x\n(p + \"/q\")")
+        );
+    }
+
+    #[test]
+    fn test_helpers() {
+        assert_eq!(
+            declared(r#"mkEnableOption "the foo service""#),
+            Some(OptionDecl {
+                description: Some("Whether to enable the foo service.".to_string()),
+                type_name: Some("bool".to_string()),
+                default: Some("false".to_string()),
+            })
+        );
+        assert_eq!(
+            declared(r#"lib.mkPackageOption pkgs "foo" { }"#).unwrap(),
+            OptionDecl {
+                description: Some("The foo package to use.".to_string()),
+                type_name: Some("package".to_string()),
+                default: Some("pkgs.foo".to_string()),
+            }
+        );
+        let decl = declared(
+            r#"mkPackageOption pkgs [ "python3Packages" "foo" ] {
+            default = [ "python3Packages" "foo-bin" ];
+        }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            decl.description.as_deref(),
+            Some("The python3Packages.foo package to use.")
+        );
+        assert_eq!(
+            decl.default.as_deref(),
+            Some("pkgs.python3Packages.foo-bin")
+        );
+        assert_eq!(
+            decl.signature(),
+            "mkOption { type = package; default = pkgs.python3Packages.foo-bin; ... }"
+        );
+        assert_eq!(declared(r#"mkIf true "foo""#), None);
+    }
+}
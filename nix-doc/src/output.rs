@@ -0,0 +1,161 @@
+//! Where results are written: stdout, or a file given with `--output`
+use crate::log;
+
+use std::env;
+use std::fs::File;
+use std::io::{self, BufWriter, IsTerminal, Write};
+use std::path::Path;
+
+/// The characters drawn around results that not every terminal can show, like the rule between
+/// human-readable results and the ellipsis table cells that don't fit end in
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Glyphs {
+    /// Ends text that was cut short
+    pub ellipsis: &'static str,
+    /// Repeated to draw a line across
+    pub rule: &'static str,
+}
+
+impl Glyphs {
+    /// The default, for terminals that can show any of unicode
+    pub const UNICODE: Glyphs = Glyphs {
+        ellipsis: "…",
+        rule: "─",
+    };
+
+    /// Plain ASCII ones for the rest (`--ascii`)
+    pub const ASCII: Glyphs = Glyphs {
+        ellipsis: "...",
+        rule: "-",
+    };
+}
+
+impl Default for Glyphs {
+    fn default() -> Self {
+        Glyphs::UNICODE
+    }
+}
+
+/// A destination for results. Files never get colors, since nobody is looking at them on a
+/// terminal, and escape codes saved in them only get in the way.
+pub struct Output {
+    out: Box<dyn Write>,
+    /// Whether the colors in what's written are kept
+    pub color: bool,
+}
+
+impl Output {
+    /// Opens `path` for writing, replacing whatever was there, or stdout if there's no path
+    pub fn open(path: Option<&Path>) -> io::Result<Output> {
+        Ok(match path {
+            Some(path) => Output {
+                out: Box::new(BufWriter::new(File::create(path).map_err(|err| {
+                    io::Error::new(
+                        err.kind(),
+                        format!("can't write to {}: {}", path.display(), err),
+                    )
+                })?)),
+                color: false,
+            },
+            // buffered, so stdout isn't locked and written to once a line
+            None => Output {
+                out: Box::new(BufWriter::new(io::stdout())),
+                color: true,
+            },
+        })
+    }
+
+    /// How many characters fit on a line, if this is stdout and it's a terminal
+    pub fn terminal_width(&self) -> Option<usize> {
+        if !self.color || !io::stdout().is_terminal() {
+            return None;
+        }
+        stdout_columns().or_else(|| env::var("COLUMNS").ok()?.parse().ok())
+    }
+
+    /// Writes `line` and a newline, without its colors if this output doesn't want them.
+    ///
+    /// While anything is logged, it's written out straight away, so the log on stderr doesn't
+    /// end up in the middle of it on a terminal.
+    pub fn line(&mut self, line: &str) -> io::Result<()> {
+        let guard = log::lock();
+        if self.color {
+            writeln!(self.out, "{}", line)?;
+        } else {
+            writeln!(self.out, "{}", strip_colors(line))?;
+        }
+        match guard {
+            Some(_) => self.out.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.out.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+/// Width of the terminal stdout is connected to, as the terminal says
+#[cfg(unix)]
+fn stdout_columns() -> Option<usize> {
+    let mut size = libc::winsize {
+        ws_row: 0,
+        ws_col: 0,
+        ws_xpixel: 0,
+        ws_ypixel: 0,
+    };
+    // the kernel only writes to `size`, which is the struct this request wants
+    let got = unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) };
+    Some(size.ws_col as usize).filter(|&cols| got == 0 && cols > 0)
+}
+
+#[cfg(not(unix))]
+fn stdout_columns() -> Option<usize> {
+    None
+}
+
+/// `s` without the ANSI escape sequences its colors are made of
+pub fn strip_colors(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(ch) = chars.next() {
+        if ch == '\x1b' {
+            // `ESC [`, then numbers separated by `;`, ended by a letter
+            for ch in chars.by_ref() {
+                if ch.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        } else {
+            out.push(ch);
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::color::{Color, Colorful};
+
+    // without colors there are none to strip
+    #[cfg(feature = "color")]
+    #[test]
+    fn test_strip_colors() {
+        let colored = format!(
+            "{} = {}",
+            "map".white().bold(),
+            "f: ...".color(Color::Grey27)
+        );
+        assert_ne!(colored, "map = f: ...");
+        assert_eq!(strip_colors(&colored), "map = f: ...");
+        assert_eq!(strip_colors("plain ─ text"), "plain ─ text");
+    }
+}
@@ -0,0 +1,905 @@
+//! Reading nix syntax: the doc comments around definitions, and what definitions evaluate to
+use crate::lines::LineIndex;
+use crate::search::visit_lambda;
+use crate::{Error, Result, SearchOptions};
+
+use rnix::ast::{
+    Apply, Assert, Attr, AttrSet, Attrpath, AttrpathValue, Entry, Expr, HasEntry, IfElse,
+    InterpolPart, Lambda, LetIn, Paren, Str, With,
+};
+use rnix::tokenizer::Tokenizer;
+use rnix::SyntaxKind::*;
+use rnix::{Root, SyntaxNode, SyntaxToken, TextSize, WalkEvent};
+use rowan::ast::AstNode;
+#[cfg(feature = "serde")]
+use serde::Serialize;
+
+use std::fs;
+use std::iter;
+use std::ops::Range;
+use std::str;
+
+/// Is `content` nothing but whitespace and comments, like an empty file or a license stub? rnix
+/// fails to parse that for want of an expression, but there's nothing wrong with it, just nothing
+/// in it to find.
+pub(crate) fn is_blank_source(content: &str) -> bool {
+    Tokenizer::new(content).all(|(kind, _)| matches!(kind, TOKEN_WHITESPACE | TOKEN_COMMENT))
+}
+
+/// Parses the nix source `content`, failing with where the first syntax error is
+pub(crate) fn parse_nix(content: &str) -> Result<Root> {
+    Root::parse(content)
+        .ok()
+        .map_err(|err| Error::parse(&err, content, None))
+}
+
+/// Cleans up a single line, erasing prefix single line comments but preserving indentation
+pub fn cleanup_single_line(s: &str) -> &str {
+    let mut cmt_new_start = 0;
+    for (idx, ch) in s.char_indices() {
+        // if we find a character, save the byte position after it as our new string start
+        if ch == '#' || ch == '*' {
+            cmt_new_start = idx + 1;
+            break;
+        }
+        // if, instead, we are on a line with no starting comment characters, leave it alone as it
+        // will be handled by dedent later
+        if !ch.is_whitespace() {
+            break;
+        }
+    }
+    &s[cmt_new_start..]
+}
+
+/// Erases indents in comments. This is *almost* a normal dedent function, but it starts by looking
+/// at the second line if it can.
+pub fn dedent_comment(s: &str) -> String {
+    let mut whitespaces = 0;
+    let mut lines = s.lines();
+    let first = lines.next();
+
+    // scan for whitespace
+    for line in lines.chain(first) {
+        let line_whitespace = line.chars().take_while(|ch| ch.is_whitespace()).count();
+
+        if line_whitespace != line.len() {
+            // a non-whitespace line, perfect for taking whitespace off of
+            whitespaces = line_whitespace;
+            break;
+        }
+    }
+
+    // maybe the first considered line we found was indented further, so let's look for more lines
+    // that might have a shorter indent. In the case of one line, do nothing.
+    for line in s.lines().skip(1) {
+        let line_whitespace = line.chars().take_while(|ch| ch.is_whitespace()).count();
+
+        if line_whitespace != line.len() {
+            whitespaces = line_whitespace.min(whitespaces);
+        }
+    }
+
+    // delete up to `whitespaces` whitespace characters from each line and reconstitute the string
+    let mut out = String::new();
+    for line in s.lines() {
+        let content_begin = line.find(|ch: char| !ch.is_whitespace()).unwrap_or(0);
+        out.push_str(&line[content_begin.min(whitespaces)..]);
+        out.push('\n');
+    }
+
+    out.truncate(out.trim_end_matches('\n').len());
+    out
+}
+
+/// Deletes whitespace and leading comment characters
+///
+/// Line comments only lose the `#` and the space after it, then whatever indent they all share,
+/// so Markdown in them, like nested lists, keeps its indentation. Block comments are dedented as
+/// a whole (see [`dedent_comment`]), after losing just their own `/*` and `*/`, so that ones
+/// inside of them, like the `/*` of `/*/*.nix files */`, are kept.
+///
+/// Oversight we are choosing to ignore: if you put # characters at the beginning of lines in a
+/// multiline comment, they will be deleted.
+pub fn cleanup_comments<S: AsRef<str>, I: DoubleEndedIterator<Item = S>>(
+    comment: &mut I,
+) -> String {
+    let mut all_line_comments = true;
+    let joined = comment
+        .rev()
+        .map(|small_comment| {
+            let small_comment = small_comment.as_ref().trim();
+            if let Some(line) = small_comment.strip_prefix('#') {
+                return line.strip_prefix(' ').unwrap_or(line).to_string();
+            }
+            all_line_comments = false;
+            // just the delimiters: block comments don't nest, so a `/*` or `*/` next to them is
+            // part of the comment, and one cut off by the end of the file has no `*/`
+            let block = small_comment.strip_prefix("/*").unwrap_or(small_comment);
+            block
+                .strip_suffix("*/")
+                .unwrap_or(block)
+                // extra space that was in the multiline
+                .trim()
+                .split('\n')
+                // erase single line comments and such
+                .map(cleanup_single_line)
+                .collect::<Vec<_>>()
+                .join("\n")
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    if all_line_comments {
+        dedent_lines(&joined)
+    } else {
+        dedent_comment(&joined)
+    }
+}
+
+/// Takes the indent every non-blank line of `s` has off all of them, keeping any indentation
+/// beyond that
+fn dedent_lines(s: &str) -> String {
+    let indent = s
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start().len())
+        .min()
+        .unwrap_or(0);
+    let out = s
+        .lines()
+        .map(|line| line.get(indent..).unwrap_or_default())
+        .collect::<Vec<_>>()
+        .join("\n");
+    out.trim_end_matches('\n').to_string()
+}
+
+/// Strips just the comment delimiters (`#`, `/*` and `*/`) from comments, keeping everything else
+/// as written
+pub(crate) fn raw_comments<S: AsRef<str>, I: DoubleEndedIterator<Item = S>>(
+    comment: &mut I,
+) -> String {
+    comment
+        .rev()
+        .map(|small_comment| {
+            let small_comment = small_comment.as_ref();
+            match small_comment.strip_prefix("/*") {
+                Some(block) => block.strip_suffix("*/").unwrap_or(block),
+                None => small_comment.strip_prefix('#').unwrap_or(small_comment),
+            }
+            .to_string()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// How comments are turned into documentation
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct DocStyle<'a> {
+    /// Keep the comments as written, only removing the delimiters
+    pub(crate) raw: bool,
+    /// Only line comments starting with this, like `##`, are documentation, and it's removed
+    /// from them. Block comments are always documentation.
+    pub(crate) line_prefix: Option<&'a str>,
+}
+
+impl DocStyle<'_> {
+    /// Is `comment`, including its delimiters, documentation?
+    fn is_doc(&self, comment: &str) -> bool {
+        match self.line_prefix {
+            Some(prefix) if !comment.starts_with("/*") => comment.starts_with(prefix),
+            _ => true,
+        }
+    }
+}
+
+/// The documentation a definition has, as written and cleaned up
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct Comment {
+    /// The comments the documentation came from, delimiters and all, or the description string
+    /// for definitions documented by one
+    pub(crate) raw: String,
+    /// The documentation, cleaned up the way the [`DocStyle`] says
+    pub(crate) doc: String,
+}
+
+impl From<String> for Comment {
+    /// A description from somewhere other than a comment, which is as written already
+    fn from(doc: String) -> Self {
+        Comment {
+            raw: doc.clone(),
+            doc,
+        }
+    }
+}
+
+/// Turns the comments found before or after a definition into its documentation, or `None` if
+/// there's nothing in them
+pub(crate) fn comment_text<S: AsRef<str>, I: DoubleEndedIterator<Item = S>>(
+    comment: &mut I,
+    style: DocStyle<'_>,
+) -> Option<Comment> {
+    let comments = comment.map(|c| c.as_ref().to_string()).collect::<Vec<_>>();
+    let raw = comments
+        .iter()
+        .rev()
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .join("\n");
+    // the longer prefix is swapped for a plain `#` for the cleanup to remove
+    let mut comment = comments.iter().map(|c| match style.line_prefix {
+        Some(prefix) => match c.strip_prefix(prefix) {
+            Some(rest) => format!("#{}", rest),
+            None => c.clone(),
+        },
+        None => c.clone(),
+    });
+    let doc = if style.raw {
+        raw_comments(&mut comment)
+    } else {
+        cleanup_comments(&mut comment)
+    };
+    Some(Comment { raw, doc }).filter(|it| !it.doc.trim().is_empty())
+}
+
+/// Get the docs for a specific function
+pub fn get_function_docs(filename: &str, line: usize, col: usize) -> Option<String> {
+    let content = fs::read(filename).ok()?;
+    let decoded = str::from_utf8(&content).ok()?;
+    let pos = LineIndex::new(decoded).offset(line, col)?;
+    let rowan_pos = TextSize::from(pos as u32);
+    let tree = Root::parse(decoded).syntax();
+
+    let mut lambda = None;
+    for node in tree.preorder() {
+        match node {
+            WalkEvent::Enter(n) => {
+                if n.text_range().start() >= rowan_pos && n.kind() == NODE_LAMBDA {
+                    lambda = Lambda::cast(n);
+                    break;
+                }
+            }
+            WalkEvent::Leave(_) => (),
+        }
+    }
+    let lambda = lambda?;
+    let res = visit_lambda(
+        "func".to_string(),
+        pos,
+        &lambda,
+        lambda.syntax(),
+        &SearchOptions::default(),
+    );
+    Some(res.format(filename, line))
+}
+
+/// Attribute path from the top of the file down to `node`, made of the keys of the bindings it is
+/// in the value of
+pub(crate) fn attr_path_to(node: &SyntaxNode) -> Vec<String> {
+    let mut path = Vec::new();
+    let mut child = node.clone();
+    while let Some(parent) = child.parent() {
+        if let Some(kv) = AttrpathValue::cast(parent.clone()) {
+            if kv.value().is_some_and(|value| value.syntax() == &child) {
+                let mut key = kv.attrpath().map(|k| key_idents(&k)).unwrap_or_default();
+                key.append(&mut path);
+                path = key;
+            }
+        }
+        child = parent;
+    }
+    path
+}
+
+/// Functions that hand back their last argument, after checking or printing something, like
+/// `lib.throwIf cond "msg" f`
+pub(crate) const PASSTHROUGH_FUNCTIONS: &[&str] = &[
+    "throwIf",
+    "throwIfNot",
+    "warnIf",
+    "warnIfNot",
+    "warn",
+    "trace",
+    "traceIf",
+    "seq",
+    "deepSeq",
+];
+
+/// Finds the function a definition evaluates to, looking through wrappers that don't change that:
+/// parentheses, `assert`, `let ... in`, `with`, `if` where both branches are functions (in which
+/// case the `then` branch is used), and the [`PASSTHROUGH_FUNCTIONS`]
+pub(crate) fn unwrap_lambda(value: &SyntaxNode) -> Option<Lambda> {
+    let mut node = value.clone();
+    loop {
+        node = match node.kind() {
+            NODE_LAMBDA => return Lambda::cast(node),
+            NODE_PAREN => Paren::cast(node)?.expr()?.syntax().clone(),
+            NODE_APPLY => {
+                let (head, mut args) = apply_parts(&node)?;
+                let name = head.rsplit('.').next()?;
+                if !PASSTHROUGH_FUNCTIONS.contains(&name) {
+                    return None;
+                }
+                args.pop()?
+            }
+            NODE_ASSERT => Assert::cast(node)?.body()?.syntax().clone(),
+            NODE_LET_IN => LetIn::cast(node)?.body()?.syntax().clone(),
+            NODE_WITH => With::cast(node)?.body()?.syntax().clone(),
+            NODE_IF_ELSE => {
+                let if_else = IfElse::cast(node)?;
+                let then_branch = unwrap_lambda(if_else.body()?.syntax())?;
+                unwrap_lambda(if_else.else_body()?.syntax())?;
+                return Some(then_branch);
+            }
+            _ => return None,
+        };
+    }
+}
+
+/// Could `lambda` return another function, so take more arguments than [`pprint::arity`] counts?
+/// That's the case when its final body is a call or a reference, whose value we can't see, or
+/// a function hidden behind something like a `let`.
+pub(crate) fn arity_may_be_higher(lambda: &Lambda) -> bool {
+    let mut body = lambda.body();
+    while let Some(Expr::Lambda(inner)) = body {
+        body = inner.body();
+    }
+    let mut body = match body {
+        Some(body) => body,
+        None => return false,
+    };
+    while let Some(inner) = Paren::cast(body.syntax().clone()).and_then(|p| p.expr()) {
+        body = inner;
+    }
+    // `a.b or c` is a select as well
+    matches!(body, Expr::Apply(_) | Expr::Ident(_) | Expr::Select(_))
+        || unwrap_lambda(body.syntax()).is_some()
+}
+
+/// Functions whose application marks a definition as deprecated, when given a message
+pub(crate) const DEPRECATION_FUNCTIONS: &[&str] = &["warn", "trace", "throw"];
+
+/// Finds the warning message of a deprecated shim such as `lib.warn "msg" realFn`,
+/// `throw "msg"`, or a curried `x: builtins.trace "msg" (realFn x)`
+pub(crate) fn deprecation_message(value: &SyntaxNode) -> Option<String> {
+    let mut node = value.clone();
+    // look through the arguments of curried definitions
+    while let Some(body) = Lambda::cast(node.clone()).and_then(|l| l.body()) {
+        node = body.syntax().clone();
+    }
+
+    let (head, args) = apply_parts(&node)?;
+    let name = head.rsplit('.').next()?;
+    if DEPRECATION_FUNCTIONS.contains(&name) {
+        string_value(args.first()?)
+    } else {
+        None
+    }
+}
+
+/// Unwinds a function application `f a b c` into the source of `f` and the arguments `[a, b, c]`,
+/// looking through parentheses
+pub(crate) fn apply_parts(value: &SyntaxNode) -> Option<(String, Vec<SyntaxNode>)> {
+    let mut node = value.clone();
+    let mut args = Vec::new();
+    loop {
+        node = match node.kind() {
+            NODE_APPLY => {
+                let app = Apply::cast(node)?;
+                args.push(app.argument()?.syntax().clone());
+                app.lambda()?.syntax().clone()
+            }
+            NODE_PAREN => Paren::cast(node)?.expr()?.syntax().clone(),
+            _ => break,
+        };
+    }
+    args.reverse();
+    Some((node.to_string(), args))
+}
+
+/// Digs through the usual wrappers around a package definition to find the attrset holding its
+/// attributes, e.g. `stdenv.mkDerivation rec { ... }` or `mkDerivation (finalAttrs: { ... })`
+pub(crate) fn package_attrs(value: &SyntaxNode) -> Option<AttrSet> {
+    let mut node = value.clone();
+    loop {
+        node = match node.kind() {
+            NODE_ATTR_SET => return AttrSet::cast(node),
+            NODE_APPLY => Apply::cast(node)?.argument()?.syntax().clone(),
+            NODE_PAREN => Paren::cast(node)?.expr()?.syntax().clone(),
+            NODE_LAMBDA => Lambda::cast(node)?.body()?.syntax().clone(),
+            NODE_WITH => With::cast(node)?.body()?.syntax().clone(),
+            _ => return None,
+        };
+    }
+}
+
+/// Renders the function a package is built with, to stand in for a signature: for
+/// `stdenv.mkDerivation { ... }` that's `stdenv.mkDerivation { ... }`
+pub(crate) fn package_head(value: &SyntaxNode) -> String {
+    match Apply::cast(value.clone()).and_then(|app| app.lambda()) {
+        Some(builder) => format!("{} {{ ... }}", builder),
+        None => "{ ... }".to_string(),
+    }
+}
+
+/// Finds the `meta.description` of a package-like value, accepting both the `meta.description =`
+/// and `meta = { description = ...; }` spellings
+pub(crate) fn find_package_description(value: &SyntaxNode) -> Option<String> {
+    let attrs = package_attrs(value)?;
+    for entry in attrs.attrpath_values() {
+        let (key, entry_value) = match (entry.attrpath(), entry.value()) {
+            (Some(k), Some(v)) => (k, v),
+            _ => continue,
+        };
+        let path = key_idents(&key);
+
+        if path == ["meta", "description"] {
+            return string_value(entry_value.syntax());
+        } else if path == ["meta"] {
+            return package_attrs(entry_value.syntax())?
+                .attrpath_values()
+                .filter(|ent| {
+                    ent.attrpath().map(|k| key_idents(&k)) == Some(vec!["description".into()])
+                })
+                .find_map(|ent| string_value(ent.value()?.syntax()));
+        }
+    }
+    None
+}
+
+/// Gets the components of an attribute path made only of identifiers and plain strings, e.g.
+/// `meta.description`. Returns an empty path if any component is computed.
+pub(crate) fn key_idents(key: &Attrpath) -> Vec<String> {
+    key.attrs()
+        .map(|part| key_part_name(&part))
+        .collect::<Option<Vec<_>>>()
+        .unwrap_or_default()
+}
+
+/// Name of one component of an attribute path: an identifier, or a string without
+/// interpolations, like `"foo-bar"`
+pub(crate) fn key_part_name(part: &Attr) -> Option<String> {
+    match part {
+        // an identifier is the one token, which may be `or` rather than a TOKEN_IDENT
+        Attr::Ident(ident) => Some(ident.syntax().to_string()),
+        Attr::Str(s) => s
+            .normalized_parts()
+            .into_iter()
+            .map(|part| match part {
+                InterpolPart::Literal(lit) => Some(lit),
+                InterpolPart::Interpolation(_) => None,
+            })
+            .collect(),
+        Attr::Dynamic(_) => None,
+    }
+}
+
+/// Gets the text of a string literal, writing interpolations back out as they appear in source
+pub(crate) fn string_value(node: &SyntaxNode) -> Option<String> {
+    let s = Str::cast(node.clone())?;
+    let mut out = String::new();
+    for part in s.normalized_parts() {
+        match part {
+            InterpolPart::Literal(lit) => out.push_str(&lit),
+            // `${` and `}` and all
+            InterpolPart::Interpolation(interpol) => out.push_str(&interpol.syntax().to_string()),
+        }
+    }
+    Some(out.trim().to_string()).filter(|s| !s.is_empty())
+}
+
+/// Gets the documentation comments before `node`, such as the value of an attrset entry or a
+/// lambda, with the comment characters and indentation cleaned up the way search results have them
+pub fn extract_doc(node: &SyntaxNode) -> Option<String> {
+    find_comment(node.clone(), DocStyle::default()).map(|comment| comment.doc)
+}
+
+/// Finds the doc comment before `node`, ignoring comments that aren't documentation in `style`
+pub(crate) fn find_comment(node: SyntaxNode, style: DocStyle<'_>) -> Option<Comment> {
+    let comments = comment_tokens(node, style);
+    comment_text(&mut comments.iter().map(|c| c.text()), style)
+}
+
+/// The comment tokens [`find_comment`] takes the documentation of `node` from, nearest first.
+/// They're found going back a token at a time from the start of `node`, through whatever it's
+/// in, until something other than comments and whitespace.
+fn comment_tokens(node: SyntaxNode, style: DocStyle<'_>) -> Vec<SyntaxToken> {
+    let mut comments = Vec::new();
+    let mut newline_since_comment = false;
+    // is there a line break between the earliest comment found so far and what it documents?
+    let mut newline_after_comment = false;
+    let mut prev = node.first_token().and_then(|token| token.prev_token());
+    // the top of the file ends the walk, with whatever was found being all there is
+    while let Some(token) = prev {
+        prev = token.prev_token();
+
+        // This stuff is found as part of `the-fn = f: ...`
+        // here:                           ^^^^^^^^
+        if token.kind() == TOKEN_ASSIGN || in_binding_key(&token) {
+            continue;
+        }
+        match token.kind() {
+            // comments that aren't documentation are skipped over like whitespace
+            TOKEN_COMMENT if !style.is_doc(token.text()) => (),
+            TOKEN_COMMENT => {
+                comments.push(token);
+                newline_after_comment = newline_since_comment;
+                newline_since_comment = false;
+            }
+            TOKEN_WHITESPACE => {
+                if token.text().contains('\n') {
+                    newline_since_comment = true;
+                }
+            }
+            t if t.is_trivia() => (),
+            kind => {
+                // a comment ending the line of the previous thing, like `a = 1; # about a`,
+                // belongs to that instead. One sharing a line with both, as in
+                // `a = 1; /* doc */ f = ...`, is right before us, so it's ours. Nothing can be
+                // documented by a comment after an opening `{` or `let` either, so those are ours
+                // too, as in `{ /* doc */ f = ...`
+                let opens_bindings = kind == TOKEN_L_BRACE || kind == TOKEN_LET;
+                let trails_previous = !newline_since_comment && newline_after_comment;
+                if !comments.is_empty() && trails_previous && !opens_bindings {
+                    comments.pop();
+                }
+                break;
+            }
+        }
+    }
+    comments
+}
+
+/// Is `token` in the key of a binding, like `a.b` in `a.b = 1;`, comments and all?
+fn in_binding_key(token: &SyntaxToken) -> bool {
+    token.parent_ancestors().any(|node| {
+        node.kind() == NODE_ATTRPATH
+            && node
+                .parent()
+                .is_some_and(|parent| parent.kind() == NODE_ATTRPATH_VALUE)
+    })
+}
+
+/// Finds a comment following `entry` on the same line, like `toUpper = s: ...; # uppercase a string`
+pub(crate) fn find_trailing_comment(entry: &SyntaxNode, style: DocStyle<'_>) -> Option<Comment> {
+    let comment = trailing_comment_token(entry, style)?;
+    comment_text(&mut iter::once(comment.text()), style)
+}
+
+/// The comment token [`find_trailing_comment`] takes the documentation of `entry` from
+fn trailing_comment_token(entry: &SyntaxNode, style: DocStyle<'_>) -> Option<SyntaxToken> {
+    let mut elem = entry.next_sibling_or_token();
+    while let Some(e) = elem {
+        match e.kind() {
+            TOKEN_COMMENT => {
+                let comment = e.as_token()?;
+                if !style.is_doc(comment.text()) {
+                    return None;
+                }
+                return Some(comment.clone());
+            }
+            TOKEN_WHITESPACE if !e.as_token()?.text().contains('\n') => (),
+            _ => return None,
+        }
+        elem = e.next_sibling_or_token();
+    }
+    None
+}
+
+/// Documentation found around a position by [`comment_for`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct Doc {
+    /// The documentation, cleaned up like that of search results
+    pub doc: String,
+
+    /// Where the comments it came from are in the source, delimiters and all, as the byte offsets
+    /// from the start of the first to the end of the last
+    pub range: Range<usize>,
+}
+
+/// The documentation of the innermost documented binding around byte `offset` of the nix source
+/// `source`, in an attrset or `let` or by `inherit`, for showing the docs of whatever is under an
+/// editor's cursor. Broken source is looked through as far as it parses.
+pub fn comment_for(source: &str, offset: usize) -> Option<Doc> {
+    comment_for_ast(&Root::parse(source).tree(), offset)
+}
+
+/// [`comment_for`] in source that has already been parsed
+pub fn comment_for_ast(root: &Root, offset: usize) -> Option<Doc> {
+    let root = root.syntax();
+    if offset > usize::from(root.text_range().end()) {
+        return None;
+    }
+    let token = root
+        .token_at_offset(TextSize::from(offset as u32))
+        .right_biased()?;
+    let style = DocStyle::default();
+    token.parent_ancestors().find_map(|node| {
+        let leading = match Entry::cast(node.clone())? {
+            // the same as search results, which look from the value
+            Entry::AttrpathValue(kv) => {
+                let value = kv.value().map(|value| value.syntax().clone());
+                comment_tokens(value.unwrap_or_else(|| node.clone()), style)
+            }
+            Entry::Inherit(_) => comment_tokens(node.clone(), style),
+        };
+        doc_from_tokens(&leading, style).or_else(|| {
+            let trailing = trailing_comment_token(&node, style)?;
+            doc_from_tokens(&[trailing], style)
+        })
+    })
+}
+
+/// The [`Doc`] in comment `tokens`, nearest to what they document first
+fn doc_from_tokens(tokens: &[SyntaxToken], style: DocStyle<'_>) -> Option<Doc> {
+    let comment = comment_text(&mut tokens.iter().map(|t| t.text()), style)?;
+    let start = usize::from(tokens.last()?.text_range().start());
+    let end = usize::from(tokens.first()?.text_range().end());
+    Some(Doc {
+        doc: comment.doc,
+        range: start..end,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DefKind;
+
+    #[test]
+    fn test_comment_stripping() {
+        let ex1 = ["/* blah blah blah\n      foooo baaar\n   blah */"];
+        assert_eq!(
+            cleanup_comments(&mut ex1.iter()),
+            "blah blah blah\n   foooo baaar\nblah"
+        );
+
+        let ex2 = ["# a1", "#    a2", "# aa"];
+        assert_eq!(cleanup_comments(&mut ex2.iter()), "aa\n   a2\na1");
+
+        // nested Markdown lists keep their indentation
+        let ex3 = ["#     - nested", "#   - item", "#", "# A list:"];
+        assert_eq!(
+            cleanup_comments(&mut ex3.iter()),
+            "A list:\n\n  - item\n    - nested"
+        );
+        let ex4 = ["#   - b", "#   - a"];
+        assert_eq!(cleanup_comments(&mut ex4.iter()), "- a\n- b");
+    }
+
+    #[test]
+    fn test_comment_delimiters() {
+        let ast = rnix::Root::parse(include_str!("../testdata/comments.nix"))
+            .ok()
+            .unwrap();
+        let docs = crate::search_ast(&regex::Regex::new("").unwrap(), &ast, &Default::default())
+            .into_iter()
+            .map(|res| (res.identifier, res.doc))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            docs.iter()
+                .map(|(name, doc)| format!("{}: {}", name, doc))
+                .collect::<Vec<_>>(),
+            [
+                "opener: /* starts a block comment",
+                "glob: /*.nix files",
+                "globs: Matches `lib/*` and `lib/**`",
+                // the first star is the comment's, and only the delimiter after it is taken off
+                "star: Ends in a star *",
+                "lineEnd: Ends like a block comment */",
+                "lineStart: Starts like one /* and never ends",
+                "quoted: Quotes one,\n/* like this",
+                "hash: Never closed, as far as a line comment goes: #",
+            ]
+        );
+        // the end of the file cuts off a block comment, which has no `*/` to take off
+        let unterminated = ["/* Never closed, like a/*b"];
+        assert_eq!(
+            cleanup_comments(&mut unterminated.iter()),
+            "Never closed, like a/*b"
+        );
+        assert_eq!(cleanup_comments(&mut ["/**/"].iter()), "");
+        assert_eq!(cleanup_comments(&mut ["/*/*/"].iter()), "/");
+    }
+
+    #[test]
+    fn test_dedent() {
+        let ex1 = "a\n   b\n   c\n     d";
+        assert_eq!(dedent_comment(ex1), "a\nb\nc\n  d");
+        let ex2 = "a\nb\nc";
+        assert_eq!(dedent_comment(ex2), ex2);
+        let ex3 = "   a\n   b\n\n     c";
+        assert_eq!(dedent_comment(ex3), "a\nb\n\n  c");
+    }
+
+    #[test]
+    fn test_single_line_comment_stripping() {
+        let ex1 = "    * a";
+        let ex2 = "    # a";
+        let ex3 = "   a";
+        assert_eq!(cleanup_single_line(ex1), " a");
+        assert_eq!(cleanup_single_line(ex2), " a");
+        assert_eq!(cleanup_single_line(ex3), ex3);
+    }
+
+    #[test]
+    fn test_nested_comments() {
+        let cases: &[(&str, Option<&str>)] = &[
+            ("{ a = { b = { /* doc */ f = x: x; }; }; }", Some("doc")),
+            (
+                "{ a = { b = { c = { d = {\n  # doc\n  f = x: x;\n}; }; }; }; }",
+                Some("doc"),
+            ),
+            // a trailing comment after a nested set belongs to it, not what comes next
+            ("{ a = { b = 1; }; # about a\n  f = x: x; }", None),
+            (
+                "{ a = { b = { c = 1; }; }; # about a\n  # doc\n  f = x: x; }",
+                Some("doc"),
+            ),
+            (
+                "{ a = {\n  b = {\n    c = 1;\n  }; # about b\n  f = x: x;\n}; }",
+                None,
+            ),
+            // leftovers at the end of a sibling's set are inside of it
+            ("{ a = {\n  b = 1;\n  # end of a\n};\nf = x: x; }", None),
+            (
+                "{ a = {\n  b = 1;\n  # end of a\n};\n# doc\nf = x: x; }",
+                Some("doc"),
+            ),
+            ("{ a = { b = 1; /* end of a */ }; f = x: x; }", None),
+            ("{ a = { b = 1; }; /* doc */ f = x: x; }", Some("doc")),
+            // comments on either side of the `=` go with the ones before the key
+            ("{ /* doc */ f /* more */ = x: x; }", Some("doc\nmore")),
+            ("{ /* doc */ f = /* more */ x: x; }", Some("doc\nmore")),
+            ("{ a = { b = 1; }; /* about a */\n  f = x: x; }", None),
+            (
+                "{ a = { b = 1; }; # about a\n  /* doc */ f = x: x; }",
+                Some("doc"),
+            ),
+            // the first entry of a nested set
+            ("{ a = { # doc\n  f = x: x; }; }", Some("doc")),
+            (
+                "{ a = rec {\n  # doc\n  f = x: x;\n  g = f; }; }",
+                Some("doc"),
+            ),
+            (
+                "{ a.b.c = { d = let\n  # doc\n  f = x: x; in f; }; }",
+                Some("doc"),
+            ),
+            // the comment before the set isn't about its first entry
+            ("{\n  # about a\n  a = {\n    f = x: x;\n  };\n}", None),
+            ("{\n  # about a\n  a = { f = x: x; };\n}", None),
+            (
+                "{\n  # about a\n  a = {\n  # doc\n  f = x: x; }; }",
+                Some("doc"),
+            ),
+            ("{ a = [ { b = 1; } ]; # about a\n  f = x: x; }", None),
+            (
+                "{ a = { b = 1; }\n  # about the update\n  // { c = 2; };\n  f = x: x; }",
+                None,
+            ),
+        ];
+        let opts = SearchOptions {
+            def_kinds: vec![DefKind::Attr, DefKind::RecAttr, DefKind::LetIn],
+            ..SearchOptions::default()
+        };
+        for (src, doc) in cases {
+            let ast = Root::parse(src).ok().unwrap();
+            let lambda = ast
+                .syntax()
+                .descendants()
+                .filter_map(AttrpathValue::cast)
+                .find(|kv| kv.attrpath().map(|k| k.syntax().to_string()) == Some("f".to_string()))
+                .and_then(|kv| kv.value())
+                .unwrap();
+            assert_eq!(
+                find_comment(lambda.syntax().clone(), opts.doc_style())
+                    .map(|comment| comment.doc)
+                    .as_deref(),
+                *doc,
+                "for {}",
+                src
+            );
+        }
+    }
+
+    #[test]
+    fn test_extract_doc() {
+        let src = "{\n  /* Adds one\n     to x */\n  inc = x: x + 1;\n  dec = x: x - 1;\n}";
+        let ast = Root::parse(src).ok().unwrap();
+        let lambdas = ast
+            .syntax()
+            .descendants()
+            .filter(|n| n.kind() == NODE_LAMBDA)
+            .collect::<Vec<_>>();
+        assert_eq!(extract_doc(&lambdas[0]).as_deref(), Some("Adds one\nto x"));
+        assert_eq!(extract_doc(&lambdas[1]), None);
+    }
+
+    #[test]
+    fn test_comment_for() {
+        let src = r#"{
+  # The outer set
+  outer = {
+    /* The inner one */
+    inner = 1;
+    plain = 2;
+  };
+
+  # Maps things
+  map = f: xs:
+    let
+      # Applies f
+      go = x: f x;
+      step = 1;
+    in builtins.map go xs;
+
+  undocumented = 3;
+  trailing = 4; # After it
+}
+"#;
+        let at = |needle: &str| {
+            let offset = src.find(needle).unwrap();
+            comment_for(src, offset).map(|doc| doc.doc)
+        };
+        assert_eq!(at("outer =").as_deref(), Some("The outer set"));
+        assert_eq!(at("inner =").as_deref(), Some("The inner one"));
+        // in the value, not the key
+        assert_eq!(at("1;").as_deref(), Some("The inner one"));
+        // not documented, so it's the set around it
+        assert_eq!(at("plain").as_deref(), Some("The outer set"));
+        assert_eq!(at("go = ").as_deref(), Some("Applies f"));
+        assert_eq!(at("f x;").as_deref(), Some("Applies f"));
+        assert_eq!(at("step").as_deref(), Some("Maps things"));
+        assert_eq!(at("builtins.map").as_deref(), Some("Maps things"));
+        assert_eq!(at("undocumented"), None);
+        assert_eq!(at("trailing").as_deref(), Some("After it"));
+        // outside of any binding
+        assert_eq!(comment_for(src, 0), None);
+        assert_eq!(comment_for(src, src.len() + 1), None);
+        assert_eq!(comment_for("", 0), None);
+
+        let doc = comment_for(src, src.find("inner =").unwrap()).unwrap();
+        assert_eq!(&src[doc.range], "/* The inner one */");
+        let doc = comment_for(src, src.find("map =").unwrap()).unwrap();
+        assert_eq!(&src[doc.range], "# Maps things");
+        let ast = Root::parse(src).tree();
+        assert_eq!(
+            comment_for_ast(&ast, src.find("go =").unwrap()).map(|doc| doc.doc),
+            at("go =")
+        );
+    }
+
+    #[test]
+    fn test_comment_for_inherit() {
+        let src = r#"let
+  # From lib
+  # (both of them)
+  inherit (lib) filter foldl;
+in {
+  inherit filter; # Keeps some
+  # The map
+  inherit (builtins) map;
+  inherit foldl;
+}
+"#;
+        let at = |needle: &str| {
+            let offset = src.rfind(needle).unwrap();
+            comment_for(src, offset)
+        };
+        let doc = comment_for(src, src.find("foldl").unwrap()).unwrap();
+        assert_eq!(doc.doc, "From lib\n(both of them)");
+        assert_eq!(&src[doc.range], "# From lib\n  # (both of them)");
+        assert_eq!(at("filter;").unwrap().doc, "Keeps some");
+        assert_eq!(at("map").unwrap().doc, "The map");
+        assert_eq!(at("builtins").unwrap().doc, "The map");
+        assert_eq!(at("foldl;"), None);
+    }
+
+    #[test]
+    fn test_comment_for_broken_source() {
+        // as being typed in an editor
+        let src = "{\n  # Adds one\n  inc = x: x + 1;\n  dec = \n";
+        let doc = comment_for(src, src.find("inc").unwrap()).unwrap();
+        assert_eq!(doc.doc, "Adds one");
+    }
+}
@@ -0,0 +1,271 @@
+//! What a search is going to do, worked out from everything asked of it, for finding out why a
+//! filter isn't doing what was expected without running the search
+use crate::filter::NixFiles;
+use crate::{DefKind, ResultKind, SearchOptions};
+
+use regex::Regex;
+
+use std::fmt::{self, Display};
+use std::path::Path;
+
+/// The plan for searching `root`, printed by `nix-doc search --explain-query`: a line for each
+/// setting that decides what's found and how it's printed, the way the search will use it
+///
+/// ```
+/// use nix_doc::plan::QueryPlan;
+/// use nix_doc::SearchOptions;
+/// use regex::Regex;
+/// use std::path::Path;
+///
+/// let opts = SearchOptions::builder().jobs(2).build().unwrap();
+/// let matching = Regex::new("^concat").unwrap();
+/// let plan = QueryPlan::new(Path::new("lib"), &matching, &opts).to_string();
+/// assert!(plan.contains("  pattern      regex `^concat`\n"));
+/// assert!(plan.contains("  threads      2, on the pool backend"));
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct QueryPlan<'a> {
+    root: &'a Path,
+    matching: &'a Regex,
+    opts: &'a SearchOptions,
+    files: Option<&'a NixFiles>,
+}
+
+impl<'a> QueryPlan<'a> {
+    /// The plan for searching `root` for `matching` with `opts`
+    pub fn new(root: &'a Path, matching: &'a Regex, opts: &'a SearchOptions) -> Self {
+        QueryPlan {
+            root,
+            matching,
+            opts,
+            files: None,
+        }
+    }
+
+    /// Searching the files `files` picks, rather than every `.nix` file
+    pub fn files(mut self, files: &'a NixFiles) -> Self {
+        self.files = Some(files);
+        self
+    }
+
+    /// The filters on what's found that are switched on, each as what a result has to be like
+    fn filters(&self) -> Vec<String> {
+        let opts = self.opts;
+        let mut filters = Vec::new();
+        if !opts.path_prefix.is_empty() {
+            let path = opts.path_prefix.join(".");
+            filters.push(match opts.path_exact {
+                true => format!("at the attribute path {}", path),
+                false => format!("at or under the attribute path {}", path),
+            });
+        }
+        if let Some(re) = &opts.file_regex {
+            filters.push(format!("in a file whose path matches `{}`", re));
+        }
+        if opts.changed.is_some() {
+            filters.push("starting on a changed line".to_string());
+        }
+        if let Some(prefix) = &opts.doc_prefix {
+            filters.push(format!("with docs in comments starting with `{}`", prefix));
+        }
+        if let Some(re) = &opts.match_synopsis {
+            filters.push(format!("with a synopsis matching `{}`", re));
+        }
+        if let Some(re) = &opts.takes_arg {
+            filters.push(format!("taking an argument matching `{}`", re));
+        }
+        if let Some(re) = &opts.arg_default {
+            filters.push(format!("with a default for an argument matching `{}`", re));
+        }
+        if let Some(re) = &opts.arg_required {
+            filters.push(format!("needing an argument matching `{}`", re));
+        }
+        if let Some(arity) = opts.arity {
+            filters.push(format!("taking {} arguments", arity));
+        }
+        if let Some(min) = opts.min_arity {
+            filters.push(format!("taking at least {} arguments", min));
+        }
+        match opts.example {
+            Some(true) => filters.push("with an example".to_string()),
+            Some(false) => filters.push("without an example".to_string()),
+            None => (),
+        }
+        match opts.typed {
+            Some(true) => filters.push("giving its type".to_string()),
+            Some(false) => filters.push("not giving its type".to_string()),
+            None => (),
+        }
+        if opts.no_deprecated {
+            filters.push("not deprecated".to_string());
+        }
+        if let Some(max) = opts.max_matches_per_file {
+            filters.push(format!("one of the first {} in its file", max));
+        }
+        filters
+    }
+}
+
+impl Display for QueryPlan<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let opts = self.opts;
+        let names = |names: Vec<&str>| names.join(", ");
+        writeln!(f, "search plan:")?;
+        writeln!(f, "  {:<12} {}", "root", self.root.display())?;
+        match &opts.fuzzy {
+            Some(fuzzy) => writeln!(f, "  {:<12} fuzzy `{}`", "pattern", fuzzy)?,
+            None => writeln!(f, "  {:<12} regex `{}`", "pattern", self.matching)?,
+        }
+        let fields = match opts.all_fields {
+            true => "names, attribute paths and docs",
+            false => "names",
+        };
+        writeln!(f, "  {:<12} {}", "matched on", fields)?;
+        let kinds = ResultKind::ALL
+            .iter()
+            .filter(|&&kind| opts.wants_kind(kind))
+            .map(|kind| kind.name());
+        writeln!(f, "  {:<12} {}", "kinds", names(kinds.collect()))?;
+        let mut definitions = DefKind::ALL
+            .iter()
+            .filter(|&&kind| opts.wants_def_kind(kind))
+            .map(|kind| kind.name())
+            .collect::<Vec<_>>();
+        if opts.any_key {
+            definitions.push("computed keys");
+        }
+        if opts.include_reexports {
+            definitions.push("documented inherits");
+        }
+        writeln!(f, "  {:<12} {}", "definitions", names(definitions))?;
+        match self.files {
+            Some(files) => writeln!(f, "  {:<12} {}", "files", files)?,
+            None => writeln!(f, "  {:<12} {}", "files", NixFiles::new())?,
+        }
+        let dirs = &opts.dirs;
+        let walked = if opts.follow_imports {
+            "none, following imports from the root instead".to_string()
+        } else if dirs.include.is_empty() && dirs.exclude.is_empty() {
+            "all but hidden ones".to_string()
+        } else {
+            let mut walked = Vec::new();
+            if !dirs.include.is_empty() {
+                walked.push(format!("only {}", dirs.include.join(", ")));
+            }
+            if !dirs.exclude.is_empty() {
+                walked.push(format!("not {}", dirs.exclude.join(", ")));
+            }
+            walked.join("; ")
+        };
+        writeln!(f, "  {:<12} {}", "directories", walked)?;
+        for filter in self.filters() {
+            writeln!(f, "  {:<12} {}", "only", filter)?;
+        }
+        let order = match opts.sort_key() {
+            Some(key) if opts.reverse => format!("by {}, reversed", key.name()),
+            Some(key) => format!("by {}", key.name()),
+            None if opts.ordered => "by file".to_string(),
+            None => "as found".to_string(),
+        };
+        writeln!(f, "  {:<12} {}", "order", order)?;
+        let output = match &opts.output {
+            Some(path) => path.display().to_string(),
+            None => "stdout".to_string(),
+        };
+        writeln!(
+            f,
+            "  {:<12} {}, to {}",
+            "format",
+            opts.format.name(),
+            output
+        )?;
+        let threads = match opts.jobs {
+            Some(jobs) => jobs.to_string(),
+            None => format!("{} (one for each CPU)", num_cpus::get()),
+        };
+        write!(
+            f,
+            "  {:<12} {}, on the {} backend",
+            "threads",
+            threads,
+            opts.backend.name()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{DirFilter, OutputFormat, SortKey};
+
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_plan() {
+        let opts = SearchOptions::builder().jobs(4).build().unwrap();
+        let matching = Regex::new("(?i)^map").unwrap();
+        assert_eq!(
+            QueryPlan::new(Path::new("lib"), &matching, &opts).to_string(),
+            "\
+search plan:
+  root         lib
+  pattern      regex `(?i)^map`
+  matched on   names
+  kinds        function
+  definitions  attr, rec
+  files        .nix files
+  directories  all but hidden ones
+  order        as found
+  format       human, to stdout
+  threads      4, on the pool backend"
+        );
+    }
+
+    #[test]
+    fn test_plan_filters() {
+        let opts = SearchOptions::builder()
+            .options(true)
+            .let_bindings(true)
+            .fuzzy("cMap".to_string())
+            .sort_by(SortKey::Name)
+            .reverse(true)
+            .takes_arg(Regex::new("^f$").unwrap())
+            .arity("2+".parse().ok())
+            .example(false)
+            .path_prefix(vec!["lib".to_string(), "lists".to_string()])
+            .dirs(DirFilter {
+                include: vec!["lib".to_string()],
+                exclude: vec!["tests".to_string()],
+            })
+            .format(OutputFormat::Csv)
+            .output(PathBuf::from("out.csv"))
+            .build()
+            .unwrap();
+        let matching = Regex::new("").unwrap();
+        let files = NixFiles::new().glob("*-packages.nix").unwrap();
+        let plan = QueryPlan::new(Path::new("."), &matching, &opts)
+            .files(&files)
+            .to_string();
+        let lines = plan.lines().skip(1).take(11).collect::<Vec<_>>();
+        assert_eq!(
+            lines,
+            [
+                "  root         .",
+                "  pattern      fuzzy `cMap`",
+                "  matched on   names",
+                "  kinds        function, option",
+                "  definitions  attr, rec, let",
+                "  files        .nix files named like *-packages.nix",
+                "  directories  only lib; not tests",
+                "  only         at or under the attribute path lib.lists",
+                "  only         taking an argument matching `^f$`",
+                "  only         taking 2+ arguments",
+                "  only         without an example",
+            ]
+        );
+        assert!(plan.contains("  order        by name, reversed\n"));
+        assert!(plan.contains("  format       csv, to out.csv\n"));
+        assert!(plan.contains("(one for each CPU), on the pool backend"));
+    }
+}
@@ -1,5 +1,7 @@
-use rnix::types::{Lambda, TypedNode};
+use rnix::ast::{Expr, Lambda, Literal, LiteralKind, Param};
 use rnix::SyntaxKind::*;
+use rnix::SyntaxNode;
+use rowan::ast::AstNode;
 
 /// Pretty-prints the arguments to a function
 pub fn pprint_args(lambda: &Lambda) -> String {
@@ -7,26 +9,20 @@ pub fn pprint_args(lambda: &Lambda) -> String {
     let mut lambda = lambda.clone();
     let mut out = String::new();
     loop {
-        let arg = lambda.arg().unwrap();
-        match arg.kind() {
-            NODE_IDENT => {
+        match lambda.param().unwrap() {
+            Param::IdentParam(arg) => {
                 out.push_str(&arg.to_string());
                 out.push_str(": ");
-                let body = lambda.body().unwrap();
-                if body.kind() == NODE_LAMBDA {
-                    lambda = Lambda::cast(body).unwrap();
-                } else {
-                    break;
+                match lambda.body().unwrap() {
+                    Expr::Lambda(body) => lambda = body,
+                    _ => break,
                 }
             }
-            NODE_PATTERN => {
+            Param::Pattern(arg) => {
                 out.push_str(&arg.to_string());
                 out.push_str(": ");
                 break;
             }
-            t => {
-                unreachable!("unhandled arg type {:?}", t);
-            }
         }
     }
     out.push_str("...");
@@ -34,3 +30,93 @@ pub fn pprint_args(lambda: &Lambda) -> String {
 
     //pprint_arg(lambda.arg());
 }
+
+/// Counts how many curried arguments a function takes: the number of leading lambdas, where a
+/// `{ ... }:` pattern counts as a single argument. So `a: b: ...` and `{ a, b }: c: ...` both
+/// have an arity of 2.
+pub fn arity(lambda: &Lambda) -> usize {
+    let mut count = 1;
+    let mut body = lambda.body();
+    while let Some(Expr::Lambda(inner)) = body {
+        count += 1;
+        body = inner.body();
+    }
+    count
+}
+
+/// A formal parameter of a function
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ArgEntry {
+    pub name: String,
+    /// Whether it can be left out, like `b` in `{ a, b ? 1 }:`
+    pub has_default: bool,
+}
+
+impl ArgEntry {
+    fn required(name: String) -> Self {
+        ArgEntry {
+            name,
+            has_default: false,
+        }
+    }
+}
+
+/// All the formal parameters of a function, at every currying level: plain `x:` arguments, the
+/// fields of `{ a, b ? 1, ... }:` patterns, and `@` bindings. The `...` of a pattern is not a
+/// name, so it isn't included.
+pub fn arg_entries(lambda: &Lambda) -> Vec<ArgEntry> {
+    let mut entries = Vec::new();
+    let mut next = Some(lambda.clone());
+    while let Some(lambda) = next {
+        match lambda.param() {
+            Some(Param::IdentParam(arg)) => entries.push(ArgEntry::required(arg.to_string())),
+            Some(Param::Pattern(pattern)) => {
+                entries.extend(pattern.pat_entries().filter_map(|entry| {
+                    Some(ArgEntry {
+                        name: entry.ident()?.to_string(),
+                        has_default: entry.default().is_some(),
+                    })
+                }));
+                entries.extend(
+                    pattern
+                        .pat_bind()
+                        .and_then(|at| at.ident())
+                        .map(|at| ArgEntry::required(at.to_string())),
+                );
+            }
+            None => (),
+        }
+        next = match lambda.body() {
+            Some(Expr::Lambda(body)) => Some(body),
+            _ => None,
+        };
+    }
+    entries
+}
+
+/// Describes what kind of value a (non-function) expression is, for showing in place of a signature
+pub fn pprint_value_kind(node: &SyntaxNode) -> String {
+    let kind = match node.kind() {
+        NODE_LITERAL => match Literal::cast(node.clone()).map(|lit| lit.kind()) {
+            Some(LiteralKind::Integer(_)) => "integer",
+            Some(LiteralKind::Float(_)) => "float",
+            Some(LiteralKind::Uri(_)) => "uri",
+            None => "literal",
+        },
+        NODE_PATH => "path",
+        NODE_STRING => "string",
+        NODE_ATTR_SET => "attrset",
+        NODE_LIST => "list",
+        NODE_LAMBDA => "function",
+        NODE_APPLY => "application",
+        NODE_IDENT | NODE_SELECT => "reference",
+        NODE_IF_ELSE => "conditional",
+        NODE_LET_IN | NODE_LEGACY_LET => "let expression",
+        NODE_WITH => "with expression",
+        NODE_ASSERT => "assertion",
+        NODE_BIN_OP | NODE_UNARY_OP => "operation",
+        NODE_PAREN => "expression",
+        _ => "value",
+    };
+    format!("<{}>", kind)
+}
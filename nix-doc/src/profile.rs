@@ -0,0 +1,158 @@
+//! Per-phase timing of a search, for `--profile`
+use std::convert::TryFrom;
+use std::fmt::{self, Display};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+/// A part of a search that time is spent in
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Phase {
+    /// Walking the directory tree for files to search
+    Walk,
+    /// Reading files off the disk
+    Read,
+    /// Parsing them
+    Parse,
+    /// Finding the functions and their doc comments in the parsed files
+    Extract,
+    /// Turning results into text
+    Format,
+    /// Writing that text out
+    Output,
+}
+
+impl Phase {
+    const ALL: [Phase; 6] = [
+        Phase::Walk,
+        Phase::Read,
+        Phase::Parse,
+        Phase::Extract,
+        Phase::Format,
+        Phase::Output,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Phase::Walk => "walk",
+            Phase::Read => "read",
+            Phase::Parse => "parse",
+            Phase::Extract => "extract",
+            Phase::Format => "format",
+            Phase::Output => "output",
+        }
+    }
+}
+
+/// Time spent in each [`Phase`], added up across every thread that did some of it, so with
+/// several workers the phases can add up to more than the time the search took
+#[derive(Debug)]
+pub struct Profile {
+    started: Instant,
+    nanos: [AtomicU64; Phase::ALL.len()],
+    files: AtomicUsize,
+}
+
+impl Default for Profile {
+    fn default() -> Self {
+        Profile {
+            started: Instant::now(),
+            nanos: Default::default(),
+            files: AtomicUsize::new(0),
+        }
+    }
+}
+
+impl Profile {
+    /// Runs `f`, counting the time it takes towards `phase`
+    pub fn time<T>(&self, phase: Phase, f: impl FnOnce() -> T) -> T {
+        let start = Instant::now();
+        let out = f();
+        self.add(phase, start.elapsed());
+        out
+    }
+
+    /// Counts `took` towards `phase`
+    pub fn add(&self, phase: Phase, took: Duration) {
+        let nanos = u64::try_from(took.as_nanos()).unwrap_or(u64::MAX);
+        self.nanos[phase as usize].fetch_add(nanos, Ordering::Relaxed);
+    }
+
+    /// Counts a file as having been searched
+    pub fn add_file(&self) {
+        self.files.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Total time spent in `phase` so far
+    pub fn spent(&self, phase: Phase) -> Duration {
+        Duration::from_nanos(self.nanos[phase as usize].load(Ordering::Relaxed))
+    }
+}
+
+/// Wraps an iterator so the time spent getting each item out of it counts towards a phase, if
+/// there's a profile to count it in
+pub struct Timed<'a, I> {
+    pub inner: I,
+    pub phase: Phase,
+    pub profile: Option<&'a Profile>,
+}
+
+impl<I: Iterator> Iterator for Timed<'_, I> {
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<I::Item> {
+        match self.profile {
+            Some(profile) => profile.time(self.phase, || self.inner.next()),
+            None => self.inner.next(),
+        }
+    }
+}
+
+/// The breakdown printed at the end of a search, one phase a line with its share of the total
+impl Display for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let total: Duration = Phase::ALL.iter().map(|&phase| self.spent(phase)).sum();
+        writeln!(
+            f,
+            "profile of {} files (phases summed over threads):",
+            self.files.load(Ordering::Relaxed)
+        )?;
+        for phase in Phase::ALL {
+            let spent = self.spent(phase);
+            let percent = (spent.as_nanos() * 100)
+                .checked_div(total.as_nanos())
+                .unwrap_or(0);
+            writeln!(f, "  {:<8} {:>10.1?} {:>3}%", phase.name(), spent, percent)?;
+        }
+        write!(f, "  {:<8} {:>10.1?}", "elapsed", self.started.elapsed())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profile() {
+        let profile = Profile::default();
+        assert_eq!(profile.time(Phase::Parse, || 1 + 1), 2);
+        profile.add(Phase::Read, Duration::from_millis(3));
+        profile.add(Phase::Read, Duration::from_millis(4));
+        profile.add_file();
+        assert_eq!(profile.spent(Phase::Read), Duration::from_millis(7));
+        assert_eq!(profile.spent(Phase::Walk), Duration::ZERO);
+
+        let walked = Timed {
+            inner: 0..3,
+            phase: Phase::Walk,
+            profile: Some(&profile),
+        };
+        assert_eq!(walked.collect::<Vec<_>>(), [0, 1, 2]);
+
+        let report = profile.to_string();
+        assert!(report.starts_with("profile of 1 files"));
+        assert!(report
+            .lines()
+            .any(|line| line.trim_start().starts_with("read") && line.contains("7.0ms")));
+        assert!(report.lines().last().unwrap().starts_with("  elapsed"));
+    }
+}
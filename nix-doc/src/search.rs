@@ -0,0 +1,2847 @@
+//! Finding documented definitions in nix source
+use crate::color::Colorful;
+use crate::doc::{doc_sections, has_example, has_type, split_tags, DocSection, DocTag};
+#[cfg(feature = "fs")]
+use crate::filter::FileFilter;
+use crate::format::{compare_results, format_with_opts, ResultPrinter};
+#[cfg(feature = "fs")]
+use crate::format::{
+    format_search_results, print_search_results, FormatContext, Formatter, PATTERN_CSV_HEADER,
+};
+use crate::fuzzy::fuzzy_match;
+use crate::index::Index;
+use crate::lines::{LineIndex, Position};
+use crate::log;
+use crate::options::option_declaration;
+use crate::output::Output;
+use crate::parse::{
+    arity_may_be_higher, attr_path_to, deprecation_message, find_comment, find_package_description,
+    find_trailing_comment, is_blank_source, key_idents, key_part_name, package_head, parse_nix,
+    unwrap_lambda, Comment,
+};
+use crate::pprint::{arg_entries, arity, pprint_args, pprint_value_kind, ArgEntry};
+use crate::profile::{Phase, Timed};
+#[cfg(feature = "fs")]
+use crate::stream::SearchStats;
+#[cfg(feature = "fs")]
+use crate::walk::imported_files;
+use crate::walk::{file_attr_path, MAX_FILE_SIZE};
+use crate::{
+    Backend, DefKind, Error, FileError, OutputFormat, Result, ResultKind, SearchOptions, SortKey,
+};
+
+use regex::Regex;
+use rnix::ast::{AttrSet, AttrpathValue, Expr, HasEntry, Lambda, LetIn, Paren};
+use rnix::SyntaxKind::*;
+use rnix::{Root, SyntaxNode, WalkEvent};
+use rowan::ast::AstNode;
+#[cfg(feature = "serde")]
+use serde::Serialize;
+#[cfg(feature = "fs")]
+use walkdir::DirEntry;
+
+use std::collections::BTreeMap;
+use std::io::{self, IsTerminal, Read};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver};
+use std::{
+    fmt::{self, Display},
+    str,
+};
+
+/// Which part of a result [`SearchOptions::all_fields`] found the pattern in
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+#[non_exhaustive]
+pub enum MatchedField {
+    Name,
+    /// The attribute path within the file, like `strings.toUpper`
+    Path,
+    Doc,
+}
+
+/// Where a definition's documentation was found
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+#[non_exhaustive]
+pub enum DocPosition {
+    /// In the comments before the definition, as usual
+    Leading,
+    /// In a comment after the definition, on the same line
+    Trailing,
+}
+
+/// A binding that matched the search pattern but was left out of the results
+pub(crate) struct Skip {
+    /// Name of the binding as written
+    pub(crate) identifier: String,
+
+    /// Why it was left out
+    pub(crate) reason: SkipReason,
+
+    /// Start of the binding's name
+    pub(crate) defined_at_start: usize,
+}
+
+pub(crate) enum SkipReason {
+    /// A function without a doc comment
+    NoComment,
+    /// Not a function; holds the kind of value it is
+    NotAFunction(String),
+    /// The key is computed, so it has no plain name
+    DynamicKey,
+    /// Brought in with `inherit`, so the definition lives elsewhere
+    Inherit,
+    /// Deprecated, and those were asked to be left out
+    Deprecated,
+    /// Takes the wrong number of arguments (or isn't visibly a function) for the filter
+    Arity(Option<usize>),
+    /// The synopsis doesn't match the synopsis filter
+    Synopsis,
+    /// A kind of result that wasn't asked for
+    Kind(ResultKind),
+    /// A function without a parameter matching the parameter filter, or not a function at all
+    TakesArg,
+    /// No parameter matching the default filter with a default (or, if this is `false`, without
+    /// one for the required filter)
+    ArgDefault(bool),
+    /// Has an example (or doesn't, if this is `false`) when the example filter asked otherwise
+    Example(bool),
+    /// Has a type signature (or doesn't) when the type filter asked otherwise
+    Type(bool),
+}
+
+impl Display for SkipReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SkipReason::NoComment => write!(f, "no doc comment"),
+            SkipReason::NotAFunction(kind) => write!(f, "value is {}, not a function", kind),
+            SkipReason::DynamicKey => write!(f, "computed key"),
+            SkipReason::Inherit => write!(f, "inherited from elsewhere"),
+            SkipReason::Deprecated => write!(f, "deprecated"),
+            SkipReason::Arity(Some(n)) => write!(f, "takes {} arguments", n),
+            SkipReason::Arity(None) => write!(f, "arguments unknown"),
+            SkipReason::Synopsis => write!(f, "synopsis does not match"),
+            SkipReason::Kind(kind) => write!(f, "is a {}, which was not asked for", kind),
+            SkipReason::TakesArg => write!(f, "takes no matching argument"),
+            SkipReason::ArgDefault(true) => write!(f, "no matching argument has a default"),
+            SkipReason::ArgDefault(false) => write!(f, "no matching argument is required"),
+            SkipReason::Example(true) => write!(f, "has an example"),
+            SkipReason::Example(false) => write!(f, "has no example"),
+            SkipReason::Type(true) => write!(f, "has a type signature"),
+            SkipReason::Type(false) => write!(f, "has no type signature"),
+        }
+    }
+}
+
+/// The first non-empty line of `doc`
+pub(crate) fn synopsis(doc: &str) -> Option<String> {
+    doc.lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .map(str::to_string)
+}
+
+/// A documented definition found by a search
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+#[non_exhaustive]
+pub struct SearchResult {
+    /// Name of the function
+    pub identifier: String,
+
+    /// Dedented documentation comments, minus any `@tag` lines
+    pub doc: String,
+
+    /// The comments the documentation came from, as written
+    pub raw_doc: String,
+
+    /// First non-empty line of the documentation, which usually sums it up
+    pub synopsis: Option<String>,
+
+    /// `@tag` lines found in the documentation comments
+    pub tags: Vec<DocTag>,
+
+    /// The headed sections of the documentation, like `# Type`
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Vec::is_empty"))]
+    pub sections: Vec<DocSection>,
+
+    /// Where the documentation comments were found
+    pub doc_position: DocPosition,
+
+    /// What kind of thing this is
+    pub kind: ResultKind,
+
+    /// What syntax the definition was made with
+    pub def_kind: DefKind,
+
+    /// Attribute path from the top of the file to the definition, e.g. `["strings", "toUpper"]`
+    pub attr_path: Vec<String>,
+
+    /// Parameter block for the function
+    #[cfg_attr(feature = "serde", serde(rename = "signature"))]
+    pub param_block: String,
+
+    /// Start of the definition: the whole key of a binding, so `foo` in `foo.bar.baz = ...`,
+    /// which is where going to the definition should go
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub defined_at_start: usize,
+
+    /// Start of the name being defined, the last part of a dotted key, so `baz` in
+    /// `foo.bar.baz = ...`. The same as `defined_at_start` unless the key is dotted.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub identifier_at_start: usize,
+
+    /// End of the definition, after the `;` of a binding
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub defined_at_end: usize,
+
+    /// Where the definition starts, as a line and column
+    pub start: Position,
+
+    /// Where the definition ends
+    pub end: Position,
+
+    /// Where the name being defined starts, as a line and column (see
+    /// [`SearchResult::identifier_at_start`])
+    pub identifier_start: Position,
+
+    /// The file the definition is in, for results of searching files. The output formats write
+    /// this out under the name they show the file by.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub file: Option<PathBuf>,
+
+    /// Warning message, if the definition is a deprecated shim around `warn`/`throw`
+    pub deprecated: Option<String>,
+
+    /// Number of curried arguments, if the definition is visibly a function
+    pub arity: Option<usize>,
+
+    /// Could the function take more arguments than `arity`, by returning a function from a call
+    /// or reference we can't see through?
+    pub arity_may_be_higher: bool,
+
+    /// The formal parameters, if the definition is visibly a function
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub args: Vec<ArgEntry>,
+
+    /// How well the identifier matched, when searching with a fuzzy pattern
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub score: Option<i64>,
+
+    /// Which characters of the identifier the fuzzy pattern matched, to highlight
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) matched: Vec<usize>,
+
+    /// [`SearchResult::relevance`], when sorting by it
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) relevance: i64,
+
+    /// What the pattern matched, when matching it against more than the name
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub matched_field: Option<MatchedField>,
+
+    /// Where in the path or docs the pattern matched, to highlight. Name matches go in `matched`.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) field_range: Option<Range<usize>>,
+
+    /// Other names the same file binds this to, with [`SearchOptions::aliases`]
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Vec::is_empty"))]
+    pub aliases: Vec<String>,
+}
+
+impl SearchResult {
+    pub(crate) fn new(
+        identifier: String,
+        comment: Comment,
+        param_block: String,
+        defined_at_start: usize,
+    ) -> Self {
+        let (doc, tags) = split_tags(&comment.doc);
+        SearchResult {
+            identifier,
+            synopsis: synopsis(&doc),
+            sections: doc_sections(&doc),
+            doc,
+            raw_doc: comment.raw,
+            tags,
+            doc_position: DocPosition::Leading,
+            kind: ResultKind::Function,
+            def_kind: DefKind::Attr,
+            attr_path: Vec::new(),
+            param_block,
+            defined_at_start,
+            identifier_at_start: defined_at_start,
+            defined_at_end: defined_at_start,
+            start: Position::default(),
+            end: Position::default(),
+            identifier_start: Position::default(),
+            file: None,
+            deprecated: None,
+            arity: None,
+            arity_may_be_higher: false,
+            args: Vec::new(),
+            score: None,
+            matched: Vec::new(),
+            relevance: 0,
+            matched_field: None,
+            field_range: None,
+            aliases: Vec::new(),
+        }
+    }
+
+    /// How likely this is to be what the `query` was looking for, in `file`. In order of weight:
+    /// where the pattern matched, with [`SearchOptions::all_fields`]; the identifier being
+    /// exactly the query, starting with it, or containing it; being short; living under a `lib`
+    /// directory; documenting its type; and having more documentation.
+    pub(crate) fn relevance(&self, query: &RelevanceQuery, file: &Path) -> i64 {
+        let identifier = self.identifier.to_lowercase();
+        let name_match = match &query.needle {
+            Some(needle) if identifier == *needle => 1000,
+            Some(needle) if identifier.starts_with(needle.as_str()) => 600,
+            Some(needle) if identifier.contains(needle.as_str()) => 300,
+            _ => 0,
+        };
+        let length_penalty = 5 * self.identifier.chars().count().min(40) as i64;
+        let lib_bonus = if file.components().any(|c| c.as_os_str() == "lib") {
+            100
+        } else {
+            0
+        };
+        let type_bonus = if has_type(&self.doc) { 80 } else { 0 };
+        let doc_bonus = self.doc.len().min(500) as i64 / 10;
+        // name hits outrank every path hit, which outrank every doc hit
+        let field_bonus = match self.matched_field {
+            Some(MatchedField::Name) => 2000,
+            Some(MatchedField::Path) => 1000,
+            Some(MatchedField::Doc) | None => 0,
+        };
+        field_bonus + name_match - length_penalty + lib_bonus + type_bonus + doc_bonus
+    }
+
+    /// Is there any documentation at all?
+    pub fn has_doc(&self) -> bool {
+        !self.doc.is_empty() || !self.tags.is_empty()
+    }
+
+    /// Uses a comment after the definition as its documentation
+    fn with_trailing_doc(self, comment: Comment) -> Self {
+        let (doc, tags) = split_tags(&comment.doc);
+        SearchResult {
+            synopsis: synopsis(&doc),
+            sections: doc_sections(&doc),
+            doc,
+            raw_doc: comment.raw,
+            tags,
+            doc_position: DocPosition::Trailing,
+            ..self
+        }
+    }
+}
+
+/// Runs a search for definitions matching the regex `matching` in `file`, returning what was found.
+/// With [`SearchOptions::cache`], what's documented in it comes from the cache if it hasn't
+/// changed since it was cached.
+pub fn search_file(
+    file: &Path,
+    matching: &Regex,
+    opts: &SearchOptions,
+) -> Result<Vec<SearchResult>> {
+    if opts.is_cancelled() {
+        return Err(Error::Cancelled);
+    }
+    // don't bother searching files that are so large they must be generated
+    let content = opts.timed(Phase::Read, || -> Result<_> {
+        let source = opts.source();
+        let size = source.size(file).map_err(|err| Error::io(file, err))?;
+        Ok(match size > MAX_FILE_SIZE {
+            true => None,
+            false => Some(source.read(file).map_err(|err| Error::io(file, err))?),
+        })
+    })?;
+    let content = match content {
+        Some(content) => content,
+        None => return Ok(Vec::new()),
+    };
+    match &opts.cache {
+        Some(cache) if Index::answers(opts) => cache.search(file, &content, matching, opts),
+        _ => search_source(file, &content, matching, opts),
+    }
+}
+
+/// Searches the nix source `content`, which came from `file`
+pub(crate) fn search_source(
+    file: &Path,
+    content: &str,
+    matching: &Regex,
+    opts: &SearchOptions,
+) -> Result<Vec<SearchResult>> {
+    if is_blank_source(content) {
+        return Ok(Vec::new());
+    }
+    let ast = opts
+        .timed(Phase::Parse, || parse_nix(content))
+        .map_err(|err| err.in_file(file))?;
+    let lines = LineIndex::new(content);
+    let (mut results, skips) = opts.timed(Phase::Extract, || {
+        let (mut results, mut skips) = search_ast_with_skips(matching, &ast, opts);
+        if opts.wants_def_kind(DefKind::File) {
+            let name = file.display().to_string();
+            if let Some(mut res) = search_file_function(matching, &ast, &name, opts, &mut skips) {
+                locate(std::slice::from_mut(&mut res), content, &lines);
+                // it starts before anything inside of it
+                results.insert(0, res);
+            }
+        }
+        (results, skips)
+    });
+
+    for skip in skips {
+        let line = lines.line(skip.defined_at_start);
+        if opts.explain_skips {
+            eprintln!(
+                "{}:{}: skipped `{}`: {}",
+                file.display(),
+                line,
+                skip.identifier,
+                skip.reason
+            );
+        } else {
+            log::info!(
+                "{}:{}: skipped `{}`: {}",
+                file.display(),
+                line,
+                skip.identifier,
+                skip.reason
+            );
+        }
+    }
+
+    for res in &mut results {
+        res.file = Some(file.to_path_buf());
+    }
+    Ok(results)
+}
+
+/// Search the `dir` for files with function definitions matching `matching`
+///
+/// With `opts.ordered`, files are walked in sorted order and their results are printed in that
+/// order. Workers still run in parallel: results that arrive ahead of their turn are parked in a
+/// reorder buffer and flushed as soon as the file before them is done, so the buffer only ever
+/// holds what the slowest in-flight file is holding up.
+///
+/// With `opts.sort_by` (or `opts.reverse`), nothing is printed until every file has been
+/// searched, since the first result could come from anywhere.
+#[cfg(feature = "fs")]
+pub fn search<F>(dir: &Path, matching: Regex, opts: SearchOptions, should_search: F) -> Result<()>
+where
+    F: FileFilter,
+{
+    // open it first, so a bad path fails before any searching is done
+    let out = Output::open(opts.output.as_deref())?;
+    let files = files_to_search(dir, &opts, &should_search);
+    run_search(files, &matching, &opts, |rx| {
+        print_search_results(rx, &matching, &opts, out)
+    })?;
+    Ok(())
+}
+
+/// A file that was searched, and what it turned up, or why it couldn't be searched
+pub type FileFound = (PathBuf, std::result::Result<Vec<SearchResult>, FileError>);
+
+/// Searches `dir` like [`search`], handing back what each file turned up instead of printing it.
+/// The files are in the order they're walked in with `opts.ordered`, sorted by path, however many
+/// [`SearchOptions::jobs`] do the searching. This is the search with none of the presenting: see
+/// [`print_results`](crate::format::print_results) for printing it like [`search`] does.
+#[cfg(feature = "fs")]
+pub fn search_files<F>(
+    dir: &Path,
+    matching: &Regex,
+    opts: &SearchOptions,
+    should_search: F,
+) -> (Vec<FileFound>, SearchStats)
+where
+    F: FileFilter,
+{
+    let opts = SearchOptions {
+        ordered: true,
+        ..opts.clone()
+    };
+    let files = files_to_search(dir, &opts, &should_search);
+    let mut found = run_search(files, matching, &opts, |rx| rx.iter().collect::<Vec<_>>());
+    found.sort_by_key(|(seq, _, _)| *seq);
+
+    let mut stats = SearchStats {
+        files: found.len(),
+        cancelled: opts.is_cancelled(),
+        ..SearchStats::default()
+    };
+    let found = found
+        .into_iter()
+        .map(|(_, file, results)| {
+            match &results {
+                Ok(results) => stats.results += results.len(),
+                Err(_) => stats.errors += 1,
+            }
+            (file, results)
+        })
+        .collect();
+    (found, stats)
+}
+
+/// Searches `dir` like [`search_files`], returning all the results together, along with the
+/// files that couldn't be searched. They're in the order [`search`] prints them in with
+/// `opts.ordered`, which is by file, then by where they are in it, unless `opts.sort_by` (or
+/// `opts.reverse`) asks for another.
+#[cfg(feature = "fs")]
+pub fn search_results<F>(
+    dir: &Path,
+    matching: &Regex,
+    opts: &SearchOptions,
+    should_search: F,
+) -> (Vec<SearchResult>, Vec<FileError>)
+where
+    F: FileFilter,
+{
+    let (found, _) = search_files(dir, matching, opts, should_search);
+    let (mut results, mut errors) = (Vec::new(), Vec::new());
+    for (_, file_results) in found {
+        match file_results {
+            Ok(file_results) => results.extend(file_results),
+            Err(err) => errors.push(err),
+        }
+    }
+    if let Some(key) = opts.sort_key() {
+        if key == SortKey::Relevance {
+            let query = RelevanceQuery::new(matching, opts);
+            for res in &mut results {
+                let file = res.file.clone().unwrap_or_default();
+                res.relevance = res.relevance(&query, &file);
+            }
+        }
+        results.sort_by(|a, b| compare_results(key, a, b));
+        if opts.reverse {
+            results.reverse();
+        }
+    }
+    (results, errors)
+}
+
+/// Searches `dir` like [`search`], handing the results to `formatter` to lay out rather than
+/// printing them in one of the built-in formats, and returning how many files and results there
+/// were
+#[cfg(feature = "fs")]
+pub fn search_formatted<F>(
+    dir: &Path,
+    matching: &Regex,
+    opts: &SearchOptions,
+    should_search: F,
+    formatter: &mut dyn Formatter,
+) -> Result<SearchStats>
+where
+    F: FileFilter,
+{
+    let cx = FormatContext {
+        matching,
+        opts,
+        color: opts.output.is_none() && io::stdout().is_terminal(),
+    };
+    let files = files_to_search(dir, opts, &should_search);
+    Ok(run_search(files, matching, opts, |rx| {
+        format_search_results(rx, &cx, formatter)
+    })?)
+}
+
+/// The files under `dir` for a search to look in, from [`SearchOptions::file_source`], each with
+/// its attribute path (see [`file_attr_path`]), until the search is interrupted. With
+/// `opts.follow_imports`, `dir` is where to start following imports from instead, and the
+/// attribute paths are from the directory the entry file is in.
+#[cfg(feature = "fs")]
+pub(crate) fn files_to_search<'a, F>(
+    dir: &'a Path,
+    opts: &'a SearchOptions,
+    should_search: &'a F,
+) -> impl Iterator<Item = (PathBuf, Vec<String>)> + 'a
+where
+    F: FileFilter,
+{
+    let (files, root): (Box<dyn Iterator<Item = PathBuf>>, _) = match opts.follow_imports {
+        true => (
+            Box::new(imported_files(dir, should_search).map(DirEntry::into_path)),
+            match dir.is_file() {
+                true => dir.parent().unwrap_or(dir),
+                false => dir,
+            },
+        ),
+        false => (
+            Box::new(
+                opts.source()
+                    .files(dir, opts.ordered, &opts.dirs)
+                    .filter(move |file| should_search.should_search(file)),
+            ),
+            dir,
+        ),
+    };
+    // files that can't have anything under the path prefix in them are left out right away
+    let files = files
+        .map(move |file| {
+            let file_path = file_attr_path(root, &file);
+            (file, file_path)
+        })
+        .filter(move |(file, file_path)| {
+            opts.path_could_match(file_path) && opts.file_changed(file)
+        })
+        .take_while(move |_| !opts.is_cancelled());
+    Timed {
+        inner: files,
+        phase: Phase::Walk,
+        profile: opts.profile.as_deref(),
+    }
+}
+
+/// Searches `dir` for each of `patterns`, given as written and as the regex to match whole
+/// identifiers with, all at once with `matching`, which has to match whatever any of them do.
+/// Results are printed grouped by the pattern they match, each group sorted (by file and line,
+/// unless `opts` says otherwise), with the patterns that matched nothing saying so. Returns those
+/// patterns, as written.
+#[cfg(feature = "fs")]
+pub fn search_patterns<F>(
+    dir: &Path,
+    matching: Regex,
+    patterns: &[(String, Regex)],
+    opts: SearchOptions,
+    should_search: F,
+) -> Result<Vec<String>>
+where
+    F: FileFilter,
+{
+    let out = Output::open(opts.output.as_deref())?;
+    let files = files_to_search(dir, &opts, &should_search);
+    let mut errors = Vec::new();
+    let mut all = run_search(files, &matching, &opts, |rx| {
+        let mut all = Vec::new();
+        for (_, file, results) in rx {
+            match results {
+                Ok(results) => all.extend(results.into_iter().map(|result| (file.clone(), result))),
+                Err(err) => errors.push(err),
+            }
+        }
+        all
+    });
+
+    let key = opts.sort_key().unwrap_or(SortKey::File);
+    let mut printer = ResultPrinter::with_csv_header(&opts, out, PATTERN_CSV_HEADER);
+    for err in errors {
+        printer.file_error(err);
+    }
+    let mut unmatched = Vec::new();
+    for (source, regex) in patterns {
+        let mut group = (0..all.len())
+            .filter(|&idx| regex.is_match(&all[idx].1.identifier))
+            .collect::<Vec<_>>();
+        if key == SortKey::Relevance {
+            let query = RelevanceQuery::new(regex, &opts);
+            for &idx in &group {
+                let (file, result) = &mut all[idx];
+                result.relevance = result.relevance(&query, file);
+            }
+        }
+        group.sort_by(|&a, &b| compare_results(key, &all[a].1, &all[b].1));
+        if opts.reverse {
+            group.reverse();
+        }
+        if group.is_empty() {
+            unmatched.push(source.clone());
+        }
+
+        let results = group.iter().map(|&idx| {
+            let (file, result) = &all[idx];
+            format_with_opts(result, opts.display_path(file).display(), &opts)
+        });
+        match opts.format {
+            OutputFormat::Human | OutputFormat::Table => {
+                let heading = format!("pattern `{}`", source);
+                let heading = match group.len() {
+                    0 => format!(
+                        "{}: {}",
+                        heading.as_str().bold(),
+                        "no documented matches".red()
+                    ),
+                    1 => format!("{}: 1 match", heading.as_str().bold()),
+                    n => format!("{}: {} matches", heading.as_str().bold(), n),
+                };
+                printer.group(&heading);
+                if opts.format == OutputFormat::Table {
+                    for &idx in &group {
+                        let (file, result) = &all[idx];
+                        printer.print_result(result, opts.display_path(file).display(), &opts);
+                    }
+                } else {
+                    printer.print(results.collect());
+                }
+            }
+            #[cfg(feature = "serde")]
+            OutputFormat::Json => {
+                let group = format!(
+                    "{{\"pattern\":{},\"results\":[{}]}}",
+                    serde_json::to_string(source)?,
+                    results.collect::<Vec<_>>().join(",")
+                );
+                printer.print(vec![group]);
+            }
+            OutputFormat::Csv => {
+                let mut field = csv::WriterBuilder::new()
+                    .terminator(csv::Terminator::Any(b','))
+                    .from_writer(Vec::new());
+                field.write_record([source])?;
+                let field = field.into_inner().map_err(|err| err.into_error())?;
+                let field = String::from_utf8_lossy(&field).into_owned();
+                printer.print(results.map(|row| format!("{}{}", field, row)).collect());
+            }
+        }
+    }
+    printer.finish()?;
+    Ok(unmatched)
+}
+
+/// The results of searching one file: which file it was in the order they were walked, its path,
+/// and what was found in it, or why it couldn't be searched
+#[cfg(feature = "fs")]
+pub(crate) type FileResults = (
+    usize,
+    PathBuf,
+    std::result::Result<Vec<SearchResult>, FileError>,
+);
+
+/// Searches each of `files` (with its attribute path, see [`file_attr_path`]) on
+/// `opts.backend`, handing `consume` the receiving end of the results as they come in. Returns
+/// what `consume` does, once it has and every file has been searched.
+#[cfg(feature = "fs")]
+pub(crate) fn run_search<I, C, R>(files: I, matching: &Regex, opts: &SearchOptions, consume: C) -> R
+where
+    I: Iterator<Item = (PathBuf, Vec<String>)>,
+    C: FnOnce(Receiver<FileResults>) -> R,
+{
+    let (tx, rx) = channel();
+    match opts.backend {
+        Backend::Pool => {
+            let pool = opts.thread_pool();
+            for (seq, (file, file_path)) in files.enumerate() {
+                let my_tx = tx.clone();
+                let matching = matching.clone();
+                let opts = opts.clone();
+                pool.push(move || {
+                    let results = search_walked_file(file, seq, &file_path, &matching, &opts);
+                    my_tx
+                        .send(results)
+                        .expect("failed to send messages to display");
+                });
+            }
+            drop(tx);
+            pool.done();
+            consume(rx)
+        }
+        #[cfg(feature = "rayon")]
+        Backend::Rayon => {
+            use rayon::prelude::*;
+
+            // rayon wants to know the work up front to split it up, and the walk isn't `Send`
+            let files = files.enumerate().collect::<Vec<_>>();
+            std::thread::scope(|scope| {
+                scope.spawn(move || {
+                    let search = move || {
+                        files
+                            .into_par_iter()
+                            .for_each_with(tx, |tx, (seq, (file, file_path))| {
+                                let results =
+                                    search_walked_file(file, seq, &file_path, matching, opts);
+                                tx.send(results)
+                                    .expect("failed to send messages to display");
+                            })
+                    };
+                    // rayon's own pool has a thread for each CPU
+                    match opts.jobs {
+                        Some(jobs) => rayon::ThreadPoolBuilder::new()
+                            .num_threads(jobs)
+                            .build()
+                            .expect("failed to start rayon's threads")
+                            .install(search),
+                        None => search(),
+                    }
+                });
+                consume(rx)
+            })
+        }
+    }
+}
+
+/// Searches a file found by the walk for [`run_search`], from [`SearchOptions::index`] if it's
+/// fresh there, keeping only the results under the path prefix
+#[cfg(feature = "fs")]
+pub(crate) fn search_walked_file(
+    file: PathBuf,
+    seq: usize,
+    file_path: &[String],
+    matching: &Regex,
+    opts: &SearchOptions,
+) -> FileResults {
+    log::info!("searching {}", file.display());
+    // still reported, so that the ordered mode doesn't wait on it forever
+    if opts.is_cancelled() {
+        return (seq, file, Ok(Vec::new()));
+    }
+    if let Some(profile) = &opts.profile {
+        profile.add_file();
+    }
+    let indexed = opts
+        .index
+        .as_deref()
+        .and_then(|index| index.results(&file, matching, opts));
+    let found = match indexed {
+        Some(results) => Ok(results),
+        None => search_file(&file, matching, opts),
+    };
+    let results = match found {
+        Ok(_) if !opts.file_matches(&file) => Ok(Vec::new()),
+        Ok(mut results) => {
+            results.retain(|res| opts.path_matches(file_path, &res.attr_path));
+            opts.retain_changed(&file, &mut results);
+            opts.cap_per_file(&mut results);
+            Ok(results)
+        }
+        // cancelled since the check above, with nothing to say about this file
+        Err(Error::Cancelled) => Ok(Vec::new()),
+        Err(err) => {
+            err.warn(opts.format);
+            Err(FileError::new(&file, &err))
+        }
+    };
+    // every file reports in, even with nothing to show, so that the ordered mode knows when it
+    // can move on to the next one
+    (seq, file, results)
+}
+
+/// Searches the nix source `source` for documented functions whose names match `pattern`, the
+/// same way a file is searched, but without one: the results have no [`SearchResult::file`].
+///
+/// ```
+/// use regex::Regex;
+///
+/// let source = "{\n  # Adds one\n  inc = x: x + 1;\n}";
+/// let results = nix_doc::search_str(source, &Regex::new("^inc$").unwrap()).unwrap();
+/// assert_eq!(results[0].identifier, "inc");
+/// assert_eq!(results[0].doc, "Adds one");
+/// assert_eq!(results[0].start.line, 3);
+/// ```
+pub fn search_str(source: &str, pattern: &Regex) -> Result<Vec<SearchResult>> {
+    let mut results = search_source(Path::new(""), source, pattern, &SearchOptions::default())?;
+    for res in &mut results {
+        res.file = None;
+    }
+    Ok(results)
+}
+
+/// Searches the nix source `source` like [`search_str`], with `opts`, reporting the results as
+/// being in the file `name`. Nothing is read from `name`, so it can be an editor buffer that
+/// hasn't been saved, or that's different from what's on disk. The name is what locations are
+/// reported in, and what options that go by the file, like [`SearchOptions::file_regex`] or
+/// [`DefKind::File`], look at.
+///
+/// ```
+/// use nix_doc::SearchOptions;
+/// use regex::Regex;
+/// use std::path::Path;
+///
+/// // what an editor plugin has in a buffer, edited since it was last saved
+/// let buffer = "{\n  # Doubles `x`\n  double = x: x * 2;\n  unsaved = y: y;\n}";
+/// let opts = SearchOptions::builder().build().unwrap();
+/// let name = Path::new("lib/numbers.nix");
+/// let results = nix_doc::search_str_named(name, buffer, &Regex::new("").unwrap(), &opts).unwrap();
+/// let found = results
+///     .iter()
+///     .map(|res| {
+///         let file = res.file.as_ref().unwrap().display();
+///         format!("{}:{}: {}", file, res.start.line, res.identifier)
+///     })
+///     .collect::<Vec<_>>();
+/// // `unsaved` has no doc comment yet
+/// assert_eq!(found, ["lib/numbers.nix:3: double"]);
+/// ```
+pub fn search_str_named(
+    name: &Path,
+    source: &str,
+    pattern: &Regex,
+    opts: &SearchOptions,
+) -> Result<Vec<SearchResult>> {
+    if !opts.file_matches(name) {
+        return Ok(Vec::new());
+    }
+    let mut results = search_source(name, source, pattern, opts)?;
+    opts.cap_per_file(&mut results);
+    Ok(results)
+}
+
+/// Searches nix source read from stdin, reporting results as coming from `filename`, or `<stdin>`
+/// if there isn't one
+pub fn search_stdin(filename: Option<&Path>, matching: Regex, opts: SearchOptions) -> Result<()> {
+    let mut input = Vec::new();
+    io::stdin().read_to_end(&mut input)?;
+    let content = String::from_utf8(input).map_err(|_| {
+        Error::from(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "stdin is not valid UTF-8",
+        ))
+    })?;
+    if content.trim().is_empty() {
+        return Err(Error::InvalidConfig("no nix source on stdin".to_string()));
+    }
+
+    let name = match filename {
+        Some(filename) => filename.display().to_string(),
+        None => STDIN_NAME.to_string(),
+    };
+    let mut results = search_source(Path::new(&name), &content, &matching, &opts)?;
+    if !opts.file_matches(Path::new(&name)) {
+        results.clear();
+    }
+    opts.cap_per_file(&mut results);
+    let out = Output::open(opts.output.as_deref())?;
+    let mut printer = ResultPrinter::start(&opts, out);
+    for result in &results {
+        printer.print_result(result, &name, &opts);
+    }
+    Ok(printer.finish()?)
+}
+
+/// Name that results from stdin are reported under
+pub(crate) const STDIN_NAME: &str = "<stdin>";
+
+/// Searches the given AST for functions called `identifier`, in the kinds of definitions
+/// `opts` asks for (see [`SearchOptions::def_kinds`], or switches like
+/// [`crate::SearchOptionsBuilder::let_bindings`])
+pub fn search_ast(identifier: &Regex, ast: &Root, opts: &SearchOptions) -> Vec<SearchResult> {
+    search_ast_with_skips(identifier, ast, opts).0
+}
+
+/// Searches the given AST like [`search_ast`], also returning the bindings that matched but were
+/// left out, if `opts.explain_skips` is set or they're logged
+pub(crate) fn search_ast_with_skips(
+    identifier: &Regex,
+    ast: &Root,
+    opts: &SearchOptions,
+) -> (Vec<SearchResult>, Vec<Skip>) {
+    let mut results = Vec::new();
+    let mut skips = Vec::new();
+    for ev in ast.syntax().preorder_with_tokens() {
+        match ev {
+            WalkEvent::Enter(enter) => {
+                let node = match enter.into_node() {
+                    Some(n) => n,
+                    None => continue,
+                };
+                if opts.is_cancelled() && matches!(node.kind(), NODE_ATTR_SET | NODE_LET_IN) {
+                    break;
+                }
+                if let Some(set) = AttrSet::cast(node.clone()) {
+                    let def_kind = if set.rec_token().is_some() {
+                        DefKind::RecAttr
+                    } else {
+                        DefKind::Attr
+                    };
+                    if opts.wants_def_kind(def_kind) {
+                        results
+                            .extend(visit_bindings(identifier, &set, def_kind, opts, &mut skips));
+                    }
+                } else if let Some(let_in) = LetIn::cast(node) {
+                    if opts.wants_def_kind(DefKind::LetIn) {
+                        results.extend(visit_bindings(
+                            identifier,
+                            &let_in,
+                            DefKind::LetIn,
+                            opts,
+                            &mut skips,
+                        ));
+                    }
+                }
+            }
+            WalkEvent::Leave(_) => {}
+        }
+    }
+    if opts.aliases && !results.is_empty() {
+        let aliases = alias_bindings(ast);
+        for res in &mut results {
+            res.aliases = aliases.get(&res.identifier).cloned().unwrap_or_default();
+        }
+    }
+    if !results.is_empty() {
+        let text = ast.syntax().to_string();
+        locate(&mut results, &text, &LineIndex::new(&text));
+    }
+    (results, skips)
+}
+
+/// Fills in the [`SearchResult::start`] and [`SearchResult::end`] of `results` from their byte
+/// offsets in `text`, which has the `lines`
+pub(crate) fn locate(results: &mut [SearchResult], text: &str, lines: &LineIndex) {
+    for res in results {
+        res.start = lines.position(text, res.defined_at_start);
+        res.end = lines.position(text, res.defined_at_end);
+        res.identifier_start = lines.position(text, res.identifier_at_start);
+    }
+}
+
+/// The bindings in `ast` whose value is just a reference to something else, by the name
+/// referred to: `join = concatStringsSep;` and `join = lib.concatStringsSep;` both make `join` an
+/// alias of `concatStringsSep`. Bindings to their own name, like `foo = lib.foo;`, don't count.
+pub(crate) fn alias_bindings(ast: &Root) -> BTreeMap<String, Vec<String>> {
+    let mut aliases = BTreeMap::<String, Vec<String>>::new();
+    for kv in ast.syntax().descendants().filter_map(AttrpathValue::cast) {
+        let name = match kv.attrpath().and_then(|key| key.attrs().last()) {
+            Some(last) => match key_part_name(&last) {
+                Some(name) => name,
+                None => continue,
+            },
+            None => continue,
+        };
+        let mut value = match kv.value() {
+            Some(value) => value,
+            None => continue,
+        };
+        while let Some(inner) = Paren::cast(value.syntax().clone()).and_then(|paren| paren.expr()) {
+            value = inner;
+        }
+        let target = match value {
+            Expr::Ident(ident) => Some(ident.syntax().to_string()),
+            // with a default, as in `lib.foo or bar`, it might not be `foo` at all
+            Expr::Select(select) if select.or_token().is_none() => select
+                .attrpath()
+                .and_then(|path| path.attrs().last())
+                .and_then(|last| key_part_name(&last)),
+            _ => None,
+        };
+        match target {
+            Some(target) if target != name => {
+                let names = aliases.entry(target).or_default();
+                if !names.contains(&name) {
+                    names.push(name);
+                }
+            }
+            _ => (),
+        }
+    }
+    aliases
+}
+
+/// What [`SearchResult::relevance`] needs to know about what was searched for
+pub(crate) struct RelevanceQuery {
+    /// The text searched for, in lower case, if it's a plain name rather than a regex (a regex
+    /// anchored with `^` and `$` still counts)
+    needle: Option<String>,
+}
+
+impl RelevanceQuery {
+    pub(crate) fn new(matching: &Regex, opts: &SearchOptions) -> Self {
+        let text = match &opts.fuzzy {
+            Some(pattern) => pattern.as_str(),
+            None => matching.as_str(),
+        };
+        let text = text.strip_prefix('^').unwrap_or(text);
+        let text = text.strip_suffix('$').unwrap_or(text);
+        let is_plain = !text.is_empty()
+            && text
+                .chars()
+                .all(|ch| ch.is_alphanumeric() || "_-'".contains(ch));
+        RelevanceQuery {
+            needle: is_plain.then(|| text.to_lowercase()),
+        }
+    }
+}
+
+/// Builds the result for a function. The doc comment is looked for before `doc_anchor`, which is
+/// the lambda itself unless it's wrapped in something else, like an `assert`.
+pub(crate) fn visit_lambda(
+    name: String,
+    defined_at_start: usize,
+    lambda: &Lambda,
+    doc_anchor: &SyntaxNode,
+    opts: &SearchOptions,
+) -> SearchResult {
+    // grab the arguments
+    let param_block = pprint_args(lambda);
+
+    // find the doc comment
+    let comment = find_comment(doc_anchor.clone(), opts.doc_style()).unwrap_or_default();
+
+    SearchResult {
+        // a wrapper like `lib.warn "msg" (x: ...)` says more than the function inside of it
+        deprecated: deprecation_message(doc_anchor)
+            .or_else(|| deprecation_message(lambda.syntax())),
+        arity: Some(arity(lambda)),
+        arity_may_be_higher: arity_may_be_higher(lambda),
+        args: arg_entries(lambda),
+        ..SearchResult::new(name, comment, param_block, defined_at_start)
+    }
+}
+
+/// The function a whole file evaluates to, like a package's `{ lib, stdenv, ... }: ...`, if it
+/// matches `identifier` under the name of the file called `file` (see [`file_function_name`]).
+/// It's documented by the comment at the top of the file.
+pub(crate) fn search_file_function(
+    identifier: &Regex,
+    ast: &Root,
+    file: &str,
+    opts: &SearchOptions,
+    skips: &mut Vec<Skip>,
+) -> Option<SearchResult> {
+    // the comment is before any parentheses around the function
+    let outermost = ast.expr()?;
+    let mut root = outermost.clone();
+    while let Expr::Paren(paren) = root {
+        root = paren.expr()?;
+    }
+    let lambda = match root {
+        Expr::Lambda(lambda) => lambda,
+        _ => return None,
+    };
+
+    let name = file_function_name(file);
+    let found = match &opts.fuzzy {
+        Some(pattern) => Some(fuzzy_match(pattern, &name)?),
+        None if identifier.is_match(&name) => None,
+        None => return None,
+    };
+    let start = usize::from(lambda.syntax().text_range().start());
+    let res = visit_lambda(name, start, &lambda, outermost.syntax(), opts);
+
+    let filtered = match filtered_out(&res, opts) {
+        _ if !res.has_doc() => Some(SkipReason::NoComment),
+        reason => reason,
+    };
+    if let Some(reason) = filtered {
+        if opts.wants_skips() {
+            skips.push(Skip {
+                identifier: res.identifier,
+                reason,
+                defined_at_start: start,
+            });
+        }
+        return None;
+    }
+
+    let (score, matched) = match found {
+        Some(found) => (Some(found.score), found.positions),
+        None => (None, Vec::new()),
+    };
+    Some(SearchResult {
+        def_kind: DefKind::File,
+        defined_at_end: usize::from(lambda.syntax().text_range().end()),
+        score,
+        matched,
+        ..res
+    })
+}
+
+/// What the function a file evaluates to is called: the file's name without the `.nix`, or for a
+/// `default.nix`, the name of the directory it's the default of, like `hello` for
+/// `pkgs/hello/default.nix`
+pub(crate) fn file_function_name(file: &str) -> String {
+    let path = Path::new(file);
+    let stem = path.file_stem().unwrap_or_default();
+    match path.parent().and_then(Path::file_name) {
+        Some(dir) if stem == "default" => dir.to_string_lossy().into_owned(),
+        _ => stem.to_string_lossy().into_owned(),
+    }
+}
+
+/// Why the filters on what was found leave `res` out, if they do
+pub(crate) fn filtered_out(res: &SearchResult, opts: &SearchOptions) -> Option<SkipReason> {
+    if !opts.wants_kind(res.kind) {
+        Some(SkipReason::Kind(res.kind))
+    } else if opts.no_deprecated && res.deprecated.is_some() {
+        Some(SkipReason::Deprecated)
+    } else if !opts.arity_matches(res.arity) {
+        Some(SkipReason::Arity(res.arity))
+    } else if !opts.synopsis_matches(res.synopsis.as_deref()) {
+        Some(SkipReason::Synopsis)
+    } else if !opts.takes_arg_matches(&res.args) {
+        Some(SkipReason::TakesArg)
+    } else if !opts.arg_default_matches(&res.args, true) {
+        Some(SkipReason::ArgDefault(true))
+    } else if !opts.arg_default_matches(&res.args, false) {
+        Some(SkipReason::ArgDefault(false))
+    } else if !opts.example_matches(res) {
+        Some(SkipReason::Example(has_example(&res.doc, &res.tags)))
+    } else if !opts.typed_matches(res) {
+        Some(SkipReason::Type(has_type(&res.doc)))
+    } else {
+        None
+    }
+}
+
+/// Searches the bindings of an attrset or `let` for definitions matching `id_needle`
+pub(crate) fn visit_bindings<S: HasEntry>(
+    id_needle: &Regex,
+    set: &S,
+    def_kind: DefKind,
+    opts: &SearchOptions,
+    skips: &mut Vec<Skip>,
+) -> Vec<SearchResult> {
+    let mut skip = |identifier: &str, reason: SkipReason, node: &SyntaxNode| {
+        if opts.wants_skips() {
+            skips.push(Skip {
+                identifier: identifier.to_string(),
+                reason,
+                defined_at_start: usize::from(node.text_range().start()),
+            });
+        }
+    };
+
+    // with a fuzzy pattern, the regex is ignored
+    let fuzzy = |name: &str| {
+        opts.fuzzy
+            .as_deref()
+            .map(|pattern| fuzzy_match(pattern, name))
+    };
+    let name_matches = |name: &str| match fuzzy(name) {
+        Some(found) => found.is_some(),
+        None => id_needle.is_match(name),
+    };
+
+    let parent_path = attr_path_to(set.syntax());
+    let mut results = Vec::new();
+    for entry in set.attrpath_values() {
+        let value = match entry.value() {
+            Some(v) => v.syntax().clone(),
+            None => continue,
+        };
+        if let Some(attr) = entry.attrpath() {
+            let last = match attr.attrs().last() {
+                Some(last) => last,
+                None => continue,
+            };
+            let ident_name = match key_part_name(&last) {
+                Some(name) => name,
+                // a computed key like `${name}`, which goes by its source
+                None if opts.any_key => last.to_string(),
+                None => {
+                    let key_text = attr.syntax().to_string();
+                    if name_matches(&key_text) {
+                        skip(&key_text, SkipReason::DynamicKey, attr.syntax());
+                    }
+                    continue;
+                }
+            };
+
+            let found = match fuzzy(&ident_name) {
+                Some(Some(found)) => Some(found),
+                None if id_needle.is_match(&ident_name) => None,
+                // the path or docs might match instead, which is checked once we have them
+                None if opts.all_fields => None,
+                // rejected, not matching our pattern
+                _ => {
+                    log::debug!("`{}` doesn't match", ident_name);
+                    continue;
+                }
+            };
+            let name_matched = opts.fuzzy.is_some() || id_needle.is_match(&ident_name);
+            // a dotted key is defined from its first part, though it's named by its last
+            let defined_at_start = usize::from(attr.syntax().text_range().start());
+            let identifier_at_start = usize::from(last.syntax().text_range().start());
+
+            let res = if let Some(lambda) = unwrap_lambda(&value) {
+                Some(visit_lambda(
+                    ident_name.clone(),
+                    defined_at_start,
+                    &lambda,
+                    &value,
+                    opts,
+                ))
+            } else if let Some(msg) = deprecation_message(&value) {
+                // a shim like `foo = lib.warn "foo is deprecated" bar;`, which stands in for a
+                // function we can't see the arguments of
+                Some(SearchResult {
+                    deprecated: Some(msg),
+                    ..SearchResult::new(
+                        ident_name.clone(),
+                        find_comment(value.clone(), opts.doc_style()).unwrap_or_default(),
+                        "...".to_string(),
+                        defined_at_start,
+                    )
+                })
+            } else if let Some(decl) = opts
+                .wants_kind(ResultKind::Option)
+                .then(|| option_declaration(&value))
+                .flatten()
+            {
+                let doc = match decl.description.clone() {
+                    Some(description) => description.into(),
+                    None => find_comment(value.clone(), opts.doc_style()).unwrap_or_default(),
+                };
+                Some(SearchResult {
+                    kind: ResultKind::Option,
+                    ..SearchResult::new(ident_name.clone(), doc, decl.signature(), defined_at_start)
+                })
+            } else if let Some(description) = opts
+                .wants_kind(ResultKind::Package)
+                .then(|| find_package_description(&value))
+                .flatten()
+            {
+                Some(SearchResult {
+                    kind: ResultKind::Package,
+                    ..SearchResult::new(
+                        ident_name.clone(),
+                        description.into(),
+                        package_head(&value),
+                        defined_at_start,
+                    )
+                })
+            } else if opts.wants_kind(ResultKind::Value) {
+                Some(SearchResult {
+                    kind: ResultKind::Value,
+                    ..SearchResult::new(
+                        ident_name.clone(),
+                        find_comment(value.clone(), opts.doc_style()).unwrap_or_default(),
+                        pprint_value_kind(&value),
+                        defined_at_start,
+                    )
+                })
+            } else {
+                None
+            };
+
+            // fall back to a comment after the definition if there's none before it
+            let res = res
+                .map(
+                    |res| match find_trailing_comment(entry.syntax(), opts.doc_style()) {
+                        Some(doc) if !res.has_doc() => res.with_trailing_doc(doc),
+                        _ => res,
+                    },
+                )
+                .filter(|res| res.has_doc());
+
+            let mut attr_path = parent_path.clone();
+            match key_idents(&attr) {
+                idents if !idents.is_empty() => attr_path.extend(idents),
+                _ => attr_path.push(ident_name.clone()),
+            }
+
+            let field_match = match &res {
+                Some(res) if opts.all_fields && found.is_none() => {
+                    field_match(id_needle, res, &attr_path)
+                }
+                _ => None,
+            };
+            if !name_matched && field_match.is_none() {
+                log::debug!("`{}` doesn't match in any field", attr_path.join("."));
+                continue;
+            }
+
+            let filtered = res.as_ref().and_then(|res| filtered_out(res, opts));
+            match (res, filtered) {
+                (Some(_), Some(reason)) => skip(&ident_name, reason, attr.syntax()),
+                (Some(res), None) => {
+                    let (score, mut matched) = match found {
+                        Some(found) => (Some(found.score), found.positions),
+                        None => (None, Vec::new()),
+                    };
+                    let (matched_field, field_range) = match field_match {
+                        Some((MatchedField::Name, range)) => {
+                            matched = char_positions(&res.identifier, range);
+                            (Some(MatchedField::Name), None)
+                        }
+                        Some((field, range)) => (Some(field), Some(range)),
+                        None => (None, None),
+                    };
+                    log::debug!("`{}` matched", attr_path.join("."));
+                    results.push(SearchResult {
+                        def_kind,
+                        attr_path,
+                        identifier_at_start,
+                        defined_at_end: usize::from(entry.syntax().text_range().end()),
+                        score,
+                        matched,
+                        matched_field,
+                        field_range,
+                        ..res
+                    })
+                }
+                (None, _) => {
+                    let reason =
+                        if unwrap_lambda(&value).is_some() || opts.wants_kind(ResultKind::Value) {
+                            SkipReason::NoComment
+                        } else {
+                            SkipReason::NotAFunction(pprint_value_kind(&value))
+                        };
+                    skip(&ident_name, reason, attr.syntax());
+                }
+            }
+        }
+    }
+
+    for inherit in set.inherits() {
+        // one comment documents every name the `inherit` brings in
+        let doc = match opts.include_reexports {
+            true => find_comment(inherit.syntax().clone(), opts.doc_style()),
+            false => None,
+        };
+        let from = inherit.from().and_then(|from| from.expr());
+        for id in inherit.attrs() {
+            // a name can be quoted, as in `inherit "foo";`
+            let name = match key_part_name(&id) {
+                Some(name) => name,
+                None => continue,
+            };
+            let name = name.as_str();
+            if !name_matches(name) {
+                continue;
+            }
+            let doc = match (&doc, opts.include_reexports) {
+                (Some(doc), _) => doc.clone(),
+                (None, true) => {
+                    skip(name, SkipReason::NoComment, id.syntax());
+                    continue;
+                }
+                (None, false) => {
+                    skip(name, SkipReason::Inherit, id.syntax());
+                    continue;
+                }
+            };
+            // `foo = x.foo` for `inherit (x) foo`, which is what it means
+            let inherited = match &from {
+                Some(from) => format!("{}.{}", from, name),
+                None => name.to_string(),
+            };
+            let range = id.syntax().text_range();
+            let res =
+                SearchResult::new(name.to_string(), doc, inherited, usize::from(range.start()));
+            if let Some(reason) = filtered_out(&res, opts) {
+                skip(name, reason, id.syntax());
+                continue;
+            }
+            let (score, matched) = match fuzzy(name).flatten() {
+                Some(found) => (Some(found.score), found.positions),
+                None => (None, Vec::new()),
+            };
+            let mut attr_path = parent_path.clone();
+            attr_path.push(name.to_string());
+            log::debug!("`{}` matched", attr_path.join("."));
+            results.push(SearchResult {
+                def_kind,
+                attr_path,
+                defined_at_end: usize::from(range.end()),
+                score,
+                matched,
+                ..res
+            });
+        }
+    }
+
+    results
+}
+
+/// Which of the name, attribute path and docs of `res` (at `attr_path`) `re` matches first, and
+/// where in it
+pub(crate) fn field_match(
+    re: &Regex,
+    res: &SearchResult,
+    attr_path: &[String],
+) -> Option<(MatchedField, Range<usize>)> {
+    if let Some(m) = re.find(&res.identifier) {
+        return Some((MatchedField::Name, m.range()));
+    }
+    if let Some(m) = re.find(&attr_path.join(".")) {
+        return Some((MatchedField::Path, m.range()));
+    }
+    re.find(&res.doc).map(|m| (MatchedField::Doc, m.range()))
+}
+
+/// Indices of the characters of `s` that are in the byte range `range`
+pub(crate) fn char_positions(s: &str, range: Range<usize>) -> Vec<usize> {
+    s.char_indices()
+        .enumerate()
+        .filter(|(_, (byte, _))| range.contains(byte))
+        .map(|(idx, _)| idx)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "serde")]
+    use crate::format::JSON_SCHEMA_VERSION;
+    use crate::walk::is_searchable;
+    #[cfg(feature = "fs")]
+    use crate::walk::walk_files;
+    use crate::{ArityRange, CancellationToken};
+
+    use std::fs;
+
+    #[test]
+    fn test_regression_11() {
+        let out = r#"Create a fixed width string with additional prefix to match
+required width.
+
+This function will fail if the input string is longer than the
+requested length.
+
+Type: fixedWidthString :: int -> string -> string
+
+Example:
+  fixedWidthString 5 "0" (toString 15)
+  => "00015""#;
+        let ast = rnix::Root::parse(include_str!("../testdata/regression-11.nix"))
+            .ok()
+            .unwrap();
+        let results = search_ast(
+            &regex::Regex::new("fixedWidthString").unwrap(),
+            &ast,
+            &SearchOptions::default(),
+        );
+        assert_eq!(results.len(), 1);
+
+        assert_eq!(results[0].doc, out);
+    }
+
+    #[test]
+    fn test_packages() {
+        let ast = rnix::Root::parse(include_str!("../testdata/packages.nix"))
+            .ok()
+            .unwrap();
+        let re = Regex::new("").unwrap();
+
+        let results = search_ast(&re, &ast, &SearchOptions::default());
+        assert!(results.is_empty());
+
+        let opts = SearchOptions {
+            packages: true,
+            ..Default::default()
+        };
+        let results = search_ast(&re, &ast, &opts);
+        let found = results
+            .iter()
+            .map(|r| {
+                (
+                    r.identifier.as_str(),
+                    r.doc.as_str(),
+                    r.param_block.as_str(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            found,
+            [
+                (
+                    "hello",
+                    "A program that produces a familiar, friendly greeting",
+                    "stdenv.mkDerivation { ... }"
+                ),
+                (
+                    "cowsay",
+                    "A program which generates ASCII pictures of a cow",
+                    "stdenv.mkDerivation { ... }"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_any_value() {
+        let ast = rnix::Root::parse(include_str!("../testdata/values.nix"))
+            .ok()
+            .unwrap();
+        let re = Regex::new("").unwrap();
+
+        let found = |opts: &SearchOptions| {
+            search_ast(&re, &ast, opts)
+                .into_iter()
+                .map(|r| (r.identifier, r.param_block))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            found(&SearchOptions::default()),
+            [("double".to_string(), "x: ...".to_string())]
+        );
+
+        let opts = SearchOptions {
+            any_value: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            found(&opts),
+            [
+                ("maxInt".to_string(), "<integer>".to_string()),
+                ("codeName".to_string(), "<string>".to_string()),
+                ("double".to_string(), "x: ...".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_explain_skips() {
+        let src = r#"{
+          # documented
+          f = x: x;
+          g = y: y;
+          v = 2;
+          ${dyn} = z: z;
+          inherit (lib) h;
+        }"#;
+        let ast = rnix::Root::parse(src).ok().unwrap();
+        let opts = SearchOptions {
+            explain_skips: true,
+            ..Default::default()
+        };
+        let (results, skips) = search_ast_with_skips(&Regex::new("").unwrap(), &ast, &opts);
+        assert_eq!(results.len(), 1);
+
+        let skips = skips
+            .iter()
+            .map(|s| {
+                let line = LineIndex::new(src).line(s.defined_at_start);
+                format!("{}:{}: {}", line, s.identifier, s.reason)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            skips,
+            [
+                "4:g: no doc comment",
+                "5:v: value is <integer>, not a function",
+                "6:${dyn}: computed key",
+                "7:h: inherited from elsewhere",
+            ]
+        );
+
+        let (_, skips) =
+            search_ast_with_skips(&Regex::new("").unwrap(), &ast, &SearchOptions::default());
+        assert!(skips.is_empty());
+    }
+
+    #[test]
+    fn test_include_reexports() {
+        let ast = rnix::Root::parse(include_str!("../testdata/inherit.nix"))
+            .ok()
+            .unwrap();
+        let re = Regex::new("").unwrap();
+        let found = |opts: &SearchOptions| {
+            search_ast(&re, &ast, opts)
+                .into_iter()
+                .map(|r| format!("{} = {}: {}", r.identifier, r.param_block, r.doc))
+                .collect::<Vec<_>>()
+        };
+        assert!(found(&SearchOptions::default()).is_empty());
+
+        let opts = SearchOptions {
+            include_reexports: true,
+            def_kinds: vec![DefKind::Attr, DefKind::LetIn],
+            explain_skips: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            found(&opts),
+            [
+                "map = builtins.map: Applies `f` to each element of `list`",
+                "concatLists = lib.lists.concatLists: Concatenates a list of lists into one list",
+                "concatStringsSep = strings.concatStringsSep: Joins the strings with `sep` between them",
+                "concatMapStringsSep = strings.concatMapStringsSep: Joins the strings with `sep` \
+                 between them",
+            ]
+        );
+        let (results, skips) = search_ast_with_skips(&re, &ast, &opts);
+        assert_eq!(results[1].attr_path, ["concatLists"]);
+        let skipped = skips
+            .iter()
+            .map(|s| format!("{}: {}", s.identifier, s.reason))
+            .collect::<Vec<_>>();
+        // the `inherit map;` in the set isn't documented itself, and nor is `undocumented`
+        assert_eq!(
+            skipped,
+            [
+                "strings: value is <reference>, not a function",
+                "map: no doc comment",
+                "undocumented: no doc comment",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_dotted_key_start() {
+        let src = "{\n  # Doc\n  foo.bar.baz = x: x;\n  # Plain\n  qux = x: x;\n}";
+        let ast = rnix::Root::parse(src).ok().unwrap();
+        let results = search_ast(&Regex::new("").unwrap(), &ast, &SearchOptions::default());
+        let starts = results
+            .iter()
+            .map(|r| {
+                (
+                    r.identifier.as_str(),
+                    (r.start.line, r.start.col_chars),
+                    (r.identifier_start.line, r.identifier_start.col_chars),
+                )
+            })
+            .collect::<Vec<_>>();
+        // the definition is from `foo` on, and the name it defines is `baz`
+        assert_eq!(starts, [("baz", (3, 3), (3, 11)), ("qux", (5, 3), (5, 3))]);
+        assert_eq!(&src[results[0].defined_at_start..][..3], "foo");
+        assert_eq!(&src[results[0].identifier_at_start..][..3], "baz");
+    }
+
+    #[test]
+    fn test_inherit_quoted() {
+        // names in `inherit` can be quoted, like keys can
+        let ast = rnix::Root::parse("{\n  # Docs\n  inherit (lib) \"a\" b;\n}")
+            .ok()
+            .unwrap();
+        let opts = SearchOptions {
+            include_reexports: true,
+            ..Default::default()
+        };
+        let found = search_ast(&Regex::new("").unwrap(), &ast, &opts)
+            .into_iter()
+            .map(|r| format!("{} = {}: {}", r.identifier, r.param_block, r.doc))
+            .collect::<Vec<_>>();
+        assert_eq!(found, ["a = lib.a: Docs", "b = lib.b: Docs"]);
+    }
+
+    #[test]
+    fn test_deprecated() {
+        let src = r#"{
+          # Old name of `bar`
+          foo = lib.warn "foo is deprecated, use bar" bar;
+          # Curried shim
+          baz = x: builtins.trace "baz is deprecated" (bar x);
+          # Removed entirely
+          qux = throw "qux has been removed";
+          # Not deprecated
+          bar = x: builtins.trace x x;
+        }"#;
+        let ast = rnix::Root::parse(src).ok().unwrap();
+        let re = Regex::new("").unwrap();
+
+        let results = search_ast(&re, &ast, &SearchOptions::default());
+        let found = results
+            .iter()
+            .map(|r| (r.identifier.as_str(), r.deprecated.as_deref()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            found,
+            [
+                ("foo", Some("foo is deprecated, use bar")),
+                ("baz", Some("baz is deprecated")),
+                ("qux", Some("qux has been removed")),
+                ("bar", None),
+            ]
+        );
+
+        let opts = SearchOptions {
+            no_deprecated: true,
+            ..Default::default()
+        };
+        let results = search_ast(&re, &ast, &opts);
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].identifier, "bar");
+    }
+
+    #[test]
+    fn test_arity() {
+        let src = r#"{
+          # zero
+          thunk = {}: 1;
+          # one
+          id = x: x;
+          # two
+          const = { a, b }: c: a;
+          # three
+          fold = op: nul: list: nul;
+        }"#;
+        let ast = rnix::Root::parse(src).ok().unwrap();
+        let re = Regex::new("").unwrap();
+        let found = |opts: &SearchOptions| {
+            search_ast(&re, &ast, opts)
+                .into_iter()
+                .map(|r| r.identifier)
+                .collect::<Vec<_>>()
+        };
+
+        let exact = SearchOptions {
+            arity: Some(ArityRange::exactly(2)),
+            ..Default::default()
+        };
+        assert_eq!(found(&exact), ["const"]);
+
+        let min = SearchOptions {
+            min_arity: Some(2),
+            ..Default::default()
+        };
+        assert_eq!(found(&min), ["const", "fold"]);
+
+        let one = SearchOptions {
+            arity: Some(ArityRange::exactly(1)),
+            ..Default::default()
+        };
+        assert_eq!(found(&one), ["thunk", "id"]);
+
+        let range = |s: &str| SearchOptions {
+            arity: Some(s.parse().unwrap()),
+            ..Default::default()
+        };
+        assert_eq!(found(&range("2+")), ["const", "fold"]);
+        assert_eq!(found(&range("2-2")), ["const"]);
+        assert_eq!(found(&range("0-1")), ["thunk", "id"]);
+        assert!("3-1".parse::<ArityRange>().is_err());
+        assert!("two".parse::<ArityRange>().is_err());
+    }
+
+    #[test]
+    fn test_arity_may_be_higher() {
+        let src = r#"{
+          # calls something that may return a function
+          flip = f: a: (f a);
+          # refers to another function
+          alias = x: other;
+          # hides a lambda behind a let
+          hidden = x: let y = x; in z: y;
+          # plainly done
+          done = x: { inherit x; };
+        }"#;
+        let ast = rnix::Root::parse(src).ok().unwrap();
+        let results = search_ast(&Regex::new("").unwrap(), &ast, &SearchOptions::default());
+        let may_be_higher = results
+            .iter()
+            .map(|r| (r.identifier.as_str(), r.arity, r.arity_may_be_higher))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            may_be_higher,
+            [
+                ("flip", Some(2), true),
+                ("alias", Some(1), true),
+                ("hidden", Some(1), true),
+                ("done", Some(1), false),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_takes_arg() {
+        let src = r#"{
+          # plain
+          map = f: list: list;
+          # pattern
+          mkDerivation = { stdenv, lib ? null, ... }@args: args;
+          # curried after a pattern
+          callWith = { pkgs }: f: f pkgs;
+          # anything at all
+          anything = { ... }: 1;
+          # not a function
+          value = 1;
+        }"#;
+        let ast = rnix::Root::parse(src).ok().unwrap();
+        let found = |arg: &str| {
+            let opts = SearchOptions {
+                takes_arg: Some(Regex::new(&format!("^(?:{})$", arg)).unwrap()),
+                any_value: true,
+                ..Default::default()
+            };
+            search_ast(&Regex::new(".").unwrap(), &ast, &opts)
+                .into_iter()
+                .map(|r| r.identifier)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(found("f"), ["map", "callWith"]);
+        assert_eq!(found("lib"), ["mkDerivation"]);
+        assert_eq!(found("args"), ["mkDerivation"]);
+        assert_eq!(found("std.*"), ["mkDerivation"]);
+        assert_eq!(found("std"), Vec::<String>::new());
+        assert_eq!(found(r"\.\.\."), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_arg_defaults() {
+        let src = r#"{
+          # optional stdenv
+          mkShell = { stdenv ? null, name }: name;
+          # required stdenv
+          mkDerivation = { stdenv, lib ? null }: stdenv;
+          # plain argument
+          callWith = stdenv: f: f stdenv;
+        }"#;
+        let ast = rnix::Root::parse(src).ok().unwrap();
+        let found = |pattern: &str, default: Option<&str>, required: Option<&str>| {
+            let whole = |arg: &str| Regex::new(&format!("^(?:{})$", arg)).unwrap();
+            let opts = SearchOptions {
+                arg_default: default.map(whole),
+                arg_required: required.map(whole),
+                ..Default::default()
+            };
+            search_ast(&Regex::new(pattern).unwrap(), &ast, &opts)
+                .into_iter()
+                .map(|r| r.identifier)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(found(".", Some("stdenv"), None), ["mkShell"]);
+        assert_eq!(
+            found(".", None, Some("stdenv")),
+            ["mkDerivation", "callWith"]
+        );
+        assert_eq!(found("^mk", None, Some("stdenv")), ["mkDerivation"]);
+        assert_eq!(found(".", Some("lib"), Some("stdenv")), ["mkDerivation"]);
+        assert_eq!(found(".", Some("name"), None), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_example_filter() {
+        let src = r#"{
+          /* Maps.
+
+             Example:
+               map (x: x + 1) [ 1 ] => [ 2 ]
+          */
+          map = f: list: list;
+          /* Filters.
+
+             # Examples
+
+             ```nix
+             filter (x: x > 1) [ 1 2 ]
+             ```
+          */
+          filter = f: list: list;
+          # Folds.
+          fold = f: z: list: z;
+        }"#;
+        let ast = rnix::Root::parse(src).ok().unwrap();
+        let found = |example| {
+            let opts = SearchOptions {
+                example,
+                ..Default::default()
+            };
+            search_ast(&Regex::new(".").unwrap(), &ast, &opts)
+                .into_iter()
+                .map(|r| r.identifier)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(found(Some(true)), ["map", "filter"]);
+        assert_eq!(found(Some(false)), ["fold"]);
+        assert_eq!(found(None).len(), 3);
+    }
+
+    #[test]
+    fn test_type_filter() {
+        let src = r#"{
+          strings = {
+            # Joins.
+            #
+            # Type: concatSep :: string -> [string] -> string
+            concatSep = sep: list: "";
+            # Splits.
+            split = sep: s: [ ];
+            /* Replaces.
+
+               # Type
+
+               ```
+               ```
+            */
+            replace = from: to: s: s;
+          };
+          # Maps.
+          #
+          # Type: map :: (a -> b) -> [a] -> [b]
+          map = f: list: list;
+        }"#;
+        let ast = rnix::Root::parse(src).ok().unwrap();
+        let found = |pattern: &str, typed| {
+            let opts = SearchOptions {
+                typed,
+                path_prefix: vec!["strings".to_string()],
+                ..Default::default()
+            };
+            search_ast(&Regex::new(pattern).unwrap(), &ast, &opts)
+                .into_iter()
+                .filter(|r| opts.path_matches(&[], &r.attr_path))
+                .map(|r| r.identifier)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(found(".", Some(true)), ["concatSep"]);
+        assert_eq!(found(".", Some(false)), ["split", "replace"]);
+        assert_eq!(found("^s", Some(false)), ["split"]);
+    }
+
+    #[test]
+    fn test_aliases() {
+        let src = r#"{
+          # Concatenate a list of strings
+          concatStrings = builtins.concatStringsSep "";
+          # Concatenate with a separator
+          concatStringsSep = sep: list: builtins.concatStringsSep sep list;
+          join = concatStringsSep;
+          strings = { joinWith = (lib.concatStringsSep); };
+          concatStringsSep' = concatStringsSep;
+          same = { concatStringsSep = lib.concatStringsSep; };
+          notAnAlias = concatStringsSep ", ";
+        }"#;
+        let ast = rnix::Root::parse(src).ok().unwrap();
+        let opts = SearchOptions {
+            aliases: true,
+            any_value: true,
+            ..SearchOptions::default()
+        };
+        let results = search_ast(&Regex::new("^concatStrings").unwrap(), &ast, &opts);
+        let found = results
+            .iter()
+            .map(|res| (res.identifier.as_str(), res.aliases.clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            found,
+            [
+                ("concatStrings", vec![]),
+                (
+                    "concatStringsSep",
+                    vec![
+                        "join".to_string(),
+                        "joinWith".to_string(),
+                        "concatStringsSep'".to_string()
+                    ]
+                ),
+            ]
+        );
+        let formatted = crate::output::strip_colors(&results[1].format("f.nix", 3));
+        assert!(formatted.contains("\naliases: join, joinWith, concatStringsSep'\n# f.nix:3"));
+        assert!(!crate::output::strip_colors(&results[0].format("f.nix", 1)).contains("aliases"));
+
+        // not looked for unless asked
+        let results = search_ast(
+            &Regex::new("^concatStringsSep$").unwrap(),
+            &ast,
+            &Default::default(),
+        );
+        assert!(results[0].aliases.is_empty());
+    }
+
+    #[test]
+    fn test_fuzzy() {
+        let src = r#"{
+          # a
+          concatMapStrings = f: list: "";
+          # b
+          concatStrings = list: "";
+          # c
+          mapAttrs = f: set: set;
+        }"#;
+        let ast = rnix::Root::parse(src).ok().unwrap();
+        let opts = SearchOptions {
+            fuzzy: Some("cMapStr".to_string()),
+            ..Default::default()
+        };
+        // the regex is ignored in favour of the fuzzy pattern
+        let results = search_ast(&Regex::new("^nothing$").unwrap(), &ast, &opts);
+        let found = results
+            .iter()
+            .map(|r| (r.identifier.as_str(), r.matched.clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(found, [("concatMapStrings", vec![0, 6, 7, 8, 9, 10, 11])]);
+        assert_eq!(opts.sort_key(), Some(SortKey::Score));
+
+        let opts = SearchOptions {
+            fuzzy: Some("map".to_string()),
+            ..Default::default()
+        };
+        let mut results = search_ast(&Regex::new("").unwrap(), &ast, &opts);
+        results.sort_by(|a, b| compare_results(SortKey::Score, a, b));
+        let names = results
+            .iter()
+            .map(|r| r.identifier.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["mapAttrs", "concatMapStrings"]);
+    }
+
+    #[test]
+    fn test_relevance() {
+        let src = r#"{
+          # contains it
+          concatMap = f: list: list;
+          # starts with it
+          mapAttrs = f: set: set;
+          # exactly it
+          map = f: list: list;
+          /* exactly it, also documenting its type
+
+             Type: map :: (a -> b) -> [a] -> [b]
+          */
+          Map = f: list: list;
+        }"#;
+        let ast = rnix::Root::parse(src).ok().unwrap();
+        let re = regex::RegexBuilder::new("map")
+            .case_insensitive(true)
+            .build()
+            .unwrap();
+        let query = RelevanceQuery::new(&re, &SearchOptions::default());
+        let ranked = |file: &str| {
+            let mut results = search_ast(&re, &ast, &SearchOptions::default())
+                .into_iter()
+                .map(|mut r| {
+                    r.relevance = r.relevance(&query, Path::new(file));
+                    r
+                })
+                .collect::<Vec<_>>();
+            results.sort_by(|a, b| compare_results(SortKey::Relevance, a, b));
+            results
+        };
+
+        let names = ranked("pkgs/a.nix")
+            .iter()
+            .map(|r| r.identifier.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["Map", "map", "mapAttrs", "concatMap"]);
+
+        let in_lib = ranked("lib/a.nix");
+        assert!(in_lib[0].relevance > ranked("pkgs/a.nix")[0].relevance);
+
+        // a regex gives nothing to compare names against, leaving the other signals
+        let regex = RelevanceQuery::new(&Regex::new("m.p").unwrap(), &SearchOptions::default());
+        assert_eq!(regex.needle, None);
+        assert_eq!(
+            RelevanceQuery::new(&Regex::new("^map$").unwrap(), &SearchOptions::default()).needle,
+            Some("map".to_string())
+        );
+    }
+
+    #[test]
+    fn test_all_fields() {
+        let src = r#"{
+          # Converts to upper case
+          toUpperCase = s: s;
+          upper = {
+            # Makes the first letter a capital
+            first = s: s;
+          };
+          # Uppercases a string
+          toCaps = s: s;
+          # Unrelated
+          toLower = s: s;
+        }"#;
+        let ast = rnix::Root::parse(src).ok().unwrap();
+        let re = regex::RegexBuilder::new("upper")
+            .case_insensitive(true)
+            .build()
+            .unwrap();
+        let opts = SearchOptions {
+            all_fields: true,
+            ..SearchOptions::default()
+        };
+        let query = RelevanceQuery::new(&re, &opts);
+        let mut results = search_ast(&re, &ast, &opts)
+            .into_iter()
+            .map(|mut r| {
+                r.relevance = r.relevance(&query, Path::new("a.nix"));
+                r
+            })
+            .collect::<Vec<_>>();
+        results.sort_by(|a, b| compare_results(opts.sort_key().unwrap(), a, b));
+        let found = results
+            .iter()
+            .map(|r| (r.identifier.as_str(), r.matched_field))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            found,
+            [
+                ("toUpperCase", Some(MatchedField::Name)),
+                ("first", Some(MatchedField::Path)),
+                ("toCaps", Some(MatchedField::Doc)),
+            ]
+        );
+        assert_eq!(results[0].matched, [2, 3, 4, 5, 6]);
+        assert_eq!(results[1].field_range, Some(0..5));
+        assert_eq!(results[2].field_range, Some(0..5));
+
+        // without it, only names are matched
+        let names = search_ast(&re, &ast, &SearchOptions::default());
+        assert_eq!(names.len(), 1);
+        assert_eq!(names[0].matched_field, None);
+    }
+
+    #[test]
+    fn test_doc_prefix() {
+        let src = r#"{
+          ## Uppercases a string
+          ##
+          ##   toUpper "a" == "A"
+          toUpper = s: s;
+          # TODO: make this faster
+          toLower = s: s;
+          ## Trims a string
+          # noise in between
+          trim = s: s;
+          /* block comments are always docs */
+          strip = s: s;
+          id = x: x; # not a doc either
+          const = x: y: x; ## but this is
+        }"#;
+        let ast = rnix::Root::parse(src).ok().unwrap();
+        let docs = |opts: &SearchOptions| {
+            search_ast(&Regex::new("").unwrap(), &ast, opts)
+                .into_iter()
+                .map(|r| (r.identifier, r.doc))
+                .collect::<Vec<_>>()
+        };
+
+        let opts = SearchOptions {
+            doc_prefix: Some("##".to_string()),
+            ..Default::default()
+        };
+        let found = docs(&opts);
+        let names = found.iter().map(|(id, _)| id.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, ["toUpper", "trim", "strip", "const"]);
+        assert_eq!(
+            found[0].1,
+            "Uppercases a string\n\n  toUpper \"a\" == \"A\""
+        );
+        assert_eq!(found[1].1, "Trims a string");
+        assert_eq!(found[3].1, "but this is");
+
+        let raw = SearchOptions {
+            raw_docs: true,
+            ..opts
+        };
+        assert_eq!(docs(&raw)[1].1, " Trims a string");
+
+        // by default every comment is documentation
+        let names = docs(&SearchOptions::default())
+            .into_iter()
+            .map(|(id, _)| id)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            ["toUpper", "toLower", "trim", "strip", "id", "const"]
+        );
+    }
+
+    #[test]
+    fn test_trailing_comment() {
+        let src = r#"{
+          toUpper = s: s; # uppercase a string
+          # lowercase a string
+          toLower = s: s;
+          noDoc = s: s;
+          # about next
+          next = s: s;
+          other = s: s; also = s: s; # about also
+        }"#;
+        let ast = rnix::Root::parse(src).ok().unwrap();
+        let results = search_ast(&Regex::new("").unwrap(), &ast, &SearchOptions::default());
+        let found = results
+            .iter()
+            .map(|r| (r.identifier.as_str(), r.doc.as_str(), r.doc_position))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            found,
+            [
+                ("toUpper", "uppercase a string", DocPosition::Trailing),
+                ("toLower", "lowercase a string", DocPosition::Leading),
+                ("next", "about next", DocPosition::Leading),
+                ("also", "about also", DocPosition::Trailing),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_wrapped_lambdas() {
+        let src = r#"{
+          # asserted
+          foo = assert true; x: x;
+          # parenthesized
+          bar = (x: x);
+          # let-bound
+          baz = let helper = 1; in x: y: helper;
+          # conditional
+          qux = if true then a: a else b: b;
+          # not a function on one side
+          quux = if true then a: a else 1;
+          # checked first
+          checked = lib.throwIf (1 > 2) "impossible" (x: y: x);
+          # deprecated, but still a function
+          old = lib.warn "old is deprecated" (x: x);
+          # calls something else that might not return a function
+          other = lib.mapAttrs (n: v: v);
+        }"#;
+        let ast = rnix::Root::parse(src).ok().unwrap();
+        let results = search_ast(&Regex::new("").unwrap(), &ast, &SearchOptions::default());
+        let found = results
+            .iter()
+            .map(|r| {
+                let line = LineIndex::new(src).line(r.defined_at_start);
+                (r.identifier.as_str(), r.param_block.as_str(), line)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            found,
+            [
+                ("foo", "x: ...", 3),
+                ("bar", "x: ...", 5),
+                ("baz", "x: y: ...", 7),
+                ("qux", "a: ...", 9),
+                ("checked", "x: y: ...", 13),
+                ("old", "x: ...", 15),
+            ]
+        );
+        assert_eq!(results[0].doc, "asserted");
+        assert_eq!(results[5].deprecated.as_deref(), Some("old is deprecated"));
+
+        let fixture = Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/testdata/wrapped.nix"));
+        let found = search_file(fixture, &Regex::new("").unwrap(), &SearchOptions::default())
+            .unwrap()
+            .into_iter()
+            .map(|r| (r.identifier, r.param_block))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            found,
+            [
+                ("div".to_string(), "b: ...".to_string()),
+                ("head".to_string(), "list: ...".to_string()),
+                ("getAttr".to_string(), "name: set: ...".to_string()),
+                ("checked".to_string(), "x: y: ...".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_def_kind() {
+        let src = r#"{
+          # plain
+          plain = x: x;
+          nested = rec {
+            # recursive
+            recursive = x: x;
+          };
+          lets = let
+            # let-bound
+            bound = x: x;
+          in bound;
+        }"#;
+        let ast = rnix::Root::parse(src).ok().unwrap();
+        let found = |def_kinds: Vec<DefKind>| {
+            let opts = SearchOptions {
+                def_kinds,
+                ..SearchOptions::default()
+            };
+            search_ast(&Regex::new("").unwrap(), &ast, &opts)
+                .into_iter()
+                .map(|r| (r.identifier, r.def_kind))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            found(vec![]),
+            [
+                ("plain".to_string(), DefKind::Attr),
+                ("recursive".to_string(), DefKind::RecAttr),
+            ]
+        );
+        assert_eq!(
+            found(vec![DefKind::LetIn]),
+            [("bound".to_string(), DefKind::LetIn)]
+        );
+        assert_eq!("rec".parse::<DefKind>(), Ok(DefKind::RecAttr));
+        assert!("inherit".parse::<DefKind>().is_err());
+    }
+
+    #[test]
+    fn test_raw_docs() {
+        let src = r#"{
+          /* Lays out a table:
+               | a | b |
+               |---|---|
+           */
+          table = x: x;
+          ## indented
+          #    keeps  spacing
+          spaced = x: x;
+        }"#;
+        let ast = rnix::Root::parse(src).ok().unwrap();
+        let opts = SearchOptions {
+            raw_docs: true,
+            ..SearchOptions::default()
+        };
+        let results = search_ast(&Regex::new("").unwrap(), &ast, &opts);
+        let docs = results.iter().map(|r| r.doc.as_str()).collect::<Vec<_>>();
+        assert_eq!(
+            docs,
+            [
+                " Lays out a table:\n               | a | b |\n               |---|---|\n           ",
+                "# indented\n    keeps  spacing",
+            ]
+        );
+
+        let cleaned = search_ast(&Regex::new("").unwrap(), &ast, &SearchOptions::default());
+        assert_eq!(cleaned[1].doc, "# indented\n   keeps  spacing");
+    }
+
+    #[test]
+    fn test_search_source() {
+        let src = "{\n  # identity\n  id = x: x;\n}";
+        let results = search_source(
+            Path::new(STDIN_NAME),
+            src,
+            &Regex::new("id").unwrap(),
+            &SearchOptions::default(),
+        )
+        .unwrap();
+        let res = &results[0];
+        assert_eq!(res.identifier, "id");
+        assert_eq!((res.start.line, res.start.col_chars), (3, 3));
+        assert_eq!((res.end.line, res.end.col_chars), (3, 13));
+        assert_eq!(res.file.as_deref(), Some(Path::new(STDIN_NAME)));
+        assert_eq!(res.raw_doc, "# identity");
+        assert!(search_source(
+            Path::new(STDIN_NAME),
+            "{ f = ",
+            &Regex::new("").unwrap(),
+            &SearchOptions::default()
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_file_functions() {
+        let src = "# Says hello\n({ lib, stdenv }: stdenv.mkDerivation { pname = \"hello\"; })";
+        let found = |name: &str, pattern: &str, opts: &SearchOptions| {
+            search_source(Path::new(name), src, &Regex::new(pattern).unwrap(), opts)
+                .unwrap()
+                .into_iter()
+                .map(|res| (res.identifier, res.doc, res.def_kind, res.start.line))
+                .collect::<Vec<_>>()
+        };
+        let opts = SearchOptions {
+            file_functions: true,
+            ..Default::default()
+        };
+
+        let hello = || {
+            let doc = "Says hello".to_string();
+            ("hello".to_string(), doc, DefKind::File, 2)
+        };
+        assert_eq!(found("pkgs/hello.nix", "^hello$", &opts), [hello()]);
+        assert_eq!(found("pkgs/hello/default.nix", "", &opts), [hello()]);
+        assert_eq!(found("pkgs/hello.nix", "bye", &opts), []);
+        assert!(found("pkgs/hello.nix", "", &Default::default()).is_empty());
+        assert_eq!(file_function_name("default.nix"), "default");
+    }
+
+    #[test]
+    fn test_blank_files() {
+        let dir = Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/testdata"));
+        let all = Regex::new("").unwrap();
+        for name in ["empty.nix", "comment-only.nix", "empty-attrs.nix"] {
+            let results = search_file(&dir.join(name), &all, &Default::default());
+            assert!(results.unwrap().is_empty(), "{}", name);
+        }
+        assert!(is_blank_source("  \n\t\n"));
+        assert!(!is_blank_source("{ }"));
+        // an unterminated comment is still a syntax error
+        assert!(!is_blank_source("/* oops"));
+    }
+
+    #[test]
+    fn test_merged_attrsets() {
+        let ast = rnix::Root::parse(include_str!("../testdata/merge.nix"))
+            .ok()
+            .unwrap();
+        let results = search_ast(&Regex::new("").unwrap(), &ast, &SearchOptions::default());
+        let found = results
+            .iter()
+            .map(|r| (r.identifier.as_str(), r.doc.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(found, [("f", "doc"), ("g", "merged in last")]);
+    }
+
+    #[test]
+    fn test_attr_paths() {
+        let src = r#"{ lib }: {
+          strings = {
+            # upper
+            toUpper = s: s;
+          };
+          # nested key
+          strings.toLower = s: s;
+          # top
+          top = x: x;
+        }"#;
+        let ast = rnix::Root::parse(src).ok().unwrap();
+        let results = search_ast(&Regex::new("").unwrap(), &ast, &SearchOptions::default());
+        let paths = results
+            .iter()
+            .map(|r| r.attr_path.join("."))
+            .collect::<Vec<_>>();
+        assert_eq!(paths, ["strings.toLower", "top", "strings.toUpper"]);
+
+        let opts = SearchOptions {
+            path_prefix: vec!["lib".into(), "str".into()],
+            ..SearchOptions::default()
+        };
+        let file_path = file_attr_path(Path::new("."), Path::new("./lib/default.nix"));
+        assert_eq!(file_path, ["lib"]);
+        assert!(opts.path_could_match(&file_path));
+        assert!(!opts.path_matches(&file_path, &results[0].attr_path));
+
+        let opts = SearchOptions {
+            path_prefix: vec!["lib".into(), "strings".into()],
+            ..SearchOptions::default()
+        };
+        assert!(opts.path_matches(&file_path, &results[0].attr_path));
+        assert!(!opts.path_matches(&file_path, &results[1].attr_path));
+        let file_path = file_attr_path(Path::new("/n"), Path::new("/n/lib/strings.nix"));
+        assert_eq!(file_path, ["lib", "strings"]);
+        assert!(opts.path_matches(&file_path, &["toUpper".to_string()]));
+
+        let exact = SearchOptions {
+            path_prefix: vec!["lib".into(), "strings".into(), "toUpper".into()],
+            path_exact: true,
+            ..SearchOptions::default()
+        };
+        assert!(exact.path_matches(&file_path, &["toUpper".to_string()]));
+        assert!(!exact.path_matches(&file_path, &["toUpper".to_string(), "x".to_string()]));
+        assert!(!exact.path_matches(&["lib".to_string()], &["strings".to_string()]));
+        assert!(
+            !opts.path_could_match(&file_attr_path(Path::new("/n"), Path::new("/n/pkgs/a.nix")))
+        );
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn test_file_regex() {
+        let dir = Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/testdata"));
+        let opts = SearchOptions {
+            file_regex: Some(Regex::new("merge|with").unwrap()),
+            ..Default::default()
+        };
+        let files = walk_files(dir, false, &opts.dirs, &is_searchable).map(|entry| {
+            let file_path = file_attr_path(dir, entry.path());
+            (entry.into_path(), file_path)
+        });
+        let (mut searched, mut found) = (0, Vec::new());
+        run_search(files, &Regex::new("").unwrap(), &opts, |rx| {
+            for (_, file, results) in rx {
+                searched += 1;
+                if !results.unwrap().is_empty() {
+                    found.push(file.file_name().unwrap().to_string_lossy().into_owned());
+                }
+            }
+        });
+        found.sort();
+
+        assert_eq!(found, ["merge.nix", "with.nix"]);
+        // every file was still searched
+        assert!(searched > found.len());
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn test_relative_to() {
+        let crate_dir = fs::canonicalize(env!("CARGO_MANIFEST_DIR")).unwrap();
+        let file = crate_dir.join("testdata/test.nix");
+        let relative_to = |base: &Path| SearchOptions {
+            relative_to: Some(base.to_path_buf()),
+            ..Default::default()
+        };
+        assert_eq!(SearchOptions::default().display_path(&file), file.as_path());
+        assert_eq!(
+            relative_to(&crate_dir).display_path(&file),
+            Path::new("testdata/test.nix")
+        );
+        // the base is compared with where the file really is, however it was found
+        let found_as = crate_dir.join("src/../testdata/test.nix");
+        assert_eq!(
+            relative_to(&crate_dir.join("testdata")).display_path(&found_as),
+            Path::new("test.nix")
+        );
+        assert_eq!(
+            relative_to(&crate_dir.join("src")).display_path(&found_as),
+            file.as_path()
+        );
+    }
+
+    #[cfg(feature = "fs")]
+    fn cancelled_token() -> CancellationToken {
+        let token = CancellationToken::new();
+        token.cancel();
+        token
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn test_interrupted() {
+        let dir = Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/testdata"));
+        let opts = SearchOptions {
+            ordered: true,
+            cancel: Some(cancelled_token()),
+            ..Default::default()
+        };
+        assert!(opts.is_cancelled());
+        assert!(!SearchOptions::default().is_cancelled());
+
+        // files already walked are still reported, just without searching them
+        let walked = walk_files(dir, true, &opts.dirs, &is_searchable)
+            .map(|entry| {
+                let file_path = file_attr_path(dir, entry.path());
+                (entry.into_path(), file_path)
+            })
+            .collect::<Vec<_>>();
+        let reported = run_search(walked.into_iter(), &Regex::new("").unwrap(), &opts, |rx| {
+            rx.iter()
+                .map(|(_, _, results)| results.unwrap().len())
+                .collect::<Vec<_>>()
+        });
+        assert!(!reported.is_empty());
+        assert!(reported.iter().all(|&found| found == 0));
+
+        // a file already being searched stops at the next attrset
+        let src = "{\n  # doc\n  f = x: x;\n  g = { # doc\n h = x: x; };\n}";
+        let ast = rnix::Root::parse(src).ok().unwrap();
+        let all = Regex::new("").unwrap();
+        assert_eq!(search_ast(&all, &ast, &SearchOptions::default()).len(), 2);
+        assert!(search_ast(&all, &ast, &opts).is_empty());
+    }
+
+    #[cfg(feature = "rayon")]
+    #[cfg(feature = "fs")]
+    #[test]
+    fn test_backends_agree() {
+        let dir = Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/testdata"));
+        let found = |backend| {
+            let opts = SearchOptions {
+                backend,
+                ..Default::default()
+            };
+            let files = walk_files(dir, false, &opts.dirs, &is_searchable).map(|entry| {
+                let file_path = file_attr_path(dir, entry.path());
+                (entry.into_path(), file_path)
+            });
+            let mut found = Vec::new();
+            run_search(files, &Regex::new("").unwrap(), &opts, |rx| {
+                for (_, file, results) in rx {
+                    for result in results.unwrap() {
+                        found.push(result.format_json(file.display()));
+                    }
+                }
+            });
+            found.sort();
+            found
+        };
+
+        let pool = found(Backend::Pool);
+        assert!(!pool.is_empty());
+        assert_eq!(pool, found(Backend::Rayon));
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn test_search_patterns() {
+        let root = std::env::temp_dir().join(format!("nix-doc-patterns-{}", std::process::id()));
+        fs::create_dir_all(&root).unwrap();
+        fs::write(
+            root.join("lib.nix"),
+            "{\n  # Join\n  concatStrings = l: l;\n  # Join with sep\n  concatStringsSep = s: l: l;\n  bare = x: x;\n}\n",
+        )
+        .unwrap();
+        let output = root.join("out.csv");
+        let opts = SearchOptions {
+            format: OutputFormat::Csv,
+            output: Some(output.clone()),
+            ..SearchOptions::default()
+        };
+        let patterns = ["^concatStrings$", "concat", "^bare$"]
+            .iter()
+            .map(|p| (p.to_string(), Regex::new(p).unwrap()))
+            .collect::<Vec<_>>();
+        let matching = Regex::new("(?:^concatStrings$)|(?:concat)|(?:^bare$)").unwrap();
+        let unmatched = search_patterns(&root, matching, &patterns, opts, is_searchable).unwrap();
+        assert_eq!(unmatched, ["^bare$"]);
+
+        let lib = root.join("lib.nix").display().to_string();
+        let written = fs::read_to_string(&output).unwrap();
+        assert_eq!(
+            written.lines().collect::<Vec<_>>(),
+            [
+                PATTERN_CSV_HEADER.to_string(),
+                format!("^concatStrings$,concatStrings,{},3,function,true,Join", lib),
+                format!("concat,concatStrings,{},3,function,true,Join", lib),
+                format!(
+                    "concat,concatStringsSep,{},5,function,true,Join with sep",
+                    lib
+                ),
+            ]
+        );
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[cfg(all(feature = "fs", feature = "serde"))]
+    #[test]
+    fn test_json_envelope() {
+        let root = std::env::temp_dir().join(format!("nix-doc-envelope-{}", std::process::id()));
+        fs::create_dir_all(&root).unwrap();
+        fs::write(root.join("good.nix"), "{\n  # Identity\n  id = x: x;\n}\n").unwrap();
+        fs::write(root.join("bad.nix"), "{ broken = ; }").unwrap();
+        let output = root.join("out.json");
+        let written = |json_compat_array| {
+            let opts = SearchOptions {
+                format: OutputFormat::Json,
+                output: Some(output.clone()),
+                ordered: true,
+                json_compat_array,
+                ..SearchOptions::default()
+            };
+            search(&root, Regex::new("").unwrap(), opts, is_searchable).unwrap();
+            serde_json::from_str::<serde_json::Value>(&fs::read_to_string(&output).unwrap())
+                .unwrap()
+        };
+
+        let envelope = written(false);
+        assert_eq!(envelope["schema_version"], JSON_SCHEMA_VERSION);
+        assert_eq!(envelope["results"].as_array().unwrap().len(), 1);
+        assert_eq!(envelope["results"][0]["identifier"], "id");
+        let errors = envelope["errors"].as_array().unwrap();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0]["kind"], "Parse");
+        assert!(errors[0]["file"].as_str().unwrap().ends_with("bad.nix"));
+
+        let array = written(true);
+        assert_eq!(array.as_array().unwrap().len(), 1);
+        assert_eq!(array[0]["identifier"], "id");
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_synopsis() {
+        let src = r#"{
+          /*
+            Splits a string on a separator.
+
+            Splitting on a regex is not supported.
+          */
+          splitString = sep: s: s;
+          # Joins strings with a separator
+          concatStringsSep = sep: l: l;
+        }"#;
+        let ast = rnix::Root::parse(src).ok().unwrap();
+        let results = search_ast(&Regex::new("").unwrap(), &ast, &SearchOptions::default());
+        assert_eq!(
+            results[0].synopsis.as_deref(),
+            Some("Splits a string on a separator.")
+        );
+
+        let opts = SearchOptions {
+            match_synopsis: Some(Regex::new("^Joins").unwrap()),
+            ..SearchOptions::default()
+        };
+        let results = search_ast(&Regex::new("").unwrap(), &ast, &opts);
+        let names = results
+            .iter()
+            .map(|r| r.identifier.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["concatStringsSep"]);
+
+        // the whole doc mentions "regex", but the synopsis doesn't
+        let opts = SearchOptions {
+            match_synopsis: Some(Regex::new("regex").unwrap()),
+            ..SearchOptions::default()
+        };
+        assert!(search_ast(&Regex::new("").unwrap(), &ast, &opts).is_empty());
+    }
+
+    #[test]
+    fn test_with() {
+        let ast = rnix::Root::parse(include_str!("../testdata/with.nix"))
+            .ok()
+            .unwrap();
+        let results = search_ast(&Regex::new("").unwrap(), &ast, &SearchOptions::default());
+        let found = results
+            .iter()
+            .map(|r| (r.attr_path.join("."), r.doc.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            found,
+            [
+                ("f".to_string(), "doc"),
+                ("g".to_string(), "g doc"),
+                ("nested.h".to_string(), "h doc"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_string_and_dynamic_keys() {
+        let src = r#"{
+          # quoted
+          "foo-bar" = x: x;
+          # interpolated
+          "foo-${x}" = x: x;
+          # computed
+          ${name} = x: x;
+        }"#;
+        let ast = rnix::Root::parse(src).ok().unwrap();
+        let found = |opts| {
+            search_ast(&Regex::new("foo|name").unwrap(), &ast, &opts)
+                .into_iter()
+                .map(|r| {
+                    (
+                        r.identifier,
+                        LineIndex::new(src).line_col(r.defined_at_start),
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            found(SearchOptions::default()),
+            [("foo-bar".to_string(), (3, 11))]
+        );
+        assert_eq!(
+            found(SearchOptions {
+                any_key: true,
+                ..SearchOptions::default()
+            }),
+            [
+                ("foo-bar".to_string(), (3, 11)),
+                ("\"foo-${x}\"".to_string(), (5, 11)),
+                ("${name}".to_string(), (7, 11)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_kinds() {
+        let src = r#"{
+          # a function
+          f = x: x;
+          # a value
+          v = 1;
+          # an option
+          o = mkEnableOption "o";
+        }"#;
+        let ast = rnix::Root::parse(src).ok().unwrap();
+        let found = |kinds: Vec<ResultKind>| {
+            let opts = SearchOptions {
+                kinds,
+                ..SearchOptions::default()
+            };
+            search_ast(&Regex::new("").unwrap(), &ast, &opts)
+                .into_iter()
+                .map(|r| (r.identifier, r.kind))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(found(vec![]), [("f".to_string(), ResultKind::Function)]);
+        assert_eq!(
+            found(vec![ResultKind::Option, ResultKind::Value]),
+            [
+                ("v".to_string(), ResultKind::Value),
+                ("o".to_string(), ResultKind::Option),
+            ]
+        );
+        assert_eq!(found(ResultKind::ALL.to_vec()).len(), 3);
+    }
+}
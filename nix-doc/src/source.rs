@@ -0,0 +1,196 @@
+//! Where the files a search reads come from: the filesystem, or a tree that isn't on disk, like
+//! the unsaved buffers of an editor or the files in a git commit
+#[cfg(feature = "fs")]
+use crate::walk::walk_files;
+use crate::walk::{is_ignored_name, DirFilter};
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::iter::FromIterator;
+use std::path::{Path, PathBuf};
+
+/// The files a search walks and reads, set with [`SearchOptions::file_source`]. Searches read
+/// from [`RealFs`] when none is set.
+///
+/// [`SearchOptions::file_source`]: crate::SearchOptions::file_source
+pub trait FileSource: Send + Sync + fmt::Debug {
+    /// The files under `dir`, sorted by path if `sorted` is set, leaving out hidden files and
+    /// the directories `dirs` says not to go into. Directories themselves aren't listed.
+    #[cfg(feature = "fs")]
+    fn files<'a>(
+        &'a self,
+        dir: &'a Path,
+        sorted: bool,
+        dirs: &'a DirFilter,
+    ) -> Box<dyn Iterator<Item = PathBuf> + 'a>;
+
+    /// How many bytes are in `file`, checked before reading it so that files too big to be
+    /// anything but generated aren't read at all
+    fn size(&self, file: &Path) -> io::Result<u64>;
+
+    /// What's in `file`
+    fn read(&self, file: &Path) -> io::Result<String>;
+
+    /// `file` as an absolute path, with any links followed, for printing it relative to
+    /// [`SearchOptions::relative_to`](crate::SearchOptions::relative_to). Paths are taken as
+    /// they are by default, since a tree that isn't on disk has no links to follow.
+    fn absolute(&self, file: &Path) -> PathBuf {
+        file.to_path_buf()
+    }
+}
+
+/// The files on disk
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RealFs;
+
+impl FileSource for RealFs {
+    #[cfg(feature = "fs")]
+    fn files<'a>(
+        &'a self,
+        dir: &'a Path,
+        sorted: bool,
+        dirs: &'a DirFilter,
+    ) -> Box<dyn Iterator<Item = PathBuf> + 'a> {
+        fn any(_: &Path) -> bool {
+            true
+        }
+        Box::new(walk_files(dir, sorted, dirs, &any).map(|entry| entry.into_path()))
+    }
+
+    fn size(&self, file: &Path) -> io::Result<u64> {
+        Ok(fs::metadata(file)?.len())
+    }
+
+    fn read(&self, file: &Path) -> io::Result<String> {
+        fs::read_to_string(file)
+    }
+
+    fn absolute(&self, file: &Path) -> PathBuf {
+        fs::canonicalize(file).unwrap_or_else(|_| file.to_path_buf())
+    }
+}
+
+/// Files kept in memory, by path. A directory is there if a file under it is.
+///
+/// ```
+/// use nix_doc::source::{FileSource, MemoryFs};
+/// use std::path::Path;
+///
+/// let files = MemoryFs::new().file("/repo/lib/lists.nix", "{ }");
+/// assert_eq!(files.read(Path::new("/repo/lib/lists.nix")).unwrap(), "{ }");
+/// assert!(files.read(Path::new("/repo/lib/strings.nix")).is_err());
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct MemoryFs {
+    files: BTreeMap<PathBuf, String>,
+}
+
+impl MemoryFs {
+    /// No files at all
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the file at `path` with `content` in it, in place of any that was there
+    pub fn file(mut self, path: impl Into<PathBuf>, content: impl Into<String>) -> Self {
+        self.files.insert(path.into(), content.into());
+        self
+    }
+
+    fn get(&self, file: &Path) -> io::Result<&String> {
+        self.files.get(file).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("{} isn't in memory", file.display()),
+            )
+        })
+    }
+}
+
+impl<P: Into<PathBuf>, S: Into<String>> FromIterator<(P, S)> for MemoryFs {
+    fn from_iter<I: IntoIterator<Item = (P, S)>>(iter: I) -> Self {
+        iter.into_iter()
+            .fold(MemoryFs::new(), |files, (path, content)| {
+                files.file(path, content)
+            })
+    }
+}
+
+impl FileSource for MemoryFs {
+    #[cfg(feature = "fs")]
+    fn files<'a>(
+        &'a self,
+        dir: &'a Path,
+        _sorted: bool,
+        dirs: &'a DirFilter,
+    ) -> Box<dyn Iterator<Item = PathBuf> + 'a> {
+        // the map keeps them sorted by path already
+        Box::new(
+            self.files
+                .keys()
+                .filter(move |file| match file.strip_prefix(dir) {
+                    Ok(relative) => !relative.iter().any(is_ignored_name) && dirs.allows(relative),
+                    Err(_) => false,
+                })
+                .cloned(),
+        )
+    }
+
+    fn size(&self, file: &Path) -> io::Result<u64> {
+        Ok(self.get(file)?.len() as u64)
+    }
+
+    fn read(&self, file: &Path) -> io::Result<String> {
+        self.get(file).cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn test_memory_files() {
+        let files = [
+            "/repo/lib/lists.nix",
+            "/repo/lib/tests/misc.nix",
+            "/repo/.git/config.nix",
+            "/repo/pkgs/hello/default.nix",
+            "/elsewhere/a.nix",
+        ]
+        .iter()
+        .map(|&path| (path, "{ }"))
+        .collect::<MemoryFs>();
+        let listed = |dir: &str, dirs: &DirFilter| {
+            files
+                .files(Path::new(dir), true, dirs)
+                .map(|file| file.display().to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            listed("/repo", &DirFilter::default()),
+            [
+                "/repo/lib/lists.nix",
+                "/repo/lib/tests/misc.nix",
+                "/repo/pkgs/hello/default.nix",
+            ]
+        );
+        let dirs = DirFilter {
+            include: vec!["lib".to_string()],
+            exclude: vec!["tests".to_string()],
+        };
+        assert_eq!(listed("/repo", &dirs), ["/repo/lib/lists.nix"]);
+        assert_eq!(
+            listed("/repo/lib/lists.nix", &DirFilter::default()).len(),
+            1
+        );
+        assert_eq!(files.size(Path::new("/elsewhere/a.nix")).unwrap(), 3);
+        assert_eq!(
+            files.read(Path::new("/nowhere.nix")).unwrap_err().kind(),
+            io::ErrorKind::NotFound
+        );
+    }
+}
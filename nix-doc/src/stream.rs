@@ -0,0 +1,617 @@
+//! Searching as an iterator, with a callback, or as an async stream, for library users who want
+//! results as soon as the files they're in have been searched, rather than once the whole search
+//! is done
+pub use crate::error::FileError;
+use crate::search::{files_to_search, search_file, FileFound, SearchResult};
+use crate::walk::is_searchable;
+use crate::{CancellationToken, Error, SearchOptions};
+
+#[cfg(feature = "async")]
+use futures_core::Stream;
+use regex::Regex;
+
+use std::collections::VecDeque;
+use std::ops::ControlFlow;
+use std::path::PathBuf;
+#[cfg(feature = "async")]
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+#[cfg(feature = "async")]
+use std::sync::mpsc::TryRecvError;
+use std::sync::mpsc::{sync_channel, Receiver};
+use std::sync::Arc;
+#[cfg(feature = "async")]
+use std::sync::{Mutex, MutexGuard};
+#[cfg(feature = "async")]
+use std::task::{Context, Poll, Waker};
+use std::thread::{self, JoinHandle};
+
+/// How many searched files' results can be waiting to be looked at before the threads searching
+/// wait for them to be, so that a slow reader doesn't have every result held in memory
+const BUFFERED_FILES: usize = 16;
+
+/// How many results a [`SearchStream`] holds on to before waiting for them to be taken out of it
+#[cfg(feature = "async")]
+const BUFFERED_RESULTS: usize = 64;
+
+/// A search of the nix files under a directory, set up a piece at a time and then run with
+/// [`Search::iter`]
+///
+/// ```no_run
+/// use regex::Regex;
+///
+/// for result in nix_doc::Search::new("nixpkgs/lib").pattern(Regex::new("^map").unwrap()).iter() {
+///     match result {
+///         Ok(found) => println!("{}", found.identifier),
+///         Err(err) => eprintln!("{}", err),
+///     }
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct Search {
+    root: PathBuf,
+    matching: Regex,
+    opts: SearchOptions,
+}
+
+impl Search {
+    /// A search of `root` for every documented function, until narrowed down with
+    /// [`Search::pattern`] and [`Search::options`]
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Search {
+            root: root.into(),
+            matching: Regex::new("").unwrap(),
+            opts: SearchOptions::default(),
+        }
+    }
+
+    /// Only finds definitions whose names match `re`
+    pub fn pattern(self, re: Regex) -> Self {
+        Search {
+            matching: re,
+            ..self
+        }
+    }
+
+    /// Searches with `opts`. The ones about printing, like the format and sort order, don't do
+    /// anything here, since the results are handed over as they're found.
+    pub fn options(self, opts: SearchOptions) -> Self {
+        Search { opts, ..self }
+    }
+
+    /// Stops the search once `token` is cancelled, from here or another thread. Like
+    /// [`SearchOptions::cancel`], which this sets.
+    pub fn cancel_with(mut self, token: CancellationToken) -> Self {
+        self.opts.cancel = Some(token);
+        self
+    }
+
+    /// Starts the search in the background, returning an iterator over what it finds
+    pub fn iter(&self) -> SearchIter {
+        SearchIter::start(self.clone())
+    }
+
+    /// Starts the search in the background, returning a stream of what it finds for async code
+    /// to await, which never blocks the task awaiting it
+    #[cfg(feature = "async")]
+    pub fn stream(&self) -> SearchStream {
+        SearchStream::start(self.iter())
+    }
+}
+
+/// Starts `search` in the background, for async code to take the results of as they come in.
+/// The [`SearchStream`] of [`Search::stream`], as a [`Stream`] that works with `StreamExt` and the
+/// like.
+///
+/// ```no_run
+/// use futures_util::StreamExt;
+/// use regex::Regex;
+///
+/// # async fn run() {
+/// let search = nix_doc::Search::new("nixpkgs/lib").pattern(Regex::new("^map").unwrap());
+/// let names = nix_doc::stream::search(search)
+///     .await
+///     .filter_map(|found| async move { found.ok() })
+///     .map(|found| found.identifier)
+///     .collect::<Vec<_>>()
+///     .await;
+/// # }
+/// ```
+#[cfg(feature = "async")]
+pub async fn search(search: Search) -> impl Stream<Item = Result<SearchResult, FileError>> {
+    search.stream()
+}
+
+/// Something that happened during a [`search_with`] search. All of a file's events come one
+/// after another, starting with its [`SearchEvent::FileStarted`].
+#[derive(Clone, Debug)]
+#[allow(clippy::large_enum_variant)]
+pub enum SearchEvent {
+    /// The file whose results come next
+    FileStarted(PathBuf),
+    /// A result found in the file
+    Result(SearchResult),
+    /// The file couldn't be searched
+    FileError {
+        path: PathBuf,
+        error: String,
+        /// Like [`FileError::kind`]
+        kind: &'static str,
+    },
+    /// The search is over, because every file was searched or the callback stopped it. This is
+    /// always the last event.
+    Finished(SearchStats),
+}
+
+/// Counts of what a [`search_with`] search got through
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SearchStats {
+    /// Files searched, including ones that couldn't be
+    pub files: usize,
+    pub results: usize,
+    /// Files that couldn't be searched
+    pub errors: usize,
+    /// Did the callback stop the search before every file was searched?
+    pub stopped: bool,
+    /// Was the search's [`CancellationToken`] cancelled before every file was searched? The
+    /// results before then were still handed over.
+    pub cancelled: bool,
+}
+
+/// Runs `search`, handing `callback` each [`SearchEvent`] as it happens instead of collecting
+/// anything. The files are searched on a thread pool, while `callback` runs on this thread.
+/// Returning [`ControlFlow::Break`] stops the search like dropping a [`SearchIter`] does, after
+/// which the callback only gets [`SearchEvent::Finished`]. Returns the same stats as that one
+/// has.
+pub fn search_with<F>(search: &Search, mut callback: F) -> SearchStats
+where
+    F: FnMut(SearchEvent) -> ControlFlow<()>,
+{
+    let mut iter = search.iter();
+    let mut stats = SearchStats::default();
+    'files: while let Some((path, found)) = iter.next_file() {
+        stats.files += 1;
+        let mut events = Vec::new();
+        match found {
+            Ok(results) => {
+                stats.results += results.len();
+                events.push(SearchEvent::FileStarted(path));
+                events.extend(results.into_iter().map(SearchEvent::Result));
+            }
+            Err(err) => {
+                stats.errors += 1;
+                events.push(SearchEvent::FileStarted(path.clone()));
+                events.push(SearchEvent::FileError {
+                    path,
+                    error: err.message,
+                    kind: err.kind,
+                });
+            }
+        }
+        for event in events {
+            if callback(event).is_break() {
+                stats.stopped = true;
+                break 'files;
+            }
+        }
+    }
+    stats.cancelled = !stats.stopped && iter.is_cancelled();
+    drop(iter);
+    let _ = callback(SearchEvent::Finished(stats));
+    stats
+}
+
+/// The results of a [`Search`], in whatever order the files get searched in, each file that
+/// couldn't be searched coming up as an `Err`. The files are searched on a thread pool while the
+/// results are being looked at, which waits for them to be once a few files' worth are. Dropping
+/// this early stops the search: the files not started on yet are left alone, and the drop waits
+/// for the ones being searched, so no threads are left running.
+pub struct SearchIter {
+    rx: Receiver<FileFound>,
+    /// Results from the last file received that haven't been handed out yet
+    pending: VecDeque<SearchResult>,
+    /// Set when this is dropped
+    cancelled: Arc<AtomicBool>,
+    /// The search's own token, from its options
+    token: Option<CancellationToken>,
+    /// The thread walking the directory and feeding the pool, which waits for the pool once the
+    /// walk is over
+    walker: Option<JoinHandle<()>>,
+}
+
+impl SearchIter {
+    fn start(search: Search) -> Self {
+        let (tx, rx) = sync_channel(BUFFERED_FILES);
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let walking = cancelled.clone();
+        let token = search.opts.cancel.clone();
+        let walker = thread::spawn(move || {
+            let Search {
+                root,
+                matching,
+                opts,
+            } = search;
+            let pool = opts.thread_pool();
+            let files = files_to_search(&root, &opts, &is_searchable)
+                .take_while(|_| !walking.load(Ordering::Relaxed));
+            for (file, file_path) in files {
+                let tx = tx.clone();
+                let cancelled = walking.clone();
+                let matching = matching.clone();
+                let opts = opts.clone();
+                pool.push(move || {
+                    if cancelled.load(Ordering::Relaxed) || opts.is_cancelled() {
+                        return;
+                    }
+                    let path = file.as_path();
+                    let found = match search_file(path, &matching, &opts) {
+                        Ok(_) if !opts.file_matches(path) => Ok(Vec::new()),
+                        Ok(mut results) => {
+                            results.retain(|res| opts.path_matches(&file_path, &res.attr_path));
+                            opts.retain_changed(path, &mut results);
+                            opts.cap_per_file(&mut results);
+                            Ok(results)
+                        }
+                        // the iterator is about to end anyway
+                        Err(Error::Cancelled) => return,
+                        Err(err) => Err(FileError::new(path, &err)),
+                    };
+                    // the iterator is only gone once it has cancelled the search
+                    let _ = tx.send((file, found));
+                });
+            }
+            drop(tx);
+            pool.done();
+        });
+        SearchIter {
+            rx,
+            pending: VecDeque::new(),
+            cancelled,
+            token,
+            walker: Some(walker),
+        }
+    }
+
+    /// Was the search's [`CancellationToken`] cancelled? The iterator ends soon after that, with
+    /// only what the files searched by then turned up.
+    pub fn is_cancelled(&self) -> bool {
+        self.token
+            .as_ref()
+            .is_some_and(CancellationToken::is_cancelled)
+    }
+
+    /// Waits for the next file to be searched, returning it with what it turned up, or `None`
+    /// once every file has been
+    fn next_file(&mut self) -> Option<FileFound> {
+        // every sender being gone means every file has been searched
+        self.rx.recv().ok()
+    }
+}
+
+impl Iterator for SearchIter {
+    type Item = Result<SearchResult, FileError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(res) = self.pending.pop_front() {
+                return Some(Ok(res));
+            }
+            match self.next_file()?.1 {
+                Ok(results) => self.pending.extend(results),
+                Err(err) => return Some(Err(err)),
+            }
+        }
+    }
+}
+
+impl Drop for SearchIter {
+    fn drop(&mut self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+        // the threads waiting to hand over what they found need it taken before they can finish
+        while self.rx.recv().is_ok() {}
+        if let Some(walker) = self.walker.take() {
+            // a panic in a search has already been reported, and there's nothing to do about it
+            let _ = walker.join();
+        }
+    }
+}
+
+/// The results of a [`Search`] for async code, the same ones a [`SearchIter`] has, from
+/// [`Search::stream`]. The search runs on threads of its own, so this works with any executor:
+/// [`SearchStream::next`] is awaited for each result, and it's a [`Stream`] for everything built
+/// on those. Only a few results are held
+/// at a time, the search waiting for them to be taken out before it goes on. Dropping it stops
+/// the search like dropping a [`SearchIter`] does, without waiting for the files being searched,
+/// which finish in the background.
+#[cfg(feature = "async")]
+pub struct SearchStream {
+    rx: Receiver<Result<SearchResult, FileError>>,
+    /// The task to wake once there's more, if one is waiting
+    waker: Arc<Mutex<Option<Waker>>>,
+    /// The [`SearchIter`]'s, set to stop the search when this is dropped
+    cancelled: Arc<AtomicBool>,
+}
+
+#[cfg(feature = "async")]
+impl SearchStream {
+    /// Hands what `iter` finds over to a stream as it comes in, on a thread that waits on it
+    fn start(iter: SearchIter) -> Self {
+        let (tx, rx) = sync_channel(BUFFERED_RESULTS);
+        let waker = Arc::new(Mutex::new(None));
+        let cancelled = iter.cancelled.clone();
+        let waking = waker.clone();
+        thread::spawn(move || {
+            for found in iter {
+                // the stream is gone, which has stopped the search
+                if tx.send(found).is_err() {
+                    break;
+                }
+                wake(lock_waker(&waking));
+            }
+            drop(tx);
+            wake(lock_waker(&waking));
+        });
+        SearchStream {
+            rx,
+            waker,
+            cancelled,
+        }
+    }
+
+    /// The next result, or `None` once every file has been searched
+    pub async fn next(&mut self) -> Option<Result<SearchResult, FileError>> {
+        std::future::poll_fn(|cx| Pin::new(&mut *self).poll_next(cx)).await
+    }
+
+    fn try_next(&self) -> Option<Poll<Option<Result<SearchResult, FileError>>>> {
+        match self.rx.try_recv() {
+            Ok(found) => Some(Poll::Ready(Some(found))),
+            Err(TryRecvError::Disconnected) => Some(Poll::Ready(None)),
+            Err(TryRecvError::Empty) => None,
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+impl Stream for SearchStream {
+    type Item = Result<SearchResult, FileError>;
+
+    /// The next result if there is one yet, or `Ready(None)` once every file has been searched.
+    /// When it's `Pending`, the task in `cx` is woken once there's more.
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        if let Some(ready) = self.try_next() {
+            return ready;
+        }
+        *lock_waker(&self.waker) = Some(cx.waker().clone());
+        // it may have come in before the waker was there to be woken
+        self.try_next().unwrap_or(Poll::Pending)
+    }
+}
+
+#[cfg(feature = "async")]
+impl Drop for SearchStream {
+    fn drop(&mut self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Locks `waker`, which a panic while it was locked can't have left half changed
+#[cfg(feature = "async")]
+fn lock_waker(waker: &Mutex<Option<Waker>>) -> MutexGuard<'_, Option<Waker>> {
+    waker
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Wakes the task in `waker`, if there is one, once it's unlocked
+#[cfg(feature = "async")]
+fn wake(mut waker: MutexGuard<'_, Option<Waker>>) {
+    let task = waker.take();
+    drop(waker);
+    if let Some(task) = task {
+        task.wake();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::fs;
+    use std::path::Path;
+
+    fn assert_send<T: Send>() {}
+
+    #[test]
+    fn test_search_iter() {
+        assert_send::<SearchIter>();
+
+        let testdata = Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/testdata"));
+        let mut found = Search::new(testdata)
+            .iter()
+            .map(|res| res.unwrap().identifier)
+            .collect::<Vec<_>>();
+        found.sort();
+        assert!(found.contains(&"the-fn".to_string()));
+
+        let mut maps = Search::new(testdata)
+            .pattern(Regex::new("^the").unwrap())
+            .iter()
+            .map(|res| res.unwrap().identifier)
+            .collect::<Vec<_>>();
+        maps.sort();
+        assert!(!maps.is_empty() && maps.len() < found.len());
+        assert!(maps.iter().all(|name| name.starts_with("the")));
+
+        // stopping early doesn't hang or leave anything running
+        let mut iter = Search::new(testdata).iter();
+        assert!(iter.next().is_some());
+        drop(iter);
+    }
+
+    #[test]
+    fn test_search_with() {
+        let testdata = Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/testdata"));
+        let mut events = Vec::new();
+        let stats = search_with(&Search::new(testdata), |event| {
+            events.push(event);
+            ControlFlow::Continue(())
+        });
+        assert!(stats.files > 1 && stats.results > 0 && !stats.stopped);
+        assert!(matches!(events.last(), Some(SearchEvent::Finished(s)) if *s == stats));
+
+        // each file's results come right after it starts, from that file
+        let mut file = None;
+        for event in &events {
+            match event {
+                SearchEvent::FileStarted(path) => file = Some(path.clone()),
+                SearchEvent::Result(res) => assert_eq!(res.file, file),
+                _ => (),
+            }
+        }
+
+        let mut seen = 0;
+        let stats = search_with(&Search::new(testdata), |event| match event {
+            SearchEvent::Result(_) => {
+                seen += 1;
+                ControlFlow::Break(())
+            }
+            _ => ControlFlow::Continue(()),
+        });
+        assert_eq!(seen, 1);
+        assert!(stats.stopped && !stats.cancelled);
+
+        // cancelling from elsewhere ends the search with what was found by then
+        let token = CancellationToken::new();
+        let mut seen = 0;
+        let search = Search::new(testdata).cancel_with(token.clone());
+        let stats = search_with(&search, |event| {
+            if let SearchEvent::Result(_) = event {
+                seen += 1;
+                token.cancel();
+            }
+            ControlFlow::Continue(())
+        });
+        assert!(stats.cancelled && !stats.stopped);
+        assert_eq!(stats.results, seen);
+    }
+
+    #[test]
+    fn test_search_iter_errors() {
+        let root = std::env::temp_dir().join(format!("nix-doc-iter-{}", std::process::id()));
+        fs::create_dir_all(&root).unwrap();
+        fs::write(root.join("good.nix"), "{\n  # Identity\n  id = x: x;\n}\n").unwrap();
+        fs::write(root.join("bad.nix"), "{ f = ").unwrap();
+
+        let (found, failed): (Vec<_>, Vec<_>) = Search::new(&root).iter().partition(Result::is_ok);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].as_ref().unwrap().identifier, "id");
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].as_ref().unwrap_err().file, root.join("bad.nix"));
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_search_iter_backpressure() {
+        let root =
+            std::env::temp_dir().join(format!("nix-doc-backpressure-{}", std::process::id()));
+        fs::create_dir_all(&root).unwrap();
+        let files = BUFFERED_FILES * 4;
+        for n in 0..files {
+            fs::write(
+                root.join(format!("f{}.nix", n)),
+                "{\n  # Identity\n  id = x: x;\n}\n",
+            )
+            .unwrap();
+        }
+        // the searchers wait for the results to be taken, which they all are in the end
+        let mut iter = Search::new(&root).iter();
+        assert!(iter.next().is_some());
+        thread::sleep(std::time::Duration::from_millis(50));
+        assert_eq!(iter.count(), files - 1);
+        // and dropping it with the buffer full doesn't leave them waiting forever
+        let mut iter = Search::new(&root).iter();
+        assert!(iter.next().is_some());
+        thread::sleep(std::time::Duration::from_millis(50));
+        drop(iter);
+        #[cfg(feature = "async")]
+        {
+            let mut stream = Search::new(&root).stream();
+            assert!(block_on(stream.next()).is_some());
+            thread::sleep(std::time::Duration::from_millis(50));
+            let mut rest = 0;
+            while block_on(stream.next()).is_some() {
+                rest += 1;
+            }
+            assert_eq!(rest, files - 1);
+        }
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    /// Runs `future` to completion on this thread, parking it until it's woken
+    #[cfg(feature = "async")]
+    fn block_on<F: std::future::Future>(future: F) -> F::Output {
+        struct Unpark(thread::Thread);
+
+        impl std::task::Wake for Unpark {
+            fn wake(self: Arc<Self>) {
+                self.0.unpark();
+            }
+        }
+
+        let waker = Waker::from(Arc::new(Unpark(thread::current())));
+        let mut cx = Context::from_waker(&waker);
+        let mut future = Box::pin(future);
+        loop {
+            match future.as_mut().poll(&mut cx) {
+                Poll::Ready(output) => return output,
+                Poll::Pending => thread::park(),
+            }
+        }
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_search_stream() {
+        fn assert_send<T: Send>(_: &T) {}
+
+        let testdata = Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/testdata"));
+        let search = Search::new(testdata).pattern(Regex::new("^the").unwrap());
+        let mut streamed = block_on(async {
+            let mut stream = search.stream();
+            let next = stream.next();
+            assert_send(&next);
+            drop(next);
+            let mut found = Vec::new();
+            while let Some(res) = stream.next().await {
+                found.push(res.unwrap().identifier);
+            }
+            found
+        });
+        streamed.sort();
+        let mut iterated = search
+            .iter()
+            .map(|res| res.unwrap().identifier)
+            .collect::<Vec<_>>();
+        iterated.sort();
+        assert!(!streamed.is_empty());
+        assert_eq!(streamed, iterated);
+
+        // dropping it early doesn't wait for the search, which stops in the background
+        let first = block_on(async { Search::new(testdata).stream().next().await });
+        assert!(first.is_some());
+
+        // and it's a Stream, for the combinators built on those
+        let mut combined = block_on(async {
+            use futures_util::StreamExt;
+
+            super::search(search.clone())
+                .await
+                .map(|res| res.unwrap().identifier)
+                .collect::<Vec<_>>()
+                .await
+        });
+        combined.sort();
+        assert_eq!(combined, iterated);
+    }
+}
@@ -0,0 +1,205 @@
+//! `--format table`: a line per result, with the columns lined up across all of them
+use crate::color::Colorful;
+use crate::output::Glyphs;
+use crate::search::SearchResult;
+use crate::width::{display_width, truncate_display_with};
+
+use std::borrow::Cow;
+use std::fmt::Display;
+
+/// Between columns
+const GAP: &str = "  ";
+
+/// Narrowest the identifier column is squeezed down to, to make room for the synopses
+const MIN_IDENTIFIER: usize = 12;
+
+/// Narrowest the synopsis column is worth squeezing the identifiers for
+const MIN_SYNOPSIS: usize = 20;
+
+/// A line of table output, held back until the widths of the columns are known
+pub(crate) enum TableLine {
+    /// A line of its own, like the pattern a group of results matched
+    Heading(String),
+    Row(TableRow),
+}
+
+/// The columns of a result in the table
+pub(crate) struct TableRow {
+    identifier: String,
+    /// `file:line`
+    location: String,
+    synopsis: String,
+}
+
+impl TableRow {
+    pub(crate) fn new<P: Display>(result: &SearchResult, filename: P) -> Self {
+        TableRow {
+            identifier: result.identifier.clone(),
+            location: format!("{}:{}", filename, result.start.line),
+            synopsis: result.synopsis.clone().unwrap_or_default(),
+        }
+    }
+}
+
+/// Lays `lines` out with the columns of the rows lined up, fitting them in `width` columns if
+/// there's a limit. The locations are never cut short, since they're for going to the result, so
+/// the synopses are, then the identifiers if there's still no room.
+pub(crate) fn layout(lines: &[TableLine], width: Option<usize>, glyphs: &Glyphs) -> Vec<String> {
+    let rows = || {
+        lines.iter().filter_map(|line| match line {
+            TableLine::Row(row) => Some(row),
+            TableLine::Heading(_) => None,
+        })
+    };
+    let widest = |column: fn(&TableRow) -> &str| {
+        rows()
+            .map(|row| display_width(column(row)))
+            .max()
+            .unwrap_or(0)
+    };
+    let mut identifier_width = widest(|row| &row.identifier);
+    let location_width = widest(|row| &row.location);
+    let synopsis_width = width.map(|width| {
+        let left = width.saturating_sub(location_width + 2 * GAP.len());
+        if identifier_width + MIN_SYNOPSIS > left {
+            identifier_width = left
+                .saturating_sub(MIN_SYNOPSIS)
+                .max(MIN_IDENTIFIER)
+                .min(identifier_width);
+        }
+        left.saturating_sub(identifier_width)
+    });
+
+    let mut out = Vec::with_capacity(lines.len());
+    for line in lines {
+        let row = match line {
+            TableLine::Row(row) => row,
+            TableLine::Heading(heading) => {
+                out.push(heading.clone());
+                continue;
+            }
+        };
+        let identifier = truncate_display_with(&row.identifier, identifier_width, glyphs.ellipsis);
+        let synopsis = match synopsis_width {
+            Some(width) => Cow::Owned(truncate_display_with(&row.synopsis, width, glyphs.ellipsis)),
+            None => Cow::Borrowed(row.synopsis.as_str()),
+        };
+        // padded by hand, since `format!` counts characters rather than columns
+        let mut formatted = format!(
+            "{}{:identifier_padding$}{}{}{:location_padding$}{}{}",
+            identifier.as_str().white().bold(),
+            "",
+            GAP,
+            row.location,
+            "",
+            GAP,
+            synopsis,
+            identifier_padding = identifier_width - display_width(&identifier),
+            location_padding = location_width - display_width(&row.location),
+        );
+        formatted.truncate(formatted.trim_end().len());
+        out.push(formatted);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::strip_colors;
+
+    fn row(identifier: &str, location: &str, synopsis: &str) -> TableLine {
+        TableLine::Row(TableRow {
+            identifier: identifier.to_string(),
+            location: location.to_string(),
+            synopsis: synopsis.to_string(),
+        })
+    }
+
+    #[test]
+    fn test_layout() {
+        let lines = [
+            TableLine::Heading("pattern `map`".to_string()),
+            row(
+                "map",
+                "lib/lists.nix:10",
+                "Applies a function to each element",
+            ),
+            row(
+                "concatMapStrings",
+                "lib/strings.nix:200",
+                "Maps and concatenates",
+            ),
+            row("undocumented", "a.nix:1", ""),
+        ];
+        let laid_out = |width| {
+            layout(&lines, width, &Glyphs::UNICODE)
+                .iter()
+                .map(|line| strip_colors(line))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            laid_out(None),
+            [
+                "pattern `map`",
+                "map               lib/lists.nix:10     Applies a function to each element",
+                "concatMapStrings  lib/strings.nix:200  Maps and concatenates",
+                "undocumented      a.nix:1",
+            ]
+        );
+        // the synopses go first
+        assert_eq!(
+            laid_out(Some(59)),
+            [
+                "pattern `map`",
+                "map               lib/lists.nix:10     Applies a function…",
+                "concatMapStrings  lib/strings.nix:200  Maps and concatenat…",
+                "undocumented      a.nix:1",
+            ]
+        );
+        // then the identifiers, down to a point
+        assert_eq!(
+            laid_out(Some(50))[2],
+            "concatMapSt…  lib/strings.nix:200  Maps and conca…"
+        );
+        assert!(laid_out(Some(50))
+            .iter()
+            .all(|line| line.chars().count() <= 50));
+        assert_eq!(laid_out(Some(20))[2], "concatMapSt…  lib/strings.nix:200");
+
+        let ascii = layout(&lines, Some(50), &Glyphs::ASCII);
+        assert_eq!(
+            strip_colors(&ascii[2]),
+            "concatMap...  lib/strings.nix:200  Maps and con..."
+        );
+    }
+
+    #[test]
+    fn test_layout_wide() {
+        let lines = [
+            row("映射", "lib/映射.nix:1", "把函数应用到每个元素"),
+            row("map", "lib/lists.nix:10", "Applies a function"),
+        ];
+        let laid_out = |width| {
+            layout(&lines, width, &Glyphs::UNICODE)
+                .iter()
+                .map(|line| strip_colors(line))
+                .collect::<Vec<_>>()
+        };
+        // lined up by the columns they take, not by characters
+        assert_eq!(
+            laid_out(None),
+            [
+                "映射  lib/映射.nix:1    把函数应用到每个元素",
+                "map   lib/lists.nix:10  Applies a function",
+            ]
+        );
+        assert_eq!(
+            laid_out(Some(33)),
+            [
+                "映射  lib/映射.nix:1    把函数应…",
+                "map   lib/lists.nix:10  Applies…",
+            ]
+        );
+    }
+}
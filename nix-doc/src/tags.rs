@@ -8,18 +8,14 @@ use std::{
     path::{Path, PathBuf},
 };
 
-use rnix::types::Inherit;
-use rnix::SyntaxNode;
-use rnix::{
-    types::{AttrSet, EntryHolder, Ident, TokenWrapper, TypedNode},
-    SmolStr,
-    SyntaxKind::*,
-    AST,
-};
+use rnix::ast::{Attr, AttrSet, HasEntry, Inherit};
+use rnix::{Root, SyntaxKind::*, SyntaxNode};
+use rowan::ast::AstNode;
 use walkdir::WalkDir;
 
+use crate::parse::key_part_name;
 use crate::threadpool::ThreadPool;
-use crate::{is_ignored, is_searchable};
+use crate::walk::{is_ignored, is_searchable};
 
 const DEBUG_TIMERS: bool = false;
 
@@ -41,19 +37,14 @@ impl Timer {
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default)]
 enum MemoValue<T> {
+    #[default]
     Uncomputed,
     Failed,
     Value(T),
 }
 
-impl<T> Default for MemoValue<T> {
-    fn default() -> Self {
-        Self::Uncomputed
-    }
-}
-
 impl<T> MemoValue<T> {
     fn get_or_compute<F>(&mut self, f: F) -> Option<&T>
     where
@@ -103,13 +94,13 @@ impl_from!(Error, Io, io::Error);
 /// One ctags file entry
 struct Tag {
     /// Name of the identifier
-    name: SmolStr,
+    name: String,
 
     /// Path relative to the tags file parent dir
     path: InternedPath,
 
     /// "address" of the tag, the line it's on, basically.
-    addr: SmolStr,
+    addr: String,
 
     /// Kind of tag
     kind: Kind,
@@ -137,10 +128,10 @@ fn escape(a: &str) -> String {
     result
 }
 
-fn make_addr(a: &str) -> SmolStr {
+fn make_addr(a: &str) -> String {
     // FIXME: delete this cloned malarkey when we can tell everyone with old nixpkgs to go eat a
     // nixpkgs-unstable cookie
-    SmolStr::from_iter(["/^", &escape(a), "$/"].iter().cloned())
+    String::from_iter(["/^", &escape(a), "$/"].iter().cloned())
 }
 
 impl Tag {
@@ -148,7 +139,7 @@ impl Tag {
         &self,
         paths: &[PathBuf],
         p: &Path,
-        memo: &mut Vec<MemoValue<PathBuf>>,
+        memo: &mut [MemoValue<PathBuf>],
         out: &mut String,
     ) -> Option<()> {
         let relpath =
@@ -175,7 +166,7 @@ struct FileJob<'a> {
 
 impl<'a> FileJob<'a> {
     fn get_source_line(&self, node: &SyntaxNode) -> &str {
-        let defined_at_start = node.text_range().start().to_usize();
+        let defined_at_start = usize::from(node.text_range().start());
         let prior = &self.source[..defined_at_start];
         let line_start = prior.rfind('\n').unwrap_or(0);
         let after = &self.source[defined_at_start..];
@@ -187,24 +178,26 @@ impl<'a> FileJob<'a> {
     }
 
     fn visit_attrset(&mut self, set: &AttrSet) {
-        for ent in set.entries() {
+        for ent in set.attrpath_values() {
             let tag = (|| {
                 let val = ent.value()?;
-                let key = ent.key()?;
+                let key = ent.attrpath()?;
 
-                let kind = match val.kind() {
+                let kind = match val.syntax().kind() {
                     NODE_LAMBDA => Kind::Function,
                     _ => Kind::Member,
                 };
 
-                let source_line = self.get_source_line(key.node());
+                let source_line = self.get_source_line(key.syntax());
 
-                let ident = key.path().last().and_then(Ident::cast);
-                let ident_name = ident.as_ref().map(|id| id.as_str())?;
+                let ident_name = match key.attrs().last()? {
+                    Attr::Ident(ident) => ident.syntax().to_string(),
+                    _ => return None,
+                };
 
                 Some(Tag {
-                    name: ident_name.into(),
-                    path: self.file.clone(),
+                    name: ident_name,
+                    path: self.file,
                     addr: source_line.into(),
                     kind,
                 })
@@ -217,28 +210,32 @@ impl<'a> FileJob<'a> {
     }
 
     fn visit_inherit(&mut self, inh: &Inherit) {
-        for id in inh.idents() {
-            (|| {
-                let name = id.as_str();
-                self.results.push(Tag {
-                    name: name.into(),
-                    path: self.file.clone(),
-                    addr: self.get_source_line(id.node()).into(),
-                    kind: Kind::Member,
-                });
-            })();
+        for id in inh.attrs() {
+            // a name can be quoted, as in `inherit "foo";`
+            let name = match key_part_name(&id) {
+                Some(name) => name,
+                None => continue,
+            };
+            self.results.push(Tag {
+                name,
+                path: self.file,
+                addr: self.get_source_line(id.syntax()).into(),
+                kind: Kind::Member,
+            });
         }
     }
 
-    fn exec(&mut self, ast: &AST) {
-        for evt in ast.node().preorder_with_tokens() {
+    fn exec(&mut self, ast: &Root) {
+        for evt in ast.syntax().preorder_with_tokens() {
             match evt {
                 rnix::WalkEvent::Enter(ent) => {
-                    ent.into_node().map(|n| match n.kind() {
-                        NODE_ATTR_SET => self.visit_attrset(&AttrSet::cast(n).unwrap()),
-                        NODE_INHERIT => self.visit_inherit(&Inherit::cast(n).unwrap()),
-                        _ => (),
-                    });
+                    if let Some(n) = ent.into_node() {
+                        match n.kind() {
+                            NODE_ATTR_SET => self.visit_attrset(&AttrSet::cast(n).unwrap()),
+                            NODE_INHERIT => self.visit_inherit(&Inherit::cast(n).unwrap()),
+                            _ => (),
+                        }
+                    }
                 }
                 rnix::WalkEvent::Leave(_) => (),
             }
@@ -251,7 +248,7 @@ impl<'a> FileJob<'a> {
     pub fn run(p_interned: InternedPath, p: &Path) -> Result<Vec<Tag>, Error> {
         assert!(p.is_absolute());
         let contents = fs::read_to_string(p)?;
-        let parsed = rnix::parse(&contents);
+        let parsed = Root::parse(&contents).tree();
         let mut results = Vec::new();
 
         let mut job = FileJob {
@@ -363,8 +360,8 @@ pub fn run_on_dir(dir: &Path, mut writer: impl io::Write) -> Result<(), Error> {
             Some(_) => (),
             None => continue,
         };
-        writer.write(out_s.as_bytes())?;
-        writer.write(b"\n")?;
+        writer.write_all(out_s.as_bytes())?;
+        writer.write_all(b"\n")?;
     }
     write_t.debug_print("write time");
 
@@ -409,12 +406,67 @@ mod tests {
                 !_TAG_PROGRAM_NAME	nix-doc tags	//
                 !_TAG_PROGRAM_URL	https://github.com/lf-/nix-doc	//
                 c	test.nix	/^   a.b.c = a: 1;$/;"	f
+                checked	wrapped.nix	/^  checked = assert true; x: y: x;$/;"	m
+                codeName	values.nix	/^  codeName = "Tapir";$/;"	m
+                concatLists	inherit.nix	/^  inherit (lib.lists) concatLists;$/;"	m
+                concatMap	pipeline/lib/lists.nix	/^  concatMap = f: list: lib.concatLists (map f list);$/;"	f
+                concatMapStrings	pipeline/lib/strings.nix	/^  concatMapStrings = f: list: lib.concatStrings (map f list);$/;"	f
+                concatMapStringsSep	inherit.nix	/^  inherit (strings) concatStringsSep concatMapStringsSep;$/;"	m
+                concatStringsSep	pipeline/lib/strings.nix	/^  concatStringsSep = sep: list: builtins.concatStringsSep sep list;$/;"	f
+                concatStringsSep	inherit.nix	/^  inherit (strings) concatStringsSep concatMapStringsSep;$/;"	m
+                cowsay	packages.nix	/^  cowsay = stdenv.mkDerivation (finalAttrs: {$/;"	m
+                description	packages.nix	/^    meta.description = "A program that produces a familiar, friendly greeting";$/;"	m
+                description	packages.nix	/^      description = "A program which generates ASCII pictures of a cow";$/;"	m
+                div	wrapped.nix	/^  div = b: assert b != 0; a: a \/ b;$/;"	f
+                double	values.nix	/^  double = x: x * 2;$/;"	f
+                everything	imports/lists.nix	/^  everything = import .\/nested { };$/;"	m
+                f	with.nix	/^with lib; { \/* doc *\/ f = x: x;$/;"	f
+                f	merge.nix	/^base \/\/ { \/* doc *\/ f = x: x; } \/\/ {$/;"	f
                 ff	test.nix	/^   inherit ff;$/;"	m
+                filter	pipeline/lib/lists.nix	/^  filter = pred: list: builtins.filter pred list;$/;"	f
                 fixedWidthString	regression-11.nix	/^  fixedWidthString = width: filler: str:$/;"	f
+                g	with.nix	/^  g = with builtins; y: y;$/;"	m
+                g	merge.nix	/^  g = y: y;$/;"	f
+                getAttr	wrapped.nix	/^  getAttr =$/;"	m
+                glob	comments.nix	/^  glob = x: x;$/;"	f
+                globs	comments.nix	/^  globs = x: x;$/;"	f
                 grub	test.nix	/^   inherit (n) grub hello;$/;"	m
+                h	with.nix	/^    h = z: z;$/;"	f
+                h	merge.nix	/^  inherit (base) h;$/;"	m
+                hash	comments.nix	/^  hash = x: x;$/;"	f
+                head	wrapped.nix	/^  head = lib.throwIf (lib.strict or false) "head: strict mode is unsupported" (list: builtins.head list);$/;"	m
+                head	imports/nested/default.nix	/^  head = xs: builtins.head xs;$/;"	f
                 hello	test.nix	/^   inherit (n) grub hello;$/;"	m
+                hello	packages.nix	/^  hello = stdenv.mkDerivation rec {$/;"	m
+                lib	imports/default.nix	/^  nested = import .\/nested { inherit lib; };$/;"	m
+                license	packages.nix	/^      license = licenses.gpl3;$/;"	m
+                lineEnd	comments.nix	/^  lineEnd = x: x;$/;"	f
+                lineStart	comments.nix	/^  lineStart = x: x;$/;"	f
+                lists	imports/default.nix	/^  lists = import .\/lists.nix;$/;"	m
+                lists	imports/nested/default.nix	/^  lists = import ..\/lists.nix;$/;"	m
+                map	pipeline/lib/lists.nix	/^  map = f: list: builtins.map f list;$/;"	f
+                map	imports/lists.nix	/^  map = f: xs: builtins.map f xs;$/;"	f
+                map	inherit.nix	/^  inherit (builtins) map;$/;"	m
+                map	inherit.nix	/^  inherit map;$/;"	m
+                mapGreeting	pipeline/pkgs/hello/default.nix	/^  mapGreeting = greeting: names: map (name: "\${greeting}, \${name}") names;$/;"	f
+                maxInt	values.nix	/^  maxInt = 9223372036854775807;$/;"	m
+                meta	packages.nix	/^    meta = with lib; {$/;"	m
+                nested	with.nix	/^  nested = with lib.strings; {$/;"	m
+                nested	imports/default.nix	/^  nested = import .\/nested { inherit lib; };$/;"	m
+                opener	comments.nix	/^  opener = x: x;$/;"	f
+                pname	packages.nix	/^    pname = "hello";$/;"	m
+                pname	packages.nix	/^    pname = "cowsay";$/;"	m
+                pname	packages.nix	/^    pname = "undescribed";$/;"	m
+                quoted	comments.nix	/^  quoted = x: x;$/;"	f
+                star	comments.nix	/^  star = x: x;$/;"	f
                 the-fn	test.nix	/^   the-fn = a: b: {z = a; y = b;};$/;"	f
                 the-snd-fn	test.nix	/^   the-snd-fn = {b, \/* doc *\/ c}: {};$/;"	f
+                undescribed	packages.nix	/^  undescribed = stdenv.mkDerivation {$/;"	m
+                undocumented	values.nix	/^  undocumented = [ 1 2 3 ];$/;"	m
+                undocumented	pipeline/lib/lists.nix	/^  undocumented = x: x;$/;"	f
+                undocumented	inherit.nix	/^  inherit (lib) undocumented;$/;"	m
+                unused	imports/unused.nix	/^  unused = x: x;$/;"	f
+                version	packages.nix	/^    version = "2.10";$/;"	m
                 withFeature	regression-11.nix	/^  withFeature = with_: feat: "--\${if with_ then "with" else "without"}-\${feat}";$/;"	f
                 withFeatureAs	regression-11.nix	/^  withFeatureAs = with_: feat: value: withFeature with_ feat + optionalString with_ "=\${value}";$/;"	f
                 y	test.nix	/^   the-fn = a: b: {z = a; y = b;};$/;"	m
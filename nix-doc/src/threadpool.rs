@@ -14,6 +14,9 @@ enum Message {
 }
 
 /// A basic thread pool implementation.
+///
+/// Dropping the pool waits for all the workers to exit, so [`ThreadPool::done`] must be called
+/// before it goes out of scope.
 pub struct ThreadPool {
     workers: Vec<Worker>,
     sender: mpsc::Sender<Message>,
@@ -36,8 +39,11 @@ impl Default for ThreadPool {
 
 impl ThreadPool {
     /// Makes a new ThreadPool with `nthreads` threads.
+    ///
+    /// A pool always has at least one worker: asking for zero gets you one, since a pool that
+    /// can't run anything would make [`ThreadPool::done`] wait forever.
     pub fn with_threads(nthreads: usize) -> ThreadPool {
-        assert!(nthreads > 0);
+        let nthreads = nthreads.max(1);
 
         let (sender, receiver) = mpsc::channel();
         let receiver = Arc::new(Mutex::new(receiver));
@@ -91,3 +97,39 @@ impl Worker {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn run_tasks(nthreads: usize, ntasks: usize) -> usize {
+        let count = Arc::new(AtomicUsize::new(0));
+        {
+            let pool = ThreadPool::with_threads(nthreads);
+            for _ in 0..ntasks {
+                let count = count.clone();
+                pool.push(move || {
+                    count.fetch_add(1, Ordering::SeqCst);
+                });
+            }
+            pool.done();
+            // dropping the pool waits for the workers to finish
+        }
+        count.load(Ordering::SeqCst)
+    }
+
+    #[test]
+    fn test_single_worker() {
+        assert_eq!(run_tasks(1, 20), 20);
+    }
+
+    #[test]
+    fn test_zero_workers_clamped() {
+        let pool = ThreadPool::with_threads(0);
+        assert_eq!(pool.workers.len(), 1);
+        pool.done();
+
+        assert_eq!(run_tasks(0, 5), 5);
+    }
+}
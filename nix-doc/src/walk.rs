@@ -0,0 +1,226 @@
+//! Finding the nix files to search under a directory
+use crate::filter::FileFilter;
+#[cfg(feature = "fs")]
+use crate::imports::reachable_files;
+use crate::output::Output;
+use crate::{Error, OutputFormat, Result};
+
+#[cfg(feature = "fs")]
+use walkdir::{DirEntry, WalkDir};
+
+use std::ffi::OsStr;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::str;
+
+/// Max size of files we will consider searching. It takes a long time to parse 300k lines of nix
+/// in hackage-packages.nix and no files this big will have search results in them as they
+/// categorically do not contain functions. 200k bytes is ~7.5k lines
+pub(crate) const MAX_FILE_SIZE: u64 = 200_000;
+
+/// Should the given path be searched?
+/// TODO: support globbing for files e.g. with lib in their name to improve perf significantly
+///       or avoid looking in absurdly large files like hackage.nix
+pub fn is_searchable(fname: &Path) -> bool {
+    fname.to_str().map(|s| s.ends_with(".nix")).unwrap_or(false)
+}
+
+/// Is the file so large it must be generated, and thus not worth searching?
+pub(crate) fn is_too_large(file: &Path) -> Result<bool> {
+    let metadata = fs::metadata(file).map_err(|err| Error::io(file, err))?;
+    Ok(metadata.len() > MAX_FILE_SIZE)
+}
+
+/// Restricts which directories a search walks into, by directory name. Names are compared
+/// against whole path components, and the directory being searched is never filtered out itself.
+#[derive(Clone, Debug, Default)]
+pub struct DirFilter {
+    /// Only search files under a directory with one of these names, at any depth. Empty means
+    /// everywhere.
+    pub include: Vec<String>,
+    /// Never walk into directories with these names. Wins over `include`, so an excluded
+    /// directory inside an included one is still skipped.
+    pub exclude: Vec<String>,
+}
+
+#[cfg(feature = "fs")]
+impl DirFilter {
+    /// Should the walk go into `entry`? Only directories are ever pruned.
+    fn descends_into(&self, entry: &DirEntry) -> bool {
+        entry.depth() == 0
+            || !entry.file_type().is_dir()
+            || !self
+                .exclude
+                .iter()
+                .any(|name| entry.file_name() == name.as_str())
+    }
+
+    /// Is the file `entry` under an included directory?
+    fn includes(&self, entry: &DirEntry) -> bool {
+        self.include.is_empty()
+            || entry
+                .path()
+                .ancestors()
+                .skip(1)
+                .take(entry.depth().saturating_sub(1))
+                .filter_map(Path::file_name)
+                .any(|dir| self.include.iter().any(|name| dir == name.as_str()))
+    }
+}
+
+impl DirFilter {
+    /// Would a walk get to the file at `relative`, a path from the directory being walked, the
+    /// way [`DirFilter::descends_into`] and [`DirFilter::includes`] decide for real directories?
+    pub(crate) fn allows(&self, relative: &Path) -> bool {
+        let dirs = || relative.parent().into_iter().flat_map(Path::iter);
+        let named = |names: &[String], dir: &OsStr| names.iter().any(|name| dir == name.as_str());
+        !dirs().any(|dir| named(&self.exclude, dir))
+            && (self.include.is_empty() || dirs().any(|dir| named(&self.include, dir)))
+    }
+}
+
+/// Is a file hidden or a unicode decode error?
+/// Let's not consider it.
+#[cfg(feature = "fs")]
+pub fn is_ignored(entry: &DirEntry) -> bool {
+    is_ignored_name(entry.file_name())
+}
+
+/// Is a file or directory called `name` left out, as [`is_ignored`] decides?
+pub(crate) fn is_ignored_name(name: &OsStr) -> bool {
+    name.to_str()
+        .map(|s| s != "." && s.starts_with('.') || s == "target")
+        .unwrap_or(true)
+}
+
+/// Walks `dir` for the files that should be searched, in sorted order if `sorted` is set
+#[cfg(feature = "fs")]
+pub(crate) fn walk_files<'a, F>(
+    dir: &Path,
+    sorted: bool,
+    dirs: &'a DirFilter,
+    should_search: &'a F,
+) -> impl Iterator<Item = DirEntry> + 'a
+where
+    F: FileFilter,
+{
+    let mut walker = WalkDir::new(dir);
+    if sorted {
+        walker = walker.sort_by(|a, b| a.file_name().cmp(b.file_name()));
+    }
+    walker
+        .into_iter()
+        .filter_entry(move |e| !is_ignored(e) && dirs.descends_into(e))
+        .filter_map(|e| e.ok())
+        .filter(move |e| {
+            dirs.includes(e) && should_search.should_search(e.path()) && e.path().is_file()
+        })
+}
+
+/// The file `entry` and the files it imports that should be searched (see [`reachable_files`]),
+/// in the order they're found
+#[cfg(feature = "fs")]
+pub(crate) fn imported_files<'a, F>(
+    entry: &Path,
+    should_search: &'a F,
+) -> impl Iterator<Item = DirEntry> + 'a
+where
+    F: FileFilter,
+{
+    reachable_files(entry)
+        .into_iter()
+        .filter_map(|file| WalkDir::new(file).into_iter().next()?.ok())
+        .filter(move |e| should_search.should_search(e.path()) && e.path().is_file())
+}
+
+/// Prints the paths of the files in `dir` that a search would look at, in sorted order, without
+/// parsing any of them, to `output` or stdout
+#[cfg(feature = "fs")]
+pub fn list_files<F>(
+    dir: &Path,
+    dirs: &DirFilter,
+    output: Option<&Path>,
+    should_search: F,
+) -> Result<()>
+where
+    F: FileFilter,
+{
+    let mut out = Output::open(output)?;
+    for direntry in walk_files(dir, true, dirs, &should_search) {
+        match is_too_large(direntry.path()) {
+            Ok(false) => out.line(&direntry.path().display().to_string())?,
+            Ok(true) => (),
+            Err(err) => err.warn(OutputFormat::Human),
+        }
+    }
+    Ok(out.flush()?)
+}
+
+/// Attribute path that the definitions in `file` are taken to be under, from where it is under
+/// `root`: `lib/strings.nix` is `lib.strings`, and `lib/default.nix` is `lib`
+pub fn file_attr_path(root: &Path, file: &Path) -> Vec<String> {
+    let relative = file.strip_prefix(root).unwrap_or(file);
+    let mut path = relative
+        .with_extension("")
+        .iter()
+        .filter_map(|part| part.to_str())
+        .filter(|part| *part != "." && *part != "/")
+        .map(str::to_string)
+        .collect::<Vec<_>>();
+    if path.last().map(String::as_str) == Some("default") {
+        path.pop();
+    }
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn test_dir_filter() {
+        let root = std::env::temp_dir().join(format!("nix-doc-dir-filter-{}", std::process::id()));
+        for file in &[
+            "top.nix",
+            "lib/a.nix",
+            "lib/tests/b.nix",
+            "pkgs/lib/c.nix",
+            "pkgs/d.nix",
+        ] {
+            let path = root.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "{ }").unwrap();
+        }
+
+        let walked = |include: &[&str], exclude: &[&str]| {
+            let dirs = DirFilter {
+                include: include.iter().map(|s| s.to_string()).collect(),
+                exclude: exclude.iter().map(|s| s.to_string()).collect(),
+            };
+            walk_files(&root, true, &dirs, &is_searchable)
+                .map(|e| {
+                    let path = e.path().strip_prefix(&root).unwrap();
+                    path.to_str().unwrap().replace('\\', "/")
+                })
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(walked(&[], &[]).len(), 5);
+        assert_eq!(
+            walked(&[], &["tests"]),
+            ["lib/a.nix", "pkgs/d.nix", "pkgs/lib/c.nix", "top.nix"]
+        );
+        assert_eq!(
+            walked(&["lib"], &[]),
+            ["lib/a.nix", "lib/tests/b.nix", "pkgs/lib/c.nix"]
+        );
+        assert_eq!(walked(&["lib"], &["tests", "pkgs"]), ["lib/a.nix"]);
+        // the root itself is never filtered
+        let name = root.file_name().unwrap().to_str().unwrap();
+        assert_eq!(walked(&[], &[name]).len(), 5);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
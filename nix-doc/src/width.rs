@@ -0,0 +1,115 @@
+//! How wide text is on a terminal, and cutting it down to fit
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthChar;
+
+/// Columns taken up by one grapheme cluster. A cluster is only as wide as its widest character,
+/// since the rest combine with it: accents don't take a column of their own, and the people in an
+/// emoji family joined by zero width joiners are drawn as one.
+fn grapheme_width(grapheme: &str) -> usize {
+    grapheme
+        .chars()
+        .map(|ch| ch.width().unwrap_or(0))
+        .max()
+        .unwrap_or(0)
+}
+
+/// Columns `s` takes up on a terminal, counting CJK and emoji as two, and combining characters as
+/// none
+pub fn display_width(s: &str) -> usize {
+    s.graphemes(true).map(grapheme_width).sum()
+}
+
+/// `s` cut down to at most `max_cols` columns (see [`display_width`]), ending in `…` if anything
+/// was cut off. Grapheme clusters are kept whole, and there's no space left dangling before the
+/// `…`.
+pub fn truncate_display(s: &str, max_cols: usize) -> String {
+    truncate_display_with(s, max_cols, "…")
+}
+
+/// `s` cut down like [`truncate_display`] does, but ending in `ellipsis` instead of `…`, like
+/// `...` for terminals that can't show it
+pub fn truncate_display_with(s: &str, max_cols: usize, ellipsis: &str) -> String {
+    if display_width(s) <= max_cols {
+        return s.to_string();
+    }
+    // room for the ellipsis, or for as much of it as fits
+    let room = match max_cols.checked_sub(display_width(ellipsis)) {
+        Some(room) => room,
+        None => return ellipsis.chars().take(max_cols).collect(),
+    };
+    let mut kept = String::new();
+    let mut width = 0;
+    for grapheme in s.graphemes(true) {
+        width += grapheme_width(grapheme);
+        if width > room {
+            break;
+        }
+        kept.push_str(grapheme);
+    }
+    format!("{}{}", kept.trim_end(), ellipsis)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display_width() {
+        assert_eq!(display_width("map"), 3);
+        assert_eq!(display_width(""), 0);
+        // CJK is two columns a character
+        assert_eq!(display_width("映射"), 4);
+        // a combining accent sits on the letter before it
+        assert_eq!(display_width("cafe\u{301}"), 4);
+        assert_eq!(display_width("🦀"), 2);
+        // a man, a woman and a girl joined into one family
+        assert_eq!(display_width("👨\u{200d}👩\u{200d}👧"), 2);
+    }
+
+    #[test]
+    fn test_truncate_display() {
+        assert_eq!(truncate_display("short", 10), "short");
+        assert_eq!(truncate_display("exactly", 7), "exactly");
+        assert_eq!(truncate_display("too long", 5), "too…");
+        assert_eq!(truncate_display("too long", 6), "too l…");
+        assert_eq!(truncate_display("anything", 0), "");
+        assert_eq!(truncate_display("anything", 1), "…");
+    }
+
+    #[test]
+    fn test_truncate_ascii() {
+        assert_eq!(truncate_display_with("too long", 6, "..."), "too...");
+        assert_eq!(truncate_display_with("too long", 8, "..."), "too long");
+        // with no room for anything else, as much of the ellipsis as fits
+        assert_eq!(truncate_display_with("too long", 2, "..."), "..");
+        assert_eq!(truncate_display_with("映射函数", 6, "..."), "映...");
+    }
+
+    #[test]
+    fn test_truncate_wide() {
+        // a wide character that doesn't fit whole is left out, rather than cut in half
+        assert_eq!(truncate_display("映射函数", 6), "映射…");
+        assert_eq!(truncate_display("映射函数", 5), "映射…");
+        assert_eq!(truncate_display("映射函数", 4), "映…");
+        assert_eq!(truncate_display("映射函数", 8), "映射函数");
+        assert_eq!(truncate_display("🦀🦀🦀", 5), "🦀🦀…");
+        assert!(display_width(&truncate_display("a映射函数b", 6)) <= 6);
+    }
+
+    #[test]
+    fn test_truncate_graphemes() {
+        // the accent stays with its letter
+        assert_eq!(truncate_display("cafe\u{301}s galore", 6), "cafe\u{301}s…");
+        assert_eq!(truncate_display("cafe\u{301}s", 5), "cafe\u{301}s");
+        // the family stays together
+        let family = "👨\u{200d}👩\u{200d}👧";
+        assert_eq!(
+            truncate_display(&format!("{} family", family), 5),
+            format!("{} f…", family)
+        );
+        assert_eq!(
+            truncate_display(&format!("{}{}", family, family), 3),
+            format!("{}…", family)
+        );
+    }
+}
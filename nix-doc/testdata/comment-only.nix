@@ -0,0 +1,5 @@
+# Copyright (c) 2024 The nix-doc authors
+#
+# SPDX-License-Identifier: LGPL-3.0-or-later
+
+/* nothing here yet */
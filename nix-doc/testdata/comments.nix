@@ -0,0 +1,31 @@
+# Comment delimiters inside of comments. Block comments don't nest, so a `/*` inside of one is
+# part of what it says, as is a `*/` in a line comment, and only the delimiters themselves
+# should come off.
+{
+  /*/* starts a block comment */
+  opener = x: x;
+
+  /*/*.nix files */
+  glob = x: x;
+
+  /* Matches `lib/*` and `lib/**` */
+  globs = x: x;
+
+  /* Ends in a star **/
+  star = x: x;
+
+  # Ends like a block comment */
+  lineEnd = x: x;
+
+  # Starts like one /* and never ends
+  lineStart = x: x;
+
+  /*
+    Quotes one,
+    /* like this
+  */
+  quoted = x: x;
+
+  /* Never closed, as far as a line comment goes: # */
+  hash = x: x;
+}
@@ -0,0 +1,5 @@
+{ lib }:
+{
+  lists = import ./lists.nix;
+  nested = import ./nested { inherit lib; };
+}
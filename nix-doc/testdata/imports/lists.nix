@@ -0,0 +1,7 @@
+rec {
+  # Applies `f` to each element of `xs`
+  map = f: xs: builtins.map f xs;
+
+  # Everything reachable from here, back round to this file
+  everything = import ./nested { };
+}
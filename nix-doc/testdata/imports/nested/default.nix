@@ -0,0 +1,7 @@
+{ ... }:
+{
+  # The first element of `xs`
+  head = xs: builtins.head xs;
+
+  lists = import ../lists.nix;
+}
@@ -0,0 +1,4 @@
+{
+  # Not imported by anything, so not found when following imports
+  unused = x: x;
+}
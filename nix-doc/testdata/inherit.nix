@@ -0,0 +1,18 @@
+{ lib }:
+let
+  /* Applies `f` to each element of `list` */
+  inherit (builtins) map;
+
+  strings = lib.strings;
+in
+{
+  inherit map;
+
+  # Concatenates a list of lists into one list
+  inherit (lib.lists) concatLists;
+
+  # Joins the strings with `sep` between them
+  inherit (strings) concatStringsSep concatMapStringsSep;
+
+  inherit (lib) undocumented;
+}
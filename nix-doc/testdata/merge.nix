@@ -0,0 +1,9 @@
+let
+  base = import ./base.nix;
+in
+base // { /* doc */ f = x: x; } // {
+  # merged in last
+  g = y: y;
+  # not visible
+  inherit (base) h;
+}
@@ -0,0 +1,20 @@
+{ stdenv, lib }:
+{
+  hello = stdenv.mkDerivation rec {
+    pname = "hello";
+    version = "2.10";
+    meta.description = "A program that produces a familiar, friendly greeting";
+  };
+
+  cowsay = stdenv.mkDerivation (finalAttrs: {
+    pname = "cowsay";
+    meta = with lib; {
+      description = "A program which generates ASCII pictures of a cow";
+      license = licenses.gpl3;
+    };
+  });
+
+  undescribed = stdenv.mkDerivation {
+    pname = "undescribed";
+  };
+}
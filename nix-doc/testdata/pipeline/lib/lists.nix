@@ -0,0 +1,13 @@
+{ lib }:
+rec {
+  # Applies `f` to each element of the list
+  map = f: list: builtins.map f list;
+
+  # Keeps the elements `pred` holds for
+  filter = pred: list: builtins.filter pred list;
+
+  # Maps, then concatenates the lists that come out
+  concatMap = f: list: lib.concatLists (map f list);
+
+  undocumented = x: x;
+}
@@ -0,0 +1,8 @@
+{ lib }:
+{
+  # Maps each element to a string and concatenates them
+  concatMapStrings = f: list: lib.concatStrings (map f list);
+
+  # Joins the strings with `sep` between them
+  concatStringsSep = sep: list: builtins.concatStringsSep sep list;
+}
@@ -0,0 +1,4 @@
+{
+  # Maps a greeting over the names
+  mapGreeting = greeting: names: map (name: "${greeting}, ${name}") names;
+}
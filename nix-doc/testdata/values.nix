@@ -0,0 +1,12 @@
+{
+  /* The largest integer Nix can represent */
+  maxInt = 9223372036854775807;
+
+  # Current release name
+  codeName = "Tapir";
+
+  # Doubles a number
+  double = x: x * 2;
+
+  undocumented = [ 1 2 3 ];
+}
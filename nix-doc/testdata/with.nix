@@ -0,0 +1,9 @@
+# not documentation for anything in the set
+with lib; { /* doc */ f = x: x;
+  # g doc
+  g = with builtins; y: y;
+  nested = with lib.strings; {
+    # h doc
+    h = z: z;
+  };
+}
@@ -0,0 +1,16 @@
+{ lib }:
+{
+  # Divides `a` by `b`, which mustn't be zero
+  div = b: assert b != 0; a: a / b;
+
+  /* Takes the head of a list, once the `strict` setting has been checked */
+  head = lib.throwIf (lib.strict or false) "head: strict mode is unsupported" (list: builtins.head list);
+
+  # Gets an attribute, checked first
+  getAttr =
+    lib.throwIfNot (builtins.isFunction builtins.getAttr) "no getAttr"
+      (name: set: set.${name});
+
+  # Checks with an assert before taking arguments
+  checked = assert true; x: y: x;
+}
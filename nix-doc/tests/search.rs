@@ -0,0 +1,401 @@
+//! Searching the fixtures in `testdata/pipeline` from end to end, the files walked and searched on
+//! the thread pool, checking the results come out the same however many threads there are
+use nix_doc::format::{print_results, FormatContext, Formatter};
+use nix_doc::source::MemoryFs;
+use nix_doc::{
+    is_searchable, search, search_files, search_formatted, search_results, DefKind, OutputFormat,
+    ResultKind, SearchOptions, SearchResult, SearchStats, SortKey,
+};
+
+use regex::Regex;
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+fn fixtures() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("testdata/pipeline")
+}
+
+/// `file:line identifier` for each result, and `file (kind)` for each file that couldn't be
+/// searched, for searching the fixtures for `pattern` with `opts`
+fn found(pattern: &str, opts: SearchOptions) -> (Vec<String>, Vec<String>) {
+    let root = fixtures();
+    let relative = |file: &Path| {
+        file.strip_prefix(&root)
+            .unwrap()
+            .display()
+            .to_string()
+            .replace('\\', "/")
+    };
+    let (results, errors) =
+        search_results(&root, &Regex::new(pattern).unwrap(), &opts, is_searchable);
+    let results = results
+        .iter()
+        .map(|res| {
+            format!(
+                "{}:{} {}",
+                relative(res.file.as_deref().unwrap()),
+                res.start.line,
+                res.identifier
+            )
+        })
+        .collect();
+    let errors = errors
+        .iter()
+        .map(|err| format!("{} ({})", relative(&err.file), err.kind))
+        .collect();
+    (results, errors)
+}
+
+/// The fixtures, read into memory under `root`, which doesn't exist
+fn in_memory(root: &Path) -> MemoryFs {
+    let (found, _) = search_files(
+        &fixtures(),
+        &Regex::new("").unwrap(),
+        &jobs(1),
+        is_searchable,
+    );
+    found
+        .into_iter()
+        .map(|(file, _)| {
+            let content = fs::read_to_string(&file).unwrap();
+            (root.join(file.strip_prefix(fixtures()).unwrap()), content)
+        })
+        .collect()
+}
+
+fn jobs(jobs: usize) -> SearchOptions {
+    SearchOptions::builder().jobs(jobs).build().unwrap()
+}
+
+#[test]
+fn test_single_threaded() {
+    let (results, errors) = found("^(concat)?[mM]ap", jobs(1));
+    assert_eq!(
+        results,
+        [
+            "lib/lists.nix:4 map",
+            "lib/lists.nix:10 concatMap",
+            "lib/strings.nix:4 concatMapStrings",
+            "pkgs/hello/default.nix:3 mapGreeting",
+        ]
+    );
+    assert!(errors.is_empty(), "{:?}", errors);
+}
+
+#[test]
+fn test_threads_agree() {
+    let one = found("", jobs(1));
+    assert_eq!(one.0.len(), 6);
+    for threads in [2, 4, 16] {
+        assert_eq!(found("", jobs(threads)), one, "with {} threads", threads);
+    }
+}
+
+#[test]
+fn test_sorted() {
+    let opts = SearchOptions::builder()
+        .jobs(4)
+        .sort_by(SortKey::Name)
+        .build()
+        .unwrap();
+    assert_eq!(
+        found("^concat", opts).0,
+        [
+            "lib/lists.nix:10 concatMap",
+            "lib/strings.nix:4 concatMapStrings",
+            "lib/strings.nix:7 concatStringsSep",
+        ]
+    );
+    let opts = SearchOptions::builder().reverse(true).build().unwrap();
+    assert_eq!(
+        found("^map", opts).0,
+        [
+            "pkgs/hello/default.nix:3 mapGreeting",
+            "lib/lists.nix:4 map"
+        ]
+    );
+}
+
+#[test]
+fn test_search_files() {
+    let root = fixtures();
+    let matching = Regex::new("^concat").unwrap();
+    let (found, stats) = search_files(&root, &matching, &jobs(4), is_searchable);
+    let found = found
+        .iter()
+        .map(|(file, results)| {
+            let names = results
+                .as_ref()
+                .unwrap()
+                .iter()
+                .map(|res| res.identifier.as_str())
+                .collect::<Vec<_>>()
+                .join(" ");
+            format!("{}: {}", file.strip_prefix(&root).unwrap().display(), names)
+        })
+        .collect::<Vec<_>>();
+    // every file searched is there, even with nothing found in it
+    assert_eq!(
+        found,
+        [
+            "lib/lists.nix: concatMap",
+            "lib/strings.nix: concatMapStrings concatStringsSep",
+            "pkgs/hello/default.nix: ",
+        ]
+    );
+    assert_eq!(
+        stats,
+        SearchStats {
+            files: 3,
+            results: 3,
+            ..SearchStats::default()
+        }
+    );
+}
+
+#[test]
+fn test_print_results_like_search() {
+    let printed = |format: OutputFormat, print: &dyn Fn(SearchOptions)| {
+        let output = std::env::temp_dir().join(format!(
+            "nix-doc-test-{}-{:?}.out",
+            std::process::id(),
+            format
+        ));
+        print(
+            SearchOptions::builder()
+                .ordered(true)
+                .format(format)
+                .output(output.clone())
+                .build()
+                .unwrap(),
+        );
+        let printed = fs::read_to_string(&output).unwrap();
+        fs::remove_file(&output).unwrap();
+        printed
+    };
+    let matching = Regex::new("[mM]ap").unwrap();
+    for format in [OutputFormat::Human, OutputFormat::Csv] {
+        let searched = printed(format, &|opts| {
+            search(&fixtures(), matching.clone(), opts, is_searchable).unwrap()
+        });
+        let presented = printed(format, &|opts| {
+            let (found, _) = search_files(&fixtures(), &matching, &opts, is_searchable);
+            print_results(found, &matching, &opts).unwrap()
+        });
+        assert!(searched.contains("concatMapStrings"));
+        assert_eq!(presented, searched);
+    }
+}
+
+#[test]
+fn test_max_matches_per_file() {
+    let capped = |max| {
+        let opts = SearchOptions::builder()
+            .jobs(2)
+            .max_matches_per_file(max)
+            .build()
+            .unwrap();
+        found("", opts).0
+    };
+    // the first in each file, whichever order they were found in
+    assert_eq!(
+        capped(1),
+        [
+            "lib/lists.nix:4 map",
+            "lib/strings.nix:4 concatMapStrings",
+            "pkgs/hello/default.nix:3 mapGreeting",
+        ]
+    );
+    assert_eq!(capped(2).len(), 5);
+    assert_eq!(capped(100), found("", jobs(2)).0);
+}
+
+/// Lays results out like `grep -n`, under a heading for each file
+#[derive(Default)]
+struct GrepFormatter {
+    out: String,
+    finished: Option<SearchStats>,
+}
+
+impl Formatter for GrepFormatter {
+    fn file_header(&mut self, file: &Path, _cx: &FormatContext<'_>) -> io::Result<()> {
+        let file = file.strip_prefix(fixtures()).unwrap_or(file);
+        self.out.push_str(&format!("{}\n", file.display()));
+        Ok(())
+    }
+
+    fn result(
+        &mut self,
+        result: &SearchResult,
+        _file: &Path,
+        cx: &FormatContext<'_>,
+    ) -> io::Result<()> {
+        let synopsis = result.synopsis.as_deref().unwrap_or_default();
+        self.out
+            .push_str(&format!("{}:{}", result.start.line, result.identifier));
+        if !cx.color {
+            self.out.push_str(&format!(": {}", synopsis));
+        }
+        self.out.push('\n');
+        Ok(())
+    }
+
+    fn finish(&mut self, stats: &SearchStats, _cx: &FormatContext<'_>) -> io::Result<()> {
+        self.finished = Some(*stats);
+        Ok(())
+    }
+}
+
+#[test]
+fn test_custom_formatter() {
+    let mut formatter = GrepFormatter::default();
+    let opts = SearchOptions::builder()
+        .jobs(3)
+        .ordered(true)
+        .output(std::env::temp_dir().join("unused"))
+        .build()
+        .unwrap();
+    let stats = search_formatted(
+        &fixtures(),
+        &Regex::new("^concat").unwrap(),
+        &opts,
+        is_searchable,
+        &mut formatter,
+    )
+    .unwrap();
+    assert_eq!(
+        formatter.out,
+        "\
+lib/lists.nix
+10:concatMap: Maps, then concatenates the lists that come out
+lib/strings.nix
+4:concatMapStrings: Maps each element to a string and concatenates them
+7:concatStringsSep: Joins the strings with `sep` between them
+"
+    );
+    assert_eq!(formatter.finished, Some(stats));
+    assert_eq!((stats.files, stats.results, stats.errors), (3, 3, 0));
+}
+
+#[test]
+fn test_memory_source() {
+    let root = Path::new("/virtual/nixpkgs");
+    let files = in_memory(root).file(root.join("lib/broken.nix"), "{ f = ; }");
+    let opts = SearchOptions::builder()
+        .jobs(2)
+        .file_source(files)
+        .relative_to(root.to_path_buf())
+        .build()
+        .unwrap();
+    let (results, errors) =
+        search_results(root, &Regex::new("^concat").unwrap(), &opts, is_searchable);
+    let results = results
+        .iter()
+        .map(|res| {
+            format!(
+                "{}:{}",
+                res.file.as_ref().unwrap().display(),
+                res.identifier
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        results,
+        [
+            "/virtual/nixpkgs/lib/lists.nix:concatMap",
+            "/virtual/nixpkgs/lib/strings.nix:concatMapStrings",
+            "/virtual/nixpkgs/lib/strings.nix:concatStringsSep",
+        ]
+    );
+    let errors = errors.iter().map(|err| err.kind).collect::<Vec<_>>();
+    assert_eq!(errors, ["Parse"]);
+
+    // printed relative to the root, though nothing there is on disk
+    let output =
+        std::env::temp_dir().join(format!("nix-doc-test-{}-memory.out", std::process::id()));
+    let opts = SearchOptions {
+        output: Some(output.clone()),
+        format: OutputFormat::Csv,
+        ordered: true,
+        ..opts
+    };
+    search(
+        root,
+        Regex::new("^concatMap$").unwrap(),
+        opts,
+        is_searchable,
+    )
+    .unwrap();
+    let printed = fs::read_to_string(&output).unwrap();
+    fs::remove_file(&output).unwrap();
+    assert!(
+        printed.contains("concatMap,lib/lists.nix,10,"),
+        "{}",
+        printed
+    );
+}
+
+/// Everything documented in the slice of nixpkgs' lib in `tests/nixpkgs-lib`, of every kind, with
+/// where it is and its doc comment as written, so that changes to how files are parsed and
+/// walked can be checked to find the same things in real-world nix
+#[test]
+fn test_nixpkgs_lib() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/nixpkgs-lib");
+    let opts = SearchOptions::builder()
+        .kinds(ResultKind::ALL.to_vec())
+        .def_kinds(DefKind::ALL.to_vec())
+        .file_functions(true)
+        .any_value(true)
+        .any_key(true)
+        .include_reexports(true)
+        .aliases(true)
+        .ordered(true)
+        .build()
+        .unwrap();
+    let (results, errors) = search_results(&root, &Regex::new("").unwrap(), &opts, is_searchable);
+    assert!(errors.is_empty(), "{:?}", errors);
+
+    let mut out = String::new();
+    for res in &results {
+        let at = |pos: &nix_doc::lines::Position| format!("{}:{}", pos.line, pos.col_chars);
+        let file = res.file.as_ref().unwrap().strip_prefix(&root).unwrap();
+        out += &format!(
+            "{} {}-{} (at {}) {} {:?} {:?} {} = {}",
+            file.display(),
+            at(&res.start),
+            at(&res.end),
+            at(&res.identifier_start),
+            res.def_kind,
+            res.kind,
+            res.doc_position,
+            res.attr_path.join("."),
+            res.param_block,
+        );
+        if let Some(arity) = res.arity {
+            let higher = if res.arity_may_be_higher { "+" } else { "" };
+            out += &format!(" arity {}{}", arity, higher);
+        }
+        out.push('\n');
+        if let Some(message) = &res.deprecated {
+            out += &format!("  deprecated: {}\n", message);
+        }
+        if !res.aliases.is_empty() {
+            out += &format!("  aliases: {}\n", res.aliases.join(", "));
+        }
+        for tag in &res.tags {
+            out += &format!("  @{} {}\n", tag.tag, tag.text);
+        }
+        for line in res.raw_doc.lines() {
+            out += &format!("  | {}\n", line);
+        }
+    }
+    expect_test::expect_file!["nixpkgs-lib/found.txt"].assert_eq(&out);
+
+    let mut tags = Vec::new();
+    nix_doc::tags::run_on_dir(&root, &mut tags).unwrap();
+    let tags = String::from_utf8(tags).unwrap();
+    let tags = tags.replace(&format!("{}/", root.display()), "");
+    expect_test::expect_file!["nixpkgs-lib/tags.txt"].assert_eq(&tags);
+}
@@ -29,6 +29,7 @@ fn main() {
     }
 
     println!("cargo:rerun-if-changed=plugin.cpp");
+    println!("cargo:rerun-if-changed=nix_doc.h");
     let nix_expr = pkg_config::Config::new()
         .atleast_version("2.1.1")
         .probe("nix-expr")
@@ -0,0 +1,26 @@
+/* The C interface to nix-doc, from libnix_doc_plugin */
+#ifndef NIX_DOC_H
+#define NIX_DOC_H
+
+#include <stddef.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Returns the documentation of the function defined at the 1-based line and
+ * column of the file at filename, to be freed with nd_free, or NULL if there
+ * isn't any or the filename is NULL or not UTF-8. */
+char const * nd_get_function_docs(char const * filename, size_t line, size_t col);
+
+/* Frees a string from nd_get_function_docs. NULL is left alone. */
+void nd_free(char const * str);
+
+/* The old name of nd_free */
+void nd_free_string(char const * str);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -5,6 +5,8 @@
 #include <dlfcn.h>
 #include <iostream>
 
+#include "nix_doc.h"
+
 #if HAVE_BOEHMGC
 
 #include <gc/gc.h>
@@ -19,11 +21,6 @@
 
 using namespace nix;
 
-extern "C" {
-char const * nd_get_function_docs(char const * filename, size_t line, size_t col);
-void nd_free_string(char const * str);
-}
-
 /* Print documentation of the given lambda. */
 void prim_getDoc(EvalState & state, const nix::Pos & pos, Value * * args, Value & v)
 {
@@ -41,7 +38,7 @@ void prim_getDoc(EvalState & state, const nix::Pos & pos, Value * * args, Value
     } else {
         // this copies the string
         mkString(v, doc);
-        nd_free_string(doc);
+        nd_free(doc);
     }
 }
 
@@ -52,7 +49,7 @@ void printLambdaDocs(Value & v)
     char const * doc = nd_get_function_docs(file.c_str(), poz.line, poz.column);
     if (doc != nullptr) {
         std::cout << doc << std::endl;
-        nd_free_string(doc);
+        nd_free(doc);
     }
 
 }
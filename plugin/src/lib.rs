@@ -6,36 +6,55 @@ use std::ptr;
 use nix_doc::get_function_docs;
 
 /// Get the docs for a function in the given file path at the given file position and return it as
-/// a C string pointer
+/// a C string pointer, to be freed with [`nd_free`]. Returns null if there aren't any, or if the
+/// path is null or isn't UTF-8. A panic in here also comes out as null, rather than unwinding into
+/// C.
+///
+/// # Safety
+///
+/// `filename` has to be null or a nul-terminated string.
 #[no_mangle]
-pub extern "C" fn nd_get_function_docs(
+pub unsafe extern "C" fn nd_get_function_docs(
     filename: *const c_char,
     line: usize,
     col: usize,
 ) -> *const c_char {
-    let fname = unsafe { CStr::from_ptr(filename) };
-    fname
-        .to_str()
-        .ok()
-        .and_then(|f| {
-            panic::catch_unwind(|| get_function_docs(f, line, col))
-                .map_err(|e| {
-                    eprintln!("panic!! {:#?}", e);
-                    e
-                })
-                .ok()
-        })
-        .flatten()
-        .and_then(|s| CString::new(s).ok())
-        .map(|s| s.into_raw() as *const c_char)
-        .unwrap_or(ptr::null())
+    if filename.is_null() {
+        return ptr::null();
+    }
+    let fname = CStr::from_ptr(filename);
+    panic::catch_unwind(|| {
+        let docs = get_function_docs(fname.to_str().ok()?, line, col)?;
+        CString::new(docs).ok()
+    })
+    .map_err(|e| {
+        eprintln!("panic!! {:#?}", e);
+        e
+    })
+    .ok()
+    .flatten()
+    .map(|s| s.into_raw() as *const c_char)
+    .unwrap_or(ptr::null())
 }
 
-/// Call this to free a string from nd_get_function_docs
+/// Call this to free a string from nd_get_function_docs. Null is left alone.
+///
+/// # Safety
+///
+/// `s` has to be null or a string from nd_get_function_docs that hasn't been freed yet.
 #[no_mangle]
-pub extern "C" fn nd_free_string(s: *const c_char) {
-    unsafe {
-        // this is maybe UB, but it is immediately dropped.
-        CString::from_raw(s as *mut c_char);
+pub unsafe extern "C" fn nd_free(s: *const c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s as *mut c_char));
     }
 }
+
+/// The old name of [`nd_free`], kept for plugins built against it
+///
+/// # Safety
+///
+/// The same as for [`nd_free`].
+#[no_mangle]
+pub unsafe extern "C" fn nd_free_string(s: *const c_char) {
+    nd_free(s)
+}
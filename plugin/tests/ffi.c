@@ -0,0 +1,55 @@
+/* Checks the C interface in nix_doc.h against the test data of nix-doc.
+ *
+ * From the repository root, in a nix-shell:
+ *
+ *     cargo build -p nix-doc-plugin
+ *     cc -Iplugin plugin/tests/ffi.c -Ltarget/debug -lnix_doc_plugin -o target/ffi-test
+ *     LD_LIBRARY_PATH=target/debug target/ffi-test nix-doc/testdata/test.nix
+ */
+#include "nix_doc.h"
+
+#include <stdio.h>
+#include <string.h>
+
+static int failures = 0;
+
+static void check(int ok, char const * what)
+{
+    if (!ok) {
+        fprintf(stderr, "FAIL: %s\n", what);
+        failures++;
+    }
+}
+
+int main(int argc, char ** argv)
+{
+    if (argc != 2) {
+        fprintf(stderr, "usage: %s path/to/testdata/test.nix\n", argv[0]);
+        return 2;
+    }
+    char const * path = argv[1];
+
+    /* the-fn = a: b: ..., whose lambda starts at line 9, column 13 */
+    char const * doc = nd_get_function_docs(path, 9, 13);
+    check(doc != NULL, "docs for the-fn are found");
+    if (doc != NULL) {
+        check(strstr(doc, "This is a function blah blah") != NULL, "the docs are the-fn's");
+        nd_free(doc);
+    }
+
+    /* the deprecated name still frees */
+    doc = nd_get_function_docs(path, 9, 13);
+    check(doc != NULL, "docs are found a second time");
+    nd_free_string(doc);
+
+    check(nd_get_function_docs(path, 1000, 1) == NULL, "a position past the end has no docs");
+    check(nd_get_function_docs("/nonexistent/file.nix", 1, 1) == NULL, "a missing file has no docs");
+    check(nd_get_function_docs(NULL, 1, 1) == NULL, "a null path has no docs");
+    check(nd_get_function_docs("\xff\xfe.nix", 1, 1) == NULL, "a path that isn't UTF-8 has no docs");
+    nd_free(NULL);
+
+    if (failures == 0) {
+        printf("ok\n");
+    }
+    return failures == 0 ? 0 : 1;
+}